@@ -4,7 +4,8 @@ use git_version::git_version;
 
 use crate::{
     completions::GenCompletionsCommand, eval::EvalCommand, export::ExportCommand,
-    pprint_ast::PprintAstCommand, query::QueryCommand, typecheck::TypecheckCommand,
+    pprint_ast::PprintAstCommand, query::QueryCommand, test::TestCommand,
+    typecheck::TypecheckCommand,
 };
 
 #[cfg(feature = "repl")]
@@ -76,6 +77,8 @@ pub enum Command {
     /// Format Nickel files
     #[cfg(feature = "format")]
     Format(FormatCommand),
+    /// Runs the tests of Nickel test files
+    Test(TestCommand),
 
     /// Generate shell completion files
     GenCompletions(GenCompletionsCommand),
@@ -40,6 +40,19 @@ pub enum Error {
     Repl {
         error: nickel_lang_core::repl::InitError,
     },
+    /// Some inputs of a REPL script failed, or some outputs didn't match the expected ones when
+    /// checking a transcript.
+    #[cfg(feature = "repl")]
+    ReplScript {
+        path: std::path::PathBuf,
+        failures: usize,
+        transcript: bool,
+    },
+    /// Some tests failed when running `nickel test`. The failures have already been reported.
+    TestFailures {
+        failed: usize,
+        total: usize,
+    },
     #[cfg(feature = "format")]
     Format {
         error: crate::format::FormatError,
@@ -231,6 +244,22 @@ impl Error {
                     }
                 }
             }
+            #[cfg(feature = "repl")]
+            Error::ReplScript {
+                path,
+                failures,
+                transcript,
+            } => {
+                let what = if transcript {
+                    "output(s) didn't match the transcript"
+                } else {
+                    "input(s) failed"
+                };
+                eprintln!("{}: {failures} {what}", path.display())
+            }
+            Error::TestFailures { failed, total } => {
+                eprintln!("error: {failed} test(s) out of {total} failed")
+            }
             #[cfg(feature = "format")]
             Error::Format { error } => eprintln!("{error}"),
             Error::CliUsage { error, mut program } => program.report(error),
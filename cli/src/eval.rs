@@ -2,22 +2,37 @@ use crate::{
     cli::GlobalOptions,
     customize::CustomizeMode,
     error::{CliResult, ResultErrorExt},
-    input::{InputOptions, Prepare},
+    input::{InputOptions, Prepare, ProfileOptions},
+    watch::{self, WatchOptions},
 };
 
 #[derive(clap::Parser, Debug)]
 pub struct EvalCommand {
     #[command(flatten)]
     pub input: InputOptions<CustomizeMode>,
+
+    #[command(flatten)]
+    pub profile: ProfileOptions,
+
+    #[command(flatten)]
+    pub watch: WatchOptions,
 }
 
 impl EvalCommand {
     pub fn run(self, global: GlobalOptions) -> CliResult<()> {
         let mut program = self.input.prepare(&global)?;
 
-        program
-            .eval_full()
-            .map(|t| println!("{t}"))
-            .report_with_program(program)
+        if self.watch.enabled {
+            watch::run(program, |program| {
+                program.eval_full().map(|t| println!("{t}"))
+            });
+        }
+
+        self.profile.enable(&mut program);
+
+        let result = program.eval_full();
+        self.profile.write(&mut program)?;
+
+        result.map(|t| println!("{t}")).report_with_program(program)
     }
 }
@@ -1,17 +1,26 @@
-use std::{fs, io::Write, path::PathBuf};
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    io::Write,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process,
+};
 
 use nickel_lang_core::{
     error::{Error, IOError},
     eval::cache::lazy::CBNCache,
     program::Program,
     serialize::{self, ExportFormat},
+    term::RichTerm,
 };
 
 use crate::{
     cli::GlobalOptions,
     customize::CustomizeMode,
     error::{CliResult, ResultErrorExt},
-    input::{InputOptions, Prepare},
+    input::{InputOptions, Prepare, ProfileOptions},
+    watch::{self, WatchOptions},
 };
 
 #[derive(clap::Parser, Debug)]
@@ -23,33 +32,67 @@ pub struct ExportCommand {
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
+    /// Evaluate the top-level fields of the exported record on up to N threads. Each thread
+    /// prepares and evaluates its own copy of the program before evaluating its fields, which only
+    /// pays off for records with many expensive fields. Calls to `std.trace` outside of the fields
+    /// may be printed several times, and resource limits apply to each thread separately
+    #[arg(short, long, value_name = "N", conflicts_with = "profile")]
+    pub jobs: Option<NonZeroUsize>,
+
     #[command(flatten)]
     pub input: InputOptions<CustomizeMode>,
+
+    #[command(flatten)]
+    pub profile: ProfileOptions,
+
+    #[command(flatten)]
+    pub watch: WatchOptions,
 }
 
 impl ExportCommand {
     pub fn run(self, global: GlobalOptions) -> CliResult<()> {
         let mut program = self.input.prepare(&global)?;
 
-        self.export(&mut program).report_with_program(program)
+        if self.watch.enabled {
+            watch::run(program, |program| {
+                self.eval(program).and_then(|rt| self.export(rt))
+            });
+        }
+
+        self.profile.enable(&mut program);
+
+        let result = self.eval(&mut program);
+        self.profile.write(&mut program)?;
+
+        result
+            .and_then(|rt| self.export(rt))
+            .report_with_program(program)
     }
 
-    fn export(self, program: &mut Program<CBNCache>) -> Result<(), Error> {
-        let rt = program.eval_full_for_export()?;
+    fn eval(&self, program: &mut Program<CBNCache>) -> Result<RichTerm, Error> {
+        match self.jobs {
+            Some(jobs) => program.eval_full_for_export_parallel(jobs),
+            None => program.eval_full_for_export(),
+        }
+    }
 
+    fn export(&self, rt: RichTerm) -> Result<(), Error> {
         // We only add a trailing newline for JSON exports. Both YAML and TOML
         // exporters already append a trailing newline by default.
         let trailing_newline = self.format == ExportFormat::Json;
 
         serialize::validate(self.format, &rt)?;
 
-        if let Some(file) = self.output {
-            let mut file = fs::File::create(file).map_err(IOError::from)?;
-            serialize::to_writer(&mut file, self.format, &rt)?;
+        if let Some(path) = &self.output {
+            write_atomically(path, |file| {
+                serialize::to_writer(&mut *file, self.format, &rt)?;
 
-            if trailing_newline {
-                writeln!(file).map_err(IOError::from)?;
-            }
+                if trailing_newline {
+                    writeln!(file).map_err(IOError::from)?;
+                }
+
+                Ok(())
+            })?;
         } else {
             serialize::to_writer(std::io::stdout(), self.format, &rt)?;
 
@@ -61,3 +104,41 @@ impl ExportCommand {
         Ok(())
     }
 }
+
+/// Write a file by writing to a temporary file next to it first and then moving it into place,
+/// such that readers never see a partial write. The file keeps its permissions if it already
+/// exists, and is otherwise created with the default permissions of new files.
+fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut fs::File) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let name = path.file_name().unwrap_or(OsStr::new("export"));
+    let mut staged_name = OsString::from(".");
+    staged_name.push(name);
+    staged_name.push(format!(".{}.tmp", process::id()));
+    let staged = path.with_file_name(staged_name);
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&staged)
+        .map_err(IOError::from)?;
+
+    let result = (|| {
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())
+                .map_err(IOError::from)?;
+        }
+
+        write(&mut file)?;
+        drop(file);
+        fs::rename(&staged, path).map_err(IOError::from)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&staged);
+    }
+
+    result
+}
@@ -1,6 +1,11 @@
-use std::path::PathBuf;
+use std::{fs, path::PathBuf, time::Duration};
 
-use nickel_lang_core::{eval::cache::lazy::CBNCache, program::Program};
+use directories::BaseDirs;
+use nickel_lang_core::{
+    eval::{cache::lazy::CBNCache, limits::EvalLimits},
+    persistent_cache::PersistentCache,
+    program::Program,
+};
 
 use crate::{cli::GlobalOptions, customize::Customize, error::CliResult};
 
@@ -14,10 +19,133 @@ pub struct InputOptions<Customize: clap::Args> {
     #[arg(long, global = true)]
     pub nostdlib: bool,
 
+    /// Report contract violations together with the chain of contract applications which led to
+    /// them. Makes contract checking slower
+    #[arg(long)]
+    pub explain_blame: bool,
+
+    #[command(flatten)]
+    pub limits: LimitOptions,
+
+    #[command(flatten)]
+    pub cache: CacheOptions,
+
     #[command(flatten)]
     pub customize_mode: Customize,
 }
 
+/// The on-disk cache of the prepared (parsed, typechecked and transformed) standard library and
+/// imported files, shared between invocations. Disabled by default.
+#[derive(clap::Parser, Debug)]
+pub struct CacheOptions {
+    /// Cache the prepared standard library and imported files on disk, to speed up later
+    /// invocations
+    #[arg(long)]
+    pub cache: bool,
+
+    /// The directory of the cache. Defaults to a `nickel` directory in the user cache directory
+    #[arg(long, value_name = "DIR", requires = "cache")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// The maximum size of the cache in megabytes, above which the least recently used entries
+    /// are removed. Defaults to 256
+    #[arg(long, value_name = "MEGABYTES", requires = "cache")]
+    pub cache_max_size: Option<u64>,
+}
+
+impl CacheOptions {
+    pub fn to_persistent_cache(&self) -> Option<PersistentCache> {
+        if !self.cache {
+            return None;
+        }
+
+        let dir = self
+            .cache_dir
+            .clone()
+            .or_else(|| BaseDirs::new().map(|dirs| dirs.cache_dir().join("nickel")))?;
+        let cache = PersistentCache::new(dir);
+
+        Some(match self.cache_max_size {
+            Some(megabytes) => cache.with_max_size(megabytes.saturating_mul(1024 * 1024)),
+            None => cache,
+        })
+    }
+}
+
+/// Resource limits of the evaluation, to abort evaluations which take too long or use too much
+/// memory. Unlimited by default.
+#[derive(clap::Parser, Debug)]
+pub struct LimitOptions {
+    /// Abort the evaluation after the given number of evaluation steps
+    #[arg(long, value_name = "STEPS")]
+    pub max_steps: Option<u64>,
+
+    /// Abort the evaluation when the size of the stack of the interpreter exceeds the given depth
+    #[arg(long, value_name = "DEPTH")]
+    pub max_stack_depth: Option<usize>,
+
+    /// Abort the evaluation when it allocates more than the given number of elements in the
+    /// evaluation cache, as a rough memory cap
+    #[arg(long, value_name = "ELEMENTS")]
+    pub max_cache_size: Option<usize>,
+
+    /// Abort the evaluation after the given number of seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    Duration::try_from_secs_f64(secs).map_err(|err| format!("{err}"))
+}
+
+impl LimitOptions {
+    pub fn to_limits(&self) -> EvalLimits {
+        EvalLimits {
+            max_steps: self.max_steps,
+            max_stack_depth: self.max_stack_depth,
+            max_cache_size: self.max_cache_size,
+            timeout: self.timeout,
+        }
+    }
+}
+
+/// Profiling of the evaluation.
+#[derive(clap::Parser, Debug)]
+pub struct ProfileOptions {
+    /// Profile the evaluation and write the result to the given file, in the folded stack format
+    /// understood by flamegraph tools. A summary of the most expensive functions, contracts and
+    /// fields is printed on the standard error
+    #[arg(long, value_name = "FILE", conflicts_with = "watch")]
+    pub profile: Option<PathBuf>,
+
+    /// The number of entries of the profiling summary
+    #[arg(long, value_name = "N", default_value_t = 20, requires = "profile")]
+    pub profile_top: usize,
+}
+
+impl ProfileOptions {
+    /// Enable profiling on the program if a profile was requested.
+    pub fn enable(&self, program: &mut Program<CBNCache>) {
+        if self.profile.is_some() {
+            program.enable_profiling();
+        }
+    }
+
+    /// Write the profile collected during the evaluation of the program, if any.
+    pub fn write(&self, program: &mut Program<CBNCache>) -> CliResult<()> {
+        let (Some(path), Some(profile)) = (&self.profile, program.take_profile()) else {
+            return Ok(());
+        };
+
+        let mut file = fs::File::create(path)?;
+        profile.write_folded(&mut file, program.files())?;
+        profile.write_summary(&mut std::io::stderr(), program.files(), self.profile_top)?;
+
+        Ok(())
+    }
+}
+
 pub trait Prepare {
     fn prepare(&self, global: &GlobalOptions) -> CliResult<Program<CBNCache>>;
 }
@@ -31,6 +159,9 @@ impl<C: clap::Args + Customize> Prepare for InputOptions<C> {
         }?;
 
         program.color_opt = global.color.into();
+        program.set_limits(self.limits.to_limits());
+        program.set_explain_blame(self.explain_blame);
+        program.set_persistent_cache(self.cache.to_persistent_cache());
 
         #[cfg(debug_assertions)]
         if self.nostdlib {
@@ -18,7 +18,10 @@ mod export;
 mod input;
 mod pprint_ast;
 mod query;
+mod snapshot;
+mod test;
 mod typecheck;
+mod watch;
 
 use std::process::ExitCode;
 
@@ -39,6 +42,7 @@ fn main() -> ExitCode {
         Command::Export(export) => export.run(opts.global),
         Command::Query(query) => query.run(opts.global),
         Command::Typecheck(typecheck) => typecheck.run(opts.global),
+        Command::Test(test) => test.run(opts.global),
         Command::GenCompletions(completions) => completions.run(opts.global),
 
         #[cfg(feature = "repl")]
@@ -1,7 +1,10 @@
 use std::path::PathBuf;
 
 use directories::BaseDirs;
-use nickel_lang_core::repl::rustyline_frontend;
+use nickel_lang_core::{
+    eval::cache::CacheImpl,
+    repl::{rustyline_frontend, script, InitError, Repl, ReplImpl},
+};
 
 use crate::{cli::GlobalOptions, error::CliResult};
 
@@ -9,10 +12,24 @@ use crate::{cli::GlobalOptions, error::CliResult};
 pub struct ReplCommand {
     #[arg(long)]
     pub history_file: Option<PathBuf>,
+
+    /// Run the REPL inputs of the given file non-interactively instead of starting an interactive
+    /// session, and print the transcript of the session
+    #[arg(long, value_name = "FILE", conflicts_with = "history_file")]
+    pub script: Option<PathBuf>,
+
+    /// Interpret the script as a transcript of a REPL session, and check that the output of each
+    /// input matches the output recorded in the transcript
+    #[arg(long, requires = "script")]
+    pub transcript: bool,
 }
 
 impl ReplCommand {
     pub fn run(self, global: GlobalOptions) -> CliResult<()> {
+        if let Some(path) = self.script {
+            return run_script(path, self.transcript, global);
+        }
+
         let histfile = if let Some(h) = self.history_file {
             h
         } else {
@@ -24,3 +41,45 @@ impl ReplCommand {
         Ok(rustyline_frontend::repl(histfile, global.color.into())?)
     }
 }
+
+fn run_script(path: PathBuf, transcript: bool, global: GlobalOptions) -> CliResult<()> {
+    let content = std::fs::read_to_string(&path)?;
+    let mut repl = ReplImpl::<CacheImpl>::new(std::io::stderr());
+
+    if let Err(err) = repl.load_stdlib() {
+        nickel_lang_core::error::report(repl.cache_mut(), err, global.color.into());
+        return Err(InitError::Stdlib.into());
+    }
+
+    let failures = if transcript {
+        let mismatches = script::check_transcript(&mut repl, &content);
+
+        for script::Mismatch { step, actual } in &mismatches {
+            eprintln!(
+                "{}:{}: unexpected output for input `{}`",
+                path.display(),
+                step.line,
+                step.input
+            );
+            eprintln!(
+                "expected:\n{}",
+                step.expected.as_deref().unwrap_or_default()
+            );
+            eprintln!("actual:\n{}\n", actual.as_str());
+        }
+
+        mismatches.len()
+    } else {
+        script::run_script(&mut repl, &content, &mut std::io::stdout())?
+    };
+
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(crate::error::Error::ReplScript {
+            path,
+            failures,
+            transcript,
+        })
+    }
+}
@@ -0,0 +1,134 @@
+//! Snapshot testing of exported configurations, used by `nickel test --snapshot`.
+//!
+//! A snapshot is the canonical JSON export of a Nickel file, or of one of its fields, stored next
+//! to the file: the snapshot of `config.ncl` is `config.snap.json`, and the snapshot of the field
+//! `server.port` of `config.ncl` is `config.server.port.snap.json`.
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// The extension of snapshot files.
+const SNAPSHOT_EXTENSION: &str = "snap.json";
+
+/// Return the path of the snapshot of `field` (or of the whole file if `field` is `None`) of the
+/// Nickel file `file`.
+pub fn snapshot_path(file: &Path, field: Option<&str>) -> PathBuf {
+    let stem = file
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let name = match field {
+        Some(field) => format!("{stem}.{field}.{SNAPSHOT_EXTENSION}"),
+        None => format!("{stem}.{SNAPSHOT_EXTENSION}"),
+    };
+
+    file.with_file_name(name)
+}
+
+/// Compare an exported value with its snapshot. Return `None` if they are equal, or a
+/// human-readable description of the differences otherwise, one difference per line.
+pub fn diff(snapshot: &str, actual: &str) -> Option<String> {
+    if snapshot.trim_end() == actual.trim_end() {
+        return None;
+    }
+
+    let (Ok(snapshot), Ok(actual)) = (
+        serde_json::from_str::<Value>(snapshot),
+        serde_json::from_str::<Value>(actual),
+    ) else {
+        return Some(String::from("the snapshot isn't valid JSON"));
+    };
+
+    let mut changes = Vec::new();
+    diff_values(&mut String::new(), &snapshot, &actual, &mut changes);
+
+    // The serialized values can differ even if the values are equal, for example if the snapshot
+    // was edited by hand.
+    (!changes.is_empty()).then(|| changes.join("\n"))
+}
+
+fn diff_values(path: &mut String, snapshot: &Value, actual: &Value, changes: &mut Vec<String>) {
+    match (snapshot, actual) {
+        (Value::Object(snapshot), Value::Object(actual)) => {
+            let mut keys: Vec<&String> = snapshot.keys().chain(actual.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let len = path.len();
+
+                if !path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(key);
+
+                match (snapshot.get(key), actual.get(key)) {
+                    (Some(old), Some(new)) => diff_values(path, old, new, changes),
+                    (Some(old), None) => changes.push(format!("- {path}: {old}")),
+                    (None, Some(new)) => changes.push(format!("+ {path}: {new}")),
+                    (None, None) => unreachable!(),
+                }
+
+                path.truncate(len);
+            }
+        }
+        (Value::Array(snapshot), Value::Array(actual)) => {
+            for index in 0..snapshot.len().max(actual.len()) {
+                let len = path.len();
+                path.push_str(&format!("[{index}]"));
+
+                match (snapshot.get(index), actual.get(index)) {
+                    (Some(old), Some(new)) => diff_values(path, old, new, changes),
+                    (Some(old), None) => changes.push(format!("- {path}: {old}")),
+                    (None, Some(new)) => changes.push(format!("+ {path}: {new}")),
+                    (None, None) => unreachable!(),
+                }
+
+                path.truncate(len);
+            }
+        }
+        (old, new) if old != new => {
+            let path = if path.is_empty() {
+                "(root)"
+            } else {
+                path.as_str()
+            };
+            changes.push(format!("~ {path}: {old} -> {new}"))
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_diff() {
+        assert_eq!(diff("{\"a\": 1}\n", "{\"a\": 1}"), None);
+        assert_eq!(diff("{\"a\":1}", "{\n  \"a\": 1\n}"), None);
+        assert_eq!(
+            diff(
+                r#"{"a": {"b": 1, "c": [1, 2]}, "d": true}"#,
+                r#"{"a": {"b": 2, "c": [1]}, "e": "new"}"#
+            )
+            .unwrap(),
+            "~ a.b: 1 -> 2\n- a.c[1]: 2\n- d: true\n+ e: \"new\""
+        );
+        assert_eq!(diff("1", "2").unwrap(), "~ (root): 1 -> 2");
+    }
+
+    #[test]
+    fn snapshot_paths() {
+        let file = Path::new("configs/server.ncl");
+        assert_eq!(
+            snapshot_path(file, None),
+            PathBuf::from("configs/server.snap.json")
+        );
+        assert_eq!(
+            snapshot_path(file, Some("http.port")),
+            PathBuf::from("configs/server.http.port.snap.json")
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! The `nickel test` subcommand.
+//!
+//! A test file is a Nickel file whose name ends with `_test.ncl` and which evaluates to a record.
+//! Each field of the `tests` field of this record is a test, which passes if it evaluates fully
+//! without error and to a value other than `false`. Each field of the `should_fail` field is an
+//! expected failure, which passes if its evaluation fails with an evaluation error, for example
+//! because of a contract violation. Errors that prevent the file from being evaluated at all, such
+//! as parse or type errors, make the expected failures fail as well:
+//!
+//! ```nickel
+//! let Port = std.contract.from_predicate (fun x => x >= 0 && x < 65536) in
+//! {
+//!   tests = {
+//!     addition = 1 + 1 == 2,
+//!     valid_port = 8080 | Port,
+//!   },
+//!   should_fail = {
+//!     invalid_port = 70000 | Port,
+//!   },
+//! }
+//! ```
+//!
+//! Each test is evaluated in isolation.
+//!
+//! With `--snapshot`, the exported value of each file (or of the fields selected with `--field`)
+//! is compared to a snapshot stored next to the file instead (see [crate::snapshot]).
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use nickel_lang_core::{
+    eval::cache::lazy::CBNCache,
+    identifier::LocIdent,
+    program::{FieldPath, Program},
+    serialize::{self, ExportFormat},
+    term::Term,
+};
+
+use crate::{
+    cli::GlobalOptions,
+    error::{CliResult, Error},
+    input::{CacheOptions, LimitOptions},
+    snapshot,
+};
+
+/// The field of a test file containing the tests which must succeed.
+const TESTS_FIELD: &str = "tests";
+/// The field of a test file containing the tests which must fail.
+const SHOULD_FAIL_FIELD: &str = "should_fail";
+/// The suffix of the test files discovered in directories.
+const TEST_FILE_SUFFIX: &str = "_test.ncl";
+
+#[derive(clap::Parser, Debug)]
+pub struct TestCommand {
+    /// Test files, or directories to search recursively for test files (files ending with
+    /// `_test.ncl`). Default to the current directory
+    pub paths: Vec<PathBuf>,
+
+    /// Only run the tests whose name contains the given string
+    #[arg(long, value_name = "PATTERN")]
+    pub filter: Option<String>,
+
+    /// Write a report of the test run in the JUnit XML format to the given file
+    #[arg(long, value_name = "FILE")]
+    pub junit: Option<PathBuf>,
+
+    /// Run the examples of the documentation instead of the tests. An example is written inside a
+    /// `nickel` code block as `<expression> => <expected result>`, where the expected result is
+    /// either a value or `error` if the evaluation of the expression must fail. Directories are
+    /// searched for all Nickel files
+    #[cfg(feature = "doc")]
+    #[arg(long, conflicts_with = "snapshot")]
+    pub doc: bool,
+
+    /// Compare the exported value of the Nickel files to their snapshot instead of running the
+    /// tests. Directories are searched for Nickel files having a snapshot
+    #[arg(long)]
+    pub snapshot: bool,
+
+    /// A field path to export and compare to its snapshot, instead of the whole file. Can be
+    /// repeated
+    #[arg(long = "field", value_name = "FIELD_PATH", requires = "snapshot")]
+    pub fields: Vec<String>,
+
+    /// Write the exported values to the snapshot files instead of comparing them, creating the
+    /// snapshots if needed
+    #[arg(long, requires = "snapshot")]
+    pub update: bool,
+
+    #[command(flatten)]
+    pub limits: LimitOptions,
+
+    #[command(flatten)]
+    pub cache: CacheOptions,
+}
+
+/// The result of a single test.
+struct TestCase {
+    name: String,
+    /// The plain text report of the failure, if the test failed.
+    failure: Option<String>,
+}
+
+/// The results of the tests of one file.
+struct TestSuite {
+    name: String,
+    cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    fn failures(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| case.failure.is_some())
+            .count()
+    }
+}
+
+impl TestCommand {
+    pub fn run(self, global: GlobalOptions) -> CliResult<()> {
+        let roots = if self.paths.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.paths.clone()
+        };
+
+        let mut files = Vec::new();
+        for root in &roots {
+            self.collect_files(root, &mut files)?;
+        }
+
+        let mut suites = Vec::new();
+        let mut filtered_out = 0;
+
+        for file in files {
+            let mut program: Program<CBNCache> = Program::new_from_file(&file, io::stderr())?;
+            program.color_opt = global.color.into();
+            program.set_limits(self.limits.to_limits());
+            program.set_persistent_cache(self.cache.to_persistent_cache());
+
+            println!("\nrunning tests of {}", file.display());
+            let mut suite = TestSuite {
+                name: file.display().to_string(),
+                cases: Vec::new(),
+            };
+
+            filtered_out += self.run_file(&file, &mut program, &mut suite);
+            suites.push(suite);
+        }
+
+        let total: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+        let failed: usize = suites.iter().map(TestSuite::failures).sum();
+
+        println!(
+            "\ntest result: {}. {} passed; {failed} failed; {filtered_out} filtered out",
+            if failed == 0 { "ok" } else { "FAILED" },
+            total - failed,
+        );
+
+        if let Some(junit) = &self.junit {
+            write_junit(&mut fs::File::create(junit)?, &suites)?;
+        }
+
+        if failed == 0 {
+            Ok(())
+        } else {
+            Err(Error::TestFailures { failed, total })
+        }
+    }
+
+    fn is_test_file(&self, path: &Path) -> bool {
+        let name = path.to_string_lossy();
+
+        if self.snapshot {
+            return name.ends_with(".ncl")
+                && self
+                    .snapshot_fields()
+                    .into_iter()
+                    .any(|field| snapshot::snapshot_path(path, field).exists());
+        }
+
+        #[cfg(feature = "doc")]
+        if self.doc {
+            return name.ends_with(".ncl");
+        }
+
+        name.ends_with(TEST_FILE_SUFFIX)
+    }
+
+    /// The fields to compare to a snapshot, where `None` stands for the whole file.
+    fn snapshot_fields(&self) -> Vec<Option<&str>> {
+        if self.fields.is_empty() {
+            vec![None]
+        } else {
+            self.fields
+                .iter()
+                .map(|field| Some(field.as_str()))
+                .collect()
+        }
+    }
+
+    /// Collect the test files found at `path`, sorted by name. Files given explicitly are always
+    /// test files, whatever their name is.
+    fn collect_files(&self, path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        if !path.is_dir() {
+            files.push(path.to_owned());
+            return Ok(());
+        }
+
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+
+        for entry in entries {
+            if entry.is_dir() {
+                self.collect_files(&entry, files)?;
+            } else if self.is_test_file(&entry) {
+                files.push(entry);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn matches_filter(&self, name: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|pattern| name.contains(pattern.as_str()))
+    }
+
+    /// Run the tests of a file and add the results to `suite`. Return the number of tests which
+    /// were filtered out.
+    fn run_file(
+        &self,
+        file: &Path,
+        program: &mut Program<CBNCache>,
+        suite: &mut TestSuite,
+    ) -> usize {
+        if self.snapshot {
+            return self.run_snapshots(file, program, suite);
+        }
+
+        #[cfg(feature = "doc")]
+        if self.doc {
+            return self.run_doc_examples(program, suite);
+        }
+
+        let tests = match list_tests(program) {
+            Ok(tests) => tests,
+            Err(error) => {
+                record(program, suite, String::from("<file>"), Err(error));
+                return 0;
+            }
+        };
+
+        let mut filtered_out = 0;
+
+        for (path, should_fail) in tests {
+            let name = path.to_string();
+
+            if !self.matches_filter(&name) {
+                filtered_out += 1;
+                continue;
+            }
+
+            program.field = path;
+            let result = match (program.eval_full(), should_fail) {
+                (Ok(value), false) if matches!(value.as_ref(), Term::Bool(false)) => Err(
+                    Failure::Message(String::from("the test evaluated to `false`")),
+                ),
+                (Ok(_), false) | (Err(nickel_lang_core::error::Error::EvalError(_)), true) => {
+                    Ok(())
+                }
+                (Ok(_), true) => Err(Failure::Message(String::from(
+                    "the test was expected to fail, but evaluated successfully",
+                ))),
+                // Parse, import or type errors are problems with the test file itself, which
+                // don't count as an expected failure.
+                (Err(error), _) => Err(Failure::Error(error)),
+            };
+
+            record(program, suite, name, result);
+        }
+
+        filtered_out
+    }
+
+    fn run_snapshots(
+        &self,
+        file: &Path,
+        program: &mut Program<CBNCache>,
+        suite: &mut TestSuite,
+    ) -> usize {
+        let mut filtered_out = 0;
+
+        for field in self.snapshot_fields() {
+            let name = format!("snapshot {}", field.unwrap_or("(root)"));
+
+            if !self.matches_filter(&name) {
+                filtered_out += 1;
+                continue;
+            }
+
+            let result = self.check_snapshot(file, field, program);
+            record(program, suite, name, result);
+        }
+
+        filtered_out
+    }
+
+    /// Export `field` of the program to JSON and compare it to its snapshot, or update the
+    /// snapshot in update mode.
+    fn check_snapshot(
+        &self,
+        file: &Path,
+        field: Option<&str>,
+        program: &mut Program<CBNCache>,
+    ) -> Result<(), Failure> {
+        program.field = match field {
+            Some(field) => program
+                .parse_field_path(field.to_owned())
+                .map_err(nickel_lang_core::error::Error::from)?,
+            None => FieldPath::new(),
+        };
+
+        let rt = program.eval_full_for_export()?;
+        serialize::validate(ExportFormat::Json, &rt)
+            .map_err(nickel_lang_core::error::Error::from)?;
+        let mut exported = serialize::to_string(ExportFormat::Json, &rt)
+            .map_err(nickel_lang_core::error::Error::from)?;
+        exported.push('\n');
+
+        let path = snapshot::snapshot_path(file, field);
+
+        if self.update {
+            return fs::write(&path, exported).map_err(|err| {
+                Failure::Message(format!(
+                    "couldn't write the snapshot `{}`: {err}",
+                    path.display()
+                ))
+            });
+        }
+
+        let stored = fs::read_to_string(&path).map_err(|err| {
+            Failure::Message(format!(
+                "couldn't read the snapshot `{}`: {err}\n\
+                Use `--update` to create the snapshot.",
+                path.display()
+            ))
+        })?;
+
+        match snapshot::diff(&stored, &exported) {
+            None => Ok(()),
+            Some(diff) => Err(Failure::Message(format!(
+                "the exported value differs from the snapshot `{}`:\n{diff}\n\
+                Use `--update` to accept the changes.",
+                path.display()
+            ))),
+        }
+    }
+
+    #[cfg(feature = "doc")]
+    fn run_doc_examples(&self, program: &mut Program<CBNCache>, suite: &mut TestSuite) -> usize {
+        let results = match program.test_doc() {
+            Ok(results) => results,
+            Err(error) => {
+                record(program, suite, String::from("<file>"), Err(error));
+                return 0;
+            }
+        };
+
+        let mut filtered_out = 0;
+
+        for result in results {
+            let name = format!(
+                "doc {} (line {})",
+                result.example.path.join("."),
+                result.example.line
+            );
+
+            if !self.matches_filter(&name) {
+                filtered_out += 1;
+                continue;
+            }
+
+            let result = if result.is_success() {
+                Ok(())
+            } else {
+                Err(Failure::DocExample(result))
+            };
+
+            record(program, suite, name, result);
+        }
+
+        filtered_out
+    }
+}
+
+/// The reason of a test failure.
+enum Failure {
+    Error(nickel_lang_core::error::Error),
+    #[cfg(feature = "doc")]
+    DocExample(nickel_lang_core::program::doc::DocTestResult),
+    Message(String),
+}
+
+impl From<nickel_lang_core::error::Error> for Failure {
+    fn from(error: nickel_lang_core::error::Error) -> Self {
+        Failure::Error(error)
+    }
+}
+
+/// Print the result of a test, report the failure if any, and add it to the test suite.
+fn record(
+    program: &mut Program<CBNCache>,
+    suite: &mut TestSuite,
+    name: String,
+    result: Result<(), impl Into<Failure>>,
+) {
+    println!(
+        "test {name} ... {}",
+        if result.is_ok() { "ok" } else { "FAILED" }
+    );
+
+    let failure = result.err().map(|failure| match failure.into() {
+        Failure::Error(error) => {
+            program.report(error.clone());
+            program.report_as_plain_str(error)
+        }
+        #[cfg(feature = "doc")]
+        Failure::DocExample(result) => {
+            program.report(result.clone());
+            program.report_as_plain_str(result)
+        }
+        Failure::Message(msg) => {
+            eprintln!("error: {msg}");
+            format!("error: {msg}")
+        }
+    });
+
+    suite.cases.push(TestCase { name, failure });
+}
+
+/// List the tests of a test file, together with a boolean indicating if the test is expected to
+/// fail.
+fn list_tests(
+    program: &mut Program<CBNCache>,
+) -> Result<Vec<(FieldPath, bool)>, nickel_lang_core::error::Error> {
+    let mut tests = Vec::new();
+
+    program.field = FieldPath::new();
+    let root = record_fields(program)?;
+
+    for (group, should_fail) in [(TESTS_FIELD, false), (SHOULD_FAIL_FIELD, true)] {
+        if !root.iter().any(|id| id.label() == group) {
+            continue;
+        }
+
+        program.field = FieldPath(vec![LocIdent::from(group)]);
+        tests.extend(
+            record_fields(program)?
+                .into_iter()
+                .map(|name| (FieldPath(vec![LocIdent::from(group), name]), should_fail)),
+        );
+    }
+
+    Ok(tests)
+}
+
+/// Return the fields of the record at `program.field`, in definition order, or an empty list if
+/// the value isn't a record.
+fn record_fields(
+    program: &mut Program<CBNCache>,
+) -> Result<Vec<LocIdent>, nickel_lang_core::error::Error> {
+    let field = program.query()?;
+
+    Ok(match field.value.as_ref().map(|value| value.as_ref()) {
+        Some(Term::Record(data)) | Some(Term::RecRecord(data, ..)) => {
+            data.fields.keys().copied().collect()
+        }
+        _ => Vec::new(),
+    })
+}
+
+/// Write the results of a test run in the JUnit XML format.
+fn write_junit(out: &mut impl Write, suites: &[TestSuite]) -> io::Result<()> {
+    let total: usize = suites.iter().map(|suite| suite.cases.len()).sum();
+    let failed: usize = suites.iter().map(TestSuite::failures).sum();
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuites name="nickel test" tests="{total}" failures="{failed}">"#
+    )?;
+
+    for suite in suites {
+        let suite_name = xml_escape(&suite.name);
+
+        writeln!(
+            out,
+            r#"  <testsuite name="{suite_name}" tests="{}" failures="{}">"#,
+            suite.cases.len(),
+            suite.failures()
+        )?;
+
+        for case in &suite.cases {
+            let case_name = xml_escape(&case.name);
+
+            match &case.failure {
+                None => writeln!(
+                    out,
+                    r#"    <testcase name="{case_name}" classname="{suite_name}"/>"#
+                )?,
+                Some(report) => {
+                    let message = xml_escape(report.lines().next().unwrap_or_default());
+                    writeln!(
+                        out,
+                        r#"    <testcase name="{case_name}" classname="{suite_name}">"#
+                    )?;
+                    writeln!(
+                        out,
+                        r#"      <failure message="{message}">{}</failure>"#,
+                        xml_escape(report)
+                    )?;
+                    writeln!(out, "    </testcase>")?;
+                }
+            }
+        }
+
+        writeln!(out, "  </testsuite>")?;
+    }
+
+    writeln!(out, "</testsuites>")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
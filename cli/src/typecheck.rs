@@ -1,19 +1,30 @@
+use nickel_lang_core::program::Program;
+
 use crate::{
     cli::GlobalOptions,
     customize::NoCustomizeMode,
     error::{CliResult, ResultErrorExt},
     input::{InputOptions, Prepare},
+    watch::{self, WatchOptions},
 };
 
 #[derive(clap::Parser, Debug)]
 pub struct TypecheckCommand {
     #[command(flatten)]
     inputs: InputOptions<NoCustomizeMode>,
+
+    #[command(flatten)]
+    watch: WatchOptions,
 }
 
 impl TypecheckCommand {
     pub fn run(self, global: GlobalOptions) -> CliResult<()> {
         let mut program = self.inputs.prepare(&global)?;
+
+        if self.watch.enabled {
+            watch::run(program, Program::typecheck);
+        }
+
         program.typecheck().report_with_program(program)
     }
 }
@@ -0,0 +1,62 @@
+//! Watch mode: run a command again each time a source file of the program is modified.
+use std::{
+    io::{stderr, IsTerminal},
+    thread,
+    time::Duration,
+};
+
+use nickel_lang_core::{error::Error, eval::cache::lazy::CBNCache, program::Program};
+
+/// The delay between two checks for modifications of the source files.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Once a modification has been detected, the delay without further modifications to wait for
+/// before running the command again. Editors and tools often write several files in a row, or a
+/// single file in several steps.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(clap::Parser, Debug)]
+pub struct WatchOptions {
+    /// Run again each time the program or one of its imports is modified, until interrupted. Not
+    /// available when reading the program from the standard input
+    #[arg(id = "watch", long = "watch", requires = "files")]
+    pub enabled: bool,
+}
+
+/// Run `command` on the program, and run it again each time a source file of the program is
+/// modified, until interrupted. Errors are reported, and don't stop the watch.
+///
+/// Only the modified files and the files importing them are processed again (see
+/// [Program::refresh]).
+pub fn run(
+    mut program: Program<CBNCache>,
+    mut command: impl FnMut(&mut Program<CBNCache>) -> Result<(), Error>,
+) -> ! {
+    loop {
+        if let Err(error) = command(&mut program) {
+            program.report(error);
+        }
+
+        wait_for_changes(&mut program);
+
+        // Clear the diagnostics and the outputs of the previous run.
+        if stderr().is_terminal() {
+            eprint!("\x1b[2J\x1b[H");
+        }
+    }
+}
+
+/// Block until a source file of the program is modified, and reload the modified files.
+fn wait_for_changes(program: &mut Program<CBNCache>) {
+    while !program.refresh() {
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    loop {
+        thread::sleep(DEBOUNCE_DELAY);
+
+        if !program.refresh() {
+            break;
+        }
+    }
+}
@@ -35,6 +35,41 @@ fn export_creates_output_files() {
     test_creates_output_files(&["export"]);
 }
 
+#[cfg(unix)]
+#[test]
+fn export_keeps_output_permissions() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let nickel_bin = env!("CARGO_BIN_EXE_nickel");
+    let dir = tempdir().expect("should be able to make a temporary directory");
+    let output = dir.path().join("output.json");
+    fs::write(&output, "old").expect("should be able to write the output file");
+    fs::set_permissions(&output, fs::Permissions::from_mode(0o640))
+        .expect("should be able to set permissions");
+
+    let mut nickel = Command::new(nickel_bin)
+        .args(["export", "-o"])
+        .arg(&output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("Nickel should be runnable");
+    let mut stdin = nickel
+        .stdin
+        .take()
+        .expect("couldn't retrieve stdin handle to Nickel");
+    stdin
+        .write_all(b"{foo=1}")
+        .expect("writing into Nickel stdin should work");
+    drop(stdin);
+
+    assert!(nickel.wait().expect("Nickel should exit").success());
+    let metadata = fs::metadata(&output).expect("the output file should exist");
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+    assert_eq!(fs::read_to_string(&output).unwrap(), "{\n  \"foo\": 1\n}\n");
+    // The staged export has been moved into place.
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
 #[test]
 fn doc_creates_output_files() {
     test_creates_output_files(&["doc", "--format", "json"]);
@@ -73,3 +108,228 @@ fn automatic_color_on_non_tty() {
         );
     }
 }
+
+#[cfg(feature = "repl")]
+fn run_repl_script(content: &str, extra_args: &[&str]) -> std::process::Output {
+    let nickel_bin = env!("CARGO_BIN_EXE_nickel");
+    let script = tempdir()
+        .expect("should be able to make a temporary directory")
+        .into_path()
+        .join("session.txt");
+    std::fs::write(&script, content).expect("should be able to write the REPL script");
+
+    Command::new(nickel_bin)
+        .args(["repl", "--script"])
+        .arg(&script)
+        .args(extra_args)
+        .output()
+        .expect("Nickel should be runnable")
+}
+
+#[cfg(feature = "repl")]
+#[test]
+fn repl_script_prints_transcript() {
+    let output = run_repl_script("let x = {\n  foo = 1,\n}\n\nx.foo + 1\n", &[]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).expect("The result of Nickel should be valid utf8"),
+        "> let x = {\n  foo = 1,\n  }\n\n> x.foo + 1\n2\n\n"
+    );
+}
+
+#[cfg(feature = "repl")]
+#[test]
+fn repl_transcript_check() {
+    let transcript =
+        "> let x = 1\n\n> x + 1\n2\n\n> x +\n  \"a\"\nerror: dynamic type error\n[...]\n";
+    assert!(run_repl_script(transcript, &["--transcript"])
+        .status
+        .success());
+
+    let wrong_transcript = "> let x = 1\n\n> x + 1\n3\n";
+    assert!(!run_repl_script(wrong_transcript, &["--transcript"])
+        .status
+        .success());
+}
+
+fn run_tests(files: &[(&str, &str)], extra_args: &[&str]) -> std::process::Output {
+    let nickel_bin = env!("CARGO_BIN_EXE_nickel");
+    let dir = tempdir()
+        .expect("should be able to make a temporary directory")
+        .into_path();
+
+    for (name, content) in files {
+        std::fs::write(dir.join(name), content).expect("should be able to write the test file");
+    }
+
+    Command::new(nickel_bin)
+        .arg("test")
+        .arg(&dir)
+        .args(extra_args)
+        .output()
+        .expect("Nickel should be runnable")
+}
+
+#[test]
+fn test_runner() {
+    let tests = r#"
+        let Port = std.contract.from_predicate (fun x => std.is_number x && x < 65536) in
+        {
+          tests = {
+            addition = 1 + 1 == 2,
+            valid_port = 8080 | Port,
+          },
+          should_fail = {
+            invalid_port = 70000 | Port,
+          },
+        }
+    "#;
+    let output = run_tests(
+        &[("port_test.ncl", tests), ("not_a_test.ncl", "false")],
+        &[],
+    );
+    assert!(output.status.success());
+    let stdout =
+        String::from_utf8(output.stdout).expect("The result of Nickel should be valid utf8");
+    assert!(stdout.contains("test tests.valid_port ... ok"));
+    assert!(stdout.contains("3 passed; 0 failed; 0 filtered out"));
+
+    let failing = "{ tests = { wrong = 1 + 1 == 3, right = true }, should_fail = { fine = 1 } }";
+    let output = run_tests(&[("failing_test.ncl", failing)], &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .expect("The result of Nickel should be valid utf8")
+        .contains("1 passed; 2 failed; 0 filtered out"));
+
+    let output = run_tests(&[("failing_test.ncl", failing)], &["--filter", "right"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_runner_junit() {
+    let report = tempdir()
+        .expect("should be able to make a temporary directory")
+        .into_path()
+        .join("report.xml");
+    let output = run_tests(
+        &[("a_test.ncl", "{ tests = { ok = true, ko = 1 + \"a\" } }")],
+        &["--junit", report.to_str().unwrap()],
+    );
+    assert!(!output.status.success());
+
+    let report = std::fs::read_to_string(report).expect("the JUnit report should exist");
+    assert!(report.contains(r#"<testsuites name="nickel test" tests="2" failures="1">"#));
+    assert!(report.contains(r#"<testcase name="tests.ko""#));
+    assert!(report.contains("<failure message=\"error: dynamic type error\">"));
+}
+
+#[cfg(feature = "doc")]
+#[test]
+fn doc_examples() {
+    let program =
+        "{ inc | doc \"```nickel\\n1 + 1 => 2\\n[] |> std.array.first => error\\n```\" = 0 }";
+    let output = run_tests(&[("inc.ncl", program)], &["--doc"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout)
+        .expect("The result of Nickel should be valid utf8")
+        .contains("2 passed; 0 failed"));
+
+    let output = run_tests(
+        &[(
+            "inc.ncl",
+            "{ inc | doc \"```nickel\\n1 + 1 => 3\\n```\" = 0 }",
+        )],
+        &["--doc"],
+    );
+    assert!(!output.status.success());
+}
+
+#[test]
+fn snapshot_tests() {
+    let nickel_bin = env!("CARGO_BIN_EXE_nickel");
+    let dir = tempdir()
+        .expect("should be able to make a temporary directory")
+        .into_path();
+    let config = dir.join("config.ncl");
+    let run = |args: &[&str]| {
+        Command::new(nickel_bin)
+            .args(["test", "--snapshot"])
+            .args(args)
+            .output()
+            .expect("Nickel should be runnable")
+    };
+
+    std::fs::write(&config, "{ port = 80, host = \"a\" }").unwrap();
+    // There's no snapshot yet
+    assert!(!run(&[config.to_str().unwrap()]).status.success());
+    assert!(run(&["--update", config.to_str().unwrap()])
+        .status
+        .success());
+    assert!(dir.join("config.snap.json").exists());
+    // The file is discovered because it has a snapshot
+    assert!(run(&[dir.to_str().unwrap()]).status.success());
+
+    std::fs::write(&config, "{ port = 8080, host = \"a\" }").unwrap();
+    let output = run(&[dir.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .expect("The error of Nickel should be valid utf8")
+        .contains("~ port: 80 -> 8080"));
+}
+
+#[test]
+fn eval_step_limit() {
+    let nickel_bin = env!("CARGO_BIN_EXE_nickel");
+    let mut nickel = Command::new(nickel_bin)
+        .args(["eval", "--max-steps", "1000"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Nickel should be runnable");
+    let mut stdin = nickel
+        .stdin
+        .take()
+        .expect("couldn't retrieve stdin handle to Nickel");
+    stdin
+        .write_all(b"let rec f = fun x => f (x + 1) in f 0")
+        .expect("writing into Nickel stdin should work");
+    drop(stdin);
+
+    let output = nickel
+        .wait_with_output()
+        .expect("couldn't retrieve stderr handle to Nickel");
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .expect("The error of Nickel should be valid utf8")
+        .contains("evaluation exceeded the limit of 1000 steps"));
+}
+
+#[test]
+fn eval_profile() {
+    let dir = tempdir().expect("creating a temporary directory should work");
+    let input = dir.path().join("input.ncl");
+    let folded = dir.path().join("profile.folded");
+    std::fs::write(
+        &input,
+        "let rec sum = fun n => if n == 0 then 0 else n + sum (n - 1) in { total = sum 100 }",
+    )
+    .expect("writing the input file should work");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nickel"))
+        .args(["export", "--profile"])
+        .arg(&folded)
+        .arg(&input)
+        .output()
+        .expect("Nickel should be runnable");
+    assert!(output.status.success());
+
+    let summary = String::from_utf8(output.stderr).expect("the summary should be valid utf8");
+    assert!(summary.contains("self steps"));
+    assert!(summary.contains("sum ("));
+
+    let folded = std::fs::read_to_string(folded).expect("the profile should have been written");
+    assert!(folded.lines().all(|line| line.starts_with("<main>")));
+    assert!(folded
+        .lines()
+        .any(|line| line.contains(";total (") && line.contains(";sum (")));
+}
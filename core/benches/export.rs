@@ -0,0 +1,53 @@
+use std::{io::Cursor, num::NonZeroUsize};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use pprof::criterion::{Output, PProfProfiler};
+
+use nickel_lang_core::{eval::cache::CacheImpl, program::Program};
+
+/// A record with `fields` independent fields, each computing a Fibonacci number around `n`.
+fn source(fields: usize, n: usize) -> String {
+    let fields: String = (0..fields)
+        .map(|i| format!("  field_{i} = fib ({n} + {}),\n", i % 2))
+        .collect();
+
+    format!(
+        "let rec fib = fun n => if n < 2 then n else fib (n - 1) + fib (n - 2) in\n{{\n{fields}}}"
+    )
+}
+
+/// Export `source`, sequentially if `jobs` is one.
+fn export(source: &str, jobs: NonZeroUsize) {
+    let mut program: Program<CacheImpl> =
+        Program::new_from_source(Cursor::new(source), "<bench>", std::io::sink()).unwrap();
+
+    if jobs.get() == 1 {
+        program.eval_full_for_export().unwrap();
+    } else {
+        program.eval_full_for_export_parallel(jobs).unwrap();
+    }
+}
+
+pub fn parallel_export(c: &mut Criterion) {
+    for (name, fields, n) in [("expensive fields", 200, 15), ("cheap fields", 200, 2)] {
+        let source = source(fields, n);
+        let mut group = c.benchmark_group(format!("export {name}"));
+        group.sample_size(10);
+
+        for jobs in [1, 2, 4, 8] {
+            let jobs = NonZeroUsize::new(jobs).unwrap();
+            group.bench_with_input(BenchmarkId::new("jobs", jobs), &jobs, |b, jobs| {
+                b.iter(|| export(&source, *jobs))
+            });
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(
+name = benches;
+config = Criterion::default().with_profiler(PProfProfiler::new(100, Output::Flamegraph(None)));
+targets = parallel_export
+);
+criterion_main!(benches);
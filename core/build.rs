@@ -1,9 +1,27 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::Path,
+};
+
 fn main() {
     lalrpop::Configuration::new()
         .use_cargo_dir_conventions()
         .process_file("src/parser/grammar.lalrpop")
         .unwrap();
 
+    // The entries of the persistent cache are encoded ASTs, produced by the program
+    // transformations of this very build. Any change to the sources may change the AST or the
+    // transformations, so the cache is keyed by a fingerprint of the sources.
+    let mut hasher = DefaultHasher::new();
+    hash_dir(Path::new("src"), &mut hasher);
+    println!(
+        "cargo:rustc-env=NICKEL_SOURCE_FINGERPRINT={:016x}",
+        hasher.finish()
+    );
+    println!("cargo:rerun-if-changed=src");
+
     #[cfg(feature = "nix-experimental")]
     {
         use cxx_build::CFG;
@@ -29,3 +47,23 @@ fn main() {
         println!("cargo:rerun-if-changed=src/nix_ffi/cpp/nix.hh");
     }
 }
+
+/// Hash the paths and the content of the files of a directory, recursively, in a deterministic
+/// order.
+fn hash_dir(dir: &Path, hasher: &mut DefaultHasher) {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        path.hash(hasher);
+
+        if path.is_dir() {
+            hash_dir(&path, hasher);
+        } else {
+            fs::read(&path).unwrap().hash(hasher);
+        }
+    }
+}
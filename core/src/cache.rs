@@ -6,6 +6,9 @@ use crate::eval::Closure;
 #[cfg(feature = "nix-experimental")]
 use crate::nix_ffi;
 use crate::parser::{lexer::Lexer, ErrorTolerantParser};
+use crate::persistent_cache::{
+    CacheKey, ContentHash, Dependency, EntryHeader, FileRef, PersistentCache,
+};
 use crate::position::TermPos;
 use crate::program::FieldPath;
 use crate::stdlib::{self as nickel_stdlib, StdlibModule};
@@ -90,6 +93,14 @@ pub struct Cache {
     wildcards: HashMap<FileId, Wildcards>,
     /// Whether processing should try to continue even in case of errors. Needed by the NLS.
     error_tolerance: ErrorTolerance,
+    /// The on-disk cache of prepared sources, shared between invocations, if enabled.
+    persistent_cache: Option<PersistentCache>,
+    /// The sources which have been restored from or stored in the persistent cache (or which
+    /// couldn't be stored), and thus don't need to be stored again.
+    persisted: HashSet<FileId>,
+    /// The files which were deleted since they were loaded, together with their id and the files
+    /// which were importing them, directly or not, at the time of deletion. See [Self::refresh].
+    deleted: HashMap<PathBuf, (FileId, HashSet<FileId>)>,
 
     #[cfg(debug_assertions)]
     /// Skip loading the stdlib, used for debugging purpose
@@ -337,6 +348,9 @@ impl Cache {
             rev_imports: HashMap::new(),
             stdlib_ids: None,
             error_tolerance,
+            persistent_cache: None,
+            persisted: HashSet::new(),
+            deleted: HashMap::new(),
 
             #[cfg(debug_assertions)]
             skip_stdlib: false,
@@ -402,6 +416,11 @@ impl Cache {
         self.files.source(id)
     }
 
+    /// Return the name under which the source with the given id was added to the cache.
+    pub fn source_path(&self, id: FileId) -> Option<&SourcePath> {
+        self.file_paths.get(&id)
+    }
+
     /// Load a new source as a string and add it to the name-id table.
     ///
     /// Do not check if a source with the same name already exists: if it is the case, this one
@@ -445,6 +464,86 @@ impl Cache {
         }
     }
 
+    /// Reload the files which have been modified on disk since they were loaded, and invalidate
+    /// the entries depending on them. Return the ids of the modified files.
+    ///
+    /// As opposed to [Self::get_or_add_file], a modified file keeps its `FileId`: its source is
+    /// updated in place, and the term entries of the file and of the files transitively importing
+    /// it are removed, such that the next call to [Self::prepare] parses, typechecks and
+    /// transforms them again. The entries of the other files, such as the standard library or
+    /// unchanged imports, are reused as they are.
+    ///
+    /// A file which can't be read anymore is removed from the name-id table, such that importing
+    /// it again reports an error. If it is created again later, it is reloaded with its previous
+    /// id, and the files which were importing it are invalidated again.
+    pub fn refresh(&mut self) -> Vec<FileId> {
+        let mut stale: Vec<(PathBuf, FileId)> = self
+            .file_ids
+            .iter()
+            .filter_map(|(name, entry)| match (name, entry.source) {
+                (SourcePath::Path(path), SourceKind::Filesystem(ts))
+                    if timestamp(path).ok() != Some(ts) =>
+                {
+                    Some((path.clone(), entry.id))
+                }
+                _ => None,
+            })
+            .collect();
+
+        stale.extend(
+            self.deleted
+                .iter()
+                .filter(|(path, _)| path.exists())
+                .map(|(path, (id, _))| (path.clone(), *id)),
+        );
+
+        let mut invalidated = HashSet::new();
+
+        for (path, id) in stale.iter().cloned() {
+            invalidated.insert(id);
+            invalidated.extend(self.get_rev_imports_transitive(id));
+
+            if let Some((_, importers)) = self.deleted.remove(&path) {
+                invalidated.extend(importers);
+            }
+
+            let reloaded = timestamp(&path).and_then(|ts| Ok((ts, fs::read_to_string(&path)?)));
+
+            match reloaded {
+                Ok((timestamp, contents)) => {
+                    self.files.update(id, contents);
+                    self.file_ids.insert(
+                        SourcePath::Path(path),
+                        NameIdEntry {
+                            id,
+                            source: SourceKind::Filesystem(timestamp),
+                        },
+                    );
+                }
+                Err(_) => {
+                    self.file_ids.remove(&SourcePath::Path(path.clone()));
+                    self.deleted
+                        .insert(path, (id, self.get_rev_imports_transitive(id)));
+                }
+            }
+        }
+
+        for id in invalidated {
+            self.terms.remove(&id);
+            self.wildcards.remove(&id);
+            self.persisted.remove(&id);
+
+            // The imports are recorded again when the entry is parsed and its imports resolved.
+            for import in self.imports.remove(&id).into_iter().flatten() {
+                if let Some(importers) = self.rev_imports.get_mut(&import) {
+                    importers.remove(&id);
+                }
+            }
+        }
+
+        stale.into_iter().map(|(_, id)| id).collect()
+    }
+
     /// Parse a source and populate the corresponding entry in the cache, or do nothing if the
     /// entry has already been parsed. This function is error tolerant: parts of the source which
     /// result in parse errors are parsed as [`crate::term::Term::ParseError`] and the
@@ -887,6 +986,10 @@ impl Cache {
     ) -> Result<CacheOp<()>, Error> {
         let mut result = CacheOp::Cached(());
 
+        if self.entry_state(file_id).is_none() && self.restore(file_id) {
+            return Ok(CacheOp::Done(()));
+        }
+
         if let CacheOp::Done(_) = self.parse(file_id)? {
             result = CacheOp::Done(());
         }
@@ -922,6 +1025,8 @@ impl Cache {
             result = CacheOp::Done(());
         };
 
+        self.persist(file_id);
+
         Ok(result)
     }
 
@@ -953,6 +1058,261 @@ impl Cache {
         Ok((term, pending))
     }
 
+    /// Enable the persistent cache, or disable it if `persistent_cache` is `None`. See
+    /// [crate::persistent_cache].
+    pub fn set_persistent_cache(&mut self, persistent_cache: Option<PersistentCache>) {
+        self.persistent_cache = persistent_cache;
+    }
+
+    /// Return the persistent cache, if enabled.
+    pub fn persistent_cache(&self) -> Option<&PersistentCache> {
+        self.persistent_cache.as_ref()
+    }
+
+    /// Try to restore an entry and its transitive imports from the persistent cache, if enabled.
+    /// Return `true` if the entry is then in the `Transformed` state, whether it has just been
+    /// restored or was already there.
+    ///
+    /// The standard library must have been loaded before, as restored terms may refer to it.
+    fn restore(&mut self, file_id: FileId) -> bool {
+        self.persistent_cache.is_some() && self.restore_(file_id, &mut HashSet::new()).is_some()
+    }
+
+    fn restore_(&mut self, file_id: FileId, in_progress: &mut HashSet<FileId>) -> Option<()> {
+        match self.entry_state(file_id) {
+            Some(EntryState::Transformed) => return Some(()),
+            Some(_) => return None,
+            None if !in_progress.insert(file_id) => return None,
+            None => (),
+        }
+
+        let Some(SourcePath::Path(path)) = self.file_paths.get(&file_id).cloned() else {
+            return None;
+        };
+        let key = CacheKey::for_source(&path, self.source(file_id));
+        let entry = self.persistent_cache.as_ref()?.load(&key)?;
+
+        // The entry is only valid if none of the transitive imports has changed.
+        let mut dep_ids = HashMap::new();
+        for dep in entry.header.deps.iter() {
+            let dep_id = self.get_or_add_file(&dep.path).ok()?.inner();
+
+            if ContentHash::of(self.source(dep_id)) != dep.hash {
+                return None;
+            }
+
+            dep_ids.insert(dep.path.clone(), dep_id);
+        }
+
+        let imports = entry
+            .header
+            .imports
+            .iter()
+            .map(|path| dep_ids.get(path).copied())
+            .collect::<Option<Vec<_>>>()?;
+
+        for import in imports.iter() {
+            self.restore_(*import, in_progress)?;
+        }
+
+        let file_ids = entry
+            .files
+            .iter()
+            .map(|file| match file {
+                FileRef::Path(file_path) if *file_path == path => Some(file_id),
+                FileRef::Path(file_path) => dep_ids.get(file_path).copied(),
+                FileRef::Std(module) => self.get_submodule_file_id(*module),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let (terms, wildcards) = entry.decode(file_ids).ok()?;
+        let [term] = <[RichTerm; 1]>::try_from(terms).ok()?;
+
+        self.terms.insert(
+            file_id,
+            TermEntry {
+                term,
+                state: EntryState::Transformed,
+                parse_errs: ParseErrors::default(),
+            },
+        );
+        self.wildcards.insert(file_id, wildcards);
+
+        for import in imports {
+            self.imports.entry(file_id).or_default().insert(import);
+            self.rev_imports.entry(import).or_default().insert(file_id);
+        }
+
+        self.persisted.insert(file_id);
+        Some(())
+    }
+
+    /// Store an entry and its transitive imports in the persistent cache, if enabled and if they
+    /// haven't been stored or restored before.
+    fn persist(&mut self, file_id: FileId) {
+        let Some(persistent_cache) = &self.persistent_cache else {
+            return;
+        };
+
+        let mut candidates = self.get_imports_transitive(file_id);
+        candidates.insert(file_id);
+        candidates.retain(|id| !self.persisted.contains(id));
+
+        let stored = candidates
+            .iter()
+            .filter(|id| self.store(persistent_cache, **id).is_some())
+            .count();
+
+        if stored > 0 {
+            let _ = persistent_cache.evict();
+        }
+
+        self.persisted.extend(candidates);
+    }
+
+    /// Store an entry in the persistent cache. Only transformed entries corresponding to files
+    /// which aren't part of an import cycle are stored.
+    fn store(&self, persistent_cache: &PersistentCache, file_id: FileId) -> Option<()> {
+        let Some(TermEntry {
+            term,
+            state: EntryState::Transformed,
+            ..
+        }) = self.terms.get(&file_id)
+        else {
+            return None;
+        };
+        let Some(SourcePath::Path(path)) = self.file_paths.get(&file_id) else {
+            return None;
+        };
+
+        let dep_ids = self.get_imports_transitive(file_id);
+
+        if dep_ids.contains(&file_id) {
+            return None;
+        }
+
+        let mut files: HashMap<FileId, FileRef> = self
+            .stdlib_ids
+            .iter()
+            .flatten()
+            .map(|(module, id)| (*id, FileRef::Std(*module)))
+            .collect();
+        files.insert(file_id, FileRef::Path(path.clone()));
+
+        let mut deps = Vec::new();
+        for dep_id in dep_ids {
+            let Some(SourcePath::Path(dep_path)) = self.file_paths.get(&dep_id) else {
+                return None;
+            };
+
+            files.insert(dep_id, FileRef::Path(dep_path.clone()));
+            deps.push(Dependency {
+                path: dep_path.clone(),
+                hash: ContentHash::of(self.source(dep_id)),
+            });
+        }
+
+        let imports = self
+            .get_imports(file_id)
+            .map(|id| match self.file_paths.get(&id) {
+                Some(SourcePath::Path(path)) => Some(path.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let wildcards = self.wildcards.get(&file_id).map_or(&[][..], Vec::as_slice);
+
+        persistent_cache
+            .store(
+                &CacheKey::for_source(path, self.source(file_id)),
+                &EntryHeader { deps, imports },
+                &files,
+                &[term],
+                wildcards,
+            )
+            .ok()
+    }
+
+    /// Try to restore the standard library from the persistent cache, if enabled and if the
+    /// standard library hasn't been loaded yet. On success, the parsed modules are inserted in the
+    /// cache, and the transformed modules are returned.
+    fn restore_stdlib(&mut self) -> Option<Vec<(FileId, RichTerm)>> {
+        if self.stdlib_ids.is_some() {
+            return None;
+        }
+
+        let persistent_cache = self.persistent_cache.as_ref()?;
+        let entries = nickel_stdlib::modules()
+            .into_iter()
+            .map(|module| {
+                Some((
+                    module,
+                    persistent_cache.load(&CacheKey::for_stdlib(module))?,
+                ))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let file_ids = self.add_stdlib_sources();
+
+        // All the entries are decoded before any of them is inserted, such that an invalid entry
+        // leaves the cache untouched and the standard library is loaded as usual.
+        let decoded = entries
+            .into_iter()
+            .map(|(module, entry)| {
+                let entry_file_ids = entry
+                    .files
+                    .iter()
+                    .map(|file| match file {
+                        FileRef::Std(module) => file_ids.get(module).copied(),
+                        FileRef::Path(_) => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                let (terms, _) = entry.decode(entry_file_ids).ok()?;
+                let [parsed, term] = <[RichTerm; 2]>::try_from(terms).ok()?;
+                Some((*file_ids.get(&module)?, parsed, term))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut transformed = Vec::new();
+        for (file_id, parsed, term) in decoded {
+            self.terms.insert(
+                file_id,
+                TermEntry {
+                    term: parsed,
+                    state: EntryState::Parsed,
+                    parse_errs: ParseErrors::default(),
+                },
+            );
+            transformed.push((file_id, term));
+        }
+
+        Some(transformed)
+    }
+
+    /// Store the standard library in the persistent cache, if enabled, given the parsed modules.
+    /// The modules must have been transformed.
+    fn persist_stdlib(&self, parsed: Vec<(StdlibModule, FileId, RichTerm)>) {
+        let Some(persistent_cache) = &self.persistent_cache else {
+            return;
+        };
+
+        let files: HashMap<FileId, FileRef> = parsed
+            .iter()
+            .map(|(module, file_id, _)| (*file_id, FileRef::Std(*module)))
+            .collect();
+
+        for (module, file_id, parsed) in parsed.iter() {
+            if let Some(transformed) = self.get_ref(*file_id) {
+                let _ = persistent_cache.store(
+                    &CacheKey::for_stdlib(*module),
+                    &EntryHeader::default(),
+                    &files,
+                    &[parsed, transformed],
+                    &[],
+                );
+            }
+        }
+
+        let _ = persistent_cache.evict();
+    }
+
     /// Retrieve the name of a source given an id.
     pub fn name(&self, file_id: FileId) -> &OsStr {
         self.files.name(file_id)
@@ -1079,6 +1439,23 @@ impl Cache {
             .copied()
     }
 
+    /// Returns the set of files that this file transitively imports. It includes the file itself
+    /// only if it's part of an import cycle.
+    pub fn get_imports_transitive(&self, file: FileId) -> HashSet<FileId> {
+        let mut ret = HashSet::new();
+        let mut stack = vec![file];
+
+        while let Some(file) = stack.pop() {
+            for f in self.get_imports(file) {
+                if ret.insert(f) {
+                    stack.push(f);
+                }
+            }
+        }
+
+        ret
+    }
+
     /// Returns the set of files that transitively depend on this file.
     pub fn get_rev_imports_transitive(&self, file: FileId) -> HashSet<FileId> {
         let mut ret = HashSet::new();
@@ -1103,8 +1480,22 @@ impl Cache {
 
     /// Load and parse the standard library in the cache.
     pub fn load_stdlib(&mut self) -> Result<CacheOp<()>, Error> {
-        if self.stdlib_ids.is_some() {
-            return Ok(CacheOp::Cached(()));
+        let mut result = CacheOp::Cached(());
+
+        for (_, file_id) in self.add_stdlib_sources() {
+            if let CacheOp::Done(_) = self.parse(file_id)? {
+                result = CacheOp::Done(());
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Add the sources of the standard library modules to the cache, if not already done, and
+    /// return their ids.
+    fn add_stdlib_sources(&mut self) -> HashMap<StdlibModule, FileId> {
+        if let Some(file_ids) = &self.stdlib_ids {
+            return file_ids.clone();
         }
 
         let file_ids: HashMap<StdlibModule, FileId> = nickel_stdlib::modules()
@@ -1118,11 +1509,8 @@ impl Cache {
             })
             .collect();
 
-        for (_, file_id) in file_ids.iter() {
-            self.parse(*file_id)?;
-        }
-        self.stdlib_ids.replace(file_ids);
-        Ok(CacheOp::Done(()))
+        self.stdlib_ids.replace(file_ids.clone());
+        file_ids
     }
 
     /// Typecheck the standard library. Currently only used in the test suite.
@@ -1170,9 +1558,30 @@ impl Cache {
         if self.skip_stdlib {
             return Ok(Envs::new());
         }
+        let restored = self.restore_stdlib();
         self.load_stdlib()?;
         let type_ctxt = self.mk_type_ctxt().unwrap();
 
+        // The typing context is built from the parsed standard library, which is thus restored
+        // together with the transformed one.
+        let parsed = match restored {
+            Some(transformed) => {
+                for (file_id, term) in transformed {
+                    self.terms.insert(
+                        file_id,
+                        TermEntry {
+                            term,
+                            state: EntryState::Transformed,
+                            parse_errs: ParseErrors::default(),
+                        },
+                    );
+                }
+                None
+            }
+            None if self.persistent_cache.is_some() => Some(self.stdlib_terms()),
+            None => None,
+        };
+
         self.stdlib_ids
             .as_ref()
             .cloned()
@@ -1198,6 +1607,11 @@ impl Cache {
                         .into(),
                 )
             })?;
+
+        if let Some(parsed) = parsed {
+            self.persist_stdlib(parsed);
+        }
+
         let eval_env = self.mk_eval_env(eval_cache).unwrap();
         Ok(Envs {
             eval_env,
@@ -1205,6 +1619,15 @@ impl Cache {
         })
     }
 
+    /// Return the terms of the standard library modules currently in the cache.
+    fn stdlib_terms(&self) -> Vec<(StdlibModule, FileId, RichTerm)> {
+        self.stdlib_ids
+            .iter()
+            .flatten()
+            .filter_map(|(module, file_id)| Some((*module, *file_id, self.get_owned(*file_id)?)))
+            .collect()
+    }
+
     /// Generate the initial typing context from the list of `file_ids` corresponding to the
     /// standard library parts.
     pub fn mk_type_ctxt(&self) -> Result<typecheck::Context, CacheError<Void>> {
@@ -1334,8 +1757,10 @@ impl ImportResolver for Cache {
             self.rev_imports.entry(file_id).or_default().insert(parent);
         }
 
-        self.parse_multi(file_id, format)
-            .map_err(|err| ImportError::ParseErrors(err, *pos))?;
+        if !self.restore(file_id) {
+            self.parse_multi(file_id, format)
+                .map_err(|err| ImportError::ParseErrors(err, *pos))?;
+        }
 
         Ok((result, file_id))
     }
@@ -59,6 +59,10 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
                 variant: v.into_label(),
                 rich_term: None,
             }),
+            Term::EnumVariant { tag, arg, .. } => visitor.visit_enum(EnumDeserializer {
+                variant: tag.into_label(),
+                rich_term: Some(arg),
+            }),
             Term::Record(record) => visit_record(record.fields, visitor),
             Term::Array(v, _) => visit_array(v, visitor),
             // unreachable(): `unwrap_term` recursively unwraps `Annotated` nodes until it
@@ -95,7 +99,8 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
         }
     }
 
-    /// deserialize `RichTerm::Enum` tags or `RichTerm::Record`s with a single item.
+    /// deserialize `RichTerm::Enum` tags, `RichTerm::EnumVariant`s or `RichTerm::Record`s with a
+    /// single item.
     fn deserialize_enum<V>(
         self,
         _name: &str,
@@ -107,6 +112,7 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
     {
         let (variant, rich_term) = match unwrap_term(self)? {
             Term::Enum(ident) => (ident.into_label(), None),
+            Term::EnumVariant { tag, arg, .. } => (tag.into_label(), Some(arg)),
             Term::Record(record) => {
                 let mut iter = record.fields.into_iter();
                 let (variant, value) = match iter.next() {
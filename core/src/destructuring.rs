@@ -6,15 +6,78 @@ use std::collections::{hash_map::Entry, HashMap};
 use crate::{
     identifier::LocIdent,
     label::Label,
+    mk_app,
     parser::error::ParseError,
     position::{RawSpan, TermPos},
+    stdlib::internals,
     term::{
+        make as mk_term,
         record::{Field, RecordAttrs, RecordData},
-        LabeledType, Term,
+        LabeledType, RichTerm, Term,
     },
     typ::{Type, TypeF},
 };
 
+/// A destructuring pattern, at the left-hand side of a let-binding or as the argument of a
+/// function.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Destruct {
+    /// A record pattern like `{ a, b = c, ..rest }`.
+    Record(RecordPattern),
+    /// An array pattern like `[a, { b }, ..rest]`.
+    Array(ArrayPattern),
+}
+
+impl Destruct {
+    /// Generate the contract elaborated from this pattern.
+    pub fn into_contract(self) -> LabeledType {
+        match self {
+            Destruct::Record(pattern) => pattern.into_contract(),
+            Destruct::Array(pattern) => pattern.into_contract(),
+        }
+    }
+
+    /// Return all the identifiers bound by this pattern, including the ones bound by nested
+    /// patterns, aliases and the `..rest` capture.
+    pub fn bound_idents(&self) -> Vec<LocIdent> {
+        match self {
+            Destruct::Record(pattern) => pattern.bound_idents(),
+            Destruct::Array(pattern) => pattern.bound_idents(),
+        }
+    }
+
+    /// Returns info about each variable bound in the pattern, except the `..rest` capture. See
+    /// [Match::to_flattened_bindings]. As array elements can't be accessed through a path of
+    /// record fields, the variables bound inside an array pattern are associated with the path of
+    /// the array itself.
+    pub fn to_flattened_bindings(&self) -> Vec<(Vec<LocIdent>, LocIdent, Field)> {
+        match self {
+            Destruct::Record(pattern) => pattern
+                .matches
+                .iter()
+                .flat_map(|m| m.to_flattened_bindings())
+                .collect(),
+            Destruct::Array(pattern) => pattern
+                .bound_idents()
+                .into_iter()
+                .map(|id| (Vec::new(), id, Field::default()))
+                .collect(),
+        }
+    }
+}
+
+impl From<RecordPattern> for Destruct {
+    fn from(pattern: RecordPattern) -> Self {
+        Destruct::Record(pattern)
+    }
+}
+
+impl From<ArrayPattern> for Destruct {
+    fn from(pattern: ArrayPattern) -> Self {
+        Destruct::Array(pattern)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum FieldPattern {
     /// An assignment match like `{ ..., a = b, ... }`
@@ -26,6 +89,35 @@ pub enum FieldPattern {
         alias: LocIdent,
         pattern: RecordPattern,
     },
+    /// A nested array pattern like `{ ..., a = [b, c], ... }`
+    ArrayPattern(ArrayPattern),
+    /// An aliased nested array pattern like `{ ..., a = b @ [c, d], ... }`
+    AliasedArrayPattern {
+        alias: LocIdent,
+        pattern: ArrayPattern,
+    },
+}
+
+impl FieldPattern {
+    /// Return all the identifiers bound by this pattern, including the ones bound by nested
+    /// patterns and aliases.
+    pub fn bound_idents(&self) -> Vec<LocIdent> {
+        match self {
+            FieldPattern::Ident(id) => vec![*id],
+            FieldPattern::RecordPattern(pattern) => pattern.bound_idents(),
+            FieldPattern::ArrayPattern(pattern) => pattern.bound_idents(),
+            FieldPattern::AliasedRecordPattern { alias, pattern } => {
+                let mut idents = pattern.bound_idents();
+                idents.push(*alias);
+                idents
+            }
+            FieldPattern::AliasedArrayPattern { alias, pattern } => {
+                let mut idents = pattern.bound_idents();
+                idents.push(*alias);
+                idents
+            }
+        }
+    }
 }
 
 /// A match field in a `Destruct` pattern. Every field can be annotated with a type, with contracts
@@ -154,6 +246,111 @@ impl RecordPattern {
     pub fn is_open(&self) -> bool {
         self.open
     }
+
+    /// Return all the identifiers bound by this pattern, including the ones bound by nested
+    /// patterns, aliases and the `..rest` capture.
+    pub fn bound_idents(&self) -> Vec<LocIdent> {
+        let mut idents: Vec<LocIdent> = self
+            .matches
+            .iter()
+            .flat_map(|m| match m {
+                Match::Simple(id, _) => vec![*id],
+                Match::Assign(_, _, pattern) => pattern.bound_idents(),
+            })
+            .collect();
+
+        idents.extend(self.rest);
+        idents
+    }
+}
+
+/// An element of an array pattern, such as `a`, `{ b, c }` or `a | Number ? 0`. As the fields of a
+/// record pattern, an element can be annotated with a type, with contracts or with a default value,
+/// which is used when the array is too short to have this element.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArrayElem {
+    pub pattern: FieldPattern,
+    pub field: Field,
+}
+
+/// A destructured array pattern
+#[derive(Debug, PartialEq, Clone)]
+pub struct ArrayPattern {
+    pub elements: Vec<ArrayElem>,
+    pub open: bool,
+    pub rest: Option<LocIdent>,
+    pub span: RawSpan,
+}
+
+impl ArrayPattern {
+    /// Check that the pattern doesn't bind the same identifier twice, and raise an error
+    /// otherwise.
+    pub fn check_matches(&self) -> Result<(), ParseError> {
+        let mut bindings: HashMap<_, LocIdent> = HashMap::new();
+
+        for ident in self.bound_idents() {
+            if let Some(prev_ident) = bindings.insert(ident.ident(), ident) {
+                return Err(ParseError::DuplicateIdentInRecordPattern { ident, prev_ident });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The minimal length of the arrays matched by this pattern. The trailing elements which have
+    /// a default value can be missing.
+    pub fn min_length(&self) -> usize {
+        self.elements
+            .iter()
+            .rposition(|elem| elem.field.value.is_none())
+            .map_or(0, |idx| idx + 1)
+    }
+
+    /// Generate the contract elaborated from this pattern, which checks that the value is an
+    /// array of the right length. The contracts of the elements are applied when they are bound.
+    pub fn into_contract(self) -> LabeledType {
+        let span = self.span;
+        self.into_contract_with_span(span)
+    }
+
+    fn into_contract_with_span(self, span: RawSpan) -> LabeledType {
+        let max_length = if self.open {
+            Term::Null.into()
+        } else {
+            mk_term::integer(self.elements.len() as i64)
+        };
+
+        let typ = Type {
+            typ: TypeF::Flat(mk_app!(
+                internals::array_pattern(),
+                mk_term::integer(self.min_length() as i64),
+                max_length
+            )),
+            pos: TermPos::Original(span),
+        };
+
+        LabeledType {
+            typ: typ.clone(),
+            label: Label {
+                typ: typ.into(),
+                span,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Return all the identifiers bound by this pattern, including the ones bound by nested
+    /// patterns, aliases and the `..rest` capture.
+    pub fn bound_idents(&self) -> Vec<LocIdent> {
+        let mut idents: Vec<LocIdent> = self
+            .elements
+            .iter()
+            .flat_map(|elem| elem.pattern.bound_idents())
+            .collect();
+
+        idents.extend(self.rest);
+        idents
+    }
 }
 
 impl Match {
@@ -185,6 +382,21 @@ impl Match {
 
                 (id, field)
             }
+            Match::Assign(
+                id,
+                mut field,
+                FieldPattern::ArrayPattern(pattern)
+                | FieldPattern::AliasedArrayPattern { pattern, .. },
+            ) => {
+                let span = RawSpan::fuse(id.pos.unwrap(), pattern.span).unwrap();
+                field
+                    .metadata
+                    .annotation
+                    .contracts
+                    .push(pattern.into_contract_with_span(span));
+
+                (id, field)
+            }
         }
     }
 
@@ -252,6 +464,128 @@ impl Match {
                 flattened.push((vec![*id], *bind_id, field));
                 flattened
             }
+            // The elements of an array can't be accessed through a path of record fields: the
+            // variables bound inside a nested array pattern are associated with the array itself.
+            Match::Assign(
+                id,
+                field,
+                pattern
+                @ (FieldPattern::ArrayPattern(_) | FieldPattern::AliasedArrayPattern { .. }),
+            ) => pattern
+                .bound_idents()
+                .into_iter()
+                .map(|bind_id| (vec![*id], bind_id, field.clone()))
+                .collect(),
+        }
+    }
+}
+
+/// A pattern of a branch of a `match` expression. As opposed to a [RecordPattern], which always
+/// matches, a match pattern can be refuted, in which case the next branch is tried.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    /// `_`, which matches any value.
+    Wildcard,
+    /// An identifier, which matches any value and binds it.
+    Any(LocIdent),
+    /// A constant (`null`, a boolean, a number or a string), which matches the equal values.
+    Constant(RichTerm),
+    /// An enum tag, such as `'None`, or an enum variant with a pattern for its argument, such as
+    /// `'Some x`.
+    Enum(LocIdent, Option<Box<Pattern>>),
+    /// A record pattern `{ foo, bar = <pattern>, ..rest }`. Unless the pattern is open, the
+    /// matched record must have exactly the fields of the pattern.
+    Record {
+        fields: Vec<(LocIdent, Pattern)>,
+        open: bool,
+        rest: Option<LocIdent>,
+    },
+    /// An array pattern `[<pattern>, <pattern>, ..rest]`. Unless the pattern is open, the matched
+    /// array must have exactly as many elements as the pattern.
+    Array {
+        elements: Vec<Pattern>,
+        open: bool,
+        rest: Option<LocIdent>,
+    },
+    /// An aliased pattern `x @ <pattern>`, which binds the whole matched value to `x`.
+    Alias(LocIdent, Box<Pattern>),
+}
+
+impl Pattern {
+    /// Return all the identifiers bound by this pattern, including the ones bound by nested
+    /// patterns, aliases and `..rest` captures.
+    pub fn bound_idents(&self) -> Vec<LocIdent> {
+        let mut idents = Vec::new();
+        self.collect_bound_idents(&mut idents);
+        idents
+    }
+
+    fn collect_bound_idents(&self, idents: &mut Vec<LocIdent>) {
+        match self {
+            Pattern::Wildcard | Pattern::Constant(_) | Pattern::Enum(_, None) => (),
+            Pattern::Any(id) => idents.push(*id),
+            Pattern::Enum(_, Some(pattern)) => pattern.collect_bound_idents(idents),
+            Pattern::Record { fields, rest, .. } => {
+                for (_, pattern) in fields {
+                    pattern.collect_bound_idents(idents);
+                }
+
+                idents.extend(rest);
+            }
+            Pattern::Array { elements, rest, .. } => {
+                for pattern in elements {
+                    pattern.collect_bound_idents(idents);
+                }
+
+                idents.extend(rest);
+            }
+            Pattern::Alias(id, pattern) => {
+                idents.push(*id);
+                pattern.collect_bound_idents(idents);
+            }
+        }
+    }
+
+    /// Check that the pattern doesn't bind the same identifier twice, and raise an error
+    /// otherwise.
+    pub fn check_bindings(&self) -> Result<(), ParseError> {
+        let mut bindings: HashMap<_, LocIdent> = HashMap::new();
+
+        for ident in self.bound_idents() {
+            if let Some(prev_ident) = bindings.insert(ident.ident(), ident) {
+                return Err(ParseError::DuplicateIdentInRecordPattern { ident, prev_ident });
+            }
         }
+
+        Ok(())
     }
+
+    /// Return `true` if the pattern matches any value.
+    pub fn is_catch_all(&self) -> bool {
+        match self {
+            Pattern::Wildcard | Pattern::Any(_) => true,
+            Pattern::Alias(_, pattern) => pattern.is_catch_all(),
+            _ => false,
+        }
+    }
+
+    /// If the pattern is an enum tag or an enum variant, possibly aliased, return the tag,
+    /// together with `true` if the pattern is a variant.
+    pub fn as_enum_tag(&self) -> Option<(LocIdent, bool)> {
+        match self {
+            Pattern::Enum(tag, arg) => Some((*tag, arg.is_some())),
+            Pattern::Alias(_, pattern) => pattern.as_enum_tag(),
+            _ => None,
+        }
+    }
+}
+
+/// A branch `<pattern> if <guard> => <body>` of a `match` expression. The guard is optional.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchBranch {
+    pub pattern: Pattern,
+    /// A condition which must evaluate to `true` for the branch to be taken, in addition to the
+    /// value matching the pattern. The variables bound by the pattern are in scope.
+    pub guard: Option<RichTerm>,
+    pub body: RichTerm,
 }
@@ -12,7 +12,7 @@ use lalrpop_util::ErrorRecovery;
 use malachite::num::conversion::traits::ToSci;
 
 use crate::{
-    eval::callstack::CallStack,
+    eval::{callstack::CallStack, limits::ResourceLimit},
     identifier::LocIdent,
     label::{
         self,
@@ -130,6 +130,13 @@ pub enum EvalError {
     UnboundIdentifier(LocIdent, TermPos),
     /// An element in the evaluation Cache was entered during its own update.
     InfiniteRecursion(CallStack, TermPos),
+    /// A resource limit set on the virtual machine was exceeded, and the evaluation was
+    /// interrupted. See [crate::eval::limits].
+    ResourceLimitExceeded {
+        limit: ResourceLimit,
+        /// The position of the term being evaluated when the limit was exceeded.
+        pos: TermPos,
+    },
     /// A serialization error occurred during a call to the builtin `serialize`.
     SerializationError(ExportError),
     /// A parse error occurred during a call to the builtin `deserialize`.
@@ -156,6 +163,13 @@ pub enum EvalError {
         /// Evaluated expression
         value: RichTerm,
     },
+    /// A value didn't match any branch of a match expression.
+    NonExhaustiveMatch {
+        /// The value being matched.
+        value: RichTerm,
+        /// The position of the match expression.
+        pos: TermPos,
+    },
     /// An unexpected internal error.
     InternalError(String, TermPos),
     /// Errors occurring rarely enough to not deserve a dedicated variant.
@@ -320,6 +334,18 @@ pub enum TypecheckError {
         /// The position of the entire type (the `{foo : 5}` in the example above).
         pos: TermPos,
     },
+    /// A type alias is applied to a different number of arguments than it has parameters, such as
+    /// `Pair` or `Pair Number String` when `Pair` is declared by `let type Pair a = ...`.
+    TypeAliasArity {
+        /// The name of the alias, at the position where it's applied.
+        alias: LocIdent,
+        /// The number of parameters of the alias.
+        expected: usize,
+        /// The number of arguments the alias is applied to.
+        found: usize,
+        /// The position of the application.
+        pos: TermPos,
+    },
     /// Unsound generalization.
     ///
     /// When typechecking polymorphic expressions, polymorphic variables introduced by a `forall`
@@ -1176,6 +1202,36 @@ impl IntoDiagnostics<FileId> for EvalError {
                     .with_message("infinite recursion")
                     .with_labels(labels)]
             }
+            EvalError::ResourceLimitExceeded { limit, pos } => {
+                let msg = match limit {
+                    ResourceLimit::Steps(max) => {
+                        format!("evaluation exceeded the limit of {max} steps")
+                    }
+                    ResourceLimit::StackDepth(max) => {
+                        format!("evaluation exceeded the maximum stack depth of {max}")
+                    }
+                    ResourceLimit::CacheSize(max) => {
+                        format!("evaluation allocated more than {max} cache elements")
+                    }
+                    ResourceLimit::Timeout(timeout) => {
+                        format!("evaluation timed out after {}s", timeout.as_secs_f64())
+                    }
+                };
+
+                let labels = pos
+                    .as_opt_ref()
+                    .map(|span| vec![primary(span).with_message("evaluation was interrupted here")])
+                    .unwrap_or_default();
+
+                vec![Diagnostic::error()
+                    .with_message(msg)
+                    .with_labels(labels)
+                    .with_notes(vec![
+                        "This can be caused by an infinite loop, such as an unbounded recursive \
+                        function, or by an unexpectedly expensive computation."
+                            .into(),
+                    ])]
+            }
             EvalError::Other(msg, span_opt) => {
                 let labels = span_opt
                     .as_opt_ref()
@@ -1290,6 +1346,19 @@ impl IntoDiagnostics<FileId> for EvalError {
                     .with_message("tried to query field of a non-record")
                     .with_labels(vec![label])]
             }
+            EvalError::NonExhaustiveMatch { value, pos } => {
+                let mut labels =
+                    vec![primary_term(&value, files)
+                        .with_message("this value doesn't match any branch")];
+
+                if let Some(span) = pos.into_opt() {
+                    labels.push(secondary(&span).with_message("in this match expression"));
+                }
+
+                vec![Diagnostic::error()
+                    .with_message("unmatched pattern")
+                    .with_labels(labels)]
+            }
         }
     }
 }
@@ -1306,7 +1375,7 @@ mod blame_error {
             ty_path::{self, PathSpan},
             Polarity,
         },
-        position::TermPos,
+        position::{RawSpan, TermPos},
         term::RichTerm,
         typ::Type,
     };
@@ -1548,6 +1617,49 @@ is None but last_arrow_elem is Some"
         .with_message("bound here")])
     }
 
+    /// Generate one note per step of the chain of contract applications recorded in explain-blame
+    /// mode. Steps which only involve the standard library or generated code are skipped.
+    fn trace_diagnostics(
+        trace: &[label::ContractStep],
+        stdlib_ids: Option<&Vec<FileId>>,
+    ) -> Vec<Diagnostic<FileId>> {
+        let user_span = |pos: &TermPos| -> Option<RawSpan> {
+            pos.into_opt()
+                .filter(|span| !stdlib_ids.is_some_and(|ids| ids.contains(&span.src_id)))
+        };
+
+        let steps: Vec<_> = trace
+            .iter()
+            .filter_map(|step| {
+                let contract = user_span(&step.contract_pos);
+                let value = user_span(&step.value_pos);
+
+                (contract.is_some() || value.is_some()).then_some((step, contract, value))
+            })
+            .collect();
+        let count = steps.len();
+
+        steps
+            .into_iter()
+            .enumerate()
+            .map(|(index, (step, contract, value))| {
+                let labels = contract
+                    .map(|span| primary(&span).with_message("contract"))
+                    .into_iter()
+                    .chain(value.map(|span| secondary(&span).with_message("value")))
+                    .collect();
+
+                Diagnostic::note()
+                    .with_message(format!(
+                        "contract trace ({}/{count}): {}",
+                        index + 1,
+                        step.description()
+                    ))
+                    .with_labels(labels)
+            })
+            .collect()
+    }
+
     /// Generate codespan diagnostics from blame data. Mostly used by `into_diagnostics`
     /// implementations.
     ///
@@ -1622,6 +1734,12 @@ is None but last_arrow_elem is Some"
             );
         }
 
+        diagnostics.extend(label.branches.iter().map(|branch| {
+            Diagnostic::note().with_message(format!("while checking {}", branch.description()))
+        }));
+
+        diagnostics.extend(trace_diagnostics(&label.trace, stdlib_ids));
+
         match stdlib_ids {
             Some(id) if !ty_path::has_no_dom(&label.path) => {
                 diagnostics.extend_with_call_stack(id, call_stack)
@@ -2119,6 +2237,31 @@ impl IntoDiagnostics<FileId> for TypecheckError {
                             .collect(),
                     )]
             }
+            TypecheckError::TypeAliasArity {
+                alias,
+                expected,
+                found,
+                pos,
+            } => {
+                let plural = |n: usize| if n == 1 { "" } else { "s" };
+
+                vec![Diagnostic::error()
+                    .with_message(format!(
+                        "type alias `{alias}` expects {expected} argument{}, got {found}",
+                        plural(expected)
+                    ))
+                    .with_labels(
+                        pos.into_opt()
+                            .map(|span| primary(&span).with_message("used here"))
+                            .into_iter()
+                            .collect(),
+                    )
+                    .with_notes(vec![
+                        "A type alias must be applied to as many types as it has parameters, \
+                            both as a type and as a contract."
+                            .into(),
+                    ])]
+            }
             TypecheckError::VarLevelMismatch {
                 type_var: constant,
                 pos,
@@ -634,7 +634,11 @@ impl ThunkUpdateFrame {
 
 /// Placeholder [Cache] for the call-by-need evaluation strategy.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct CBNCache {}
+pub struct CBNCache {
+    /// The number of thunks allocated so far, used to enforce
+    /// [crate::eval::limits::EvalLimits::max_cache_size].
+    allocated: usize,
+}
 
 impl Cache for CBNCache {
     type UpdateIndex = ThunkUpdateFrame;
@@ -662,12 +666,18 @@ impl Cache for CBNCache {
     }
 
     fn add(&mut self, clos: Closure, bty: BindingType) -> CacheIndex {
+        self.allocated += 1;
+
         match bty {
             BindingType::Normal => Thunk::new(clos),
             BindingType::Revertible(deps) => Thunk::new_rev(clos, deps),
         }
     }
 
+    fn allocated(&self) -> usize {
+        self.allocated
+    }
+
     fn patch<F: FnOnce(&mut Closure)>(&mut self, mut idx: CacheIndex, f: F) {
         f(&mut idx.borrow_mut());
     }
@@ -681,7 +691,7 @@ impl Cache for CBNCache {
     }
 
     fn new() -> Self {
-        CBNCache {}
+        CBNCache { allocated: 0 }
     }
 
     fn reset_index_state(&mut self, idx: &mut Self::UpdateIndex) {
@@ -11,6 +11,10 @@ use crate::{
 };
 
 pub mod lazy;
+// The incremental cache can't be used as is: cache indices are embedded in terms
+// ([crate::term::Term::Closure]) and environments, which are tied to [lazy::Thunk]. Reevaluation
+// after a change reuses the evaluated imports instead, see
+// [super::VirtualMachine::forget_stale_imports].
 // pub mod incremental;
 
 /// An index to a specific item stored in the cache
@@ -44,6 +48,9 @@ pub trait Cache: Clone {
     /// Adds an element into the [Cache] and returns its index.
     fn add(&mut self, clos: Closure, bty: BindingType) -> CacheIndex;
 
+    /// Returns the number of elements added into the [Cache] since its creation.
+    fn allocated(&self) -> usize;
+
     /// Applies `f` to the [Closure] stored inside the element at index `idx`.
     fn patch<F: Fn(&mut Closure)>(&mut self, idx: CacheIndex, f: F);
 
@@ -210,6 +210,29 @@ impl CallStack {
         (entered, pending.pop())
     }
 
+    /// Return the name of the function called by the application at position `pos_app`, if the
+    /// application is on the callstack and its head was a variable or a record field. As in
+    /// [Self::group_by_calls], the name is the first identifier entered during the evaluation of
+    /// the application whose position is included in the one of the application.
+    pub fn head_of_call(&self, pos_app: TermPos) -> Option<LocIdent> {
+        let span_app = pos_app.into_opt()?;
+        let start = self
+            .0
+            .iter()
+            .rposition(|elem| matches!(elem, StackElem::App(pos) if *pos == pos_app))?;
+
+        self.0[start + 1..].iter().find_map(|elem| match elem {
+            StackElem::Var { id, .. } if id.is_generated() => None,
+            StackElem::Var { id, pos }
+            | StackElem::Field {
+                id,
+                pos_access: pos,
+                ..
+            } => pos.into_opt().filter(|span| *span <= span_app).map(|_| *id),
+            _ => None,
+        })
+    }
+
     /// Return the length of the callstack. Wrapper for `callstack.0.len()`.
     pub fn len(&self) -> usize {
         self.0.len()
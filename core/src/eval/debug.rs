@@ -0,0 +1,17 @@
+//! Support for debuggers.
+//!
+//! A [DebugHook] is called by the [virtual machine][super::VirtualMachine] before each evaluation
+//! step, with a mutable access to the machine. A debugger can use it to suspend the evaluation
+//! (by blocking in [DebugHook::step]), to inspect the state of the machine, for example the
+//! environment of the current closure or the [call stack][super::VirtualMachine::call_stack], and
+//! to evaluate expressions in the current scope with
+//! [eval_isolated][super::VirtualMachine::eval_isolated].
+use super::{cache::Cache, Closure, VirtualMachine};
+use crate::cache::ImportResolver;
+
+/// A hook called before each evaluation step.
+pub trait DebugHook<R: ImportResolver, C: Cache> {
+    /// Called before the evaluation of `current`. The hook is disabled for the duration of the
+    /// call, so that it isn't called recursively by evaluations performed from within the hook.
+    fn step(&mut self, vm: &mut VirtualMachine<R, C>, current: &Closure);
+}
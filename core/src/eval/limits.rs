@@ -0,0 +1,102 @@
+//! Limits on the resources used by an evaluation.
+//!
+//! Evaluating a Nickel program might not terminate, or might take an unreasonable amount of time
+//! or memory, for example because of a buggy recursive definition. Embedders which evaluate
+//! untrusted or arbitrary programs (the LSP, language bindings, CI tooling, etc.) can set
+//! [EvalLimits] on the [virtual machine][super::VirtualMachine] to interrupt such evaluations with
+//! an [EvalError::ResourceLimitExceeded][crate::error::EvalError::ResourceLimitExceeded] error.
+//!
+//! All limits are counted from the last [reset][super::VirtualMachine::reset] of the virtual
+//! machine, or from its creation.
+use std::time::{Duration, Instant};
+
+/// The number of steps between two checks of the elapsed time, which is more expensive to check
+/// than the other limits.
+const TIMEOUT_CHECK_PERIOD: u64 = 1024;
+
+/// Resource limits of an evaluation. A `None` value means that the corresponding resource is
+/// unlimited, which is the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvalLimits {
+    /// The maximum number of reduction steps of the virtual machine.
+    pub max_steps: Option<u64>,
+    /// The maximum size of the stack of the virtual machine, which roughly corresponds to the
+    /// depth of nested function calls and pending primitive operations.
+    pub max_stack_depth: Option<usize>,
+    /// The maximum number of elements allocated in the evaluation cache.
+    pub max_cache_size: Option<usize>,
+    /// The maximum wall-clock duration of the evaluation.
+    pub timeout: Option<Duration>,
+}
+
+/// A resource limit which has been exceeded, together with the value of the limit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResourceLimit {
+    Steps(u64),
+    StackDepth(usize),
+    CacheSize(usize),
+    Timeout(Duration),
+}
+
+/// The resources consumed by the current evaluation.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Usage {
+    steps: u64,
+    /// The number of cache elements allocated at the time of the first step. `None` if the
+    /// evaluation hasn't started yet.
+    cache_start: Option<usize>,
+    /// The time of the first step. Only recorded when a timeout is set, as the clock isn't
+    /// available on every platform (in particular WebAssembly).
+    start: Option<Instant>,
+}
+
+impl EvalLimits {
+    /// Return `true` if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        *self == EvalLimits::default()
+    }
+
+    /// Record a new step of the evaluation and check that no limit has been exceeded.
+    ///
+    /// # Arguments
+    ///
+    /// - `stack_depth`: the current size of the stack of the virtual machine.
+    /// - `cache_allocated`: the number of elements allocated in the cache since its creation.
+    pub(super) fn step(
+        &self,
+        usage: &mut Usage,
+        stack_depth: usize,
+        cache_allocated: usize,
+    ) -> Result<(), ResourceLimit> {
+        let cache_start = *usage.cache_start.get_or_insert(cache_allocated);
+        usage.steps += 1;
+
+        if let Some(max) = self.max_steps.filter(|max| usage.steps > *max) {
+            return Err(ResourceLimit::Steps(max));
+        }
+
+        if let Some(max) = self.max_stack_depth.filter(|max| stack_depth > *max) {
+            return Err(ResourceLimit::StackDepth(max));
+        }
+
+        if let Some(max) = self
+            .max_cache_size
+            .filter(|max| cache_allocated.saturating_sub(cache_start) > *max)
+        {
+            return Err(ResourceLimit::CacheSize(max));
+        }
+
+        match self.timeout {
+            Some(timeout) => {
+                let start = *usage.start.get_or_insert_with(Instant::now);
+
+                if usage.steps.is_multiple_of(TIMEOUT_CHECK_PERIOD) && start.elapsed() > timeout {
+                    Err(ResourceLimit::Timeout(timeout))
+                } else {
+                    Ok(())
+                }
+            }
+            None => Ok(()),
+        }
+    }
+}
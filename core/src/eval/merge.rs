@@ -29,7 +29,7 @@ use super::*;
 use crate::closurize::Closurize;
 use crate::combine::Combine;
 use crate::error::{EvalError, IllegalPolymorphicTailAction};
-use crate::label::{Label, MergeLabel};
+use crate::label::{ContractStep, ContractStepKind, Label, MergeLabel};
 use crate::position::TermPos;
 use crate::term::{
     record::{self, Field, FieldDeps, FieldMetadata, RecordAttrs, RecordData},
@@ -160,6 +160,34 @@ pub fn merge<C: Cache>(
                 })
             }
         }
+        // Enum variants with the same tag are merged by merging their arguments.
+        (
+            Term::EnumVariant {
+                tag: tag1,
+                arg: arg1,
+                ..
+            },
+            Term::EnumVariant {
+                tag: tag2,
+                arg: arg2,
+                ..
+            },
+        ) if tag1 == tag2 => {
+            let arg = mk_term::op2(
+                BinaryOp::Merge(mode.into()),
+                arg1.closurize(cache, env1),
+                arg2.closurize(cache, env2),
+            );
+
+            Ok(Closure::atomic_closure(RichTerm::new(
+                Term::EnumVariant {
+                    tag: tag1,
+                    arg,
+                    attrs: Default::default(),
+                },
+                pos_op.into_inherited(),
+            )))
+        }
         // There are several different (and valid) ways of merging arrays. We don't want to choose
         // for the user, so future custom merge functions will provide a way to overload the native
         // merging function. For the time being, we still need to be idempotent: thus we rewrite
@@ -218,13 +246,14 @@ pub fn merge<C: Cache>(
         }
         // Merge put together the fields of records, and recursively merge
         // fields that are present in both terms
-        (Term::Record(r1), Term::Record(r2)) => {
+        (Term::Record(r1), Term::Record(mut r2)) => {
             // While it wouldn't be impossible to merge records with sealed tails,
             // working out how to do so in a "sane" way that preserves parametricity
             // is non-trivial. It's also not entirely clear that this is something
             // users will generally have reason to do, so in the meantime we've
             // decided to just prevent this entirely
-            if let Some(record::SealedTail { label, .. }) = r1.sealed_tail.or(r2.sealed_tail) {
+            if let Some(record::SealedTail { label, .. }) = r1.sealed_tail.or(r2.sealed_tail.take())
+            {
                 return Err(EvalError::IllegalPolymorphicTailAccess {
                     action: IllegalPolymorphicTailAction::Merge,
                     evaluated_arg: label.get_evaluated_arg(cache),
@@ -233,6 +262,14 @@ pub fn merge<C: Cache>(
                 });
             }
 
+            // In explain-blame mode, the contracts of the fields of a record contract continue the
+            // chain of contract applications which led to the record contract.
+            if let MergeMode::Contract(label) = &mode {
+                if !label.trace.is_empty() {
+                    extend_field_traces(&mut r2, label, pos1, pos2);
+                }
+            }
+
             let split::SplitResult {
                 left,
                 center,
@@ -344,6 +381,28 @@ pub fn merge<C: Cache>(
     }
 }
 
+/// Prepend the trace of the label of a record contract, followed by a merge step, to the traces of
+/// the pending contracts of the fields of this record contract.
+fn extend_field_traces(
+    contract: &mut RecordData,
+    label: &Label,
+    pos_value: TermPos,
+    pos_contract: TermPos,
+) {
+    for (id, field) in contract.fields.iter_mut() {
+        for ctr in field.pending_contracts.iter_mut() {
+            let mut trace = label.trace.clone();
+            trace.push(ContractStep {
+                kind: ContractStepKind::Merge { field: *id },
+                contract_pos: pos_contract,
+                value_pos: pos_value,
+            });
+            trace.append(&mut ctr.label.trace);
+            ctr.label.trace = trace;
+        }
+    }
+}
+
 /// Take two record fields in their respective environment and combine both their metadata and
 /// values. Apply the required saturate, revert or closurize operation, including on the final
 /// field returned.
@@ -89,23 +89,29 @@ use crate::{
         array::ArrayAttrs,
         make as mk_term,
         record::{Field, RecordData},
-        BinaryOp, BindingType, LetAttrs, RecordOpKind, RichTerm, RuntimeContract, StrChunk, Term,
-        UnaryOp,
+        BinaryOp, BindingType, EnumVariantAttrs, LetAttrs, RecordOpKind, RichTerm, RuntimeContract,
+        StrChunk, Term, UnaryOp,
     },
 };
 
-use std::io::Write;
+use std::{collections::HashMap, io::Write};
 
 pub mod cache;
 pub mod callstack;
+pub mod debug;
 pub mod fixpoint;
+pub mod limits;
 pub mod merge;
 pub mod operation;
+pub mod profile;
 pub mod stack;
 
 use callstack::*;
 use codespan::FileId;
+use debug::DebugHook;
+use limits::{EvalLimits, Usage};
 use operation::OperationCont;
+use profile::{Profile, Profiler};
 use stack::{Stack, StrAccData};
 
 use self::cache::{Cache, CacheIndex};
@@ -130,6 +136,19 @@ pub struct VirtualMachine<R: ImportResolver, C: Cache> {
     initial_env: Environment,
     // The stream for writing trace output.
     trace: Box<dyn Write>,
+    // The resource limits of an evaluation.
+    limits: EvalLimits,
+    // The resources consumed by the current evaluation.
+    usage: Usage,
+    // The profiler, if profiling is enabled.
+    profiler: Option<Profiler>,
+    // Whether to record the chain of contract applications in labels, to explain blame errors.
+    explain_blame: bool,
+    // The hook called before each step, if a debugger is attached.
+    debug_hook: Option<Box<dyn DebugHook<R, C>>>,
+    // The evaluated imports, which are shared between the evaluations of the machine. See
+    // [VirtualMachine::forget_stale_imports].
+    imports: HashMap<FileId, CacheIndex>,
 }
 
 impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
@@ -141,6 +160,12 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             cache: Cache::new(),
             initial_env: Environment::new(),
             trace: Box::new(trace),
+            limits: EvalLimits::default(),
+            usage: Usage::default(),
+            profiler: None,
+            explain_blame: false,
+            debug_hook: None,
+            imports: HashMap::new(),
         }
     }
 
@@ -152,6 +177,12 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             cache,
             trace: Box::new(trace),
             initial_env: Environment::new(),
+            limits: EvalLimits::default(),
+            usage: Usage::default(),
+            profiler: None,
+            explain_blame: false,
+            debug_hook: None,
+            imports: HashMap::new(),
         }
     }
 
@@ -160,12 +191,98 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
     pub fn reset(&mut self) {
         self.call_stack.0.clear();
         self.stack.reset(&mut self.cache);
+        self.usage = Usage::default();
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.reset();
+        }
+    }
+
+    /// Set the resource limits of the next evaluations. The limits are enforced separately for
+    /// each evaluation, where the resources consumed are reset by [Self::reset].
+    pub fn set_limits(&mut self, limits: EvalLimits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> &EvalLimits {
+        &self.limits
+    }
+
+    pub fn explain_blame(&self) -> bool {
+        self.explain_blame
+    }
+
+    /// Enable or disable the explain-blame mode. In this mode, the machine records the chain of
+    /// contract applications leading to each label in [crate::label::Label::trace], which is
+    /// reported together with blame errors. This makes contract checking slower.
+    pub fn set_explain_blame(&mut self, explain_blame: bool) {
+        self.explain_blame = explain_blame;
+    }
+
+    /// Attach a debugger to the machine, or detach it if `hook` is `None`. See [debug].
+    pub fn set_debug_hook(&mut self, hook: Option<Box<dyn DebugHook<R, C>>>) {
+        self.debug_hook = hook;
+    }
+
+    /// The call stack of the current evaluation.
+    pub fn call_stack(&self) -> &CallStack {
+        &self.call_stack
+    }
+
+    /// The current size of the stack of the machine. The size of the stack grows when the machine
+    /// starts forcing a thunk or the operand of a primitive operation, and shrinks back when the
+    /// result is available.
+    pub fn stack_depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Evaluate a closure to a weak head normal form independently of the current evaluation,
+    /// which is left untouched. Used by debuggers to evaluate expressions while the evaluation is
+    /// suspended, and by union contracts to try a contract without aborting the evaluation.
+    ///
+    /// The closure can depend on thunks which are being forced by the current evaluation, in
+    /// which case the isolated evaluation fails with an infinite recursion error.
+    ///
+    /// The resources consumed by the isolated evaluation count toward the limits of the current
+    /// evaluation (see [Self::set_limits]).
+    pub fn eval_isolated(&mut self, clos: Closure) -> Result<Closure, EvalError> {
+        let stack = std::mem::replace(&mut self.stack, Stack::new());
+        let call_stack = std::mem::take(&mut self.call_stack);
+
+        let result = self.eval_closure(clos);
+
+        self.stack.reset(&mut self.cache);
+        self.stack = stack;
+        self.call_stack = call_stack;
+
+        result
+    }
+
+    /// Start profiling the next evaluations. If profiling was already enabled, the data collected
+    /// so far are kept.
+    pub fn enable_profiling(&mut self) {
+        self.profiler.get_or_insert_with(Profiler::new);
+    }
+
+    /// Stop profiling and return the data collected since profiling was enabled, or `None` if
+    /// profiling wasn't enabled.
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.profiler.take().map(Profiler::finish)
     }
 
     pub fn import_resolver(&self) -> &R {
         &self.import_resolver
     }
 
+    /// Forget the evaluated imports whose term isn't available from the import resolver anymore,
+    /// typically because the file or one of its imports has been modified (see
+    /// [crate::cache::Cache::refresh]). Such imports are evaluated again the next time they're
+    /// accessed, while the other ones keep their value, evaluated or not, across evaluations.
+    pub fn forget_stale_imports(&mut self) {
+        self.imports
+            .retain(|file_id, _| self.import_resolver.get(*file_id).is_some());
+    }
+
     pub fn import_resolver_mut(&mut self) -> &mut R {
         &mut self.import_resolver
     }
@@ -400,6 +517,13 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         Ok(field)
     }
 
+    /// Notify the profiler, if any, that some update indices may have been popped from the stack.
+    fn leave_profiled_bindings(&mut self) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.leave_bindings(self.stack.len());
+        }
+    }
+
     fn enter_cache_index(
         &mut self,
         var: Option<LocIdent>,
@@ -411,12 +535,29 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         // is going to be discarded anyway
         std::mem::drop(env);
 
-        match self.cache.get_update_index(&mut idx) {
-            Ok(Some(idx_upd)) => self.stack.push_update_index(idx_upd),
-            Ok(None) => {}
+        let has_update_index = match self.cache.get_update_index(&mut idx) {
+            Ok(Some(idx_upd)) => {
+                self.stack.push_update_index(idx_upd);
+                true
+            }
+            Ok(None) => false,
             Err(_blackholed_error) => {
                 return Err(EvalError::InfiniteRecursion(self.call_stack.clone(), pos))
             }
+        };
+
+        if has_update_index {
+            if let Some(profiler) = self.profiler.as_mut() {
+                // A thunk which isn't accessed through a variable is usually a record field which
+                // has just been accessed.
+                let name = var.or_else(|| match self.call_stack.0.last() {
+                    Some(StackElem::Field { id, pos_field, .. }) if *pos_field == pos => Some(*id),
+                    _ => None,
+                });
+                let def_pos = self.cache.get_then(idx.clone(), |clos| clos.body.pos);
+
+                profiler.enter_binding(name, pos, def_pos, self.stack.len() - 1);
+            }
         }
 
         if let Some(var) = var {
@@ -477,6 +618,29 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 mut env,
             } = clos;
 
+            if !self.limits.is_unlimited() {
+                self.limits
+                    .step(&mut self.usage, self.stack.len(), self.cache.allocated())
+                    .map_err(|limit| EvalError::ResourceLimitExceeded { limit, pos })?;
+            }
+
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.step(self.call_stack.len());
+            }
+
+            if let Some(mut hook) = self.debug_hook.take() {
+                let current = Closure {
+                    body: RichTerm {
+                        term: shared_term.clone(),
+                        pos,
+                    },
+                    env: env.clone(),
+                };
+
+                hook.step(self, &current);
+                self.debug_hook = Some(hook);
+            }
+
             let has_cont_on_stack = self.stack.is_top_idx() || self.stack.is_top_cont();
 
             clos = match_sharedterm!(match (shared_term) {
@@ -495,6 +659,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     // the "unsealed" value to the result of the `Seq` operation. See also:
                     // https://github.com/tweag/nickel/issues/123
                     update_at_indices(&mut self.cache, &mut self.stack, &closure);
+                    self.leave_profiled_bindings();
 
                     // We have to peek the stack to see what operation is coming next and decide
                     // what to do.
@@ -550,7 +715,14 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     );
                     Closure { body: t1, env }
                 }
-                Term::Let(x, bound, body, LetAttrs { binding_type, rec }) => {
+                Term::Let(
+                    x,
+                    bound,
+                    body,
+                    LetAttrs {
+                        binding_type, rec, ..
+                    },
+                ) => {
                     let bound_closure: Closure = Closure {
                         body: bound,
                         env: env.clone(),
@@ -742,14 +914,28 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     }
                 }
                 Term::ResolvedImport(id) => {
-                    if let Some(t) = self.import_resolver.get(id) {
-                        Closure::atomic_closure(t)
-                    } else {
-                        return Err(EvalError::InternalError(
-                            format!("Resolved import not found ({id:?})"),
-                            pos,
-                        ));
-                    }
+                    // Imports are closed terms: each of them is allocated in the cache once, such
+                    // that its value is shared by every occurrence of the import, and by the next
+                    // evaluations.
+                    let idx = match self.imports.get(&id) {
+                        Some(idx) => idx.clone(),
+                        None => {
+                            let Some(t) = self.import_resolver.get(id) else {
+                                return Err(EvalError::InternalError(
+                                    format!("Resolved import not found ({id:?})"),
+                                    pos,
+                                ));
+                            };
+
+                            let idx = self
+                                .cache
+                                .add(Closure::atomic_closure(t), BindingType::Normal);
+                            self.imports.insert(id, idx.clone());
+                            idx
+                        }
+                    };
+
+                    self.enter_cache_index(None, idx, pos, env)?
                 }
                 Term::Import(path) => {
                     return Err(EvalError::InternalError(
@@ -791,6 +977,18 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         env: Environment::new(),
                     }
                 }
+                // Closurize the argument of an enum variant if it's not already done.
+                Term::EnumVariant { tag, arg, attrs } if !attrs.closurized => Closure {
+                    body: RichTerm::new(
+                        Term::EnumVariant {
+                            tag,
+                            arg: arg.closurize(&mut self.cache, env),
+                            attrs: EnumVariantAttrs { closurized: true },
+                        },
+                        pos,
+                    ),
+                    env: Environment::new(),
+                },
                 Term::ParseError(parse_error) => {
                     return Err(EvalError::ParseError(parse_error));
                 }
@@ -835,6 +1033,13 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 Term::Fun(x, t) if !has_cont_on_stack => {
                     if let Some((idx, pos_app)) = self.stack.pop_arg_as_idx(&mut self.cache) {
                         self.call_stack.enter_fun(pos_app);
+
+                        if let Some(profiler) = self.profiler.as_mut().filter(|_| pos_app.is_def())
+                        {
+                            let head = self.call_stack.head_of_call(pos_app);
+                            profiler.enter_function(head, pos, pos_app, self.call_stack.len() - 1);
+                        }
+
                         env.insert(x.ident(), idx);
                         Closure { body: t, env }
                     } else {
@@ -910,6 +1115,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     // update of the corresponding cached value.
                     if self.stack.is_top_idx() {
                         update_at_indices(&mut self.cache, &mut self.stack, &evaluated);
+                        self.leave_profiled_bindings();
                         evaluated
                     }
                     // If there is a primitive operator continuation on the stack, we proceed with
@@ -1106,6 +1312,9 @@ pub fn subst<C: Cache>(
         p @ Term::FunPattern(..) => panic!(
             "Pattern {p:?} has not been transformed before evaluation"
         ),
+        p @ Term::MatchPattern(..) => panic!(
+            "Pattern {p:?} has not been transformed before evaluation"
+        ),
         Term::App(t1, t2) => {
             let t1 = subst(cache, t1, initial_env, env);
             let t2 = subst(cache, t2, initial_env, env);
@@ -1150,6 +1359,19 @@ pub fn subst<C: Cache>(
             let t = subst(cache, t, initial_env, env);
             RichTerm::new(Term::Sealed(i, t, lbl), pos)
         }
+        Term::EnumVariant { tag, arg, .. } => {
+            let arg = subst(cache, arg, initial_env, env);
+
+            // see [^subst-closurized-false]
+            RichTerm::new(
+                Term::EnumVariant {
+                    tag,
+                    arg,
+                    attrs: Default::default(),
+                },
+                pos,
+            )
+        }
         Term::Record(record) => {
             let mut record = record
                 .map_defined_values(|_, value| subst(cache, value, initial_env, env));
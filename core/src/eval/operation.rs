@@ -19,7 +19,7 @@ use crate::{
     closurize::Closurize,
     error::{EvalError, IllegalPolymorphicTailAction},
     identifier::LocIdent,
-    label::{ty_path, Polarity, TypeVarData},
+    label::{ty_path, ContractBranch, ContractStep, ContractStepKind, Polarity, TypeVarData},
     match_sharedterm, mk_app, mk_fun, mk_opn, mk_record,
     parser::utils::parse_number,
     position::TermPos,
@@ -28,9 +28,12 @@ use crate::{
     stdlib::internals,
     term::{
         array::{Array, ArrayAttrs, OutOfBoundError},
-        make as mk_term,
+        make as mk_term, net,
         record::{self, Field, FieldMetadata, RecordData},
-        string::NickelString,
+        string::{
+            round_half_away_from_zero, to_decimal_string, NickelString, RegexMatch,
+            MAX_DECIMAL_DIGITS,
+        },
         *,
     },
     typecheck::eq::contract_eq,
@@ -38,7 +41,7 @@ use crate::{
 
 use malachite::{
     num::{
-        arithmetic::traits::Pow,
+        arithmetic::traits::{CheckedSqrt, Gcd, Pow, UnsignedAbs},
         basic::traits::Zero,
         conversion::traits::{RoundingFrom, ToSci},
     },
@@ -46,6 +49,11 @@ use malachite::{
     Integer,
 };
 
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
 use md5::digest::Digest;
 use simple_counter::*;
 use unicode_segmentation::UnicodeSegmentation;
@@ -230,7 +238,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Term::Num(_) => "Number",
                     Term::Bool(_) => "Bool",
                     Term::Str(_) => "String",
-                    Term::Enum(_) => "Enum",
+                    Term::Enum(_) | Term::EnumVariant { .. } => "Enum",
                     Term::Fun(..) | Term::Match { .. } => "Function",
                     Term::Array(..) => "Array",
                     Term::Record(..) | Term::RecRecord(..) => "Record",
@@ -301,7 +309,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 _ => Err(mk_type_error!("blame", "Label")),
             }),
             UnaryOp::Embed(_id) => {
-                if let Term::Enum(_) = &*t {
+                if let Term::Enum(_) | Term::EnumVariant { .. } = &*t {
                     Ok(Closure::atomic_closure(RichTerm {
                         term: t,
                         pos: pos_op_inh,
@@ -351,15 +359,23 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             env: cases_env,
                         })
                         .or(default)
-                        .ok_or_else(||
-                        // ? We should have a dedicated error for unmatched pattern
-                        mk_type_error!("match", "Enum"))
+                        .ok_or_else(|| EvalError::NonExhaustiveMatch {
+                            value: RichTerm { term: t, pos },
+                            pos: pos_op,
+                        })
                 } else if let Some(clos) = default {
                     Ok(clos)
                 } else {
-                    Err(mk_type_error!("match", "Enum", 2))
+                    Err(EvalError::NonExhaustiveMatch {
+                        value: RichTerm { term: t, pos },
+                        pos: pos_op,
+                    })
                 }
             }
+            UnaryOp::MatchFailure() => Err(EvalError::NonExhaustiveMatch {
+                value: RichTerm { term: t, pos },
+                pos: pos_op,
+            }),
             UnaryOp::ChangePolarity() => match_sharedterm!(match (t) {
                 Term::Lbl(l) => {
                     let mut l = l;
@@ -678,6 +694,14 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                                 missing_def_err.into_eval_err(pos, pos_op)
                             })?;
 
+                        if let Some(profiler) = self.profiler.as_mut() {
+                            profiler.name_fields(
+                                defined
+                                    .iter()
+                                    .map(|(id, field)| (LocIdent::from(*id), field.pos)),
+                            );
+                        }
+
                         let terms = defined.into_iter().map(|(_, field)| field);
 
                         Ok(Closure {
@@ -704,6 +728,10 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             env: Environment::new(),
                         })
                     }
+                    Term::EnumVariant { arg, .. } => Ok(Closure {
+                        body: seq_terms(std::iter::once(arg), pos_op),
+                        env,
+                    }),
                     _ => {
                         if let Some((next, ..)) = self.stack.pop_arg(&self.cache) {
                             Ok(next)
@@ -828,6 +856,22 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Err(mk_type_error!("string_lowercase", "String"))
                 }
             }
+            UnaryOp::StrToSnakeCase() | UnaryOp::StrToKebabCase() | UnaryOp::StrToCamelCase() => {
+                if let Term::Str(s) = &*t {
+                    let result = match u_op {
+                        UnaryOp::StrToSnakeCase() => s.to_snake_case(),
+                        UnaryOp::StrToKebabCase() => s.to_kebab_case(),
+                        _ => s.to_camel_case(),
+                    };
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Str(result),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(mk_type_error!(u_op.to_string(), "String"))
+                }
+            }
             UnaryOp::StrLength() => {
                 if let Term::Str(s) = &*t {
                     let length = s.graphemes(true).count();
@@ -883,6 +927,33 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Err(mk_type_error!("enum_from_str", "String"))
                 }
             }
+            UnaryOp::EnumGetTag() => match &*t {
+                Term::Enum(tag) | Term::EnumVariant { tag, .. } => Ok(Closure::atomic_closure(
+                    RichTerm::new(Term::Enum(*tag), pos_op_inh),
+                )),
+                _ => Err(mk_type_error!("enum_get_tag", "Enum")),
+            },
+            UnaryOp::EnumGetArg() => {
+                if let Term::EnumVariant { arg, .. } = &*t {
+                    Ok(Closure {
+                        body: arg.clone(),
+                        env,
+                    })
+                } else {
+                    Err(mk_type_error!("enum_get_arg", "Enum"))
+                }
+            }
+            UnaryOp::EnumIsVariant() => match &*t {
+                Term::Enum(_) => Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Bool(false),
+                    pos_op_inh,
+                ))),
+                Term::EnumVariant { .. } => Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Bool(true),
+                    pos_op_inh,
+                ))),
+                _ => Err(mk_type_error!("enum_is_variant", "Enum")),
+            },
             UnaryOp::StrIsMatch() => {
                 if let Term::Str(s) = &*t {
                     let re = regex::Regex::new(s)
@@ -927,6 +998,28 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Err(mk_type_error!("str_find", "String", 1))
                 }
             }
+            UnaryOp::StrFindAll() => {
+                if let Term::Str(s) = &*t {
+                    let re = regex::Regex::new(s)
+                        .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
+
+                    let param = LocIdent::fresh();
+                    let matcher = Term::Fun(
+                        param,
+                        RichTerm::new(
+                            Term::Op1(
+                                UnaryOp::StrFindAllCompiled(re.into()),
+                                RichTerm::new(Term::Var(param), pos_op_inh),
+                            ),
+                            pos_op_inh,
+                        ),
+                    );
+
+                    Ok(Closure::atomic_closure(RichTerm::new(matcher, pos)))
+                } else {
+                    Err(mk_type_error!("str_find_all", "String", 1))
+                }
+            }
             UnaryOp::StrIsMatchCompiled(regex) => {
                 if let Term::Str(s) = &*t {
                     Ok(Closure::atomic_closure(RichTerm::new(
@@ -981,6 +1074,25 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
+            UnaryOp::StrFindAllCompiled(regex) => {
+                if let Term::Str(s) = &*t {
+                    let matches = s
+                        .find_all_regex(&regex)
+                        .into_iter()
+                        .map(mk_regex_match_record)
+                        .collect();
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Array(matches, ArrayAttrs::new().closurized()),
+                        pos_op_inh,
+                    )))
+                } else {
+                    Err(mk_type_error!(
+                        "a compiled regular expression match",
+                        "String"
+                    ))
+                }
+            }
             UnaryOp::Force {
                 ignore_not_exported,
             } => {
@@ -996,6 +1108,16 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
 
                 match_sharedterm!(match (t) {
                     Term::Record(record) if !record.fields.is_empty() => {
+                        // When profiling, the forced values keep the position of the original
+                        // values, which is used by the profiler to name them after their field.
+                        let profiling = self.profiler.is_some();
+
+                        if let Some(profiler) = self.profiler.as_mut() {
+                            profiler.name_fields(record.fields.iter().filter_map(|(id, field)| {
+                                field.value.as_ref().map(|value| (*id, value.pos))
+                            }));
+                        }
+
                         let fields = record
                             .fields
                             .into_iter()
@@ -1004,12 +1126,19 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                                     || (ignore_not_exported && field.metadata.not_exported))
                             })
                             .map_values_closurize(&mut self.cache, &env, |_, value| {
-                                mk_term::op1(
+                                let value_pos = value.pos;
+                                let forced = mk_term::op1(
                                     UnaryOp::Force {
                                         ignore_not_exported,
                                     },
                                     value,
-                                )
+                                );
+
+                                if profiling {
+                                    forced.with_pos(value_pos)
+                                } else {
+                                    forced
+                                }
                             })
                             .map_err(|e| e.into_eval_err(pos, pos_op))?;
 
@@ -1060,6 +1189,29 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             env: Environment::new(),
                         })
                     }
+                    Term::EnumVariant { tag, arg, attrs } => {
+                        let arg = mk_term::op1(
+                            UnaryOp::Force {
+                                ignore_not_exported,
+                            },
+                            arg,
+                        )
+                        .closurize(&mut self.cache, env);
+
+                        let cont = RichTerm::new(
+                            Term::EnumVariant {
+                                tag,
+                                arg: arg.clone(),
+                                attrs,
+                            },
+                            pos.into_inherited(),
+                        );
+
+                        Ok(Closure {
+                            body: seq_terms(std::iter::once(arg), pos_op, cont),
+                            env: Environment::new(),
+                        })
+                    }
                     _ => Ok(Closure {
                         body: RichTerm { term: t, pos },
                         env
@@ -1325,6 +1477,203 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Err(mk_type_error!("pow", "Number", 1, t1, pos1))
                 }
             }
+            BinaryOp::NumMath() => {
+                let mk_err_fst = |t1| {
+                    Err(mk_type_error!(
+                        "num_math",
+                        "[| 'Sqrt, 'Exp, 'Ln, 'Log2, 'Log10, 'Sin, 'Cos, 'Tan, 'Asin, 'Acos, 'Atan |]",
+                        1,
+                        t1,
+                        pos1
+                    ))
+                };
+
+                let Term::Enum(id) = &*t1 else {
+                    return mk_err_fst(t1);
+                };
+
+                let Term::Num(ref n) = *t2 else {
+                    return Err(mk_type_error!("num_math", "Number", 2, t2, pos2));
+                };
+
+                let f: fn(f64) -> f64 = match id.as_ref() {
+                    "Sqrt" => f64::sqrt,
+                    "Exp" => f64::exp,
+                    "Ln" => f64::ln,
+                    "Log2" => f64::log2,
+                    "Log10" => f64::log10,
+                    "Sin" => f64::sin,
+                    "Cos" => f64::cos,
+                    "Tan" => f64::tan,
+                    "Asin" => f64::asin,
+                    "Acos" => f64::acos,
+                    "Atan" => f64::atan,
+                    _ => return mk_err_fst(t1),
+                };
+
+                // The square root of a perfect square is computed exactly, without going through
+                // floats. Negative numbers are left to the float implementation, which returns
+                // NaN and is reported as an error below.
+                let exact = match id.as_ref() {
+                    "Sqrt" if *n >= 0 => n.checked_sqrt(),
+                    _ => None,
+                };
+
+                let result = match exact {
+                    Some(result) => result,
+                    None => num_from_f64(f(num_to_f64(n)), pos_op, || {
+                        format!("{}({n})", id.as_ref().to_lowercase())
+                    })?,
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Num(result),
+                    pos_op_inh,
+                )))
+            }
+            BinaryOp::NumAtan2() => {
+                let Term::Num(ref y) = *t1 else {
+                    return Err(mk_type_error!("num_atan2", "Number", 1, t1, pos1));
+                };
+
+                let Term::Num(ref x) = *t2 else {
+                    return Err(mk_type_error!("num_atan2", "Number", 2, t2, pos2));
+                };
+
+                let result = num_from_f64(num_to_f64(y).atan2(num_to_f64(x)), pos_op, || {
+                    format!("atan2({y}, {x})")
+                })?;
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Num(result),
+                    pos_op_inh,
+                )))
+            }
+            BinaryOp::NumRound() => {
+                let mk_err_fst = |t1| {
+                    Err(mk_type_error!(
+                        "num_round",
+                        "[| 'Floor, 'Ceil, 'TowardZero, 'AwayFromZero, 'HalfToEven, \
+                        'HalfAwayFromZero |]",
+                        1,
+                        t1,
+                        pos1
+                    ))
+                };
+
+                let Term::Enum(id) = &*t1 else {
+                    return mk_err_fst(t1);
+                };
+
+                let Term::Num(ref n) = *t2 else {
+                    return Err(mk_type_error!("num_round", "Number", 2, t2, pos2));
+                };
+
+                let rounded = match id.as_ref() {
+                    "Floor" => Integer::rounding_from(n, RoundingMode::Floor).0,
+                    "Ceil" => Integer::rounding_from(n, RoundingMode::Ceiling).0,
+                    "TowardZero" => Integer::rounding_from(n, RoundingMode::Down).0,
+                    "AwayFromZero" => Integer::rounding_from(n, RoundingMode::Up).0,
+                    "HalfToEven" => Integer::rounding_from(n, RoundingMode::Nearest).0,
+                    "HalfAwayFromZero" => round_half_away_from_zero(n),
+                    _ => return mk_err_fst(t1),
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Num(Number::from(rounded)),
+                    pos_op_inh,
+                )))
+            }
+            BinaryOp::NumGcd()
+            | BinaryOp::NumBitAnd()
+            | BinaryOp::NumBitOr()
+            | BinaryOp::NumBitXor()
+            | BinaryOp::NumBitShift() => {
+                let op_name = b_op.to_string();
+
+                let Term::Num(ref n1) = *t1 else {
+                    return Err(mk_type_error!(op_name, "Number", 1, t1, pos1));
+                };
+
+                let Term::Num(ref n2) = *t2 else {
+                    return Err(mk_type_error!(op_name, "Number", 2, t2, pos2));
+                };
+
+                let to_integer = |n: &Number, pos| {
+                    Integer::try_from(n).map_err(|_| {
+                        EvalError::Other(format!("{op_name}: expected an integer, got {n}"), pos)
+                    })
+                };
+
+                let i1 = to_integer(n1, pos1)?;
+                let i2 = to_integer(n2, pos2)?;
+
+                let result = match b_op {
+                    BinaryOp::NumGcd() => Integer::from(i1.unsigned_abs().gcd(i2.unsigned_abs())),
+                    BinaryOp::NumBitAnd() => i1 & i2,
+                    BinaryOp::NumBitOr() => i1 | i2,
+                    BinaryOp::NumBitXor() => i1 ^ i2,
+                    BinaryOp::NumBitShift() => {
+                        // Shifting to the left allocates a number with as many bits as the shift
+                        // amount, so we bound it to avoid aborting on an allocation failure.
+                        // Shifting to the right is cheap whatever the amount.
+                        let shift = i64::try_from(&i2)
+                            .ok()
+                            .filter(|shift| *shift <= MAX_BIT_SHIFT)
+                            .ok_or_else(|| {
+                                EvalError::Other(
+                                    format!(
+                                        "{op_name}: the shift amount {i2} is too large \
+                                        (the maximum is {MAX_BIT_SHIFT})"
+                                    ),
+                                    pos2,
+                                )
+                            })?;
+
+                        i1 << shift
+                    }
+                    _ => unreachable!(),
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Num(Number::from(result)),
+                    pos_op_inh,
+                )))
+            }
+            BinaryOp::NumToDecimalStr() => {
+                let Term::Num(ref digits) = *t1 else {
+                    return Err(mk_type_error!("num_to_decimal_str", "Number", 1, t1, pos1));
+                };
+
+                let Term::Num(ref n) = *t2 else {
+                    return Err(mk_type_error!("num_to_decimal_str", "Number", 2, t2, pos2));
+                };
+
+                let digits = u32::try_from(digits).map_err(|_| {
+                    EvalError::Other(
+                        format!(
+                            "num_to_decimal_str: expected the number of digits to be a \
+                            natural number, got {digits}"
+                        ),
+                        pos1,
+                    )
+                })?;
+
+                if digits > MAX_DECIMAL_DIGITS {
+                    return Err(EvalError::Other(
+                        format!(
+                            "num_to_decimal_str: the number of digits {digits} is too large \
+                            (the maximum is {MAX_DECIMAL_DIGITS})"
+                        ),
+                        pos1,
+                    ));
+                }
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Str(to_decimal_string(n, digits).into()),
+                    pos_op_inh,
+                )))
+            }
             BinaryOp::StrConcat() => {
                 if let Term::Str(s1) = &*t1 {
                     if let Term::Str(s2) = &*t2 {
@@ -1351,6 +1700,18 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     l.arg_pos = self.cache.get_then(idx.clone(), |c| c.body.pos);
                     l.arg_idx = Some(idx);
 
+                    if self.explain_blame {
+                        l.push_trace_step(ContractStep {
+                            kind: ContractStepKind::Apply,
+                            contract_pos: pos1,
+                            value_pos: l.arg_pos,
+                        });
+                    }
+
+                    if let Some(profiler) = self.profiler.as_mut() {
+                        profiler.apply_contract(&l, pos2.into_inherited());
+                    }
+
                     self.stack.push_arg(
                         Closure::atomic_closure(RichTerm::new(Term::Lbl(l), pos2.into_inherited())),
                         pos2.into_inherited(),
@@ -1954,76 +2315,309 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     mk_err_fst(t1)
                 }
             }
-            BinaryOp::Serialize() => {
+            BinaryOp::Encode() | BinaryOp::Decode() => {
                 let mk_err_fst = |t1| {
                     Err(mk_type_error!(
-                        "serialize",
-                        "[| 'Json, 'Yaml, 'Toml |]",
+                        b_op.to_string(),
+                        "[| 'Base64, 'Base64Url, 'Hex, 'Percent |]",
                         1,
                         t1,
                         pos1
                     ))
                 };
 
-                if let Term::Enum(ref id) = t1.as_ref() {
-                    // Serialization needs all variables term to be fully substituted
-                    let initial_env = Environment::new();
-                    let rt2 = subst(
-                        &self.cache,
-                        RichTerm {
-                            term: t2,
-                            pos: pos2,
-                        },
-                        &initial_env,
-                        &env2,
-                    );
+                let Term::Enum(id) = &*t1 else {
+                    return mk_err_fst(t1);
+                };
 
-                    let format = match id.to_string().as_str() {
-                        "Json" => ExportFormat::Json,
-                        "Yaml" => ExportFormat::Yaml,
-                        "Toml" => ExportFormat::Toml,
-                        _ => return mk_err_fst(t1),
-                    };
+                let Term::Str(s) = &*t2 else {
+                    return Err(mk_type_error!(b_op.to_string(), "String", 2, t2, pos2));
+                };
 
-                    serialize::validate(format, &rt2)?;
-                    Ok(Closure::atomic_closure(RichTerm::new(
-                        Term::Str(serialize::to_string(format, &rt2)?.into()),
-                        pos_op_inh,
-                    )))
+                let result = if let BinaryOp::Encode() = b_op {
+                    match id.as_ref() {
+                        "Base64" => base64::engine::general_purpose::STANDARD.encode(s.as_str()),
+                        "Base64Url" => {
+                            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(s.as_str())
+                        }
+                        "Hex" => encode_hex(s),
+                        "Percent" => encode_percent(s),
+                        _ => return mk_err_fst(t1),
+                    }
                 } else {
-                    mk_err_fst(t1)
-                }
+                    let bytes = match id.as_ref() {
+                        "Base64" => BASE64_LENIENT
+                            .decode(s.as_str())
+                            .map_err(|err| err.to_string()),
+                        "Base64Url" => BASE64_URL_LENIENT
+                            .decode(s.as_str())
+                            .map_err(|err| err.to_string()),
+                        "Hex" => decode_hex(s),
+                        "Percent" => decode_percent(s),
+                        _ => return mk_err_fst(t1),
+                    }
+                    .map_err(|msg| {
+                        EvalError::Other(format!("decode: invalid {id} input: {msg}"), pos2)
+                    })?;
+
+                    String::from_utf8(bytes).map_err(|_| {
+                        EvalError::Other(
+                            format!("decode: the decoded {id} input isn't valid UTF-8 text"),
+                            pos2,
+                        )
+                    })?
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Str(result.into()),
+                    pos_op_inh,
+                )))
             }
-            BinaryOp::Deserialize() => {
+            BinaryOp::Quote() => {
+                let mk_err_fst =
+                    |t1| Err(mk_type_error!("quote", "[| 'Json, 'Shell |]", 1, t1, pos1));
+
+                let Term::Enum(id) = &*t1 else {
+                    return mk_err_fst(t1);
+                };
+
+                let Term::Str(s) = &*t2 else {
+                    return Err(mk_type_error!("quote", "String", 2, t2, pos2));
+                };
+
+                let result = match id.as_ref() {
+                    "Json" => serde_json::Value::from(s.as_str()).to_string(),
+                    "Shell" => quote_shell(s),
+                    _ => return mk_err_fst(t1),
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Str(result.into()),
+                    pos_op_inh,
+                )))
+            }
+            BinaryOp::NetValidate() => {
                 let mk_err_fst = |t1| {
                     Err(mk_type_error!(
-                        "deserialize",
-                        "[| 'Json, 'Yaml, 'Toml |]",
+                        "net_validate",
+                        "[| 'Ipv4, 'Ipv6, 'Cidr |]",
                         1,
                         t1,
                         pos1
                     ))
                 };
 
-                if let Term::Enum(id) = &*t1 {
-                    if let Term::Str(s) = &*t2 {
-                        let rt: RichTerm = match id.as_ref() {
-                            "Json" => serde_json::from_str(s).map_err(|err| {
-                                EvalError::DeserializationError(
-                                    String::from("json"),
-                                    format!("{err}"),
-                                    pos_op,
-                                )
-                            })?,
-                            "Yaml" => serde_yaml::from_str(s).map_err(|err| {
-                                EvalError::DeserializationError(
-                                    String::from("yaml"),
-                                    format!("{err}"),
-                                    pos_op,
-                                )
-                            })?,
-                            "Toml" => toml::from_str(s).map_err(|err| {
-                                EvalError::DeserializationError(
+                let Term::Enum(id) = &*t1 else {
+                    return mk_err_fst(t1);
+                };
+
+                let Term::Str(s) = &*t2 else {
+                    return Err(mk_type_error!("net_validate", "String", 2, t2, pos2));
+                };
+
+                let result = match id.as_ref() {
+                    "Ipv4" => net::parse_ipv4(s).map(|_| ()),
+                    "Ipv6" => net::parse_ipv6(s).map(|_| ()),
+                    "Cidr" => net::Cidr::parse(s).map(|_| ()),
+                    _ => return mk_err_fst(t1),
+                };
+
+                Ok(Closure::atomic_closure(mk_validation_result(
+                    result, pos_op_inh,
+                )))
+            }
+            BinaryOp::CidrContains() => match (&*t1, &*t2) {
+                (Term::Str(cidr), Term::Str(other)) => {
+                    let cidr = net::Cidr::parse(cidr)
+                        .map_err(|err| EvalError::Other(format!("cidr_contains: {err}"), pos1))?;
+
+                    let other = if other.as_str().contains('/') {
+                        net::Cidr::parse(other)
+                    } else {
+                        net::parse_ip(other).map(net::Cidr::from_address)
+                    }
+                    .map_err(|err| EvalError::Other(format!("cidr_contains: {err}"), pos2))?;
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Bool(cidr.contains(&other)),
+                        pos_op_inh,
+                    )))
+                }
+                (Term::Str(_), _) => Err(mk_type_error!("cidr_contains", "String", 2, t2, pos2)),
+                (_, _) => Err(mk_type_error!("cidr_contains", "String", 1, t1, pos1)),
+            },
+            BinaryOp::CidrSubnets() => match (&*t1, &*t2) {
+                (Term::Num(n), Term::Str(cidr)) => {
+                    let Ok(prefix_len) = u8::try_from(n) else {
+                        return Err(EvalError::Other(
+                            format!(
+                                "cidr_subnets: expected the prefix length to be a natural number \
+                                smaller than 256, got {n}"
+                            ),
+                            pos1,
+                        ));
+                    };
+
+                    let cidr = net::Cidr::parse(cidr)
+                        .map_err(|err| EvalError::Other(format!("cidr_subnets: {err}"), pos2))?;
+
+                    let subnets = cidr
+                        .subnets(prefix_len)
+                        .map_err(|err| EvalError::Other(format!("cidr_subnets: {err}"), pos_op))?;
+
+                    let result = subnets
+                        .into_iter()
+                        .map(|subnet| RichTerm::from(Term::Str(subnet.to_string().into())))
+                        .collect();
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Array(result, ArrayAttrs::new().closurized()),
+                        pos_op_inh,
+                    )))
+                }
+                (Term::Num(_), _) => Err(mk_type_error!("cidr_subnets", "String", 2, t2, pos2)),
+                (_, _) => Err(mk_type_error!("cidr_subnets", "Number", 1, t1, pos1)),
+            },
+            BinaryOp::SemverValidate() => {
+                let mk_err_fst = |t1| {
+                    Err(mk_type_error!(
+                        "semver_validate",
+                        "[| 'Version, 'Requirement |]",
+                        1,
+                        t1,
+                        pos1
+                    ))
+                };
+
+                let Term::Enum(id) = &*t1 else {
+                    return mk_err_fst(t1);
+                };
+
+                let Term::Str(s) = &*t2 else {
+                    return Err(mk_type_error!("semver_validate", "String", 2, t2, pos2));
+                };
+
+                let result = match id.as_ref() {
+                    "Version" => semver::Version::parse(s)
+                        .map(|_| ())
+                        .map_err(|err| format!("invalid semantic version `{s}`: {err}")),
+                    "Requirement" => semver::VersionReq::parse(s)
+                        .map(|_| ())
+                        .map_err(|err| format!("invalid version requirement `{s}`: {err}")),
+                    _ => return mk_err_fst(t1),
+                };
+
+                Ok(Closure::atomic_closure(mk_validation_result(
+                    result, pos_op_inh,
+                )))
+            }
+            BinaryOp::SemverCompare() => match (&*t1, &*t2) {
+                (Term::Str(s1), Term::Str(s2)) => {
+                    let v1 = parse_semver("semver_compare", s1, pos1)?;
+                    let v2 = parse_semver("semver_compare", s2, pos2)?;
+
+                    let result = match v1.cmp_precedence(&v2) {
+                        std::cmp::Ordering::Less => "Lesser",
+                        std::cmp::Ordering::Equal => "Equal",
+                        std::cmp::Ordering::Greater => "Greater",
+                    };
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Enum(LocIdent::from(result)),
+                        pos_op_inh,
+                    )))
+                }
+                (Term::Str(_), _) => Err(mk_type_error!("semver_compare", "String", 2, t2, pos2)),
+                (_, _) => Err(mk_type_error!("semver_compare", "String", 1, t1, pos1)),
+            },
+            BinaryOp::SemverMatches() => match (&*t1, &*t2) {
+                (Term::Str(req), Term::Str(version)) => {
+                    let req = semver::VersionReq::parse(req).map_err(|err| {
+                        EvalError::Other(
+                            format!("semver_matches: invalid version requirement `{req}`: {err}"),
+                            pos1,
+                        )
+                    })?;
+                    let version = parse_semver("semver_matches", version, pos2)?;
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Bool(req.matches(&version)),
+                        pos_op_inh,
+                    )))
+                }
+                (Term::Str(_), _) => Err(mk_type_error!("semver_matches", "String", 2, t2, pos2)),
+                (_, _) => Err(mk_type_error!("semver_matches", "String", 1, t1, pos1)),
+            },
+            BinaryOp::Serialize() => {
+                let mk_err_fst = |t1| {
+                    Err(mk_type_error!(
+                        "serialize",
+                        "[| 'Json, 'Yaml, 'Toml |]",
+                        1,
+                        t1,
+                        pos1
+                    ))
+                };
+
+                if let Term::Enum(ref id) = t1.as_ref() {
+                    // Serialization needs all variables term to be fully substituted
+                    let initial_env = Environment::new();
+                    let rt2 = subst(
+                        &self.cache,
+                        RichTerm {
+                            term: t2,
+                            pos: pos2,
+                        },
+                        &initial_env,
+                        &env2,
+                    );
+
+                    let format = match id.to_string().as_str() {
+                        "Json" => ExportFormat::Json,
+                        "Yaml" => ExportFormat::Yaml,
+                        "Toml" => ExportFormat::Toml,
+                        _ => return mk_err_fst(t1),
+                    };
+
+                    serialize::validate(format, &rt2)?;
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Str(serialize::to_string(format, &rt2)?.into()),
+                        pos_op_inh,
+                    )))
+                } else {
+                    mk_err_fst(t1)
+                }
+            }
+            BinaryOp::Deserialize() => {
+                let mk_err_fst = |t1| {
+                    Err(mk_type_error!(
+                        "deserialize",
+                        "[| 'Json, 'Yaml, 'Toml |]",
+                        1,
+                        t1,
+                        pos1
+                    ))
+                };
+
+                if let Term::Enum(id) = &*t1 {
+                    if let Term::Str(s) = &*t2 {
+                        let rt: RichTerm = match id.as_ref() {
+                            "Json" => serde_json::from_str(s).map_err(|err| {
+                                EvalError::DeserializationError(
+                                    String::from("json"),
+                                    format!("{err}"),
+                                    pos_op,
+                                )
+                            })?,
+                            "Yaml" => serde_yaml::from_str(s).map_err(|err| {
+                                EvalError::DeserializationError(
+                                    String::from("yaml"),
+                                    format!("{err}"),
+                                    pos_op,
+                                )
+                            })?,
+                            "Toml" => toml::from_str(s).map_err(|err| {
+                                EvalError::DeserializationError(
                                     String::from("toml"),
                                     format!("{err}"),
                                     pos_op,
@@ -2062,6 +2656,72 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 (Term::Str(_), _) => Err(mk_type_error!("str_contains", "String", 2, t2, pos2)),
                 (_, _) => Err(mk_type_error!("str_contains", "String", 1, t1, pos1)),
             },
+            BinaryOp::StrStartsWith() | BinaryOp::StrEndsWith() => match (&*t1, &*t2) {
+                (Term::Str(s1), Term::Str(s2)) => {
+                    let result = if let BinaryOp::StrStartsWith() = b_op {
+                        s1.starts_with(s2.as_str())
+                    } else {
+                        s1.ends_with(s2.as_str())
+                    };
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Bool(result),
+                        pos_op_inh,
+                    )))
+                }
+                (Term::Str(_), _) => Err(mk_type_error!(b_op.to_string(), "String", 2, t2, pos2)),
+                (_, _) => Err(mk_type_error!(b_op.to_string(), "String", 1, t1, pos1)),
+            },
+            BinaryOp::StrIndent() => match (&*t1, &*t2) {
+                (Term::Str(s), Term::Num(n)) => {
+                    let Ok(indent) = usize::try_from(n) else {
+                        return Err(EvalError::Other(
+                            format!(
+                                "str_indent: expected the indentation to be a natural number, \
+                                got {n}"
+                            ),
+                            pos2,
+                        ));
+                    };
+
+                    Ok(Closure::atomic_closure(RichTerm::new(
+                        Term::Str(s.indent(indent)),
+                        pos_op_inh,
+                    )))
+                }
+                (Term::Str(_), _) => Err(mk_type_error!("str_indent", "Number", 2, t2, pos2)),
+                (_, _) => Err(mk_type_error!("str_indent", "String", 1, t1, pos1)),
+            },
+            BinaryOp::StrFormat() => {
+                let Term::Str(template) = &*t1 else {
+                    return Err(mk_type_error!("str_format", "String", 1, t1, pos1));
+                };
+
+                // Formatting needs the values to be fully substituted
+                let initial_env = Environment::new();
+                let rt2 = subst(
+                    &self.cache,
+                    RichTerm {
+                        term: t2,
+                        pos: pos2,
+                    },
+                    &initial_env,
+                    &env2,
+                );
+
+                let Term::Record(values) = rt2.as_ref() else {
+                    return Err(mk_type_error!("str_format", "Record", 2, rt2.term, pos2));
+                };
+
+                let result = template
+                    .format(values)
+                    .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Str(result),
+                    pos_op_inh,
+                )))
+            }
             BinaryOp::ArrayLazyAppCtr() => {
                 let (ctr, _) = self.stack.pop_arg(&self.cache).ok_or_else(|| {
                     EvalError::NotEnoughArgs(3, String::from("array_lazy_app_ctr"), pos_op)
@@ -2073,11 +2733,19 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 } = ctr;
 
                 // FIXME: use match?
-                let lbl = match_sharedterm!(match (t1) {
+                let mut lbl = match_sharedterm!(match (t1) {
                     Term::Lbl(lbl) => lbl,
                     _ => return Err(mk_type_error!("array_lazy_app_ctr", "Label", 1, t1, pos1)),
                 });
 
+                if self.explain_blame {
+                    lbl.push_trace_step(ContractStep {
+                        kind: ContractStepKind::ArrayElements,
+                        contract_pos: rt3.pos,
+                        value_pos: pos2,
+                    });
+                }
+
                 match_sharedterm!(match (t2) {
                     Term::Array(ts, attrs) => {
                         let mut attrs = attrs;
@@ -2115,11 +2783,19 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     EvalError::NotEnoughArgs(3, String::from("record_lazy_app_ctr"), pos_op)
                 })?;
 
-                let label = match_sharedterm!(match (t1) {
+                let mut label = match_sharedterm!(match (t1) {
                     Term::Lbl(label) => label,
                     _ => return Err(mk_type_error!("record_lazy_app_ctr", "Label", 1, t1, pos1)),
                 });
 
+                if self.explain_blame {
+                    label.push_trace_step(ContractStep {
+                        kind: ContractStepKind::RecordFields,
+                        contract_pos: contract_term.pos,
+                        value_pos: pos2,
+                    });
+                }
+
                 match_sharedterm!(match (t2) {
                     Term::Record(record_data) => {
                         // due to a limitation of `match_sharedterm`: see the macro's
@@ -2287,6 +2963,39 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     pos2.into_inherited(),
                 )))
             }
+            BinaryOp::LabelGoBranch() => {
+                let branch = match &*t1 {
+                    Term::Enum(tag) => ContractBranch::from_tag(tag.label()),
+                    _ => None,
+                };
+
+                let Some(branch) = branch else {
+                    return Err(mk_type_error!(
+                        "label_go_branch",
+                        "<| 'UnionLeft, 'UnionRight, 'IntersectionLeft, 'IntersectionRight |>",
+                        1,
+                        t1,
+                        pos1
+                    ));
+                };
+
+                let t2 = t2.into_owned();
+
+                let Term::Lbl(label) = t2 else {
+                    return Err(mk_type_error!(
+                        "label_go_branch",
+                        "Label",
+                        2,
+                        t2.into(),
+                        pos2
+                    ));
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::Lbl(label.go_branch(branch)),
+                    pos2.into_inherited(),
+                )))
+            }
             BinaryOp::LookupTypeVar() => {
                 let t1 = t1.into_owned();
                 let t2 = t2.into_owned();
@@ -2334,7 +3043,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         // Currently, for fixed arity primitive operators, the parser must ensure that they get
         // exactly the right number of argument: if it is not the case, this is a bug, and we panic.
         match n_op {
-            NAryOp::StrReplace() | NAryOp::StrReplaceRegex() => {
+            NAryOp::StrReplace()
+            | NAryOp::StrReplaceRegex()
+            | NAryOp::StrReplaceRegexCaptures() => {
                 let mut args_wo_env = args
                     .into_iter()
                     .map(|(clos, pos)| (clos.body.term, clos.body.pos, pos));
@@ -2351,7 +3062,11 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             let re = regex::Regex::new(from)
                                 .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
 
-                            s.replace_regex(&CompiledRegex(re), to)
+                            if let NAryOp::StrReplaceRegexCaptures() = n_op {
+                                s.replace_regex_captures(&CompiledRegex(re), to)
+                            } else {
+                                s.replace_regex(&CompiledRegex(re), to)
+                            }
                         };
 
                         Ok(Closure::atomic_closure(RichTerm::new(
@@ -2391,6 +3106,164 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     }),
                 }
             }
+            NAryOp::StrReplaceRegexWith() => {
+                let mut args = args.into_iter();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: t1,
+                                pos: pos1,
+                            },
+                        ..
+                    },
+                    arg_pos1,
+                ) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: t2,
+                                pos: pos2,
+                            },
+                        ..
+                    },
+                    arg_pos2,
+                ) = args.next().unwrap();
+                let (f, _) = args.next().unwrap();
+                debug_assert!(args.next().is_none());
+
+                let Term::Str(s) = &*t1 else {
+                    return Err(EvalError::NAryPrimopTypeError {
+                        primop: String::from("str_replace_regex_with"),
+                        expected: String::from("String"),
+                        arg_number: 1,
+                        arg_pos: arg_pos1,
+                        arg_evaluated: RichTerm {
+                            term: t1,
+                            pos: pos1,
+                        },
+                    });
+                };
+
+                let Term::Str(regex) = &*t2 else {
+                    return Err(EvalError::NAryPrimopTypeError {
+                        primop: String::from("str_replace_regex_with"),
+                        expected: String::from("String"),
+                        arg_number: 2,
+                        arg_pos: arg_pos2,
+                        arg_evaluated: RichTerm {
+                            term: t2,
+                            pos: pos2,
+                        },
+                    });
+                };
+
+                let re = regex::Regex::new(regex)
+                    .map_err(|err| EvalError::Other(err.to_string(), pos_op))?;
+                let f_as_var = f.body.closurize(&mut self.cache, f.env);
+
+                // We build the string interpolation `"<before 1>%{f match1}<before 2>%{f match2}
+                // ...<rest>"`, which is then evaluated as usual.
+                let mut chunks = Vec::new();
+                let mut prev_match_end = 0;
+
+                for m in s.find_all_regex(&CompiledRegex(re)) {
+                    let range = m.byte_range.clone();
+                    chunks.push(StrChunk::Literal(s[prev_match_end..range.start].to_owned()));
+                    chunks.push(StrChunk::Expr(
+                        RichTerm::new(
+                            Term::App(f_as_var.clone(), mk_regex_match_record(m)),
+                            pos_op_inh,
+                        ),
+                        0,
+                    ));
+                    prev_match_end = range.end;
+                }
+
+                chunks.push(StrChunk::Literal(s[prev_match_end..].to_owned()));
+                // Chunks are stored in reverse order (see [`Term::StrChunks`])
+                chunks.reverse();
+
+                Ok(Closure {
+                    body: RichTerm::new(Term::StrChunks(chunks), pos_op_inh),
+                    env: Environment::new(),
+                })
+            }
+            NAryOp::StrPadLeft() | NAryOp::StrPadRight() => {
+                let mut args_wo_env = args
+                    .into_iter()
+                    .map(|(clos, pos)| (clos.body.term, clos.body.pos, pos));
+                let (fst, pos1, fst_pos) = args_wo_env.next().unwrap();
+                let (snd, pos2, snd_pos) = args_wo_env.next().unwrap();
+                let (thd, pos3, thd_pos) = args_wo_env.next().unwrap();
+                debug_assert!(args_wo_env.next().is_none());
+
+                match (&*fst, &*snd, &*thd) {
+                    (Term::Str(s), Term::Num(width), Term::Str(fill)) => {
+                        let Ok(width) = usize::try_from(width) else {
+                            return Err(EvalError::Other(
+                                format!(
+                                    "{n_op}: expected the length to be a natural number, \
+                                    got {width}"
+                                ),
+                                snd_pos,
+                            ));
+                        };
+
+                        if fill.length() != 1 {
+                            return Err(EvalError::Other(
+                                format!(
+                                    "{n_op}: expected the padding to be a single character, \
+                                    got \"{fill}\""
+                                ),
+                                thd_pos,
+                            ));
+                        }
+
+                        let result = if let NAryOp::StrPadLeft() = n_op {
+                            s.pad_left(width, fill)
+                        } else {
+                            s.pad_right(width, fill)
+                        };
+
+                        Ok(Closure::atomic_closure(RichTerm::new(
+                            Term::Str(result),
+                            pos_op_inh,
+                        )))
+                    }
+                    (Term::Str(_), Term::Num(_), _) => Err(EvalError::NAryPrimopTypeError {
+                        primop: format!("{n_op}"),
+                        expected: String::from("String"),
+                        arg_number: 3,
+                        arg_pos: thd_pos,
+                        arg_evaluated: RichTerm {
+                            term: thd,
+                            pos: pos3,
+                        },
+                    }),
+                    (Term::Str(_), _, _) => Err(EvalError::NAryPrimopTypeError {
+                        primop: format!("{n_op}"),
+                        expected: String::from("Number"),
+                        arg_number: 2,
+                        arg_pos: snd_pos,
+                        arg_evaluated: RichTerm {
+                            term: snd,
+                            pos: pos2,
+                        },
+                    }),
+                    (_, _, _) => Err(EvalError::NAryPrimopTypeError {
+                        primop: format!("{n_op}"),
+                        expected: String::from("String"),
+                        arg_number: 1,
+                        arg_pos: fst_pos,
+                        arg_evaluated: RichTerm {
+                            term: fst,
+                            pos: pos1,
+                        },
+                    }),
+                }
+            }
             NAryOp::StrSubstr() => {
                 let mut args_wo_env = args
                     .into_iter()
@@ -2905,6 +3778,72 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     env: env3,
                 })
             }
+            NAryOp::ContractCheck() => {
+                let mut args = args.into_iter();
+                let (contract, _) = args.next().unwrap();
+                let (
+                    Closure {
+                        body:
+                            RichTerm {
+                                term: label,
+                                pos: label_pos,
+                            },
+                        ..
+                    },
+                    snd_pos,
+                ) = args.next().unwrap();
+                let (value, _) = args.next().unwrap();
+                debug_assert!(args.next().is_none());
+
+                let label = label.into_owned();
+
+                let Term::Lbl(label) = label else {
+                    return Err(EvalError::NAryPrimopTypeError {
+                        primop: String::from("contract_check"),
+                        expected: String::from("Label"),
+                        arg_number: 2,
+                        arg_pos: snd_pos,
+                        arg_evaluated: RichTerm::new(label, label_pos),
+                    });
+                };
+
+                let contract = contract.body.closurize(&mut self.cache, contract.env);
+                let value = value.body.closurize(&mut self.cache, value.env);
+
+                // The whole result is forced, so that the lazy parts of the contract (such as
+                // the contracts on record fields or on array elements) are checked as well.
+                let checked = mk_term::op1(
+                    UnaryOp::Force {
+                        ignore_not_exported: false,
+                    },
+                    mk_app!(
+                        mk_term::op2(BinaryOp::ApplyContract(), contract, Term::Lbl(label)),
+                        value
+                    ),
+                );
+
+                let (tag, arg) = match self.eval_isolated(Closure::atomic_closure(checked)) {
+                    Ok(result) => ("Ok", result.body.closurize(&mut self.cache, result.env)),
+                    Err(EvalError::BlameError { label, .. }) => {
+                        let message = label
+                            .current_diagnostic()
+                            .and_then(|diagnostic| diagnostic.message.clone())
+                            .unwrap_or_default();
+
+                        ("Error", mk_term::string(message))
+                    }
+                    Err(err) => return Err(err),
+                };
+
+                Ok(Closure::atomic_closure(RichTerm::new(
+                    Term::EnumVariant {
+                        tag: LocIdent::from(tag),
+                        arg,
+                        attrs: EnumVariantAttrs { closurized: true },
+                    },
+                    pos_op_inh,
+                )))
+            }
         }
     }
 }
@@ -3050,6 +3989,196 @@ impl RecPriority {
     }
 }
 
+/// Convert a number to the nearest 64-bit float.
+fn num_to_f64(n: &Number) -> f64 {
+    f64::rounding_from(n, RoundingMode::Nearest).0
+}
+
+/// Convert the result of a floating-point operation back to a number. NaN and infinities aren't
+/// representable in Nickel, in which case an error is returned. `op` gives a textual
+/// representation of the operation for the error message.
+fn num_from_f64(
+    result: f64,
+    pos_op: TermPos,
+    op: impl FnOnce() -> String,
+) -> Result<Number, EvalError> {
+    Number::try_from_float_simplest(result).map_err(|_| {
+        let op = op();
+
+        EvalError::Other(
+            format!(
+                "invalid arithmetic operation: \
+                {op} returned {result}, \
+                but {result} isn't representable in Nickel"
+            ),
+            pos_op,
+        )
+    })
+}
+
+/// Convert a regex match to a Nickel record
+/// `{matched: String, index: Number, groups: Array String, named_groups: {_: String}}`, as
+/// returned by [`UnaryOp::StrFindAll`].
+fn mk_regex_match_record(m: RegexMatch) -> RichTerm {
+    let named_groups = RecordData::with_field_values(
+        m.named_groups
+            .into_iter()
+            .map(|(name, group)| (LocIdent::from(name), Term::Str(group).into())),
+    );
+
+    mk_record!(
+        ("matched", Term::Str(m.mtch)),
+        ("index", Term::Num(m.index)),
+        (
+            "groups",
+            Term::Array(
+                Array::from_iter(m.groups.into_iter().map(|s| Term::Str(s).into())),
+                ArrayAttrs::new().closurized()
+            )
+        ),
+        ("named_groups", Term::Record(named_groups))
+    )
+}
+
+/// Builds the result of a validation primop such as [`BinaryOp::NetValidate`]: `'Ok` if the
+/// input is valid, and `'Error message` otherwise.
+fn mk_validation_result(result: Result<(), impl std::fmt::Display>, pos: TermPos) -> RichTerm {
+    let term = match result {
+        Ok(()) => Term::Enum(LocIdent::from("Ok")),
+        Err(err) => Term::EnumVariant {
+            tag: LocIdent::from("Error"),
+            arg: mk_term::string(err.to_string()),
+            attrs: EnumVariantAttrs { closurized: true },
+        },
+    };
+
+    RichTerm::new(term, pos)
+}
+
+/// Parses a semantic version for the primop `primop`, turning a parse error into an
+/// [`EvalError`] located at `pos`.
+fn parse_semver(primop: &str, s: &str, pos: TermPos) -> Result<semver::Version, EvalError> {
+    semver::Version::parse(s).map_err(|err| {
+        EvalError::Other(
+            format!("{primop}: invalid semantic version `{s}`: {err}"),
+            pos,
+        )
+    })
+}
+
+/// Base64 engine for decoding, accepting input with or without padding.
+/// The largest amount by which `%num_bit_shift%` shifts a number to the left.
+const MAX_BIT_SHIFT: i64 = 1 << 20;
+
+const BASE64_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// URL-safe Base64 engine for decoding, accepting input with or without padding.
+const BASE64_URL_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Encode the bytes of a string as lowercase hexadecimal digits.
+fn encode_hex(s: &str) -> String {
+    s.bytes().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decode a pair of hexadecimal digits, in either case, to the corresponding byte.
+fn decode_hex_byte(pair: &[u8]) -> Option<u8> {
+    let digit = |d: u8| char::from(d).to_digit(16);
+
+    match pair {
+        [high, low] => Some((digit(*high)? * 16 + digit(*low)?) as u8),
+        _ => None,
+    }
+}
+
+/// Decode a string of hexadecimal digits. On error, return a description of the problem.
+fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() % 2 == 1 {
+        return Err(String::from(
+            "expected an even number of hexadecimal digits",
+        ));
+    }
+
+    s.as_bytes()
+        .chunks(2)
+        .enumerate()
+        .map(|(i, pair)| {
+            decode_hex_byte(pair).ok_or_else(|| {
+                format!(
+                    "invalid hexadecimal digits `{}` at offset {}",
+                    String::from_utf8_lossy(pair),
+                    i * 2
+                )
+            })
+        })
+        .collect()
+}
+
+/// Percent-encode a string, as specified by [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986).
+/// All the bytes of the UTF-8 encoding of the string are encoded, except unreserved characters,
+/// that is ASCII letters, digits, `-`, `.`, `_` and `~`.
+fn encode_percent(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            result.push(char::from(byte));
+        } else {
+            result.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    result
+}
+
+/// Decode a percent-encoded string. Contrary to form encoding, `+` isn't decoded to a space. On
+/// error, return a description of the problem.
+fn decode_percent(s: &str) -> Result<Vec<u8>, String> {
+    let bytes = s.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = bytes
+                .get(i + 1..i + 3)
+                .and_then(decode_hex_byte)
+                .ok_or_else(|| {
+                    format!(
+                        "invalid escape sequence at offset {i}: \
+                        `%` must be followed by two hexadecimal digits"
+                    )
+                })?;
+
+            result.push(byte);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Quote a string so that a POSIX shell interprets it as a single word, with the same semantics
+/// as Python's `shlex.quote`. Strings which only contain safe characters are left unchanged.
+/// Otherwise, the string is enclosed in single quotes, and single quotes are escaped as `'"'"'`.
+fn quote_shell(s: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./-_".contains(c);
+
+    if !s.is_empty() && s.chars().all(is_safe) {
+        s.to_owned()
+    } else {
+        format!("'{}'", s.replace('\'', r#"'"'"'"#))
+    }
+}
+
 /// Compute the equality of two terms, represented as closures.
 ///
 /// # Parameters
@@ -3124,6 +4253,24 @@ fn eq<C: Cache>(
         (Term::Lbl(l1), Term::Lbl(l2)) => Ok(EqResult::Bool(l1 == l2)),
         (Term::SealingKey(s1), Term::SealingKey(s2)) => Ok(EqResult::Bool(s1 == s2)),
         (Term::Enum(id1), Term::Enum(id2)) => Ok(EqResult::Bool(id1 == id2)),
+        (
+            Term::EnumVariant {
+                tag: tag1,
+                arg: arg1,
+                ..
+            },
+            Term::EnumVariant {
+                tag: tag2,
+                arg: arg2,
+                ..
+            },
+        ) => {
+            if tag1 == tag2 {
+                Ok(gen_eqs(cache, std::iter::once((arg1, arg2)), env1, env2))
+            } else {
+                Ok(EqResult::Bool(false))
+            }
+        }
         (Term::Record(r1), Term::Record(r2)) => {
             let merge::split::SplitResult {
                 left,
@@ -0,0 +1,521 @@
+//! Profiling of evaluation.
+//!
+//! When profiling is enabled, the [virtual machine][super::VirtualMachine] maintains a stack of
+//! profiling frames alongside its evaluation stack, and attributes each reduction step, as well as
+//! the time spent between two changes of the current frame, to the current stack of frames.
+//!
+//! Because Nickel is lazy, there is no well-delimited stack frame in general (see
+//! [super::callstack]). Frames are thus approximated as follows:
+//!
+//! - A *binding* frame (a variable, a record field or any other expression stored in the cache) is
+//!   entered when the machine starts evaluating a thunk, and is left when the result of this
+//!   evaluation is written back to the cache, which is exact.
+//! - A *function* frame is entered when the body of a function is entered, and is left when the
+//!   call stack is truncated below the corresponding call, that is when the primitive operation
+//!   waiting for the result of the call resumes, or when an enclosing binding frame is left.
+//!   Contracts are functions, and are named after the contract being applied.
+//!
+//! The result is a [Profile], which can be rendered as a flamegraph-compatible folded stack file
+//! or as a summary table of the most expensive frames.
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use codespan::Files;
+
+use crate::{
+    identifier::LocIdent,
+    label::Label,
+    position::{RawSpan, TermPos},
+};
+
+/// The name of the root frame, which collects the steps executed outside of any other frame.
+const ROOT_NAME: &str = "<main>";
+
+/// The maximum length of the name of a contract frame, in characters. Longer contracts, such as
+/// inline record contracts, are just named `contract`.
+const MAX_CONTRACT_NAME_LENGTH: usize = 40;
+
+/// The description of a profiling frame: a name and the position of the corresponding code.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FrameLabel {
+    pub name: String,
+    pub pos: TermPos,
+}
+
+impl FrameLabel {
+    /// Render the label as `name (file:line:col)`, or just `name` if the position is unknown.
+    pub fn render(&self, files: &Files<String>) -> String {
+        let Some(span) = self.pos.into_opt() else {
+            return self.name.clone();
+        };
+
+        match files.location(span.src_id, span.start) {
+            Ok(location) => format!(
+                "{} ({}:{}:{})",
+                self.name,
+                files.name(span.src_id).to_string_lossy(),
+                location.line.number(),
+                location.column.number()
+            ),
+            Err(_) => self.name.clone(),
+        }
+    }
+}
+
+/// A node of the tree of all the frame stacks encountered during profiling.
+#[derive(Clone, Debug)]
+struct Node {
+    /// The label of the frame, or `None` for the root.
+    label: Option<FrameLabel>,
+    parent: usize,
+    children: HashMap<FrameLabel, usize>,
+    /// The number of steps executed while this node was the top of the frame stack.
+    self_steps: u64,
+    /// The time spent while this node was the top of the frame stack.
+    self_time: Duration,
+}
+
+impl Node {
+    fn new(label: Option<FrameLabel>, parent: usize) -> Self {
+        Node {
+            label,
+            parent,
+            children: HashMap::new(),
+            self_steps: 0,
+            self_time: Duration::ZERO,
+        }
+    }
+}
+
+/// The condition for leaving an active frame.
+#[derive(Clone, Copy, Debug)]
+enum Extent {
+    /// The frame is left when the update index at the given position is popped from the stack.
+    Binding { marker: usize },
+    /// The frame is left when the call stack is truncated below the given length.
+    Function { call_stack_len: usize, app: RawSpan },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ActiveFrame {
+    node: usize,
+    extent: Extent,
+}
+
+/// The state of the profiler of the virtual machine.
+#[derive(Clone, Debug)]
+pub(super) struct Profiler {
+    nodes: Vec<Node>,
+    active: Vec<ActiveFrame>,
+    /// The names of the contracts which have been applied but whose body hasn't been entered yet,
+    /// indexed by the position of the corresponding application.
+    pending_contracts: HashMap<RawSpan, FrameLabel>,
+    /// The names of the record fields which are going to be forced without being accessed
+    /// explicitly, typically during a deep evaluation, indexed by the position of their value.
+    pending_fields: HashMap<RawSpan, LocIdent>,
+    /// The last time the current frame changed.
+    last_switch: Instant,
+}
+
+impl Profiler {
+    pub(super) fn new() -> Self {
+        Profiler {
+            nodes: vec![Node::new(None, 0)],
+            active: Vec::new(),
+            pending_contracts: HashMap::new(),
+            pending_fields: HashMap::new(),
+            last_switch: Instant::now(),
+        }
+    }
+
+    fn current(&self) -> usize {
+        self.active.last().map(|frame| frame.node).unwrap_or(0)
+    }
+
+    /// Attribute the time elapsed since the last switch to the current frame. Must be called
+    /// before any change to the active frames.
+    fn switch(&mut self) {
+        let now = Instant::now();
+        let current = self.current();
+        self.nodes[current].self_time += now - self.last_switch;
+        self.last_switch = now;
+    }
+
+    fn push(&mut self, label: FrameLabel, extent: Extent) {
+        self.switch();
+
+        let parent = self.current();
+        let node = match self.nodes[parent].children.get(&label) {
+            Some(node) => *node,
+            None => {
+                let node = self.nodes.len();
+                self.nodes.push(Node::new(Some(label.clone()), parent));
+                self.nodes[parent].children.insert(label, node);
+                node
+            }
+        };
+
+        self.active.push(ActiveFrame { node, extent });
+    }
+
+    fn truncate(&mut self, len: usize) {
+        if len < self.active.len() {
+            self.switch();
+            self.active.truncate(len);
+        }
+    }
+
+    /// Record a reduction step. Function frames whose call has been dropped from the call stack
+    /// are left first.
+    pub(super) fn step(&mut self, call_stack_len: usize) {
+        let len = self
+            .active
+            .iter()
+            .rposition(|frame| match frame.extent {
+                Extent::Function {
+                    call_stack_len: frame_len,
+                    ..
+                } => frame_len <= call_stack_len,
+                Extent::Binding { .. } => true,
+            })
+            .map(|index| index + 1)
+            .unwrap_or(0);
+
+        self.truncate(len);
+
+        let current = self.current();
+        self.nodes[current].self_steps += 1;
+    }
+
+    /// Enter a binding frame, whose update index is at the position `marker` on the stack.
+    ///
+    /// # Arguments
+    ///
+    /// - `var`: the name of the binding, if known.
+    /// - `pos`: the position of the expression being evaluated, for example a variable.
+    /// - `def_pos`: the position of the definition of the binding.
+    /// - `marker`: the position of the update index on the stack.
+    pub(super) fn enter_binding(
+        &mut self,
+        var: Option<LocIdent>,
+        pos: TermPos,
+        def_pos: TermPos,
+        marker: usize,
+    ) {
+        let var = var.or_else(|| {
+            pos.into_opt()
+                .and_then(|span| self.pending_fields.remove(&span))
+        });
+
+        let name = match var {
+            Some(id) if !id.is_generated() => id.to_string(),
+            _ => String::from("<expr>"),
+        };
+
+        let pos = if def_pos.is_def() { def_pos } else { pos };
+        self.push(FrameLabel { name, pos }, Extent::Binding { marker });
+    }
+
+    /// Record the names of record fields which are going to be forced without being accessed
+    /// through the usual field access operators, given the positions of their values.
+    pub(super) fn name_fields(&mut self, fields: impl Iterator<Item = (LocIdent, TermPos)>) {
+        self.pending_fields
+            .extend(fields.filter_map(|(id, pos)| pos.into_opt().map(|span| (span, id))));
+    }
+
+    /// Leave the binding frames whose update index isn't on the stack anymore, as well as all the
+    /// frames entered after them.
+    pub(super) fn leave_bindings(&mut self, stack_len: usize) {
+        let first_left = self.active.iter().position(
+            |frame| matches!(frame.extent, Extent::Binding { marker } if marker >= stack_len),
+        );
+
+        if let Some(index) = first_left {
+            self.truncate(index);
+        }
+    }
+
+    /// Record the application of a contract, whose body is going to be entered as a function
+    /// applied at the position `pos_app`.
+    pub(super) fn apply_contract(&mut self, label: &Label, pos_app: TermPos) {
+        let Some(app) = pos_app.into_opt() else {
+            return;
+        };
+
+        let typ = label.typ.to_string();
+        let name = if typ.len() <= MAX_CONTRACT_NAME_LENGTH && !typ.contains('\n') {
+            format!("contract {typ}")
+        } else {
+            String::from("contract")
+        };
+
+        self.pending_contracts.insert(
+            app,
+            FrameLabel {
+                name,
+                pos: label.span.into(),
+            },
+        );
+    }
+
+    /// Enter a function frame.
+    ///
+    /// # Arguments
+    ///
+    /// - `head`: the name of the called function, if any.
+    /// - `pos_fun`: the position of the definition of the function.
+    /// - `pos_app`: the position of the application.
+    /// - `call_stack_len`: the length of the call stack before the call was entered.
+    pub(super) fn enter_function(
+        &mut self,
+        head: Option<LocIdent>,
+        pos_fun: TermPos,
+        pos_app: TermPos,
+        call_stack_len: usize,
+    ) {
+        let Some(app) = pos_app.into_opt() else {
+            return;
+        };
+
+        // A multi-ary application `f x y` enters several function bodies, the first one being
+        // applied to the smallest application `f x`. They correspond to the same call.
+        if let Some(ActiveFrame {
+            extent: Extent::Function { app: previous, .. },
+            ..
+        }) = self.active.last()
+        {
+            if previous.src_id == app.src_id
+                && previous.start == app.start
+                && previous.end <= app.end
+            {
+                return;
+            }
+        }
+
+        let label = self
+            .pending_contracts
+            .remove(&app)
+            .unwrap_or_else(|| FrameLabel {
+                name: head
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| String::from("<func>")),
+                pos: if pos_fun.is_def() { pos_fun } else { pos_app },
+            });
+
+        self.push(
+            label,
+            Extent::Function {
+                call_stack_len,
+                app,
+            },
+        );
+    }
+
+    /// Leave all the frames, when the virtual machine is reset. The collected data are kept.
+    pub(super) fn reset(&mut self) {
+        self.truncate(0);
+        self.pending_contracts.clear();
+        self.pending_fields.clear();
+    }
+
+    /// Stop profiling and return the collected data.
+    pub(super) fn finish(mut self) -> Profile {
+        self.truncate(0);
+        self.switch();
+
+        Profile { nodes: self.nodes }
+    }
+}
+
+/// The aggregated cost of all the occurrences of a frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileEntry {
+    pub label: FrameLabel,
+    /// The number of steps executed directly in this frame.
+    pub self_steps: u64,
+    /// The number of steps executed in this frame or in the frames it entered. Recursive
+    /// occurrences of a frame are only counted once.
+    pub total_steps: u64,
+    /// The time spent directly in this frame.
+    pub self_time: Duration,
+    /// The time spent in this frame or in the frames it entered.
+    pub total_time: Duration,
+}
+
+/// The result of profiling one or several evaluations.
+#[derive(Clone, Debug)]
+pub struct Profile {
+    nodes: Vec<Node>,
+}
+
+impl Profile {
+    /// The total number of steps recorded.
+    pub fn total_steps(&self) -> u64 {
+        self.nodes.iter().map(|node| node.self_steps).sum()
+    }
+
+    /// Return the list of the stacks of frames, each stack being ordered from the root to the
+    /// innermost frame, together with the number of steps executed with this exact stack.
+    pub fn stacks(&self) -> Vec<(Vec<&FrameLabel>, u64)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.self_steps > 0)
+            .map(|(index, node)| {
+                let mut stack = Vec::new();
+                let mut current = index;
+
+                while current != 0 {
+                    let node = &self.nodes[current];
+                    stack.extend(node.label.as_ref());
+                    current = node.parent;
+                }
+
+                stack.reverse();
+                (stack, node.self_steps)
+            })
+            .collect()
+    }
+
+    /// Write the profile in the folded stack format understood by flamegraph tools: one line per
+    /// stack of frames, with the frames separated by semicolons, followed by the number of steps
+    /// executed with this stack.
+    pub fn write_folded(&self, out: &mut impl Write, files: &Files<String>) -> io::Result<()> {
+        for (stack, steps) in self.stacks() {
+            let frames: Vec<String> = std::iter::once(String::from(ROOT_NAME))
+                .chain(
+                    stack
+                        .into_iter()
+                        .map(|label| label.render(files).replace(';', ",")),
+                )
+                .collect();
+
+            writeln!(out, "{} {steps}", frames.join(";"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Aggregate the costs of the frames by label, sorted by decreasing number of self steps.
+    pub fn entries(&self) -> Vec<ProfileEntry> {
+        // Children are always created after their parent, so a reverse traversal accumulates the
+        // costs of the subtrees.
+        let mut totals: Vec<(u64, Duration)> = self
+            .nodes
+            .iter()
+            .map(|node| (node.self_steps, node.self_time))
+            .collect();
+
+        for index in (1..self.nodes.len()).rev() {
+            let parent = self.nodes[index].parent;
+            let (steps, time) = totals[index];
+            totals[parent].0 += steps;
+            totals[parent].1 += time;
+        }
+
+        let mut entries: HashMap<&FrameLabel, ProfileEntry> = HashMap::new();
+        // The number of occurrences of each label on the path from the root to the current node.
+        let mut on_path: HashMap<&FrameLabel, usize> = HashMap::new();
+        // A depth-first traversal, where `Err(index)` marks the end of the visit of a node.
+        let mut todo: Vec<Result<usize, usize>> = vec![Ok(0)];
+
+        while let Some(next) = todo.pop() {
+            match next {
+                Ok(index) => {
+                    let node = &self.nodes[index];
+
+                    if let Some(label) = &node.label {
+                        let entry = entries.entry(label).or_insert_with(|| ProfileEntry {
+                            label: label.clone(),
+                            self_steps: 0,
+                            total_steps: 0,
+                            self_time: Duration::ZERO,
+                            total_time: Duration::ZERO,
+                        });
+
+                        entry.self_steps += node.self_steps;
+                        entry.self_time += node.self_time;
+
+                        let count = on_path.entry(label).or_insert(0);
+
+                        // Only count the outermost occurrence of recursive frames.
+                        if *count == 0 {
+                            entry.total_steps += totals[index].0;
+                            entry.total_time += totals[index].1;
+                        }
+
+                        *count += 1;
+                    }
+
+                    todo.push(Err(index));
+                    todo.extend(node.children.values().map(|child| Ok(*child)));
+                }
+                Err(index) => {
+                    if let Some(count) = self.nodes[index]
+                        .label
+                        .as_ref()
+                        .and_then(|label| on_path.get_mut(label))
+                    {
+                        *count -= 1;
+                    }
+                }
+            }
+        }
+
+        let mut entries: Vec<ProfileEntry> = entries.into_values().collect();
+        entries.sort_by(|e1, e2| {
+            e2.self_steps
+                .cmp(&e1.self_steps)
+                .then_with(|| e2.total_steps.cmp(&e1.total_steps))
+                .then_with(|| e1.label.name.cmp(&e2.label.name))
+        });
+        entries
+    }
+
+    /// Write a table of the `top` frames with the most self steps.
+    pub fn write_summary(
+        &self,
+        out: &mut impl Write,
+        files: &Files<String>,
+        top: usize,
+    ) -> io::Result<()> {
+        let total = self.total_steps().max(1);
+
+        writeln!(
+            out,
+            "{:>12} {:>7} {:>12} {:>7} {:>10} {:>10}  frame",
+            "self steps", "self %", "total steps", "total %", "self time", "total time"
+        )?;
+
+        for entry in self.entries().into_iter().take(top) {
+            writeln!(
+                out,
+                "{:>12} {:>6.1}% {:>12} {:>6.1}% {:>10} {:>10}  {}",
+                entry.self_steps,
+                entry.self_steps as f64 * 100.0 / total as f64,
+                entry.total_steps,
+                entry.total_steps as f64 * 100.0 / total as f64,
+                format_duration(entry.self_time),
+                format_duration(entry.total_time),
+                entry.label.render(files)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let micros = duration.as_micros();
+
+    if micros < 1_000 {
+        format!("{micros}µs")
+    } else if micros < 1_000_000 {
+        format!("{:.1}ms", micros as f64 / 1_000.0)
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
@@ -178,7 +178,9 @@ pub mod ty_path {
             (TypeF::Record(rows), next @ Some(Elem::Field(ident))) => {
                 for row_item in rows.iter() {
                     match row_item {
-                        RecordRowsIteratorItem::Row(RecordRowF { id, typ: ty }) if id == *ident => {
+                        RecordRowsIteratorItem::Row(RecordRowF { id, typ: ty, .. })
+                            if id == *ident =>
+                        {
                             let path_span = span(path_it, ty)?;
 
                             return Some(PathSpan {
@@ -307,6 +309,90 @@ pub struct Label {
     /// while first transforming a record as part of the pending contract generation.
     /// Contract applications outside of records will have this field set to `None`.
     pub field_name: Option<LocIdent>,
+
+    /// The chain of contract applications which led to this label, from the outermost to the
+    /// innermost one. Only recorded when the virtual machine runs in explain-blame mode, and empty
+    /// otherwise.
+    pub trace: Vec<ContractStep>,
+
+    /// The branches of union and intersection contracts taken to reach the current contract, from
+    /// the outermost to the innermost one.
+    pub branches: Vec<ContractBranch>,
+}
+
+/// A branch of a union contract `A \/ B` or of an intersection contract `A /\ B`. See
+/// [Label::branches].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractBranch {
+    UnionLeft,
+    UnionRight,
+    IntersectionLeft,
+    IntersectionRight,
+}
+
+impl ContractBranch {
+    /// Parse a branch from the name of the enum tag used to represent it in Nickel code.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "UnionLeft" => Some(ContractBranch::UnionLeft),
+            "UnionRight" => Some(ContractBranch::UnionRight),
+            "IntersectionLeft" => Some(ContractBranch::IntersectionLeft),
+            "IntersectionRight" => Some(ContractBranch::IntersectionRight),
+            _ => None,
+        }
+    }
+
+    /// A short description of the branch, used in error messages.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ContractBranch::UnionLeft => "the left branch of a union contract",
+            ContractBranch::UnionRight => "the right branch of a union contract",
+            ContractBranch::IntersectionLeft => "the left branch of an intersection contract",
+            ContractBranch::IntersectionRight => "the right branch of an intersection contract",
+        }
+    }
+}
+
+/// A step of the chain of contract applications leading to a blame error. See [Label::trace].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractStep {
+    pub kind: ContractStepKind,
+    /// The position of the contract.
+    pub contract_pos: TermPos,
+    /// The position of the value the contract is applied to.
+    pub value_pos: TermPos,
+}
+
+/// The different kinds of contract applications.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContractStepKind {
+    /// A contract is applied to a value.
+    Apply,
+    /// A contract is lazily applied to the elements of an array.
+    ArrayElements,
+    /// A contract is lazily applied to the fields of a record.
+    RecordFields,
+    /// A record contract is merged with a record value, which applies the contracts of `field` in
+    /// the record contract to the corresponding field of the value.
+    Merge { field: LocIdent },
+}
+
+impl ContractStep {
+    /// A short description of the step, used in error messages.
+    pub fn description(&self) -> String {
+        match &self.kind {
+            ContractStepKind::Apply => String::from("contract applied to a value"),
+            ContractStepKind::ArrayElements => {
+                String::from("contract applied to the elements of an array")
+            }
+            ContractStepKind::RecordFields => {
+                String::from("contract applied to the fields of a record")
+            }
+            ContractStepKind::Merge { field } => {
+                format!("record contract merged with a record, checking the field `{field}`")
+            }
+        }
+    }
 }
 
 /// Data about type variables that is needed for polymorphic contracts to decide which actions to
@@ -503,6 +589,17 @@ impl Label {
         Label { field_name, ..self }
     }
 
+    /// Record a new step of the chain of contract applications leading to this label.
+    pub fn push_trace_step(&mut self, step: ContractStep) {
+        self.trace.push(step);
+    }
+
+    /// Record that the current contract is a branch of a union or an intersection contract.
+    pub fn go_branch(mut self, branch: ContractBranch) -> Self {
+        self.branches.push(branch);
+        self
+    }
+
     /// Tests if the contract associated to this label might have polymorphic subcontracts
     /// (equivalently, if the contract is derived from a type which has free type variables). Such
     /// contracts are special, in particular because they aren't idempotent and thus can't be
@@ -534,6 +631,8 @@ impl Default for Label {
             path: Default::default(),
             type_environment: Default::default(),
             field_name: None,
+            trace: Vec::new(),
+            branches: Vec::new(),
         }
     }
 }
@@ -11,6 +11,7 @@ pub mod label;
 #[cfg(feature = "nix-experimental")]
 pub mod nix_ffi;
 pub mod parser;
+pub mod persistent_cache;
 pub mod position;
 pub mod pretty;
 pub mod program;
@@ -23,6 +24,7 @@ pub mod typ;
 pub mod typecheck;
 
 pub(crate) mod metrics;
+pub(crate) mod parallel;
 
 #[cfg(feature = "format")]
 pub mod format;
@@ -0,0 +1,218 @@
+//! Parallel evaluation of programs for export.
+//!
+//! Exported configurations often consist of many independent fields, which could be evaluated
+//! concurrently. However, terms, environments and the evaluation cache are built on
+//! [std::rc::Rc] and can't be shared between threads. Instead, each worker thread owns a separate
+//! copy of the program, built once from a [Fork]. A worker evaluates its copy to a record, and
+//! then deeply evaluates the top-level fields it is assigned to, one after the other, in the same
+//! environment, such that the values shared by several fields are only computed once per worker.
+//! The results are sent back as [Value]s, a plain data representation of exportable values.
+//!
+//! Building a copy of the program means preparing the standard library and the sources of the
+//! program on each worker, which is only worth it for programs with many expensive fields. The
+//! [persistent cache][crate::persistent_cache], if enabled, is shared between workers and cuts
+//! most of this cost.
+//!
+//! Error reporting is deterministic: the fields are assigned in the order in which a sequential
+//! evaluation forces them, and once a field fails, the workers stop picking fields coming after it.
+//! Worker errors aren't sent back, as they refer to the terms and the sources of the copy. Instead,
+//! the fields which failed or were skipped are evaluated again on the calling thread, in order,
+//! which reports the same error as a sequential evaluation. Successful evaluations don't pay this
+//! cost.
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    cache::SourcePath,
+    eval::{cache::Cache as EvalCache, limits::EvalLimits, Closure, Environment},
+    identifier::{Ident, LocIdent},
+    persistent_cache::PersistentCache,
+    program::{FieldOverride, FieldPath, Program},
+    term::{array::Array, record::RecordData, Number, RichTerm, RuntimeContract, Term},
+};
+
+/// Everything needed to build a copy of a program on another thread.
+pub(crate) struct Fork {
+    pub(crate) source_path: SourcePath,
+    pub(crate) source: String,
+    pub(crate) overrides: Vec<FieldOverride>,
+    pub(crate) field: FieldPath,
+    pub(crate) limits: EvalLimits,
+    pub(crate) explain_blame: bool,
+    pub(crate) persistent_cache: Option<PersistentCache>,
+}
+
+/// A fully evaluated, exportable value. Unlike [RichTerm], values can be sent between threads.
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Num(Number),
+    Str(String),
+    Enum(Ident),
+    EnumVariant(Ident, Box<Value>),
+    Array(Vec<Value>),
+    Record(Vec<(Ident, Value)>),
+}
+
+impl Value {
+    /// Convert a fully evaluated term. Return `None` if the term contains values which can't be
+    /// exported, such as functions or fields without definition.
+    fn from_term(rt: &RichTerm) -> Option<Self> {
+        let value = match rt.as_ref() {
+            Term::Null => Value::Null,
+            Term::Bool(b) => Value::Bool(*b),
+            Term::Num(n) => Value::Num(n.clone()),
+            Term::Str(s) => Value::Str(s.to_string()),
+            Term::Enum(id) => Value::Enum(id.ident()),
+            Term::EnumVariant { tag, arg, .. } => {
+                Value::EnumVariant(tag.ident(), Box::new(Value::from_term(arg)?))
+            }
+            Term::Annotated(_, inner) => return Value::from_term(inner),
+            Term::Array(elements, _) => Value::Array(
+                elements
+                    .iter()
+                    .map(Value::from_term)
+                    .collect::<Option<_>>()?,
+            ),
+            Term::Record(record) => Value::Record(
+                record
+                    .iter_serializable()
+                    .map(|field| {
+                        let (id, value) = field.ok()?;
+                        Some((id, Value::from_term(value)?))
+                    })
+                    .collect::<Option<_>>()?,
+            ),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+}
+
+impl From<Value> for RichTerm {
+    fn from(value: Value) -> Self {
+        let term = match value {
+            Value::Null => Term::Null,
+            Value::Bool(b) => Term::Bool(b),
+            Value::Num(n) => Term::Num(n),
+            Value::Str(s) => Term::Str(s.into()),
+            Value::Enum(id) => Term::Enum(id.into()),
+            Value::EnumVariant(tag, arg) => Term::EnumVariant {
+                tag: tag.into(),
+                arg: (*arg).into(),
+                attrs: Default::default(),
+            },
+            Value::Array(elements) => Term::Array(
+                elements.into_iter().map(RichTerm::from).collect::<Array>(),
+                Default::default(),
+            ),
+            Value::Record(fields) => Term::Record(RecordData::with_field_values(
+                fields
+                    .into_iter()
+                    .map(|(id, value)| (id.into(), value.into())),
+            )),
+        };
+
+        term.into()
+    }
+}
+
+/// Return the fields of a record which are part of an export, in the order in which a sequential
+/// evaluation forces them. Return `None` if one of them doesn't have a definition, in which case
+/// the sequential evaluation fails right away.
+pub(crate) fn exported_fields(record: &RecordData) -> Option<Vec<LocIdent>> {
+    record
+        .fields
+        .iter()
+        .filter(|(_, field)| !field.metadata.not_exported && !field.is_empty_optional())
+        .map(|(id, field)| field.value.is_some().then_some(*id))
+        .collect()
+}
+
+/// Return the value of the field `id` of an evaluated record, with its pending contracts applied,
+/// as a closure ready to be evaluated. Return `None` if the field doesn't exist or doesn't have a
+/// definition.
+pub(crate) fn field_closure(
+    record: &RecordData,
+    env: &Environment,
+    id: LocIdent,
+) -> Option<Closure> {
+    let field = record.fields.get(&id)?;
+    let value = field.value.clone()?;
+    let pos = value.pos;
+
+    Some(Closure {
+        body: RuntimeContract::apply_all(value, field.pending_contracts.iter().cloned(), pos),
+        env: env.clone(),
+    })
+}
+
+/// Evaluate the top-level `fields` of the program described by `fork` on up to `jobs` worker
+/// threads, which must all be exported fields of the record the program evaluates to. Return the
+/// result of each field, in the order of `fields`, or `None` if the field failed or was skipped
+/// because a previous field failed.
+pub(crate) fn eval_fields<EC: EvalCache>(
+    fork: &Fork,
+    fields: &[LocIdent],
+    jobs: NonZeroUsize,
+) -> Vec<Option<Value>> {
+    let next = AtomicUsize::new(0);
+    let first_failure = AtomicUsize::new(usize::MAX);
+    let results = Mutex::new(Vec::from_iter(fields.iter().map(|_| None)));
+
+    let worker = || {
+        // The indices of the fields are taken in increasing order. Once a field has failed, the
+        // next ones aren't needed anymore, as the calling thread will stop at the failing field.
+        let next_field = || {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            (index < fields.len() && index < first_failure.load(Ordering::Relaxed)).then_some(index)
+        };
+        let fail = |index| {
+            first_failure.fetch_min(index, Ordering::Relaxed);
+        };
+
+        let Some(mut index) = next_field() else {
+            return;
+        };
+
+        let mut program = Program::<EC>::new_from_fork(fork);
+        let whnf = program.eval_whnf().ok();
+        let Some((Term::Record(record), env)) =
+            whnf.map(|closure| (closure.body.term.into_owned(), closure.env))
+        else {
+            fail(index);
+            return;
+        };
+
+        loop {
+            let value = field_closure(&record, &env, fields[index])
+                .and_then(|closure| program.eval_full_for_export_closure(closure).ok())
+                .and_then(|result| Value::from_term(&result));
+
+            match value {
+                Some(value) => results.lock().unwrap()[index] = Some(value),
+                None => fail(index),
+            }
+
+            match next_field() {
+                Some(next_index) => index = next_index,
+                None => break,
+            }
+        }
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.get().min(fields.len()) {
+            scope.spawn(worker);
+        }
+    });
+
+    results.into_inner().unwrap()
+}
@@ -106,6 +106,8 @@ pub enum NormalToken<'input> {
     Let,
     #[token("rec")]
     Rec,
+    #[token("type")]
+    Type,
     #[token("match")]
     Match,
 
@@ -155,6 +157,10 @@ pub enum NormalToken<'input> {
     At,
     #[token("&&")]
     DoubleAnd,
+    #[token("/\\")]
+    Intersection,
+    #[token("\\/")]
+    Union,
     #[token("||")]
     DoublePipe,
     #[token("!")]
@@ -282,6 +288,24 @@ pub enum NormalToken<'input> {
 
     #[token("%hash%")]
     OpHash,
+    #[token("%encode%")]
+    Encode,
+    #[token("%decode%")]
+    Decode,
+    #[token("%quote%")]
+    Quote,
+    #[token("%net_validate%")]
+    NetValidate,
+    #[token("%cidr_contains%")]
+    CidrContains,
+    #[token("%cidr_subnets%")]
+    CidrSubnets,
+    #[token("%semver_validate%")]
+    SemverValidate,
+    #[token("%semver_compare%")]
+    SemverCompare,
+    #[token("%semver_matches%")]
+    SemverMatches,
     #[token("%serialize%")]
     Serialize,
     #[token("%deserialize%")]
@@ -296,26 +320,74 @@ pub enum NormalToken<'input> {
     StrUppercase,
     #[token("%str_lowercase%")]
     StrLowercase,
+    #[token("%str_to_snake_case%")]
+    StrToSnakeCase,
+    #[token("%str_to_kebab_case%")]
+    StrToKebabCase,
+    #[token("%str_to_camel_case%")]
+    StrToCamelCase,
     #[token("%str_contains%")]
     StrContains,
+    #[token("%str_starts_with%")]
+    StrStartsWith,
+    #[token("%str_ends_with%")]
+    StrEndsWith,
+    #[token("%str_indent%")]
+    StrIndent,
+    #[token("%str_format%")]
+    StrFormat,
     #[token("%str_replace%")]
     StrReplace,
     #[token("%str_replace_regex%")]
     StrReplaceRegex,
+    #[token("%str_replace_regex_captures%")]
+    StrReplaceRegexCaptures,
+    #[token("%str_replace_regex_with%")]
+    StrReplaceRegexWith,
     #[token("%str_is_match%")]
     StrIsMatch,
     #[token("%str_find%")]
     StrFind,
+    #[token("%str_find_all%")]
+    StrFindAll,
     #[token("%str_length%")]
     StrLength,
     #[token("%str_substr%")]
     StrSubstr,
+    #[token("%str_pad_left%")]
+    StrPadLeft,
+    #[token("%str_pad_right%")]
+    StrPadRight,
     #[token("%to_str%")]
     ToStr,
     #[token("%num_from_str%")]
     NumFromStr,
+    #[token("%num_math%")]
+    NumMath,
+    #[token("%num_atan2%")]
+    NumAtan2,
+    #[token("%num_round%")]
+    NumRound,
+    #[token("%num_gcd%")]
+    NumGcd,
+    #[token("%num_bit_and%")]
+    NumBitAnd,
+    #[token("%num_bit_or%")]
+    NumBitOr,
+    #[token("%num_bit_xor%")]
+    NumBitXor,
+    #[token("%num_bit_shift%")]
+    NumBitShift,
+    #[token("%num_to_decimal_str%")]
+    NumToDecimalStr,
     #[token("%enum_from_str%")]
     EnumFromStr,
+    #[token("%enum_get_tag%")]
+    EnumGetTag,
+    #[token("%enum_get_arg%")]
+    EnumGetArg,
+    #[token("%enum_is_variant%")]
+    EnumIsVariant,
     #[token("%label_with_message%")]
     LabelWithMessage,
     #[token("%label_with_notes%")]
@@ -324,6 +396,10 @@ pub enum NormalToken<'input> {
     LabelAppendNote,
     #[token("%label_push_diag%")]
     LabelPushDiag,
+    #[token("%label_go_branch%")]
+    LabelGoBranch,
+    #[token("%contract_check%")]
+    ContractCheck,
     #[token("%array_slice%")]
     ArraySlice,
     #[token("%eval_nix%")]
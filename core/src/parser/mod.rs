@@ -1,3 +1,4 @@
+use crate::destructuring::Destruct;
 use crate::error::{ParseError, ParseErrors};
 use crate::identifier::LocIdent;
 use crate::position::RawSpan;
@@ -29,10 +30,17 @@ mod tests;
 /// nickel>let foo = 1
 /// nickel>foo
 /// 1
+/// nickel>let {bar, baz = { qux }} = { bar = 2, baz = { qux = 3 } }
+/// nickel>bar + qux
+/// 5
 /// ```
+#[derive(Debug)]
 pub enum ExtendedTerm {
     RichTerm(RichTerm),
     ToplevelLet(LocIdent, RichTerm),
+    /// A toplevel let-binding with a destructuring pattern, optionally aliased as in `let x @ {a,
+    /// b} = ...`.
+    ToplevelLetPattern(Option<LocIdent>, Destruct, RichTerm),
 }
 
 // The interface of LALRPOP-generated parsers, for each public rule. This trait is used as a facade
@@ -4,7 +4,7 @@ use super::lexer::{Lexer, MultiStringToken, NormalToken, StringToken, SymbolicSt
 use super::utils::{build_record, FieldPathElem};
 use crate::error::ParseError;
 use crate::identifier::LocIdent;
-use crate::parser::{error::ParseError as InternalParseError, ErrorTolerantParser};
+use crate::parser::{error::ParseError as InternalParseError, ErrorTolerantParser, ExtendedTerm};
 use crate::term::array::Array;
 use crate::term::Number;
 use crate::term::Term::*;
@@ -188,6 +188,42 @@ fn lets() {
     assert_matches!(parse("x (let x1 = x2 in x3) y"), Ok(..));
 }
 
+fn parse_extended(s: &str) -> Result<ExtendedTerm, ParseError> {
+    let id = Files::new().add("<test>", String::from(s));
+
+    super::grammar::ExtendedTermParser::new()
+        .parse_strict(id, Lexer::new(s))
+        .map_err(|errs| errs.errors.first().unwrap().clone())
+}
+
+#[test]
+fn toplevel_lets() {
+    assert_matches!(
+        parse_extended("let x = 1"),
+        Ok(ExtendedTerm::ToplevelLet(id, _)) if id.label() == "x"
+    );
+    assert_matches!(
+        parse_extended("let rec f = fun x => f x"),
+        Ok(ExtendedTerm::ToplevelLet(id, _)) if id.label() == "f"
+    );
+    assert_matches!(
+        parse_extended("let {a, b = { c }, ..rest} = x"),
+        Ok(ExtendedTerm::ToplevelLetPattern(None, ..))
+    );
+    assert_matches!(
+        parse_extended("let r @ {a} = x"),
+        Ok(ExtendedTerm::ToplevelLetPattern(Some(_), ..))
+    );
+    assert_matches!(
+        parse_extended("let rec {a} = x"),
+        Err(ParseError::RecursiveLetPattern(..))
+    );
+    assert_matches!(
+        parse_extended("let x = 1 in x"),
+        Ok(ExtendedTerm::RichTerm(..))
+    );
+}
+
 #[test]
 fn unary_op() {
     assert_eq!(
@@ -10,11 +10,11 @@ use crate::{
     position::{RawSpan, TermPos},
     term::{
         record::{Field, FieldMetadata, RecordAttrs},
-        LabeledType, MergePriority, RichTerm, Term, TypeAnnotation,
+        LabeledType, MergePriority, RichTerm, SharedTerm, Term, TypeAnnotation,
     },
     typ::{
-        DictTypeFlavour, EnumRows, EnumRowsIteratorItem, RecordRow, RecordRows, RecordRowsF, Type,
-        TypeF, VarKind,
+        DictTypeFlavour, EnumRows, EnumRowsF, RecordRow, RecordRows, RecordRowsF, Type, TypeF,
+        VarKind,
     },
 };
 
@@ -306,7 +306,7 @@ impl UniRecord {
                                     typ: Some(_),
                                     contracts,
                                 },
-                            opt: false,
+                            opt: _,
                             not_exported: false,
                             priority: MergePriority::Neutral,
                         },
@@ -341,7 +341,7 @@ impl UniRecord {
                                     typ: Some(labeled_ty),
                                     contracts,
                                 },
-                            opt: false,
+                            opt,
                             not_exported: false,
                             priority: MergePriority::Neutral,
                         },
@@ -352,6 +352,7 @@ impl UniRecord {
                     row: RecordRow {
                         id,
                         typ: Box::new(labeled_ty.typ),
+                        opt,
                     },
                     tail: Box::new(tail),
                 })),
@@ -669,13 +670,31 @@ impl FixTypeVars for Type {
             | TypeF::Bool
             | TypeF::String
             | TypeF::Symbol
-            | TypeF::Flat(_)
             // We don't fix type variables inside a dictionary contract. A dictionary contract
             // should not be considered as a static type, but instead work as a contract. In
             // particular mustn't be allowed to capture type variables from the enclosing type: see
             // https://github.com/tweag/nickel/issues/1228.
             | TypeF::Dict { flavour: DictTypeFlavour::Contract, ..}
             | TypeF::Wildcard(_) => Ok(()),
+            // A contract can't capture type variables in general, but the arguments of a contract
+            // application can, as in `forall a. Pair a -> a` where `Pair` is a type alias. The
+            // type arguments have already been fixed on their own when they were converted to
+            // terms, which turned the variables bound here into contracts: we turn them back into
+            // type variables.
+            TypeF::Flat(ref mut rt) => {
+                if let Term::Var(id) = rt.as_ref() {
+                    if let Some(cell) = bound_vars.get(&id.ident()) {
+                        let id = *id;
+                        cell.try_set(VarKind::Type)
+                            .map_err(|_| ParseError::TypeVariableKindMismatch { ty_var: id, span })?;
+                        self.typ = TypeF::Var(id.ident());
+                    }
+
+                    Ok(())
+                } else {
+                    fix_app_args_type_vars(rt, &bound_vars, span)
+                }
+            }
             TypeF::Arrow(ref mut s, ref mut t) => {
                 (*s).fix_type_vars_env(bound_vars.clone(), span)?;
                 (*t).fix_type_vars_env(bound_vars, span)?;
@@ -731,6 +750,44 @@ impl FixTypeVars for Type {
     }
 }
 
+/// Fix the type variables bound by an enclosing forall which appear as the arguments of the
+/// contract application `rt`, if `rt` is an application. See the `TypeF::Flat` case of
+/// [FixTypeVars::fix_type_vars_env].
+fn fix_app_args_type_vars(
+    rt: &mut RichTerm,
+    bound_vars: &BoundVarEnv,
+    span: RawSpan,
+) -> Result<(), ParseError> {
+    if !matches!(rt.as_ref(), Term::App(..)) {
+        return Ok(());
+    }
+
+    let Term::App(head, arg) = SharedTerm::make_mut(&mut rt.term) else {
+        unreachable!()
+    };
+
+    fix_app_args_type_vars(head, bound_vars, span)?;
+
+    match SharedTerm::make_mut(&mut arg.term) {
+        Term::Var(id) => {
+            if let Some(cell) = bound_vars.get(&id.ident()) {
+                let id = *id;
+                cell.try_set(VarKind::Type)
+                    .map_err(|_| ParseError::TypeVariableKindMismatch { ty_var: id, span })?;
+                *arg = RichTerm::new(
+                    Term::Type(Type::from(TypeF::Var(id.ident())).with_pos(arg.pos)),
+                    arg.pos,
+                );
+            }
+
+            Ok(())
+        }
+        Term::Type(ty) => ty.fix_type_vars_env(bound_vars.clone(), span),
+        Term::App(..) => fix_app_args_type_vars(arg, bound_vars, span),
+        _ => Ok(()),
+    }
+}
+
 impl FixTypeVars for RecordRows {
     fn fix_type_vars_env(
         &mut self,
@@ -778,26 +835,27 @@ impl FixTypeVars for EnumRows {
         bound_vars: BoundVarEnv,
         span: RawSpan,
     ) -> Result<(), ParseError> {
-        // An enum row doesn't contain any subtypes (beside other enum rows). No term variable can
-        // appear in it, so we don't have to traverse for fixing type variables properly.
-        //
-        // However, the second task of the fix_type_vars phase is to determine the variable kind of
-        // forall binders: here, we do need to check if the tail of this enum is an enum row type
-        // variable.
-        let mut iter = self
-            .iter()
-            .skip_while(|item| matches!(item, EnumRowsIteratorItem::Row(_)));
-        match iter.next() {
-            Some(EnumRowsIteratorItem::TailVar(id)) => {
+        match self.0 {
+            EnumRowsF::Empty => Ok(()),
+            // We can't have a contract in tail position, so we don't fix `TailVar`. However, we
+            // have to set the correct kind for the corresponding forall binder.
+            EnumRowsF::TailVar(ref id) => {
                 if let Some(cell) = bound_vars.get(&id.ident()) {
                     cell.try_set(VarKind::EnumRows)
                         .map_err(|_| ParseError::TypeVariableKindMismatch { ty_var: *id, span })?;
                 }
                 Ok(())
             }
-            // unreachable(): we consumed all the rows item via the `skip_while()` call above
-            Some(EnumRowsIteratorItem::Row(_)) => unreachable!(),
-            None => Ok(()),
+            EnumRowsF::Extend {
+                ref mut row,
+                ref mut tail,
+            } => {
+                if let Some(ref mut typ) = row.typ {
+                    typ.fix_type_vars_env(bound_vars.clone(), span)?;
+                }
+
+                tail.fix_type_vars_env(bound_vars, span)
+            }
         }
     }
 }
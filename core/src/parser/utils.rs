@@ -6,11 +6,11 @@ use std::rc::Rc;
 
 use codespan::FileId;
 
-use super::error::ParseError;
+use super::{error::ParseError, ExtendedTerm};
 
 use crate::{
     combine::Combine,
-    destructuring::FieldPattern,
+    destructuring::{Destruct, FieldPattern, MatchBranch, Pattern},
     eval::{
         merge::{merge_doc, split},
         operation::RecPriority,
@@ -73,11 +73,52 @@ pub enum StringEndDelimiter {
     Special,
 }
 
-/// Distinguish between a normal case `id => exp` and a default case `_ => exp`.
+/// The last element of a record or an array pattern in a match expression: either a normal
+/// element, or an ellipsis `..` matching the remaining fields or elements, which are optionally
+/// captured in a variable.
 #[derive(Clone, Debug)]
-pub enum MatchCase {
-    Normal(LocIdent, RichTerm),
-    Default(RichTerm),
+pub enum PatternLastElem<T> {
+    Elem(T),
+    Ellipsis(Option<LocIdent>),
+}
+
+/// Push the last element of a record or an array pattern, if any, to the other elements, and
+/// return whether the pattern is open together with the optional identifier capturing the rest.
+pub fn close_pattern_elems<T>(
+    elems: &mut Vec<T>,
+    last: Option<PatternLastElem<T>>,
+) -> (bool, Option<LocIdent>) {
+    match last {
+        Some(PatternLastElem::Elem(elem)) => {
+            elems.push(elem);
+            (false, None)
+        }
+        Some(PatternLastElem::Ellipsis(rest)) => (true, rest),
+        None => (false, None),
+    }
+}
+
+/// Build a match expression from its branches. Match expressions which only dispatch on enum tags,
+/// that is whose branches are unguarded enum tags, optionally followed by a final wildcard, are
+/// represented as a [Term::Match]. Other match expressions are represented as a
+/// [Term::MatchPattern].
+pub fn mk_match(branches: Vec<MatchBranch>) -> Term {
+    let mut cases = IndexMap::with_capacity(branches.len());
+    let mut default = None;
+
+    for (i, branch) in branches.iter().enumerate() {
+        let is_last = i + 1 == branches.len();
+
+        match (&branch.pattern, &branch.guard) {
+            (Pattern::Enum(tag, None), None) if !cases.contains_key(tag) => {
+                cases.insert(*tag, branch.body.clone());
+            }
+            (Pattern::Wildcard, None) if is_last => default = Some(branch.body.clone()),
+            _ => return Term::MatchPattern(branches),
+        }
+    }
+
+    Term::Match { cases, default }
 }
 
 /// Left hand side of a record field declaration.
@@ -654,12 +695,33 @@ pub fn mk_let(
         FieldPattern::Ident(id) if rec => Ok(mk_term::let_rec_in(id, t1, t2)),
         FieldPattern::Ident(id) => Ok(mk_term::let_in(id, t1, t2)),
         _ if rec => Err(ParseError::RecursiveLetPattern(span)),
-        FieldPattern::RecordPattern(pat) => {
-            let id: Option<LocIdent> = None;
-            Ok(mk_term::let_pat(id, pat, t1, t2))
+        assgn => {
+            let (alias, pattern) = split_alias(assgn);
+            Ok(mk_term::let_pat(alias, pattern, t1, t2))
         }
-        FieldPattern::AliasedRecordPattern { alias, pattern } => {
-            Ok(mk_term::let_pat(Some(alias), pattern, t1, t2))
+    }
+}
+
+/// Generate a toplevel let-binding (a let-binding without an `in`, only accepted by the REPL).
+///
+/// A recursive binding `let rec x = t` is turned into the non-recursive binding `let x = (let rec
+/// x = t in x)`, such that the REPL can handle all toplevel bindings uniformly.
+pub fn mk_toplevel_let(
+    rec: bool,
+    assgn: FieldPattern,
+    t: RichTerm,
+    span: RawSpan,
+) -> Result<ExtendedTerm, ParseError> {
+    match assgn {
+        FieldPattern::Ident(id) if rec => Ok(ExtendedTerm::ToplevelLet(
+            id,
+            mk_term::let_rec_in(id, t, mk_term::var(id)),
+        )),
+        FieldPattern::Ident(id) => Ok(ExtendedTerm::ToplevelLet(id, t)),
+        _ if rec => Err(ParseError::RecursiveLetPattern(span)),
+        assgn => {
+            let (alias, pattern) = split_alias(assgn);
+            Ok(ExtendedTerm::ToplevelLetPattern(alias, pattern, t))
         }
     }
 }
@@ -670,13 +732,25 @@ pub fn mk_let(
 pub fn mk_fun(assgn: FieldPattern, body: RichTerm) -> Term {
     match assgn {
         FieldPattern::Ident(id) => Term::Fun(id, body),
-        FieldPattern::RecordPattern(pat) => Term::FunPattern(None, pat, body),
-        FieldPattern::AliasedRecordPattern { alias, pattern } => {
-            Term::FunPattern(Some(alias), pattern, body)
+        assgn => {
+            let (alias, pattern) = split_alias(assgn);
+            Term::FunPattern(alias, pattern, body)
         }
     }
 }
 
+/// Split a destructuring pattern into its optional alias and the pattern itself. Panics if the
+/// pattern is a simple identifier.
+fn split_alias(assgn: FieldPattern) -> (Option<LocIdent>, Destruct) {
+    match assgn {
+        FieldPattern::Ident(_) => panic!("split_alias: expected a destructuring pattern"),
+        FieldPattern::RecordPattern(pattern) => (None, pattern.into()),
+        FieldPattern::ArrayPattern(pattern) => (None, pattern.into()),
+        FieldPattern::AliasedRecordPattern { alias, pattern } => (Some(alias), pattern.into()),
+        FieldPattern::AliasedArrayPattern { alias, pattern } => (Some(alias), pattern.into()),
+    }
+}
+
 /// Determine the minimal level of indentation of a multi-line string.
 ///
 /// The result is determined by computing the minimum indentation level among all lines, where the
@@ -0,0 +1,1327 @@
+//! Binary encoding of the terms stored in the [persistent cache](super).
+//!
+//! The encoding is a straightforward, compact serialization of the AST. It isn't meant to be
+//! stable: the persistent cache is invalidated whenever the version of Nickel or the sources of the
+//! interpreter change.
+//!
+//! Terms are shared (through [SharedTerm]) after program transformations, in particular the
+//! contracts and labels generated for each field of a record. Shared terms and label types are
+//! thus encoded only once, and referred to by their index afterwards, which also preserves the
+//! sharing upon decoding.
+//!
+//! Some terms only appear during evaluation (closures, sealing keys, etc.) and are not supported:
+//! trying to encode them fails with [CodecError::Unsupported], in which case the source is simply
+//! not persisted.
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    hash::Hash,
+    rc::Rc,
+    str::FromStr,
+};
+
+use codespan::{ByteIndex, FileId};
+use indexmap::IndexMap;
+
+use crate::{
+    destructuring::{
+        ArrayElem, ArrayPattern, Destruct, FieldPattern, Match, MatchBranch, Pattern, RecordPattern,
+    },
+    identifier::{Ident, LocIdent, GEN_PREFIX},
+    label::{
+        ty_path::Elem, ContractBranch, ContractDiagnostic, ContractStep, ContractStepKind, Label,
+        MergeKind, MergeLabel, Polarity,
+    },
+    position::{RawSpan, TermPos},
+    term::{
+        array::{Array, ArrayAttrs},
+        record::{Field, FieldDeps, FieldMetadata, RecordAttrs, RecordData, RecordDeps},
+        string::NickelString,
+        BinaryOp, BindingType, CompiledRegex, EnumVariantAttrs, LabeledType, LetAttrs,
+        MergePriority, NAryOp, Number, RecordExtKind, RecordOpKind, RichTerm, RuntimeContract,
+        SharedTerm, StrChunk, Term, TypeAnnotation, UnaryOp,
+    },
+    typ::{
+        DictTypeFlavour, EnumRowF, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, Type,
+        TypeF, VarKind,
+    },
+};
+
+use super::FileRef;
+
+/// An error occurring when encoding or decoding a term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecError {
+    /// The value contains a construct which can't be persisted.
+    Unsupported,
+    /// The encoded data is invalid.
+    Malformed,
+}
+
+pub(super) struct Encoder<'a> {
+    buffer: Vec<u8>,
+    /// The files that positions and resolved imports are allowed to refer to, together with their
+    /// reference to be stored on disk.
+    allowed_files: &'a HashMap<FileId, FileRef>,
+    /// The files actually referred to, in order of first occurrence. Files are encoded as their
+    /// index in this table.
+    files: Vec<FileRef>,
+    file_indices: HashMap<FileId, usize>,
+    terms: HashMap<*const Term, usize>,
+    types: HashMap<*const Type, usize>,
+}
+
+impl<'a> Encoder<'a> {
+    pub(super) fn new(allowed_files: &'a HashMap<FileId, FileRef>) -> Self {
+        Encoder {
+            buffer: Vec::new(),
+            allowed_files,
+            files: Vec::new(),
+            file_indices: HashMap::new(),
+            terms: HashMap::new(),
+            types: HashMap::new(),
+        }
+    }
+
+    /// Return the encoded data and the table of the files it refers to.
+    pub(super) fn finish(self) -> (Vec<u8>, Vec<FileRef>) {
+        (self.buffer, self.files)
+    }
+
+    pub(super) fn u8(&mut self, byte: u8) {
+        self.buffer.push(byte);
+    }
+
+    /// Encode an unsigned integer using a variable length encoding (LEB128).
+    pub(super) fn u64(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.buffer.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        self.buffer.push(n as u8);
+    }
+
+    pub(super) fn usize(&mut self, n: usize) {
+        self.u64(n as u64);
+    }
+
+    pub(super) fn bytes(&mut self, bytes: &[u8]) {
+        self.usize(bytes.len());
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub(super) fn str(&mut self, s: &str) {
+        self.bytes(s.as_bytes());
+    }
+
+    fn file(&mut self, file_id: FileId) -> Result<(), CodecError> {
+        let index = match self.file_indices.get(&file_id) {
+            Some(index) => *index,
+            None => {
+                let file = self
+                    .allowed_files
+                    .get(&file_id)
+                    .ok_or(CodecError::Unsupported)?;
+                let index = self.files.len();
+                self.files.push(file.clone());
+                self.file_indices.insert(file_id, index);
+                index
+            }
+        };
+
+        self.usize(index);
+        Ok(())
+    }
+
+    fn shared_term(&mut self, term: &SharedTerm) -> Result<(), CodecError> {
+        let ptr: *const Term = term.as_ref();
+
+        if let Some(index) = self.terms.get(&ptr).copied() {
+            self.u8(1);
+            self.usize(index);
+        } else {
+            self.u8(0);
+            term.as_ref().encode(self)?;
+            self.terms.insert(ptr, self.terms.len());
+        }
+
+        Ok(())
+    }
+
+    fn shared_type(&mut self, typ: &Rc<Type>) -> Result<(), CodecError> {
+        let ptr = Rc::as_ptr(typ);
+
+        if let Some(index) = self.types.get(&ptr).copied() {
+            self.u8(1);
+            self.usize(index);
+        } else {
+            self.u8(0);
+            typ.as_ref().encode(self)?;
+            self.types.insert(ptr, self.types.len());
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) struct Decoder<'a> {
+    data: &'a [u8],
+    /// The files referred to by the encoded data, in the order of the table built by the
+    /// [Encoder].
+    files: Vec<FileId>,
+    terms: Vec<SharedTerm>,
+    types: Vec<Rc<Type>>,
+    /// Generated identifiers are only unique within a session, and are thus renamed to fresh
+    /// identifiers upon decoding.
+    generated: HashMap<String, Ident>,
+}
+
+impl<'a> Decoder<'a> {
+    pub(super) fn new(data: &'a [u8]) -> Self {
+        Decoder {
+            data,
+            files: Vec::new(),
+            terms: Vec::new(),
+            types: Vec::new(),
+            generated: HashMap::new(),
+        }
+    }
+
+    /// The number of bytes which remain to be decoded.
+    pub(super) fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Set the files referred to by the data that remains to be decoded.
+    pub(super) fn set_files(&mut self, files: Vec<FileId>) {
+        self.files = files;
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, CodecError> {
+        let (byte, rest) = self.data.split_first().ok_or(CodecError::Malformed)?;
+        self.data = rest;
+        Ok(*byte)
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, CodecError> {
+        let mut result = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.u8()?;
+
+            if shift >= 64 {
+                return Err(CodecError::Malformed);
+            }
+
+            result |= u64::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+
+            shift += 7;
+        }
+    }
+
+    pub(super) fn usize(&mut self) -> Result<usize, CodecError> {
+        usize::try_from(self.u64()?).map_err(|_| CodecError::Malformed)
+    }
+
+    pub(super) fn bytes(&mut self) -> Result<&'a [u8], CodecError> {
+        let len = self.usize()?;
+
+        if len > self.data.len() {
+            return Err(CodecError::Malformed);
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    pub(super) fn str(&mut self) -> Result<&'a str, CodecError> {
+        std::str::from_utf8(self.bytes()?).map_err(|_| CodecError::Malformed)
+    }
+
+    /// Decode the length of a sequence, making sure that it's not larger than the remaining data
+    /// (each element takes at least one byte), to avoid huge allocations on malformed data.
+    fn len(&mut self) -> Result<usize, CodecError> {
+        let len = self.usize()?;
+
+        if len > self.data.len() {
+            Err(CodecError::Malformed)
+        } else {
+            Ok(len)
+        }
+    }
+
+    fn file(&mut self) -> Result<FileId, CodecError> {
+        let index = self.usize()?;
+        self.files.get(index).copied().ok_or(CodecError::Malformed)
+    }
+
+    fn ident(&mut self) -> Result<Ident, CodecError> {
+        let label = self.str()?;
+
+        if label.starts_with(GEN_PREFIX) {
+            Ok(*self
+                .generated
+                .entry(label.to_owned())
+                .or_insert_with(|| LocIdent::fresh().ident()))
+        } else {
+            Ok(Ident::new(label))
+        }
+    }
+
+    fn shared_term(&mut self) -> Result<SharedTerm, CodecError> {
+        match self.u8()? {
+            0 => {
+                let term = SharedTerm::new(Term::decode(self)?);
+                self.terms.push(term.clone());
+                Ok(term)
+            }
+            1 => {
+                let index = self.usize()?;
+                self.terms.get(index).cloned().ok_or(CodecError::Malformed)
+            }
+            _ => Err(CodecError::Malformed),
+        }
+    }
+
+    fn shared_type(&mut self) -> Result<Rc<Type>, CodecError> {
+        match self.u8()? {
+            0 => {
+                let typ = Rc::new(Type::decode(self)?);
+                self.types.push(typ.clone());
+                Ok(typ)
+            }
+            1 => {
+                let index = self.usize()?;
+                self.types.get(index).cloned().ok_or(CodecError::Malformed)
+            }
+            _ => Err(CodecError::Malformed),
+        }
+    }
+}
+
+/// A value which can be stored in the persistent cache.
+pub(super) trait Persist: Sized {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError>;
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError>;
+}
+
+/// Implement [Persist] for a struct by encoding each of its fields in order. The fields must be
+/// listed exhaustively.
+macro_rules! persist_struct {
+    ($ty:ident { $($field:ident),* $(,)? }) => {
+        impl Persist for $ty {
+            fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+                let $ty { $($field),* } = self;
+                $($field.encode(enc)?;)*
+                Ok(())
+            }
+
+            fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+                Ok($ty {
+                    $($field: Persist::decode(dec)?),*
+                })
+            }
+        }
+    };
+}
+
+/// Implement [Persist] for an enum by encoding the given tag of a variant followed by its fields.
+/// The variants must be listed exhaustively, either as unit variants (`Foo`), tuple variants with
+/// a name for each field (`Foo(x, y)`, including unit-like tuple variants `Foo()`) or struct
+/// variants (`Foo { x, y }`).
+macro_rules! persist_enum {
+    (
+        $ty:ty as $path:ident {
+            $(
+                $(#[$attr:meta])*
+                $tag:literal => $variant:ident
+                    $(( $($field:ident),* ))?
+                    $({ $($named:ident),* })?
+            ),* $(,)?
+        }
+    ) => {
+        impl Persist for $ty {
+            fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+                match self {
+                    $(
+                        $(#[$attr])*
+                        $path::$variant $(( $($field),* ))? $({ $($named),* })? => {
+                            enc.u8($tag);
+                            $($($field.encode(enc)?;)*)?
+                            $($($named.encode(enc)?;)*)?
+                        }
+                    )*
+                }
+
+                Ok(())
+            }
+
+            fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+                match dec.u8()? {
+                    $(
+                        $(#[$attr])*
+                        $tag => Ok($path::$variant
+                            $(( $({ let $field = Persist::decode(dec)?; $field }),* ))?
+                            $({ $($named: Persist::decode(dec)?),* })?
+                        ),
+                    )*
+                    _ => Err(CodecError::Malformed),
+                }
+            }
+        }
+    };
+}
+
+impl Persist for bool {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.u8(u8::from(*self));
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        match dec.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(CodecError::Malformed),
+        }
+    }
+}
+
+impl Persist for usize {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.usize(*self);
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.usize()
+    }
+}
+
+impl Persist for i32 {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.is_negative().encode(enc)?;
+        enc.u64(self.unsigned_abs().into());
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let negative = bool::decode(dec)?;
+        let abs = i64::from(u32::try_from(dec.u64()?).map_err(|_| CodecError::Malformed)?);
+        let n = if negative { -abs } else { abs };
+        i32::try_from(n).map_err(|_| CodecError::Malformed)
+    }
+}
+
+impl Persist for String {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(self);
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.str().map(String::from)
+    }
+}
+
+impl Persist for OsString {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(self.to_str().ok_or(CodecError::Unsupported)?);
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.str().map(OsString::from)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        match self {
+            None => {
+                enc.u8(0);
+                Ok(())
+            }
+            Some(value) => {
+                enc.u8(1);
+                value.encode(enc)
+            }
+        }
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        match dec.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(dec)?)),
+            _ => Err(CodecError::Malformed),
+        }
+    }
+}
+
+impl<T: Persist> Persist for Box<T> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.as_ref().encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        T::decode(dec).map(Box::new)
+    }
+}
+
+impl<T: Persist> Persist for Rc<T> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.as_ref().encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        T::decode(dec).map(Rc::new)
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.usize(self.len());
+        self.iter().try_for_each(|elt| elt.encode(enc))
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let len = dec.len()?;
+        (0..len).map(|_| T::decode(dec)).collect()
+    }
+}
+
+impl<T: Persist + Eq + Hash> Persist for HashSet<T> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.usize(self.len());
+        self.iter().try_for_each(|elt| elt.encode(enc))
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let len = dec.len()?;
+        (0..len).map(|_| T::decode(dec)).collect()
+    }
+}
+
+impl<K: Persist + Eq + Hash, V: Persist> Persist for IndexMap<K, V> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.usize(self.len());
+        self.iter().try_for_each(|(key, value)| {
+            key.encode(enc)?;
+            value.encode(enc)
+        })
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let len = dec.len()?;
+        (0..len)
+            .map(|_| Ok((K::decode(dec)?, V::decode(dec)?)))
+            .collect()
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.0.encode(enc)?;
+        self.1.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Ok((A::decode(dec)?, B::decode(dec)?))
+    }
+}
+
+impl Persist for FileId {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.file(*self)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.file()
+    }
+}
+
+impl Persist for ByteIndex {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.u64(self.0.into());
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let index = dec.u64()?;
+        Ok(ByteIndex(
+            index.try_into().map_err(|_| CodecError::Malformed)?,
+        ))
+    }
+}
+
+persist_struct!(RawSpan { src_id, start, end });
+
+persist_enum!(TermPos as TermPos {
+    0 => None,
+    1 => Original(span),
+    2 => Inherited(span),
+});
+
+impl Persist for Ident {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(self.label());
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.ident()
+    }
+}
+
+impl Persist for LocIdent {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.ident().encode(enc)?;
+        self.pos.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let ident = dec.ident()?;
+        let pos = TermPos::decode(dec)?;
+        Ok(LocIdent::from(ident).with_pos(pos))
+    }
+}
+
+impl Persist for Number {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(&self.to_string());
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Number::from_str(dec.str()?).map_err(|_| CodecError::Malformed)
+    }
+}
+
+impl Persist for NickelString {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(self.as_str());
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.str().map(NickelString::from)
+    }
+}
+
+impl Persist for CompiledRegex {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(self.0.as_str());
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        regex::Regex::new(dec.str()?)
+            .map(CompiledRegex)
+            .map_err(|_| CodecError::Malformed)
+    }
+}
+
+impl Persist for RichTerm {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.shared_term(&self.term)?;
+        self.pos.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let term = dec.shared_term()?;
+        let pos = TermPos::decode(dec)?;
+        Ok(RichTerm { term, pos })
+    }
+}
+
+impl Persist for Term {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        match self {
+            Term::Null => enc.u8(0),
+            Term::Bool(b) => {
+                enc.u8(1);
+                b.encode(enc)?;
+            }
+            Term::Num(n) => {
+                enc.u8(2);
+                n.encode(enc)?;
+            }
+            Term::Str(s) => {
+                enc.u8(3);
+                s.encode(enc)?;
+            }
+            Term::StrChunks(chunks) => {
+                enc.u8(4);
+                chunks.encode(enc)?;
+            }
+            Term::Fun(id, body) => {
+                enc.u8(5);
+                id.encode(enc)?;
+                body.encode(enc)?;
+            }
+            Term::FunPattern(id, pat, body) => {
+                enc.u8(6);
+                id.encode(enc)?;
+                pat.encode(enc)?;
+                body.encode(enc)?;
+            }
+            Term::Lbl(label) => {
+                enc.u8(7);
+                label.encode(enc)?;
+            }
+            Term::Let(id, bound, body, attrs) => {
+                enc.u8(8);
+                id.encode(enc)?;
+                bound.encode(enc)?;
+                body.encode(enc)?;
+                attrs.encode(enc)?;
+            }
+            Term::LetPattern(id, pat, bound, body) => {
+                enc.u8(9);
+                id.encode(enc)?;
+                pat.encode(enc)?;
+                bound.encode(enc)?;
+                body.encode(enc)?;
+            }
+            Term::App(head, arg) => {
+                enc.u8(10);
+                head.encode(enc)?;
+                arg.encode(enc)?;
+            }
+            Term::Var(id) => {
+                enc.u8(11);
+                id.encode(enc)?;
+            }
+            Term::Enum(id) => {
+                enc.u8(12);
+                id.encode(enc)?;
+            }
+            Term::Record(data) => {
+                enc.u8(13);
+                data.encode(enc)?;
+            }
+            Term::RecRecord(data, dyn_fields, deps) => {
+                enc.u8(14);
+                data.encode(enc)?;
+                dyn_fields.encode(enc)?;
+                deps.encode(enc)?;
+            }
+            Term::Match { cases, default } => {
+                enc.u8(15);
+                cases.encode(enc)?;
+                default.encode(enc)?;
+            }
+            Term::Array(array, attrs) => {
+                enc.u8(16);
+                array.encode(enc)?;
+                attrs.encode(enc)?;
+            }
+            Term::Op1(op, t) => {
+                enc.u8(17);
+                op.encode(enc)?;
+                t.encode(enc)?;
+            }
+            Term::Op2(op, t1, t2) => {
+                enc.u8(18);
+                op.encode(enc)?;
+                t1.encode(enc)?;
+                t2.encode(enc)?;
+            }
+            Term::OpN(op, args) => {
+                enc.u8(19);
+                op.encode(enc)?;
+                args.encode(enc)?;
+            }
+            Term::Annotated(annot, t) => {
+                enc.u8(20);
+                annot.encode(enc)?;
+                t.encode(enc)?;
+            }
+            Term::Import(path) => {
+                enc.u8(21);
+                path.encode(enc)?;
+            }
+            Term::ResolvedImport(file_id) => {
+                enc.u8(22);
+                file_id.encode(enc)?;
+            }
+            Term::Type(typ) => {
+                enc.u8(23);
+                typ.encode(enc)?;
+            }
+            // Sealing keys appear in the contracts of polymorphic types, where they are numbered
+            // locally, but not the sealed terms, which are only created during evaluation.
+            Term::SealingKey(key) => {
+                enc.u8(24);
+                key.encode(enc)?;
+            }
+            Term::MatchPattern(branches) => {
+                enc.u8(25);
+                branches.encode(enc)?;
+            }
+            Term::EnumVariant { tag, arg, attrs } => {
+                enc.u8(26);
+                tag.encode(enc)?;
+                arg.encode(enc)?;
+                attrs.encode(enc)?;
+            }
+            // Those terms don't appear in successfully prepared sources.
+            Term::Sealed(..) | Term::ParseError(_) | Term::RuntimeError(_) | Term::Closure(_) => {
+                return Err(CodecError::Unsupported)
+            }
+        }
+
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let term = match dec.u8()? {
+            0 => Term::Null,
+            1 => Term::Bool(Persist::decode(dec)?),
+            2 => Term::Num(Persist::decode(dec)?),
+            3 => Term::Str(Persist::decode(dec)?),
+            4 => Term::StrChunks(Persist::decode(dec)?),
+            5 => Term::Fun(Persist::decode(dec)?, Persist::decode(dec)?),
+            6 => Term::FunPattern(
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+            ),
+            7 => Term::Lbl(Persist::decode(dec)?),
+            8 => Term::Let(
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+            ),
+            9 => Term::LetPattern(
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+            ),
+            10 => Term::App(Persist::decode(dec)?, Persist::decode(dec)?),
+            11 => Term::Var(Persist::decode(dec)?),
+            12 => Term::Enum(Persist::decode(dec)?),
+            13 => Term::Record(Persist::decode(dec)?),
+            14 => Term::RecRecord(
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+            ),
+            15 => Term::Match {
+                cases: Persist::decode(dec)?,
+                default: Persist::decode(dec)?,
+            },
+            16 => Term::Array(Persist::decode(dec)?, Persist::decode(dec)?),
+            17 => Term::Op1(Persist::decode(dec)?, Persist::decode(dec)?),
+            18 => Term::Op2(
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+                Persist::decode(dec)?,
+            ),
+            19 => Term::OpN(Persist::decode(dec)?, Persist::decode(dec)?),
+            20 => Term::Annotated(Persist::decode(dec)?, Persist::decode(dec)?),
+            21 => Term::Import(Persist::decode(dec)?),
+            22 => Term::ResolvedImport(Persist::decode(dec)?),
+            23 => Term::Type(Persist::decode(dec)?),
+            24 => Term::SealingKey(Persist::decode(dec)?),
+            25 => Term::MatchPattern(Persist::decode(dec)?),
+            26 => Term::EnumVariant {
+                tag: Persist::decode(dec)?,
+                arg: Persist::decode(dec)?,
+                attrs: Persist::decode(dec)?,
+            },
+            _ => return Err(CodecError::Malformed),
+        };
+
+        Ok(term)
+    }
+}
+
+persist_enum!(StrChunk<RichTerm> as StrChunk {
+    0 => Literal(s),
+    1 => Expr(t, indent),
+});
+
+persist_struct!(LetAttrs {
+    binding_type,
+    rec,
+    type_alias,
+});
+
+persist_enum!(BindingType as BindingType {
+    0 => Normal,
+    1 => Revertible(deps),
+});
+
+persist_enum!(FieldDeps as FieldDeps {
+    0 => Known(deps),
+    1 => Unknown,
+});
+
+persist_struct!(RecordDeps {
+    stat_fields,
+    dyn_fields,
+});
+
+persist_struct!(RecordAttrs { open, closurized });
+
+impl Persist for RecordData {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        let RecordData {
+            fields,
+            attrs,
+            sealed_tail,
+        } = self;
+
+        // The sealed tail of a record is only set during evaluation.
+        if sealed_tail.is_some() {
+            return Err(CodecError::Unsupported);
+        }
+
+        fields.encode(enc)?;
+        attrs.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Ok(RecordData {
+            fields: Persist::decode(dec)?,
+            attrs: Persist::decode(dec)?,
+            sealed_tail: None,
+        })
+    }
+}
+
+persist_struct!(Field {
+    value,
+    metadata,
+    pending_contracts,
+});
+
+persist_struct!(FieldMetadata {
+    doc,
+    annotation,
+    opt,
+    not_exported,
+    priority,
+});
+
+persist_enum!(MergePriority as MergePriority {
+    0 => Bottom,
+    1 => Neutral,
+    2 => Numeral(n),
+    3 => Top,
+});
+
+persist_struct!(TypeAnnotation { typ, contracts });
+
+persist_struct!(LabeledType { typ, label });
+
+persist_struct!(RuntimeContract { contract, label });
+
+impl Persist for Array {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.usize(self.len());
+        self.iter().try_for_each(|elt| elt.encode(enc))
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        let len = dec.len()?;
+        (0..len).map(|_| RichTerm::decode(dec)).collect()
+    }
+}
+
+persist_struct!(ArrayAttrs {
+    closurized,
+    pending_contracts,
+});
+
+persist_struct!(EnumVariantAttrs { closurized });
+
+persist_struct!(RecordPattern {
+    matches,
+    open,
+    rest,
+    span,
+});
+
+persist_struct!(ArrayPattern {
+    elements,
+    open,
+    rest,
+    span,
+});
+
+persist_struct!(ArrayElem { pattern, field });
+
+persist_enum!(Destruct as Destruct {
+    0 => Record(pat),
+    1 => Array(pat),
+});
+
+persist_enum!(Match as Match {
+    0 => Assign(id, field, pat),
+    1 => Simple(id, field),
+});
+
+persist_enum!(FieldPattern as FieldPattern {
+    0 => Ident(id),
+    1 => RecordPattern(pat),
+    2 => AliasedRecordPattern { alias, pattern },
+    3 => ArrayPattern(pat),
+    4 => AliasedArrayPattern { alias, pattern },
+});
+
+persist_enum!(Pattern as Pattern {
+    0 => Wildcard,
+    1 => Any(id),
+    2 => Constant(t),
+    3 => Enum(id, arg),
+    4 => Record { fields, open, rest },
+    5 => Array { elements, open, rest },
+    6 => Alias(id, pattern),
+});
+
+persist_struct!(MatchBranch {
+    pattern,
+    guard,
+    body,
+});
+
+impl Persist for Label {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        let Label {
+            typ,
+            diagnostics,
+            span,
+            arg_idx,
+            arg_pos,
+            polarity,
+            path,
+            type_environment,
+            field_name,
+            trace,
+            branches,
+        } = self;
+
+        // The argument and the type environment are only set during evaluation.
+        if arg_idx.is_some() || !type_environment.is_empty() {
+            return Err(CodecError::Unsupported);
+        }
+
+        enc.shared_type(typ)?;
+        diagnostics.encode(enc)?;
+        span.encode(enc)?;
+        arg_pos.encode(enc)?;
+        polarity.encode(enc)?;
+        path.encode(enc)?;
+        field_name.encode(enc)?;
+        trace.encode(enc)?;
+        branches.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Ok(Label {
+            typ: dec.shared_type()?,
+            diagnostics: Persist::decode(dec)?,
+            span: Persist::decode(dec)?,
+            arg_idx: None,
+            arg_pos: Persist::decode(dec)?,
+            polarity: Persist::decode(dec)?,
+            path: Persist::decode(dec)?,
+            type_environment: HashMap::new(),
+            field_name: Persist::decode(dec)?,
+            trace: Persist::decode(dec)?,
+            branches: Persist::decode(dec)?,
+        })
+    }
+}
+
+persist_struct!(ContractDiagnostic { message, notes });
+
+persist_struct!(ContractStep {
+    kind,
+    contract_pos,
+    value_pos,
+});
+
+persist_enum!(ContractStepKind as ContractStepKind {
+    0 => Apply,
+    1 => ArrayElements,
+    2 => RecordFields,
+    3 => Merge { field },
+});
+
+persist_enum!(Polarity as Polarity {
+    0 => Positive,
+    1 => Negative,
+});
+
+persist_enum!(ContractBranch as ContractBranch {
+    0 => UnionLeft,
+    1 => UnionRight,
+    2 => IntersectionLeft,
+    3 => IntersectionRight,
+});
+
+persist_enum!(Elem as Elem {
+    0 => Domain,
+    1 => Codomain,
+    2 => Field(id),
+    3 => Array,
+    4 => Dict,
+});
+
+persist_struct!(MergeLabel { span, kind });
+
+persist_enum!(MergeKind as MergeKind {
+    0 => Standard,
+    1 => PiecewiseDef,
+});
+
+persist_struct!(Type { typ, pos });
+
+persist_enum!(TypeF<Box<Type>, RecordRows, EnumRows> as TypeF {
+    0 => Dyn,
+    1 => Number,
+    2 => Bool,
+    3 => String,
+    4 => Symbol,
+    5 => Flat(t),
+    6 => Arrow(dom, codom),
+    7 => Var(id),
+    8 => Forall { var, var_kind, body },
+    9 => Enum(erows),
+    10 => Record(rrows),
+    11 => Dict { type_fields, flavour },
+    12 => Array(ty),
+    13 => Wildcard(id),
+});
+
+persist_enum!(VarKind as VarKind {
+    0 => Type,
+    1 => EnumRows,
+    2 => RecordRows { excluded },
+});
+
+persist_enum!(DictTypeFlavour as DictTypeFlavour {
+    0 => Type,
+    1 => Contract,
+});
+
+impl Persist for RecordRows {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.0.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Persist::decode(dec).map(RecordRows)
+    }
+}
+
+persist_enum!(RecordRowsF<Box<Type>, Box<RecordRows>> as RecordRowsF {
+    0 => Empty,
+    1 => Extend { row, tail },
+    2 => TailVar(id),
+    3 => TailDyn,
+});
+
+impl Persist for RecordRowF<Box<Type>> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.id.encode(enc)?;
+        self.typ.encode(enc)?;
+        self.opt.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Ok(RecordRowF {
+            id: Persist::decode(dec)?,
+            typ: Persist::decode(dec)?,
+            opt: Persist::decode(dec)?,
+        })
+    }
+}
+
+impl Persist for EnumRows {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.0.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Persist::decode(dec).map(EnumRows)
+    }
+}
+
+impl Persist for EnumRowF<Box<Type>> {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.id.encode(enc)?;
+        self.typ.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Ok(EnumRowF {
+            id: Persist::decode(dec)?,
+            typ: Persist::decode(dec)?,
+        })
+    }
+}
+
+persist_enum!(EnumRowsF<Box<Type>, Box<EnumRows>> as EnumRowsF {
+    0 => Empty,
+    1 => Extend { row, tail },
+    2 => TailVar(id),
+});
+
+persist_enum!(RecordOpKind as RecordOpKind {
+    0 => IgnoreEmptyOpt,
+    1 => ConsiderAllFields,
+});
+
+persist_enum!(RecordExtKind as RecordExtKind {
+    0 => WithValue,
+    1 => WithoutValue,
+});
+
+persist_enum!(UnaryOp as UnaryOp {
+    0 => Ite(),
+    1 => Typeof(),
+    2 => BoolAnd(),
+    3 => BoolOr(),
+    4 => BoolNot(),
+    5 => Blame(),
+    6 => Embed(id),
+    7 => Match { has_default },
+    8 => StaticAccess(id),
+    9 => ArrayMap(),
+    10 => RecordMap(),
+    11 => ChangePolarity(),
+    12 => Pol(),
+    13 => GoDom(),
+    14 => GoCodom(),
+    15 => GoArray(),
+    16 => GoDict(),
+    17 => Seq(),
+    18 => DeepSeq(),
+    19 => ArrayLength(),
+    20 => ArrayGen(),
+    21 => ChunksConcat(),
+    22 => FieldsOf(),
+    23 => ValuesOf(),
+    24 => StrTrim(),
+    25 => StrChars(),
+    26 => StrUppercase(),
+    27 => StrLowercase(),
+    28 => StrLength(),
+    29 => ToStr(),
+    30 => NumFromStr(),
+    31 => EnumFromStr(),
+    32 => StrIsMatch(),
+    33 => StrFind(),
+    34 => StrIsMatchCompiled(regex),
+    35 => StrFindCompiled(regex),
+    36 => Force { ignore_not_exported },
+    37 => RecDefault(),
+    38 => RecForce(),
+    39 => RecordEmptyWithTail(),
+    40 => Trace(),
+    41 => LabelPushDiag(),
+    42 => MatchFailure(),
+    43 => EnumGetTag(),
+    44 => EnumGetArg(),
+    45 => EnumIsVariant(),
+    46 => StrToSnakeCase(),
+    47 => StrToKebabCase(),
+    48 => StrToCamelCase(),
+    49 => StrFindAll(),
+    50 => StrFindAllCompiled(regex),
+    #[cfg(feature = "nix-experimental")]
+    51 => EvalNix(),
+});
+
+persist_enum!(BinaryOp as BinaryOp {
+    0 => Plus(),
+    1 => Sub(),
+    2 => Mult(),
+    3 => Div(),
+    4 => Modulo(),
+    5 => Pow(),
+    6 => StrConcat(),
+    7 => Eq(),
+    8 => LessThan(),
+    9 => LessOrEq(),
+    10 => GreaterThan(),
+    11 => GreaterOrEq(),
+    12 => ApplyContract(),
+    13 => Unseal(),
+    14 => GoField(),
+    15 => DynExtend {
+        metadata,
+        pending_contracts,
+        ext_kind,
+        op_kind
+    },
+    16 => DynRemove(op_kind),
+    17 => DynAccess(),
+    18 => HasField(op_kind),
+    19 => ArrayConcat(),
+    20 => ArrayElemAt(),
+    21 => Merge(label),
+    22 => Hash(),
+    23 => Serialize(),
+    24 => Deserialize(),
+    25 => StrSplit(),
+    26 => StrContains(),
+    27 => Seal(),
+    28 => ArrayLazyAppCtr(),
+    29 => RecordLazyAppCtr(),
+    30 => LabelWithMessage(),
+    31 => LabelWithNotes(),
+    32 => LabelAppendNote(),
+    33 => LookupTypeVar(),
+    34 => LabelGoBranch(),
+    35 => NumMath(),
+    36 => NumAtan2(),
+    37 => NumRound(),
+    38 => NumGcd(),
+    39 => NumBitAnd(),
+    40 => NumBitOr(),
+    41 => NumBitXor(),
+    42 => NumBitShift(),
+    43 => NumToDecimalStr(),
+    44 => StrStartsWith(),
+    45 => StrEndsWith(),
+    46 => StrIndent(),
+    47 => StrFormat(),
+    48 => Encode(),
+    49 => Decode(),
+    50 => Quote(),
+    51 => NetValidate(),
+    52 => CidrContains(),
+    53 => CidrSubnets(),
+    54 => SemverValidate(),
+    55 => SemverCompare(),
+    56 => SemverMatches(),
+});
+
+persist_enum!(NAryOp as NAryOp {
+    0 => StrReplace(),
+    1 => StrReplaceRegex(),
+    2 => StrSubstr(),
+    3 => MergeContract(),
+    4 => RecordSealTail(),
+    5 => RecordUnsealTail(),
+    6 => InsertTypeVar(),
+    7 => ArraySlice(),
+    8 => ContractCheck(),
+    9 => StrPadLeft(),
+    10 => StrPadRight(),
+    11 => StrReplaceRegexCaptures(),
+    12 => StrReplaceRegexWith(),
+});
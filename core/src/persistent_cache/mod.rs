@@ -0,0 +1,402 @@
+//! An on-disk cache of prepared sources, shared between invocations.
+//!
+//! Each invocation of Nickel prepares the standard library and the imported files for evaluation
+//! again: parsing, import resolution, typechecking and program transformations. The persistent
+//! cache stores the result of this preparation in a directory, that is the transformed term
+//! together with the types inferred for the wildcards, such that later invocations can skip it
+//! altogether for the sources which didn't change.
+//!
+//! An entry is identified by a hash of the path and the content of the source, and of the version
+//! of Nickel. The version includes the content of the standard library and a fingerprint of the
+//! sources of the interpreter computed at build time, as the entries are encoded ASTs which are
+//! only meaningful to the build which produced them. Typechecking a source depends
+//! on its imports, so an entry also records the path and a hash of the content of the transitive
+//! imports of the source, and is only reused if none of them changed.
+//!
+//! The cache is bounded in size: once the total size of the entries exceeds the limit, the least
+//! recently used ones are removed.
+//!
+//! The persistent cache is best effort: any error when reading or writing entries is ignored, and
+//! the source is just prepared as usual. In particular, an entry which can't be decoded exactly,
+//! or which was written by another build, is never used.
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use codespan::FileId;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    stdlib::{self, StdlibModule},
+    term::RichTerm,
+    typ::Type,
+};
+
+use codec::{Decoder, Encoder, Persist};
+
+pub use codec::CodecError;
+
+mod codec;
+
+/// The first bytes of an entry.
+const MAGIC: &[u8] = b"NCLCACHE";
+
+/// The extension of the entry files.
+const EXTENSION: &str = "entry";
+
+/// A hash of the version of Nickel, the sources of this build and the standard library, which is
+/// part of the key of each entry and is recorded in each entry.
+static VERSION_HASH: Lazy<[u8; 32]> = Lazy::new(|| {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(env!("NICKEL_SOURCE_FINGERPRINT").as_bytes());
+
+    for module in stdlib::modules() {
+        hasher.update(module.content().as_bytes());
+    }
+
+    hasher.finalize().into()
+});
+
+/// The on-disk cache of prepared sources. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct PersistentCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+impl PersistentCache {
+    /// The default maximum size of the cache, in bytes.
+    pub const DEFAULT_MAX_SIZE: u64 = 256 * 1024 * 1024;
+
+    /// Create a persistent cache stored in `dir`, which is created if needed. The maximum size is
+    /// [Self::DEFAULT_MAX_SIZE].
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        PersistentCache {
+            dir: dir.into(),
+            max_size: Self::DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Set the maximum total size of the entries, in bytes.
+    pub fn with_max_size(self, max_size: u64) -> Self {
+        PersistentCache { max_size, ..self }
+    }
+
+    /// The directory where the entries are stored.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(format!("{key}.{EXTENSION}"))
+    }
+
+    /// Load an entry, or return `None` if there is no such entry or if it's invalid. Mark the entry
+    /// as recently used.
+    pub(crate) fn load(&self, key: &CacheKey) -> Option<Entry> {
+        let path = self.entry_path(key);
+        let data = fs::read(&path).ok()?;
+        let entry = Entry::from_data(data).ok()?;
+
+        // Eviction removes the entries which were modified the longest time ago first.
+        let _ = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+
+        Some(entry)
+    }
+
+    /// Encode and store an entry. `files` are the files the terms are allowed to refer to, usually
+    /// the source itself, its dependencies and the standard library modules.
+    ///
+    /// Fails with an [io::ErrorKind::InvalidData] error if the terms can't be encoded, for example
+    /// if they refer to a file which isn't in `files`.
+    pub(crate) fn store(
+        &self,
+        key: &CacheKey,
+        header: &EntryHeader,
+        files: &HashMap<FileId, FileRef>,
+        terms: &[&RichTerm],
+        wildcards: &[Type],
+    ) -> io::Result<()> {
+        let mut body = Encoder::new(files);
+        for term in terms {
+            term.encode(&mut body)?;
+        }
+        wildcards.iter().try_for_each(|ty| ty.encode(&mut body))?;
+        let (body, file_table) = body.finish();
+
+        let no_files = HashMap::new();
+        let mut enc = Encoder::new(&no_files);
+        enc.bytes(MAGIC);
+        enc.bytes(&*VERSION_HASH);
+        enc.usize(terms.len());
+        enc.usize(wildcards.len());
+        header.deps.encode(&mut enc)?;
+        header.imports.encode(&mut enc)?;
+        file_table.encode(&mut enc)?;
+        let (mut data, _) = enc.finish();
+        data.extend(body);
+
+        fs::create_dir_all(&self.dir)?;
+
+        // Write to a temporary file first, so that concurrent invocations never observe a partially
+        // written entry.
+        let tmp_path = self.dir.join(format!("{key}.{}.tmp", std::process::id()));
+        let result = fs::File::create(&tmp_path)
+            .and_then(|mut file| file.write_all(&data))
+            .and_then(|_| fs::rename(&tmp_path, self.entry_path(key)));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+
+        result
+    }
+
+    /// Remove the least recently used entries until the total size of the cache is below the
+    /// maximum size.
+    pub(crate) fn evict(&self) -> io::Result<()> {
+        let mut entries = Vec::new();
+        let mut total_size = 0;
+
+        for dir_entry in fs::read_dir(&self.dir)? {
+            let path = dir_entry?.path();
+
+            if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+                continue;
+            }
+
+            let metadata = fs::metadata(&path)?;
+            total_size += metadata.len();
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+
+        entries.sort();
+
+        for (_, size, path) in entries {
+            if total_size <= self.max_size {
+                break;
+            }
+
+            fs::remove_file(path)?;
+            total_size -= size;
+        }
+
+        Ok(())
+    }
+}
+
+/// The key of an entry of the persistent cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey([u8; 32]);
+
+impl CacheKey {
+    /// The key of a source file with the given normalized path and content.
+    pub(crate) fn for_source(path: &Path, content: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(*VERSION_HASH);
+        hasher.update(b"source");
+        hasher.update(path.as_os_str().as_encoded_bytes());
+        hasher.update([0]);
+        hasher.update(content.as_bytes());
+        CacheKey(hasher.finalize().into())
+    }
+
+    /// The key of a module of the standard library.
+    pub(crate) fn for_stdlib(module: StdlibModule) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(*VERSION_HASH);
+        hasher.update(b"stdlib");
+        hasher.update(module.name().as_bytes());
+        CacheKey(hasher.finalize().into())
+    }
+}
+
+impl fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// A hash of the content of a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ContentHash([u8; 32]);
+
+impl ContentHash {
+    pub(crate) fn of(content: &str) -> Self {
+        ContentHash(Sha256::digest(content.as_bytes()).into())
+    }
+}
+
+impl Persist for ContentHash {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.bytes(&self.0);
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.bytes()?
+            .try_into()
+            .map(ContentHash)
+            .map_err(|_| CodecError::Malformed)
+    }
+}
+
+/// A reference to a file which is stable across invocations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FileRef {
+    /// A file identified by its normalized path.
+    Path(PathBuf),
+    /// A module of the standard library.
+    Std(StdlibModule),
+}
+
+impl Persist for FileRef {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        match self {
+            FileRef::Path(path) => {
+                enc.u8(0);
+                path.encode(enc)
+            }
+            FileRef::Std(module) => {
+                enc.u8(1);
+                enc.str(module.name());
+                Ok(())
+            }
+        }
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        match dec.u8()? {
+            0 => PathBuf::decode(dec).map(FileRef::Path),
+            1 => {
+                let name = dec.str()?;
+                stdlib::modules()
+                    .into_iter()
+                    .find(|module| module.name() == name)
+                    .map(FileRef::Std)
+                    .ok_or(CodecError::Malformed)
+            }
+            _ => Err(CodecError::Malformed),
+        }
+    }
+}
+
+impl Persist for PathBuf {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        enc.str(self.to_str().ok_or(CodecError::Unsupported)?);
+        Ok(())
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        dec.str().map(PathBuf::from)
+    }
+}
+
+/// A transitive import of a source, which must not have changed for the entry of the source to be
+/// valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Dependency {
+    pub(crate) path: PathBuf,
+    pub(crate) hash: ContentHash,
+}
+
+impl Persist for Dependency {
+    fn encode(&self, enc: &mut Encoder) -> Result<(), CodecError> {
+        self.path.encode(enc)?;
+        self.hash.encode(enc)
+    }
+
+    fn decode(dec: &mut Decoder) -> Result<Self, CodecError> {
+        Ok(Dependency {
+            path: PathBuf::decode(dec)?,
+            hash: ContentHash::decode(dec)?,
+        })
+    }
+}
+
+/// The part of an entry which describes the dependencies of the source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct EntryHeader {
+    /// The transitive imports of the source.
+    pub(crate) deps: Vec<Dependency>,
+    /// The paths of the direct imports of the source, which are all part of `deps`.
+    pub(crate) imports: Vec<PathBuf>,
+}
+
+/// An entry read from the persistent cache, whose terms haven't been decoded yet.
+#[derive(Debug, Clone)]
+pub(crate) struct Entry {
+    pub(crate) header: EntryHeader,
+    /// The files the terms refer to. They must be mapped to the corresponding file ids of the
+    /// current session to decode the terms.
+    pub(crate) files: Vec<FileRef>,
+    terms_count: usize,
+    wildcards_count: usize,
+    data: Vec<u8>,
+    /// The offset of the encoded terms in `data`.
+    body_start: usize,
+}
+
+impl Entry {
+    fn from_data(data: Vec<u8>) -> Result<Self, CodecError> {
+        let mut dec = Decoder::new(&data);
+
+        if dec.bytes()? != MAGIC || dec.bytes()? != *VERSION_HASH {
+            return Err(CodecError::Malformed);
+        }
+
+        let terms_count = dec.usize()?;
+        let wildcards_count = dec.usize()?;
+        let deps = Persist::decode(&mut dec)?;
+        let imports = Persist::decode(&mut dec)?;
+        let files = Persist::decode(&mut dec)?;
+        let body_start = data.len() - dec.remaining();
+
+        Ok(Entry {
+            header: EntryHeader { deps, imports },
+            files,
+            terms_count,
+            wildcards_count,
+            data,
+            body_start,
+        })
+    }
+
+    /// Decode the terms and the wildcards of the entry, given the file ids corresponding to
+    /// [Self::files].
+    pub(crate) fn decode(
+        &self,
+        file_ids: Vec<FileId>,
+    ) -> Result<(Vec<RichTerm>, Vec<Type>), CodecError> {
+        let mut dec = Decoder::new(&self.data[self.body_start..]);
+        dec.set_files(file_ids);
+
+        let terms = (0..self.terms_count)
+            .map(|_| RichTerm::decode(&mut dec))
+            .collect::<Result<_, _>>()?;
+        let wildcards = (0..self.wildcards_count)
+            .map(|_| Type::decode(&mut dec))
+            .collect::<Result<_, _>>()?;
+
+        if dec.remaining() != 0 {
+            return Err(CodecError::Malformed);
+        }
+
+        Ok((terms, wildcards))
+    }
+}
+
+impl From<CodecError> for io::Error {
+    fn from(err: CodecError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{err:?}"))
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt;
 
-use crate::destructuring::{self, FieldPattern, RecordPattern};
+use crate::destructuring::{
+    self, ArrayPattern, Destruct, FieldPattern, MatchBranch, Pattern, RecordPattern,
+};
 use crate::identifier::LocIdent;
 use crate::parser::lexer::KEYWORDS;
 use crate::term::record::RecordData;
@@ -504,13 +506,87 @@ where
         match self {
             FieldPattern::Ident(id) => allocator.as_string(id),
             FieldPattern::RecordPattern(rp) => rp.pretty(allocator),
+            FieldPattern::ArrayPattern(ap) => ap.pretty(allocator),
             FieldPattern::AliasedRecordPattern { alias, pattern } => {
                 docs![allocator, alias.to_string(), " @ ", pattern]
             }
+            FieldPattern::AliasedArrayPattern { alias, pattern } => {
+                docs![allocator, alias.to_string(), " @ ", pattern]
+            }
+        }
+    }
+}
+
+impl<'a, D, A> Pretty<'a, D, A> for &Destruct
+where
+    D: NickelAllocatorExt<'a, A>,
+    D::Doc: Clone,
+    A: Clone + 'a,
+{
+    fn pretty(self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        match self {
+            Destruct::Record(rp) => rp.pretty(allocator),
+            Destruct::Array(ap) => ap.pretty(allocator),
         }
     }
 }
 
+impl<'a, D, A> Pretty<'a, D, A> for &ArrayPattern
+where
+    D: NickelAllocatorExt<'a, A>,
+    D::Doc: Clone,
+    A: Clone + 'a,
+{
+    fn pretty(self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        let ArrayPattern {
+            elements,
+            open,
+            rest,
+            ..
+        } = self;
+        docs![
+            allocator,
+            allocator.line_(),
+            allocator.intersperse(
+                elements.iter().map(|elem| {
+                    docs![
+                        allocator,
+                        &elem.pattern,
+                        &elem.field.metadata.annotation,
+                        match &elem.field.value {
+                            Some(value) => {
+                                docs![allocator, allocator.line(), "? ", allocator.atom(value)]
+                            }
+                            None => allocator.nil(),
+                        },
+                        ","
+                    ]
+                    .nest(2)
+                }),
+                allocator.line()
+            ),
+            if *open {
+                docs![
+                    allocator,
+                    allocator.line(),
+                    "..",
+                    if let Some(rest) = rest {
+                        allocator.as_string(rest)
+                    } else {
+                        allocator.nil()
+                    },
+                ]
+            } else {
+                allocator.nil()
+            },
+        ]
+        .nest(2)
+        .append(allocator.line_())
+        .brackets()
+        .group()
+    }
+}
+
 impl<'a, D, A> Pretty<'a, D, A> for &RecordPattern
 where
     D: NickelAllocatorExt<'a, A>,
@@ -595,6 +671,114 @@ where
     }
 }
 
+impl<'a, D, A> Pretty<'a, D, A> for &Pattern
+where
+    D: NickelAllocatorExt<'a, A>,
+    D::Doc: Clone,
+    A: Clone + 'a,
+{
+    fn pretty(self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        let rest_doc = |open: bool, rest: &Option<LocIdent>| {
+            if open {
+                docs![
+                    allocator,
+                    allocator.line(),
+                    "..",
+                    if let Some(rest) = rest {
+                        allocator.as_string(rest)
+                    } else {
+                        allocator.nil()
+                    },
+                ]
+            } else {
+                allocator.nil()
+            }
+        };
+
+        match self {
+            Pattern::Wildcard => allocator.text("_"),
+            Pattern::Any(id) => allocator.as_string(id),
+            Pattern::Constant(rt) => rt.pretty(allocator),
+            Pattern::Enum(tag, None) => allocator
+                .text("'")
+                .append(allocator.text(ident_quoted(tag))),
+            Pattern::Enum(tag, Some(arg)) => docs![
+                allocator,
+                "'",
+                ident_quoted(tag),
+                " ",
+                match &**arg {
+                    Pattern::Enum(_, Some(_)) | Pattern::Alias(..) => {
+                        docs![allocator, &**arg].parens()
+                    }
+                    _ => docs![allocator, &**arg],
+                },
+            ],
+            Pattern::Record { fields, open, rest } => docs![
+                allocator,
+                allocator.line(),
+                allocator.intersperse(
+                    fields.iter().map(|(id, pattern)| match pattern {
+                        Pattern::Any(bound) if bound.ident() == id.ident() => {
+                            docs![allocator, ident_quoted(id), ","]
+                        }
+                        _ => docs![allocator, ident_quoted(id), " = ", pattern, ","],
+                    }),
+                    allocator.line()
+                ),
+                rest_doc(*open, rest),
+            ]
+            .nest(2)
+            .append(allocator.line())
+            .braces()
+            .group(),
+            Pattern::Array {
+                elements,
+                open,
+                rest,
+            } => docs![
+                allocator,
+                allocator.line_(),
+                allocator.intersperse(
+                    elements
+                        .iter()
+                        .map(|pattern| docs![allocator, pattern, ","]),
+                    allocator.line()
+                ),
+                rest_doc(*open, rest),
+            ]
+            .nest(2)
+            .append(allocator.line_())
+            .brackets()
+            .group(),
+            Pattern::Alias(id, pattern) => docs![allocator, id.to_string(), " @ ", &**pattern],
+        }
+    }
+}
+
+impl<'a, D, A> Pretty<'a, D, A> for &MatchBranch
+where
+    D: NickelAllocatorExt<'a, A>,
+    D::Doc: Clone,
+    A: Clone + 'a,
+{
+    fn pretty(self, allocator: &'a D) -> DocBuilder<'a, D, A> {
+        docs![
+            allocator,
+            &self.pattern,
+            match &self.guard {
+                Some(guard) => docs![allocator, " if ", guard],
+                None => allocator.nil(),
+            },
+            " =>",
+            allocator.line(),
+            &self.body,
+            ","
+        ]
+        .nest(2)
+    }
+}
+
 impl<'a, D, A> Pretty<'a, D, A> for &RichTerm
 where
     D: NickelAllocatorExt<'a, A>,
@@ -669,6 +853,37 @@ where
                 .group()
             }
             Lbl(_lbl) => allocator.text("%<label>").append(allocator.line()),
+            // A type alias is bound to a function from its parameters to its definition, see
+            // [crate::typ::TypeAlias::to_term].
+            Let(id, rt, body, attrs) if attrs.type_alias => {
+                let mut params = Vec::new();
+                let mut def = rt;
+
+                while let Fun(param, inner) = def.as_ref() {
+                    params.push(param.to_string());
+                    def = inner;
+                }
+
+                docs![
+                    allocator,
+                    "let type ",
+                    id.to_string(),
+                    allocator.concat(
+                        params
+                            .into_iter()
+                            .map(|param| allocator.text(" ").append(param))
+                    ),
+                    allocator.line(),
+                    "= ",
+                    def,
+                    allocator.line(),
+                    "in",
+                ]
+                .nest(2)
+                .append(allocator.line())
+                .append(body.pretty(allocator).nest(2))
+                .group()
+            }
             Let(id, rt, body, attrs) => docs![
                 allocator,
                 "let ",
@@ -753,6 +968,24 @@ where
                     allocator.atom(rt2)
                 ]
                 .group(),
+                // Union and intersection contracts are parsed as applications of the internal
+                // symbols `$union` and `$intersection`, which aren't valid identifiers.
+                App(f, t) if matches!(f.as_ref(), Var(id) if id.label() == "$union" || id.label() == "$intersection") =>
+                {
+                    let op = match f.as_ref() {
+                        Var(id) if id.label() == "$union" => "\\/ ",
+                        _ => "/\\ ",
+                    };
+
+                    docs![
+                        allocator,
+                        allocator.atom(t),
+                        allocator.line(),
+                        op,
+                        allocator.atom(rt2)
+                    ]
+                    .group()
+                }
                 App(..) => docs![
                     allocator,
                     rt1,
@@ -770,6 +1003,14 @@ where
             },
             Var(id) => allocator.as_string(id),
             Enum(id) => allocator.text("'").append(allocator.text(ident_quoted(id))),
+            EnumVariant { tag, arg, .. } => docs![
+                allocator,
+                "'",
+                ident_quoted(tag),
+                docs![allocator, allocator.line(), allocator.atom(arg)]
+                    .nest(2)
+                    .group()
+            ],
             Record(record_data) => allocator.record(record_data, &[]),
             RecRecord(record_data, dyn_fields, _) => allocator.record(record_data, dyn_fields),
             Match { cases, default } => docs![
@@ -800,6 +1041,19 @@ where
                 .braces()
             ]
             .group(),
+            MatchPattern(branches) => docs![
+                allocator,
+                "match ",
+                docs![
+                    allocator,
+                    allocator.line(),
+                    allocator.intersperse(branches.iter(), allocator.line()),
+                ]
+                .nest(2)
+                .append(allocator.line())
+                .braces()
+            ]
+            .group(),
             Array(fields, _) =>
             // NOTE: the Array attributes are ignored here. They contain only
             // information that has no surface syntax.
@@ -898,7 +1152,12 @@ where
             EnumRowsF::Extend { row, tail } => docs![
                 allocator,
                 "'",
-                ident_quoted(row),
+                ident_quoted(&row.id),
+                match &row.typ {
+                    Some(ty) if ty.fmt_is_atom() => docs![allocator, " ", ty.as_ref()],
+                    Some(ty) => docs![allocator, " (", ty.as_ref(), ")"],
+                    None => allocator.nil(),
+                },
                 if let EnumRowsF::Extend { .. } = tail.0 {
                     docs![allocator, ",", allocator.line()]
                 } else {
@@ -922,12 +1181,12 @@ where
             RecordRowsF::TailDyn => docs![allocator, ";", allocator.line(), "Dyn"],
             RecordRowsF::TailVar(id) => docs![allocator, ";", allocator.line(), id.to_string()],
             RecordRowsF::Extend {
-                row: RecordRowF { id, typ },
+                row: RecordRowF { id, typ, opt },
                 tail,
             } => docs![
                 allocator,
                 ident_quoted(id),
-                " : ",
+                if *opt { " ? " } else { " : " },
                 typ.as_ref(),
                 if let RecordRowsF::Extend { .. } = tail.0 {
                     docs![allocator, ",", allocator.line()]
@@ -1571,6 +1830,7 @@ mod tests {
         assert_format_eq("{ x : (Bool -> Bool) -> Bool, y : Bool }");
         assert_format_eq("forall r. { x : Bool, y : Bool, z : Bool; r }");
         assert_format_eq("{ x : Bool, y : Bool, z : Bool }");
+        assert_format_eq("{ x : Bool, y ? Number; Dyn }");
 
         assert_format_eq("[| 'a, 'b, 'c, 'd |]");
         assert_format_eq("forall r. [| 'tag1, 'tag2, 'tag3; r |]");
@@ -23,23 +23,30 @@
 use crate::{
     cache::*,
     error::{report, ColorOpt, Error, EvalError, IOError, IntoDiagnostics, ParseError},
-    eval::{cache::Cache as EvalCache, Closure, VirtualMachine},
+    eval::{
+        cache::Cache as EvalCache, debug::DebugHook, limits::EvalLimits, profile::Profile, Closure,
+        VirtualMachine,
+    },
     identifier::LocIdent,
     label::Label,
     metrics::increment,
+    parallel::{self, Fork},
+    persistent_cache::PersistentCache,
     term::{
-        make as mk_term, make::builder, record::Field, BinaryOp, MergePriority, RichTerm, Term,
+        make as mk_term, make::builder, record::Field, record::RecordData, BinaryOp, MergePriority,
+        RichTerm, Term,
     },
 };
 
-use codespan::FileId;
-use codespan_reporting::term::termcolor::Ansi;
+use codespan::{FileId, Files};
+use codespan_reporting::term::termcolor::{Ansi, NoColor, WriteColor};
 use std::path::PathBuf;
 
 use std::{
     ffi::OsString,
     fmt,
     io::{self, Cursor, Read, Write},
+    num::NonZeroUsize,
     result::Result,
 };
 
@@ -271,6 +278,24 @@ impl<EC: EvalCache> Program<EC> {
         })
     }
 
+    /// Create a copy of a program from a [Fork], to evaluate it on another thread.
+    pub(crate) fn new_from_fork(fork: &Fork) -> Self {
+        let mut cache = Cache::new(ErrorTolerance::Strict);
+        cache.set_persistent_cache(fork.persistent_cache.clone());
+        let main_id = cache.add_string(fork.source_path.clone(), fork.source.clone());
+        let mut vm = VirtualMachine::new(cache, io::stderr());
+        vm.set_limits(fork.limits);
+        vm.set_explain_blame(fork.explain_blame);
+
+        Self {
+            main_id,
+            vm,
+            color_opt: clap::ColorChoice::Auto.into(),
+            overrides: fork.overrides.clone(),
+            field: fork.field.clone(),
+        }
+    }
+
     /// Parse an assignment of the form `path.to_field=value` as an override, with the provided
     /// merge priority. Assignments are typically provided by the user on the command line, as part
     /// of the customize mode.
@@ -297,6 +322,69 @@ impl<EC: EvalCache> Program<EC> {
         self.overrides.extend(overrides);
     }
 
+    /// Replace the overrides of the program. Overrides are applied anew at each evaluation, so
+    /// changing them doesn't require to process the sources of the program again.
+    pub fn set_overrides(&mut self, overrides: impl IntoIterator<Item = FieldOverride>) {
+        self.overrides = overrides.into_iter().collect();
+    }
+
+    /// Reload the source files of the program which have been modified on disk since they were
+    /// loaded, such that the next evaluation uses their new content. Only the modified files and
+    /// the files importing them, directly or not, are parsed, typechecked, transformed and
+    /// evaluated again: the values of the other imports, as computed by the previous evaluations,
+    /// are reused. Return `true` if any file was modified. See [Cache::refresh].
+    pub fn refresh(&mut self) -> bool {
+        let modified = !self.vm.import_resolver_mut().refresh().is_empty();
+
+        if modified {
+            self.vm.forget_stale_imports();
+        }
+
+        modified
+    }
+
+    /// Set the resource limits of the evaluation of the program. Exceeding a limit aborts the
+    /// evaluation with [EvalError::ResourceLimitExceeded]. Each evaluation of the program (for
+    /// example each call to [Self::eval_full]) is subject to the limits separately.
+    pub fn set_limits(&mut self, limits: EvalLimits) {
+        self.vm.set_limits(limits);
+    }
+
+    /// Enable or disable the explain-blame mode, where contract violations are reported together
+    /// with the chain of contract applications which led to them.
+    pub fn set_explain_blame(&mut self, explain_blame: bool) {
+        self.vm.set_explain_blame(explain_blame);
+    }
+
+    /// Store the prepared standard library and imported files on disk, to reuse them in later
+    /// invocations, or disable it if `persistent_cache` is `None`. See [crate::persistent_cache].
+    pub fn set_persistent_cache(&mut self, persistent_cache: Option<PersistentCache>) {
+        self.vm
+            .import_resolver_mut()
+            .set_persistent_cache(persistent_cache);
+    }
+
+    /// Attach a debugger to the evaluation of the program. See [crate::eval::debug].
+    pub fn set_debug_hook(&mut self, hook: Box<dyn DebugHook<Cache, EC>>) {
+        self.vm.set_debug_hook(Some(hook));
+    }
+
+    /// Start profiling the evaluations of the program. See [crate::eval::profile].
+    pub fn enable_profiling(&mut self) {
+        self.vm.enable_profiling();
+    }
+
+    /// Stop profiling and return the data collected so far, or `None` if profiling wasn't
+    /// enabled. The positions of the profile can be rendered using [Self::files].
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.vm.take_profile()
+    }
+
+    /// Return the database of the source files of the program.
+    pub fn files(&self) -> &Files<String> {
+        self.vm.import_resolver().files()
+    }
+
     /// Only parse the program, don't typecheck or evaluate. returns the [`RichTerm`] AST
     pub fn parse(&mut self) -> Result<RichTerm, Error> {
         self.vm
@@ -406,6 +494,92 @@ impl<EC: EvalCache> Program<EC> {
         Ok(self.vm.eval_full_for_export_closure(prepared)?)
     }
 
+    /// Same as [Self::eval_full_for_export], but evaluates the top-level fields of the result on
+    /// up to `jobs` threads. See [crate::parallel].
+    ///
+    /// The program is first evaluated to a weak head normal form. If the result is a record with
+    /// several exported fields, each worker thread builds its own copy of the program, prepares it
+    /// and evaluates it again to a record, before evaluating the fields it is assigned to. This
+    /// only pays off when the fields are expensive to evaluate compared to preparing the program.
+    /// Otherwise, or if `jobs` is one, the program is evaluated sequentially.
+    ///
+    /// The resource limits of the program (see [Self::set_limits]) apply to each worker
+    /// separately.
+    pub fn eval_full_for_export_parallel(&mut self, jobs: NonZeroUsize) -> Result<RichTerm, Error> {
+        let whnf = self.eval_whnf()?;
+
+        let fields = match whnf.body.as_ref() {
+            Term::Record(record) if jobs.get() > 1 => {
+                parallel::exported_fields(record).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+
+        if fields.len() < 2 {
+            return Ok(self.vm.eval_full_for_export_closure(whnf)?);
+        }
+
+        let cache = self.vm.import_resolver();
+        let fork = Fork {
+            source_path: cache
+                .source_path(self.main_id)
+                .cloned()
+                .unwrap_or_else(|| SourcePath::Generated("main".into())),
+            source: cache.source(self.main_id).to_owned(),
+            overrides: self.overrides.clone(),
+            field: self.field.clone(),
+            limits: *self.vm.limits(),
+            explain_blame: self.vm.explain_blame(),
+            persistent_cache: cache.persistent_cache().cloned(),
+        };
+
+        let results = parallel::eval_fields::<EC>(&fork, &fields, jobs);
+
+        // The fields which failed on a worker, or which were skipped because a previous field
+        // failed, are evaluated again here in order, to report the same error as a sequential
+        // evaluation.
+        let Term::Record(record) = whnf.body.as_ref() else {
+            unreachable!("the program has been checked to evaluate to a record")
+        };
+        let mut values = Vec::with_capacity(fields.len());
+
+        for (id, result) in fields.into_iter().zip(results) {
+            let value = match result {
+                Some(value) => value.into(),
+                None => {
+                    let closure = parallel::field_closure(record, &whnf.env, id)
+                        .expect("exported fields have a definition");
+                    self.vm.eval_full_for_export_closure(closure)?
+                }
+            };
+
+            values.push((id, value));
+        }
+
+        Ok(RichTerm::new(
+            Term::Record(RecordData::with_field_values(values)),
+            whnf.body.pos,
+        ))
+    }
+
+    /// Prepare the program and evaluate it to a weak head normal form.
+    pub(crate) fn eval_whnf(&mut self) -> Result<Closure, Error> {
+        let prepared = self.prepare_eval()?;
+
+        self.vm.reset();
+        Ok(self.vm.eval_closure(prepared)?)
+    }
+
+    /// Deeply evaluate a closure for export, in the same virtual machine as the program. Contrary
+    /// to the evaluation of the program, the resources consumed by previous evaluations still
+    /// count toward the limits.
+    pub(crate) fn eval_full_for_export_closure(
+        &mut self,
+        closure: Closure,
+    ) -> Result<RichTerm, EvalError> {
+        self.vm.eval_full_for_export_closure(closure)
+    }
+
     /// Same as `eval_full`, but does not substitute all variables.
     pub fn eval_deep(&mut self) -> Result<RichTerm, Error> {
         let prepared = self.prepare_eval()?;
@@ -455,24 +629,40 @@ impl<EC: EvalCache> Program<EC> {
 
     /// Build an error report as a string and return it.
     pub fn report_as_str<E>(&mut self, error: E) -> String
+    where
+        E: IntoDiagnostics<FileId>,
+    {
+        let mut buffer = Ansi::new(Cursor::new(Vec::new()));
+        self.write_report(error, &mut buffer);
+        // unwrap(): emit() should only print valid utf8 to the the buffer
+        String::from_utf8(buffer.into_inner().into_inner()).unwrap()
+    }
+
+    /// Same as [Self::report_as_str], but without colors.
+    pub fn report_as_plain_str<E>(&mut self, error: E) -> String
+    where
+        E: IntoDiagnostics<FileId>,
+    {
+        let mut buffer = NoColor::new(Vec::new());
+        self.write_report(error, &mut buffer);
+        // unwrap(): emit() should only print valid utf8 to the the buffer
+        String::from_utf8(buffer.into_inner()).unwrap()
+    }
+
+    fn write_report<E>(&mut self, error: E, buffer: &mut dyn WriteColor)
     where
         E: IntoDiagnostics<FileId>,
     {
         let cache = self.vm.import_resolver_mut();
         let stdlib_ids = cache.get_all_stdlib_modules_file_id();
         let diagnostics = error.into_diagnostics(cache.files_mut(), stdlib_ids.as_ref());
-        let mut buffer = Ansi::new(Cursor::new(Vec::new()));
         let config = codespan_reporting::term::Config::default();
         // write to `buffer`
         diagnostics
             .iter()
-            .try_for_each(|d| {
-                codespan_reporting::term::emit(&mut buffer, &config, cache.files_mut(), d)
-            })
+            .try_for_each(|d| codespan_reporting::term::emit(buffer, &config, cache.files_mut(), d))
             // safe because writing to a cursor in memory
             .unwrap();
-        // unwrap(): emit() should only print valid utf8 to the the buffer
-        String::from_utf8(buffer.into_inner().into_inner()).unwrap()
     }
 
     /// Evaluate a program into a record spine, a form suitable for extracting the general
@@ -617,6 +807,96 @@ impl<EC: EvalCache> Program<EC> {
         ))
     }
 
+    /// Extract the examples of the documentation of the program (see
+    /// [doc::ExtractedDocumentation::examples]) and run them. Examples are evaluated in the
+    /// initial environment, containing the stdlib, and don't have access to the content of the
+    /// program.
+    #[cfg(feature = "doc")]
+    pub fn test_doc(&mut self) -> Result<Vec<doc::DocTestResult>, Error> {
+        use doc::{DocTestOutcome, DocTestResult, ExpectedResult};
+
+        let examples = self.extract_doc()?.examples();
+        let type_ctxt = self.vm.prepare_stdlib()?.type_ctxt;
+
+        let results = examples
+            .into_iter()
+            .map(|example| {
+                let name = format!(
+                    "doc example of {} (line {})",
+                    example.path.join("."),
+                    example.line
+                );
+
+                let outcome = match &example.expected {
+                    ExpectedResult::Value(expected) => {
+                        let check = format!("({}\n) == ({}\n)", example.input, expected);
+
+                        match self.eval_doc_source(name.clone(), check, &type_ctxt) {
+                            Ok(rt) if matches!(rt.as_ref(), Term::Bool(true)) => {
+                                DocTestOutcome::Passed
+                            }
+                            Ok(_) => {
+                                match self.eval_doc_source(name, example.input.clone(), &type_ctxt)
+                                {
+                                    Ok(actual) => DocTestOutcome::Mismatch { actual },
+                                    Err(err) => DocTestOutcome::Error(err),
+                                }
+                            }
+                            Err(err) => DocTestOutcome::Error(err),
+                        }
+                    }
+                    ExpectedResult::Error(message) => {
+                        match self.eval_doc_source(name, example.input.clone(), &type_ctxt) {
+                            Ok(actual) => DocTestOutcome::UnexpectedSuccess { actual },
+                            // Only evaluation errors count: an example which doesn't parse or
+                            // doesn't typecheck is broken, not failing.
+                            Err(Error::EvalError(err)) => {
+                                let matches = message.as_ref().is_none_or(|message| {
+                                    self.report_as_plain_str(err.clone()).contains(message)
+                                });
+
+                                if matches {
+                                    DocTestOutcome::Passed
+                                } else {
+                                    DocTestOutcome::Error(Error::EvalError(err))
+                                }
+                            }
+                            Err(err) => DocTestOutcome::Error(err),
+                        }
+                    }
+                };
+
+                DocTestResult { example, outcome }
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Add a generated source to the cache, prepare it and evaluate it fully in the initial
+    /// environment.
+    #[cfg(feature = "doc")]
+    fn eval_doc_source(
+        &mut self,
+        name: String,
+        source: String,
+        type_ctxt: &crate::typecheck::Context,
+    ) -> Result<RichTerm, Error> {
+        let file_id = self
+            .vm
+            .import_resolver_mut()
+            .add_string(SourcePath::Generated(name), source);
+        self.vm.import_resolver_mut().prepare(file_id, type_ctxt)?;
+        // unwrap(): the source has just been prepared successfully
+        let term = self.vm.import_resolver().get(file_id).unwrap();
+
+        self.vm.reset();
+        Ok(self
+            .vm
+            .eval_full_closure(Closure::atomic_closure(term))?
+            .body)
+    }
+
     #[cfg(debug_assertions)]
     pub fn set_skip_stdlib(&mut self) {
         self.vm.import_resolver_mut().skip_stdlib = true;
@@ -650,9 +930,13 @@ impl<EC: EvalCache> Program<EC> {
 }
 
 #[cfg(feature = "doc")]
-mod doc {
-    use crate::error::{Error, ExportError, IOError};
+pub mod doc {
+    use crate::error::{Diagnostic, Error, ExportError, IOError, IntoDiagnostics};
+    use crate::parser::{grammar::TermParser, lexer::Lexer, ErrorTolerantParser};
+    use crate::position::TermPos;
     use crate::term::{RichTerm, Term};
+    use codespan::{FileId, Files};
+    use codespan_reporting::diagnostic::Label;
     use comrak::arena_tree::NodeEdge;
     use comrak::nodes::{
         Ast, AstNode, ListDelimType, ListType, NodeCode, NodeHeading, NodeList, NodeValue,
@@ -660,6 +944,7 @@ mod doc {
     use comrak::{format_commonmark, parse_document, Arena, ComrakOptions};
     use serde::{Deserialize, Serialize};
     use std::collections::HashMap;
+    use std::fmt;
     use std::io::Write;
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -670,6 +955,9 @@ mod doc {
 
     #[derive(Clone, Debug, Serialize, Deserialize)]
     struct DocumentationField {
+        /// Position of the field name, used to report failing examples
+        #[serde(skip)]
+        pos: TermPos,
         /// Field value [`ExtractedDocumentation`], if any
         fields: Option<ExtractedDocumentation>,
         /// Rendered type annotation, if any
@@ -715,6 +1003,7 @@ mod doc {
                             (
                                 ident.label().to_owned(),
                                 DocumentationField {
+                                    pos: ident.pos,
                                     fields,
                                     typ,
                                     contracts,
@@ -729,6 +1018,41 @@ mod doc {
             }
         }
 
+        /// Collect the examples of the form `<input> => <expected>` found in the `nickel` code
+        /// blocks of the documentation, recursively. Fields are visited in alphabetical order.
+        pub fn examples(&self) -> Vec<DocExample> {
+            let mut examples = Vec::new();
+            self.collect_examples(&mut Vec::new(), &mut examples);
+            examples
+        }
+
+        fn collect_examples(&self, path: &mut Vec<String>, examples: &mut Vec<DocExample>) {
+            let mut entries: Vec<(_, _)> = self.fields.iter().collect();
+            entries.sort_by_key(|(k, _)| *k);
+
+            for (ident, field) in entries {
+                path.push(ident.clone());
+
+                if let Some(ref doc) = field.documentation {
+                    examples.extend(parse_examples(doc).into_iter().map(
+                        |(line, input, expected)| DocExample {
+                            path: path.clone(),
+                            pos: field.pos,
+                            line,
+                            input,
+                            expected,
+                        },
+                    ));
+                }
+
+                if let Some(ref subfields) = field.fields {
+                    subfields.collect_examples(path, examples);
+                }
+
+                path.pop();
+            }
+        }
+
         pub fn write_json(&self, out: &mut dyn Write) -> Result<(), Error> {
             serde_json::to_writer(out, self)
                 .map_err(|e| Error::ExportError(ExportError::Other(e.to_string())))
@@ -789,6 +1113,218 @@ mod doc {
         }
     }
 
+    /// The expected result of a documentation example.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum ExpectedResult {
+        /// The example must evaluate to a value equal to the given expression.
+        Value(String),
+        /// The example must fail during evaluation, which is written `<input> => error`. If a
+        /// message is given, as in `<input> => error: <message>`, the error report must contain
+        /// it.
+        Error(Option<String>),
+    }
+
+    impl fmt::Display for ExpectedResult {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ExpectedResult::Value(expected) => write!(f, "{}", expected.trim()),
+                ExpectedResult::Error(None) => write!(f, "error"),
+                ExpectedResult::Error(Some(message)) => write!(f, "error: {message}"),
+            }
+        }
+    }
+
+    /// An example of the form `<input> => <expected>` extracted from the documentation of a field.
+    #[derive(Clone, Debug)]
+    pub struct DocExample {
+        /// The path of the documented field.
+        pub path: Vec<String>,
+        /// The position of the name of the documented field.
+        pub pos: TermPos,
+        /// The line of the example inside the documentation, starting from 1.
+        pub line: usize,
+        /// The Nickel expression to evaluate.
+        pub input: String,
+        /// The expected result.
+        pub expected: ExpectedResult,
+    }
+
+    /// The outcome of running a documentation example.
+    #[derive(Clone, Debug)]
+    pub enum DocTestOutcome {
+        Passed,
+        /// The example evaluated to a value different from the expected one.
+        Mismatch {
+            actual: RichTerm,
+        },
+        /// The example was expected to fail but evaluated successfully.
+        UnexpectedSuccess {
+            actual: RichTerm,
+        },
+        /// The evaluation of the example failed unexpectedly.
+        Error(Error),
+    }
+
+    /// A documentation example together with the outcome of running it.
+    #[derive(Clone, Debug)]
+    pub struct DocTestResult {
+        pub example: DocExample,
+        pub outcome: DocTestOutcome,
+    }
+
+    impl DocTestResult {
+        pub fn is_success(&self) -> bool {
+            matches!(self.outcome, DocTestOutcome::Passed)
+        }
+    }
+
+    impl IntoDiagnostics<FileId> for DocTestResult {
+        fn into_diagnostics(
+            self,
+            files: &mut Files<String>,
+            stdlib_ids: Option<&Vec<FileId>>,
+        ) -> Vec<Diagnostic<FileId>> {
+            let DocTestResult { example, outcome } = self;
+            let path = example.path.join(".");
+            let labels: Vec<_> = example
+                .pos
+                .into_opt()
+                .map(|span| {
+                    Label::primary(span.src_id, span.start.to_usize()..span.end.to_usize())
+                        .with_message("in the documentation of this field")
+                })
+                .into_iter()
+                .collect();
+            let mut notes = vec![
+                format!(
+                    "The example starts at line {} of the documentation.",
+                    example.line
+                ),
+                format!("Example: {}", example.input.trim()),
+            ];
+
+            let message = match outcome {
+                DocTestOutcome::Passed => return Vec::new(),
+                DocTestOutcome::Mismatch { actual } => {
+                    notes.push(format!("Expected: {}", example.expected));
+                    notes.push(format!("Actual: {actual}"));
+                    "documentation example evaluated to an unexpected value"
+                }
+                DocTestOutcome::UnexpectedSuccess { actual } => {
+                    notes.push(format!("Expected: {}", example.expected));
+                    notes.push(format!("Actual: {actual}"));
+                    "documentation example was expected to fail"
+                }
+                DocTestOutcome::Error(error) => {
+                    if let ExpectedResult::Error(_) = example.expected {
+                        notes.push(format!("Expected: {}", example.expected));
+                    }
+
+                    let mut diags = error.into_diagnostics(files, stdlib_ids);
+                    diags.push(
+                        Diagnostic::note()
+                            .with_message(format!(
+                                "when running a documentation example of `{path}`"
+                            ))
+                            .with_labels(labels)
+                            .with_notes(notes),
+                    );
+                    return diags;
+                }
+            };
+
+            vec![Diagnostic::error()
+                .with_message(format!("{message} in `{path}`"))
+                .with_labels(labels)
+                .with_notes(notes)]
+        }
+    }
+
+    /// Check if a string is a complete Nickel expression, that is if it parses without error.
+    fn is_complete(src: &str) -> bool {
+        let file_id = Files::new().add("<doc example>", String::from(src));
+        TermParser::new()
+            .parse_strict(file_id, Lexer::new(src))
+            .is_ok()
+    }
+
+    /// Extract the examples of the form `<input> => <expected>` from the `nickel` code blocks of a
+    /// piece of documentation. Return, for each example, the line where it starts (starting from
+    /// 1), the input and the expected result.
+    ///
+    /// The arrow separating the input from the expected result is the first one such that
+    /// everything before it forms a complete expression. This way, we don't confuse it with the
+    /// arrow of a function or of a match branch. The expected result spans as many lines as needed
+    /// to form a complete expression.
+    fn parse_examples(doc: &str) -> Vec<(usize, String, ExpectedResult)> {
+        let lines: Vec<&str> = doc.lines().collect();
+        let mut examples = Vec::new();
+        let mut in_code_block = false;
+        let mut input = String::new();
+        let mut start = 0;
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line = lines[index];
+            let trimmed = line.trim();
+            index += 1;
+
+            if trimmed.starts_with("```") {
+                in_code_block = !in_code_block && trimmed == "```nickel";
+                input.clear();
+                continue;
+            }
+
+            if !in_code_block || (input.is_empty() && trimmed.is_empty()) {
+                continue;
+            }
+
+            if input.is_empty() {
+                start = index;
+            }
+
+            // The first arrow of the line such that everything before it forms a complete
+            // expression.
+            let split = line
+                .match_indices("=>")
+                .map(|(pos, _)| pos)
+                .find(|&pos| is_complete(&format!("{input}\n{}", &line[..pos])));
+
+            if let Some(pos) = split {
+                let (before, after) = line.split_at(pos);
+                input.push('\n');
+                input.push_str(before);
+                let rest = &after["=>".len()..];
+                let mut expected = String::from(rest);
+
+                while !is_complete(&expected)
+                    && index < lines.len()
+                    && !lines[index].trim().starts_with("```")
+                {
+                    expected.push('\n');
+                    expected.push_str(lines[index]);
+                    index += 1;
+                }
+
+                let expected = match expected.trim().strip_prefix("error") {
+                    Some("") => ExpectedResult::Error(None),
+                    Some(rest) => match rest.strip_prefix(':') {
+                        Some(message) => ExpectedResult::Error(Some(String::from(message.trim()))),
+                        None => ExpectedResult::Value(expected),
+                    },
+                    None => ExpectedResult::Value(expected),
+                };
+
+                examples.push((start, std::mem::take(&mut input), expected));
+            } else {
+                input.push('\n');
+                input.push_str(line);
+            }
+        }
+
+        examples
+    }
+
     /// Parses a string into markdown and increases any headers in the markdown by the specified
     /// level. This allows having headers in documentation without clashing with the structure of
     /// the document.
@@ -972,4 +1508,68 @@ mod tests {
             Err(Error::ParseErrors(_))
         );
     }
+
+    #[cfg(feature = "doc")]
+    #[test]
+    fn doc_examples() {
+        use crate::program::doc::{DocTestOutcome, ExpectedResult};
+
+        let src = Cursor::new(
+            r####"
+            {
+              add
+                | doc m%"
+                    Adds two numbers.
+
+                    ```nickel
+                    std.number.max 1 2 => 2
+
+                    [1, 2]
+                    |> std.array.map (fun x => x + 1)
+                    => [2, 3]
+
+                    std.array.at 1 [] =>
+                      error
+                    ```
+                  "%
+                = 1,
+              sub | doc m%"
+                    ```nickel
+                    1 - 1 => 1
+                    1 | String => error: contract broken by a value
+                    1 | String => error: missing field
+                    (1 + "a" : Number) => error
+                    ```
+                  "% = 0,
+            }
+            "####,
+        );
+
+        let mut p: Program<CacheImpl> =
+            Program::new_from_source(src, "<test>", std::io::sink()).unwrap();
+        let results = p.test_doc().unwrap();
+
+        assert_eq!(results.len(), 7);
+        assert_eq!(results[0].example.path, vec![String::from("add")]);
+        assert_eq!(results[0].example.line, 4);
+        assert_eq!(results[1].example.line, 6);
+        assert_eq!(results[2].example.expected, ExpectedResult::Error(None));
+        assert!(results[..3].iter().all(|result| result.is_success()));
+        assert_matches!(results[3].outcome, DocTestOutcome::Mismatch { .. });
+        assert_eq!(
+            results[4].example.expected,
+            ExpectedResult::Error(Some(String::from("contract broken by a value")))
+        );
+        assert!(results[4].is_success());
+        // The error doesn't have the expected message
+        assert_matches!(
+            results[5].outcome,
+            DocTestOutcome::Error(Error::EvalError(_))
+        );
+        // The example is ill-typed, which doesn't count as an expected failure
+        assert_matches!(
+            results[6].outcome,
+            DocTestOutcome::Error(Error::TypecheckError(_))
+        );
+    }
 }
@@ -98,7 +98,7 @@ impl FromStr for Command {
     type Err = ReplError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let cmd_end = s.find(' ').unwrap_or(s.len());
+        let cmd_end = s.find(char::is_whitespace).unwrap_or(s.len());
         let cmd_str: String = s.chars().take(cmd_end).collect();
         let cmd: CommandType = cmd_str
             .parse()
@@ -118,7 +118,6 @@ impl FromStr for Command {
                 } else {
                     arg
                 };
-                println!("{arg}");
                 Ok(Command::Load(OsString::from(arg)))
             }
             CommandType::Typecheck => {
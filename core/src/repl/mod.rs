@@ -9,12 +9,12 @@
 use crate::cache::{Cache, Envs, ErrorTolerance, SourcePath};
 use crate::error::{Error, EvalError, IOError, ParseError, ParseErrors, ReplError};
 use crate::eval::cache::Cache as EvalCache;
-use crate::eval::{Closure, VirtualMachine};
+use crate::eval::{limits::EvalLimits, Closure, VirtualMachine};
 use crate::identifier::LocIdent;
 use crate::parser::{grammar, lexer, ErrorTolerantParser, ExtendedTerm};
 use crate::program::FieldPath;
 use crate::term::TraverseOrder;
-use crate::term::{record::Field, RichTerm, Term, Traverse};
+use crate::term::{make as mk_term, record::Field, RichTerm, Term, Traverse};
 use crate::transform::import_resolution;
 use crate::typ::Type;
 use crate::{eval, transform, typecheck};
@@ -37,7 +37,9 @@ pub mod command;
 pub mod query_print;
 #[cfg(feature = "repl")]
 pub mod rustyline_frontend;
-#[cfg(feature = "repl-wasm")]
+#[cfg(any(feature = "repl", feature = "repl-wasm"))]
+pub mod script;
+#[cfg(any(feature = "repl", feature = "repl-wasm"))]
 pub mod simple_frontend;
 #[cfg(feature = "repl-wasm")]
 pub mod wasm_frontend;
@@ -98,6 +100,12 @@ impl<EC: EvalCache> ReplImpl<EC> {
         }
     }
 
+    /// Set the resource limits of the evaluation of the inputs. Each input is subject to the
+    /// limits separately.
+    pub fn set_limits(&mut self, limits: EvalLimits) {
+        self.vm.set_limits(limits);
+    }
+
     /// Load and process the stdlib, and use it to populate the eval environment as well as the
     /// typing environment.
     pub fn load_stdlib(&mut self) -> Result<(), Error> {
@@ -201,13 +209,39 @@ impl<EC: EvalCache> ReplImpl<EC> {
                 .into())
             }
             ExtendedTerm::ToplevelLet(id, t) => {
-                let t = self.prepare(Some(id), t)?;
-                let local_env = self.env.eval_env.clone();
-                eval::env_add(&mut self.vm.cache, &mut self.env.eval_env, id, t, local_env);
+                self.bind(id, t)?;
+                Ok(EvalResult::Bound(id))
+            }
+            ExtendedTerm::ToplevelLetPattern(alias, pat, t) => {
+                // We first bind the whole value to the alias, or to a fresh identifier if there's
+                // none, and then bind each variable of the pattern to `let <pat> = <id> in <var>`.
+                // Because evaluation is lazy and shared, the bound expression is evaluated at most
+                // once.
+                let id = alias.unwrap_or_else(LocIdent::fresh);
+                self.bind(id, t)?;
+
+                for var in pat.bound_idents() {
+                    let t = mk_term::let_pat(
+                        None::<LocIdent>,
+                        pat.clone(),
+                        mk_term::var(id),
+                        mk_term::var(var),
+                    );
+                    self.bind(var, t)?;
+                }
+
                 Ok(EvalResult::Bound(id))
             }
         }
     }
+
+    /// Prepare a term and add it to the current environments under the name `id`.
+    fn bind(&mut self, id: LocIdent, t: RichTerm) -> Result<(), Error> {
+        let t = self.prepare(Some(id), t)?;
+        let local_env = self.env.eval_env.clone();
+        eval::env_add(&mut self.vm.cache, &mut self.env.eval_env, id, t, local_env);
+        Ok(())
+    }
 }
 
 impl<EC: EvalCache> Repl for ReplImpl<EC> {
@@ -358,9 +392,11 @@ pub enum InputStatus {
 /// Validator enabling multiline input.
 ///
 /// The behavior is the following:
-/// - always end an input that starts with the command prefix `:`
-/// - otherwise, try to parse the input. If an unexpected end of file error occurs, continue
-///   the input in a new line. Otherwise, accept and end the input.
+/// - end an input that starts with the command prefix `:`, unless the command takes an expression
+///   as an argument (`:print` or `:typecheck`) which is incomplete
+/// - otherwise, try to parse the input. If an unexpected end of file error occurs (unclosed
+///   delimiter, unterminated string, missing body, etc.), continue the input in a new line.
+///   Otherwise, accept and end the input.
 //TODO: the validator throws away the result of parsing, or the parse error, when accepting an
 //input, meaning that the work is done a second time by the REPL. Validator's work could be
 //reused. This overhead shouldn't be dramatic for the typical REPL input size, though.
@@ -388,10 +424,28 @@ impl InputParser {
     }
 
     pub fn parse(&self, input: &str) -> InputStatus {
-        if input.starts_with(':') || input.trim().is_empty() {
+        if input.trim().is_empty() {
             return InputStatus::Command;
         }
 
+        // Commands always end the input, unless they take an expression as an argument and this
+        // expression is incomplete.
+        if let Some(cmd) = input.strip_prefix(':') {
+            let (cmd, arg) = cmd.split_once(char::is_whitespace).unwrap_or((cmd, ""));
+
+            return match cmd.parse::<command::CommandType>() {
+                Ok(command::CommandType::Print | command::CommandType::Typecheck)
+                    if !arg.trim().is_empty() =>
+                {
+                    match self.parse(arg) {
+                        InputStatus::Partial => InputStatus::Partial,
+                        _ => InputStatus::Command,
+                    }
+                }
+                _ => InputStatus::Command,
+            };
+        }
+
         let result = self
             .parser
             .parse_tolerant(self.file_id, lexer::Lexer::new(input));
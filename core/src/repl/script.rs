@@ -0,0 +1,312 @@
+//! Non-interactive execution of REPL sessions.
+//!
+//! A **script** is a sequence of REPL inputs written as they would be typed in the interactive
+//! REPL. Inputs are separated in the same way as in the interactive REPL: a new input starts as
+//! soon as the previous one is complete, and inputs can span several lines (see [InputParser]).
+//!
+//! A **transcript** is a recorded REPL session, using the same format as the `nickel #repl` code
+//! blocks of the user manual:
+//!
+//! ```text
+//! > let foo =
+//!     fun x => x
+//!   in foo 5
+//! 5
+//!
+//! > std.function.id 5
+//! 5
+//! ```
+//!
+//! Each input starts with `> `, continuation lines are indented by at least one space, and the
+//! lines following an input up to the next input are the expected output. If the expected output
+//! starts with `error:`, the input is expected to fail and the expected output is compared with
+//! the error report. A final `[...]` means that the expected output is merely a prefix of the
+//! actual one.
+//!
+//! Running a script with [run_script] produces a transcript of the session, which can then be
+//! checked against subsequent runs with [check_transcript].
+use super::{command::Command, simple_frontend, *};
+use crate::error;
+use codespan_reporting::term::termcolor::NoColor;
+
+/// The prefix of an input in a transcript.
+pub const INPUT_PREFIX: &str = "> ";
+
+/// A single input of a script or of a transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// The input, possibly spanning several lines.
+    pub input: String,
+    /// The line (starting from 1) at which the input starts in the script or transcript.
+    pub line: usize,
+    /// The expected output. Always `None` for a script.
+    pub expected: Option<String>,
+}
+
+/// The result of running a single input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The input succeeded, with the corresponding output (which may be empty).
+    Output(String),
+    /// The input failed, with the corresponding rendered error report.
+    Error(String),
+}
+
+impl Outcome {
+    /// Return the output or the error report as a string.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Outcome::Output(s) | Outcome::Error(s) => s,
+        }
+    }
+}
+
+/// An input of a transcript whose output doesn't match the expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The input which produced an unexpected output.
+    pub step: Step,
+    /// The actual output.
+    pub actual: Outcome,
+}
+
+/// Split a script into inputs, using the same rule as the interactive REPL to determine where an
+/// input ends. Blank lines between inputs are ignored.
+pub fn parse_script(content: &str, validator: &InputParser) -> Vec<Step> {
+    let mut steps = Vec::new();
+    let mut current: Option<Step> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let step = match current.take() {
+            Some(mut step) => {
+                step.input.push('\n');
+                step.input.push_str(line);
+                step
+            }
+            None if line.trim().is_empty() => continue,
+            None => Step {
+                input: String::from(line),
+                line: index + 1,
+                expected: None,
+            },
+        };
+
+        match validator.parse(&step.input) {
+            InputStatus::Partial => current = Some(step),
+            _ => steps.push(step),
+        }
+    }
+
+    // An incomplete input at the end of the script is still run, so that the corresponding parse
+    // error is reported.
+    steps.extend(current);
+    steps
+}
+
+/// Split a transcript into inputs together with their expected output.
+pub fn parse_transcript(content: &str) -> Vec<Step> {
+    let mut steps: Vec<Step> = Vec::new();
+    // Whether we are still reading the continuation lines of the last input.
+    let mut in_input = false;
+
+    for (index, line) in content.lines().enumerate() {
+        if let Some(input) = line.strip_prefix('>') {
+            steps.push(Step {
+                input: String::from(input.strip_prefix(' ').unwrap_or(input)),
+                line: index + 1,
+                expected: Some(String::new()),
+            });
+            in_input = true;
+        } else if let Some(step) = steps.last_mut() {
+            if in_input && line.starts_with(' ') {
+                step.input.push('\n');
+                step.input.push_str(line);
+            } else {
+                in_input = false;
+                // unwrap(): steps of a transcript always have an expected output
+                let expected = step.expected.as_mut().unwrap();
+                expected.push_str(line);
+                expected.push('\n');
+            }
+        }
+        // Text before the first input is ignored.
+    }
+
+    steps
+}
+
+/// Run a single input and return the produced output or the rendered error report, without any
+/// color.
+pub fn run_input<R: Repl>(repl: &mut R, input: &str) -> Outcome {
+    let result = match input.strip_prefix(':').map(str::parse::<Command>) {
+        // The simple frontend doesn't support `:load`, since it's not available on all platforms.
+        // We handle it here instead.
+        Some(Ok(Command::Load(path))) => repl
+            .load(&path)
+            .map(|term| {
+                let count = match term.as_ref() {
+                    Term::Record(record) | Term::RecRecord(record, ..) => record.fields.len(),
+                    _ => 0,
+                };
+                simple_frontend::InputResult::Success(format!(
+                    "Loaded {count} symbol(s) in the environment."
+                ))
+            })
+            .map_err(simple_frontend::InputError::from),
+        _ => simple_frontend::input(repl, input),
+    };
+
+    match result {
+        Ok(simple_frontend::InputResult::Success(output)) => Outcome::Output(output),
+        Ok(simple_frontend::InputResult::Blank | simple_frontend::InputResult::Partial) => {
+            Outcome::Output(String::new())
+        }
+        Err(simple_frontend::InputError::NickelError(err)) => {
+            let mut buffer = NoColor::new(Vec::<u8>::new());
+            let stdlib_ids = repl.cache_mut().get_all_stdlib_modules_file_id();
+            error::report_with(
+                &mut buffer,
+                repl.cache_mut().files_mut(),
+                stdlib_ids.as_ref(),
+                err,
+            );
+            Outcome::Error(String::from_utf8_lossy(&buffer.into_inner()).into_owned())
+        }
+        Err(simple_frontend::InputError::Other(msg)) => Outcome::Error(format!("error: {msg}")),
+    }
+}
+
+/// Run a script and write the transcript of the session to `out`. Return the number of inputs
+/// which failed.
+pub fn run_script<R: Repl>(
+    repl: &mut R,
+    script: &str,
+    out: &mut impl Write,
+) -> std::io::Result<usize> {
+    let validator = InputParser::new(
+        repl.cache_mut()
+            .replace_string(SourcePath::ReplInput(0), String::new()),
+    );
+    let mut failures = 0;
+
+    for step in parse_script(script, &validator) {
+        let outcome = run_input(repl, &step.input);
+
+        if matches!(outcome, Outcome::Error(_)) {
+            failures += 1;
+        }
+
+        write_step(out, &step.input, &outcome)?;
+    }
+
+    Ok(failures)
+}
+
+/// Run the inputs of a transcript and return the list of inputs whose output doesn't match the
+/// expected output.
+pub fn check_transcript<R: Repl>(repl: &mut R, transcript: &str) -> Vec<Mismatch> {
+    parse_transcript(transcript)
+        .into_iter()
+        .filter_map(|step| {
+            let actual = run_input(repl, &step.input);
+            let expected = step.expected.as_deref().unwrap_or_default();
+
+            (!output_matches(&actual, expected)).then_some(Mismatch { step, actual })
+        })
+        .collect()
+}
+
+/// Write an input and its outcome in the transcript format.
+pub fn write_step(out: &mut impl Write, input: &str, outcome: &Outcome) -> std::io::Result<()> {
+    let mut lines = input.lines();
+
+    if let Some(first) = lines.next() {
+        writeln!(out, "{INPUT_PREFIX}{first}")?;
+    }
+
+    for line in lines {
+        // Continuation lines must be indented to be recognized as such.
+        if line.starts_with(' ') {
+            writeln!(out, "{line}")?;
+        } else {
+            writeln!(out, "  {line}")?;
+        }
+    }
+
+    let output = normalize(outcome.as_str());
+
+    if !output.is_empty() {
+        writeln!(out, "{output}")?;
+    }
+
+    writeln!(out)
+}
+
+/// Check if an outcome matches an expected output. Trailing whitespace is ignored. An expected
+/// error report ending with `[...]` only needs to be a prefix of the actual report.
+fn output_matches(actual: &Outcome, expected: &str) -> bool {
+    let expected = normalize(expected);
+    let actual_str = normalize(actual.as_str());
+
+    match (actual, expected.strip_suffix("[...]")) {
+        (Outcome::Error(_), Some(prefix)) if expected.starts_with("error:") => {
+            actual_str.starts_with(prefix.trim_end())
+        }
+        _ => actual_str == expected,
+    }
+}
+
+/// Remove trailing whitespace from each line, as well as leading and trailing blank lines.
+fn normalize(s: &str) -> String {
+    s.lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_parsing() {
+        let transcript = "\
+Some text which is ignored.
+> let foo =
+    fun x => x
+  in foo 5
+5
+
+> let x = 1
+
+> x +
+1
+";
+        let steps = parse_transcript(transcript);
+
+        assert_eq!(steps.len(), 3);
+        assert_eq!(steps[0].input, "let foo =\n    fun x => x\n  in foo 5");
+        assert_eq!(steps[0].line, 2);
+        assert_eq!(steps[0].expected.as_deref(), Some("5\n\n"));
+        assert_eq!(steps[1].input, "let x = 1");
+        assert_eq!(steps[1].expected.as_deref(), Some("\n"));
+        assert_eq!(steps[2].input, "x +");
+        assert_eq!(steps[2].expected.as_deref(), Some("1\n"));
+    }
+
+    #[test]
+    fn output_matching() {
+        assert!(output_matches(&Outcome::Output("5\n".into()), "5  \n\n"));
+        assert!(!output_matches(&Outcome::Output("5\n".into()), "6\n"));
+        assert!(output_matches(
+            &Outcome::Error("error: unbound identifier `y`\n  ┌─ <repl-input-1>:1:1".into()),
+            "error: unbound identifier `y`\n[...]"
+        ));
+        assert!(!output_matches(
+            &Outcome::Output("error: this is not an error".into()),
+            "error: this[...]"
+        ));
+    }
+}
@@ -4,6 +4,7 @@ use super::{Repl, ReplImpl};
 use crate::cache::Cache;
 use crate::error::IntoDiagnostics;
 use crate::eval::cache::CacheImpl;
+use crate::eval::limits::EvalLimits;
 use crate::serialize::ExportFormat;
 use codespan::{FileId, Files};
 use codespan_reporting::{
@@ -349,6 +350,25 @@ pub fn repl_init(trace_callback: Option<js_sys::Function>) -> WasmInitResult {
     }
 }
 
+/// Set the resource limits of the evaluation of the next inputs of the WASM REPL, so that a
+/// non-terminating input doesn't freeze the page. A missing value means that the corresponding
+/// resource is unlimited. Timeouts aren't supported, as there is no clock available in
+/// WebAssembly.
+#[wasm_bindgen]
+pub fn repl_set_limits(
+    state: &mut ReplState,
+    max_steps: Option<u32>,
+    max_stack_depth: Option<u32>,
+    max_cache_size: Option<u32>,
+) {
+    state.0.set_limits(EvalLimits {
+        max_steps: max_steps.map(u64::from),
+        max_stack_depth: max_stack_depth.map(|depth| depth as usize),
+        max_cache_size: max_cache_size.map(|size| size as usize),
+        timeout: None,
+    });
+}
+
 /// Evaluate an input in the WASM REPL.
 #[wasm_bindgen]
 pub fn repl_input(state: &mut ReplState, line: &str) -> WasmInputResult {
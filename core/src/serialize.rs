@@ -8,7 +8,7 @@ use crate::{
     term::{
         array::{Array, ArrayAttrs},
         record::RecordData,
-        IndexMap, Number, RichTerm, Term, TypeAnnotation,
+        EnumVariantAttrs, IndexMap, Number, RichTerm, Term, TypeAnnotation,
     },
 };
 
@@ -166,6 +166,22 @@ where
     Ok((terms, Default::default()))
 }
 
+/// Serializer for an enum variant. A variant `'Tag arg` is serialized as a record with one field
+/// `{ Tag = arg }`.
+pub fn serialize_enum_variant<S>(
+    tag: &LocIdent,
+    arg: &RichTerm,
+    _attrs: &EnumVariantAttrs,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map_ser = serializer.serialize_map(Some(1))?;
+    map_ser.serialize_entry(&tag.to_string(), arg)?;
+    map_ser.end()
+}
+
 impl Serialize for RichTerm {
     /// Serialize the underlying term.
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -186,8 +202,9 @@ impl<'de> Deserialize<'de> for RichTerm {
     }
 }
 
-/// Check that a term is serializable. Serializable terms are booleans, numbers, strings, enum,
-/// arrays of serializable terms or records of serializable terms.
+/// Check that a term is serializable. Serializable terms are booleans, numbers, strings, enum
+/// tags, enum variants with a serializable argument, arrays of serializable terms or records of
+/// serializable terms.
 pub fn validate(format: ExportFormat, t: &RichTerm) -> Result<(), ExportError> {
     use Term::*;
 
@@ -235,6 +252,7 @@ pub fn validate(format: ExportFormat, t: &RichTerm) -> Result<(), ExportError> {
                 array.iter().try_for_each(|t| validate(format, t))?;
                 Ok(())
             }
+            EnumVariant { arg, .. } => validate(format, arg),
             _ => Err(ExportError::NonSerializable(t.clone())),
         }
     }
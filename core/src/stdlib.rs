@@ -87,6 +87,9 @@ pub mod internals {
     generate_accessor!(dyn_tail);
     generate_accessor!(empty_tail);
     generate_accessor!(stdlib_contract_equal);
+    generate_accessor!(union);
+    generate_accessor!(intersection);
+    generate_accessor!(array_pattern);
 
     generate_accessor!(rec_default);
     generate_accessor!(rec_force);
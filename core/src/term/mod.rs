@@ -10,6 +10,7 @@
 //! It also features types and type annotations, and other typechecking or contracts-related
 //! constructs (label, symbols, etc.).
 pub mod array;
+pub mod net;
 pub mod record;
 pub mod string;
 
@@ -18,7 +19,7 @@ use record::{Field, FieldDeps, FieldMetadata, RecordData, RecordDeps};
 use string::NickelString;
 
 use crate::{
-    destructuring::RecordPattern,
+    destructuring::{Destruct, MatchBranch},
     error::{EvalError, ParseError},
     eval::cache::CacheIndex,
     eval::Environment,
@@ -95,7 +96,7 @@ pub enum Term {
 
     /// A function able to destruct its arguments.
     #[serde(skip)]
-    FunPattern(Option<LocIdent>, RecordPattern, RichTerm),
+    FunPattern(Option<LocIdent>, Destruct, RichTerm),
 
     /// A blame label.
     #[serde(skip)]
@@ -107,7 +108,7 @@ pub enum Term {
 
     /// A destructuring let-binding.
     #[serde(skip)]
-    LetPattern(Option<LocIdent>, RecordPattern, RichTerm, RichTerm),
+    LetPattern(Option<LocIdent>, Destruct, RichTerm, RichTerm),
 
     /// An application.
     #[serde(skip)]
@@ -117,9 +118,18 @@ pub enum Term {
     #[serde(skip)]
     Var(LocIdent),
 
-    /// An enum variant.
+    /// An enum tag.
     Enum(LocIdent),
 
+    /// An enum variant, that is an enum tag carrying a payload, such as `'Some 5`.
+    #[serde(serialize_with = "crate::serialize::serialize_enum_variant")]
+    #[serde(skip_deserializing)]
+    EnumVariant {
+        tag: LocIdent,
+        arg: RichTerm,
+        attrs: EnumVariantAttrs,
+    },
+
     /// A record, mapping identifiers to terms.
     #[serde(serialize_with = "crate::serialize::serialize_record")]
     #[serde(deserialize_with = "crate::serialize::deserialize_record")]
@@ -143,6 +153,14 @@ pub enum Term {
         default: Option<RichTerm>,
     },
 
+    /// A match expression whose branches use general patterns or guards. As [Term::Match], it
+    /// still has to be applied to the matched value. Match expressions are parsed as a
+    /// [Term::Match] whenever possible, that is when they only dispatch on enum tags. A general
+    /// match expression is desugared to simpler terms after typechecking (see
+    /// [crate::transform::desugar_destructuring]).
+    #[serde(skip)]
+    MatchPattern(Vec<MatchBranch>),
+
     /// An array.
     #[serde(serialize_with = "crate::serialize::serialize_array")]
     #[serde(deserialize_with = "crate::serialize::deserialize_array")]
@@ -283,6 +301,18 @@ impl PartialEq for Term {
             (Self::App(l0, l1), Self::App(r0, r1)) => l0 == r0 && l1 == r1,
             (Self::Var(l0), Self::Var(r0)) => l0 == r0,
             (Self::Enum(l0), Self::Enum(r0)) => l0 == r0,
+            (
+                Self::EnumVariant {
+                    tag: l_tag,
+                    arg: l_arg,
+                    attrs: l_attrs,
+                },
+                Self::EnumVariant {
+                    tag: r_tag,
+                    arg: r_arg,
+                    attrs: r_attrs,
+                },
+            ) => l_tag == r_tag && l_arg == r_arg && l_attrs == r_attrs,
             (Self::Record(l0), Self::Record(r0)) => l0 == r0,
             (Self::RecRecord(l0, l1, l2), Self::RecRecord(r0, r1, r2)) => {
                 l0 == r0 && l1 == r1 && l2 == r2
@@ -297,6 +327,7 @@ impl PartialEq for Term {
                     default: r_default,
                 },
             ) => l_cases == r_cases && l_default == r_default,
+            (Self::MatchPattern(l0), Self::MatchPattern(r0)) => l0 == r0,
             (Self::Array(l0, l1), Self::Array(r0, r1)) => l0 == r0 && l1 == r1,
             (Self::Op1(l0, l1), Self::Op1(r0, r1)) => l0 == r0 && l1 == r1,
             (Self::Op2(l0, l1, l2), Self::Op2(r0, r1, r2)) => l0 == r0 && l1 == r1 && l2 == r2,
@@ -477,6 +508,20 @@ pub struct LetAttrs {
 
     /// A recursive let binding adds its binding to the environment of the expression.
     pub rec: bool,
+
+    /// A type alias `let type Name a b = <type> in <body>`, whose bound value is the runtime
+    /// representation of a [crate::typ::TypeAlias].
+    pub type_alias: bool,
+}
+
+/// The attributes of an enum variant.
+#[derive(Debug, Default, Eq, PartialEq, Clone)]
+pub struct EnumVariantAttrs {
+    /// An enum variant is closurized when its argument is a [Term::Closure] or a constant.
+    ///
+    /// As for arrays, variants produced by the parser aren't closurized. At the first evaluation,
+    /// the argument is put in a cache node and this flag is set to true.
+    pub closurized: bool,
 }
 
 /// The metadata that can be attached to a let.
@@ -809,9 +854,9 @@ impl Term {
             Term::Num(_) => Some("Number".to_owned()),
             Term::Str(_) => Some("String".to_owned()),
             Term::Fun(_, _) | Term::FunPattern(_, _, _) => Some("Function".to_owned()),
-            Term::Match { .. } => Some("MatchExpression".to_owned()),
+            Term::Match { .. } | Term::MatchPattern(_) => Some("MatchExpression".to_owned()),
             Term::Lbl(_) => Some("Label".to_owned()),
-            Term::Enum(_) => Some("Enum".to_owned()),
+            Term::Enum(_) | Term::EnumVariant { .. } => Some("Enum".to_owned()),
             Term::Record(..) | Term::RecRecord(..) => Some("Record".to_owned()),
             Term::Array(..) => Some("Array".to_owned()),
             Term::SealingKey(_) => Some("SealingKey".to_owned()),
@@ -846,12 +891,14 @@ impl Term {
             | Term::Match {..}
             | Term::Lbl(_)
             | Term::Enum(_)
+            | Term::EnumVariant { .. }
             | Term::Record(..)
             | Term::Array(..)
             | Term::SealingKey(_) => true,
             Term::Let(..)
             | Term::LetPattern(..)
             | Term::FunPattern(..)
+            | Term::MatchPattern(..)
             | Term::App(..)
             | Term::Var(_)
             | Term::Closure(_)
@@ -890,12 +937,14 @@ impl Term {
             | Term::SealingKey(_) => true,
             Term::Let(..)
             | Term::LetPattern(..)
+            | Term::EnumVariant { .. }
             | Term::Record(..)
             | Term::Array(..)
             | Term::Fun(..)
             | Term::FunPattern(..)
             | Term::App(_, _)
             | Term::Match { .. }
+            | Term::MatchPattern(..)
             | Term::Var(_)
             | Term::Closure(_)
             | Term::Op1(..)
@@ -942,7 +991,9 @@ impl Term {
             Term::Num(n) if *n >= 0 => true,
             Term::Let(..)
             | Term::Num(..)
+            | Term::EnumVariant { .. }
             | Term::Match { .. }
+            | Term::MatchPattern(..)
             | Term::LetPattern(..)
             | Term::Fun(..)
             | Term::FunPattern(..)
@@ -1073,6 +1124,10 @@ pub enum UnaryOp {
     /// Evaluate a match block applied to an argument.
     Match { has_default: bool },
 
+    /// Raise an error signaling that the argument didn't match any branch of a match expression.
+    /// Only generated by the desugaring of [Term::MatchPattern].
+    MatchFailure(),
+
     /// Static access to a record field.
     ///
     /// Static means that the field identifier is a statically known string inside the source.
@@ -1169,6 +1224,15 @@ pub enum UnaryOp {
     /// Transform a string to lowercase.
     StrLowercase(),
 
+    /// Transform a string to `snake_case`.
+    StrToSnakeCase(),
+
+    /// Transform a string to `kebab-case`.
+    StrToKebabCase(),
+
+    /// Transform a string to `camelCase`.
+    StrToCamelCase(),
+
     /// Return the length of a string.
     StrLength(),
 
@@ -1181,6 +1245,16 @@ pub enum UnaryOp {
     /// Transform a string to an enum.
     EnumFromStr(),
 
+    /// Return the tag of an enum, that is the enum itself for a bare enum tag, or the tag without
+    /// the argument for an enum variant.
+    EnumGetTag(),
+
+    /// Return the argument of an enum variant.
+    EnumGetArg(),
+
+    /// Test if an enum is an enum variant carrying an argument, as opposed to a bare enum tag.
+    EnumIsVariant(),
+
     /// Test if a regex matches a string.
     /// Like [`UnaryOp::StrFind`], this is a unary operator because we would like a way to share the
     /// same "compiled regex" for many matching calls. This is done by returning functions
@@ -1191,12 +1265,19 @@ pub enum UnaryOp {
     /// match, etc.
     StrFind(),
 
+    /// Match a regex on a string, and returns all the successive matches, each with its captured
+    /// groups, named groups and index.
+    StrFindAll(),
+
     /// Version of [`UnaryOp::StrIsMatch`] which remembers the compiled regex.
     StrIsMatchCompiled(CompiledRegex),
 
     /// Version of [`UnaryOp::StrFind`] which remembers the compiled regex.
     StrFindCompiled(CompiledRegex),
 
+    /// Version of [`UnaryOp::StrFindAll`] which remembers the compiled regex.
+    StrFindAllCompiled(CompiledRegex),
+
     /// Force full evaluation of a term and return it.
     ///
     /// This was added in the context of [`BinaryOp::ArrayLazyAppCtr`], in particular to make
@@ -1299,6 +1380,7 @@ impl fmt::Display for UnaryOp {
             Blame() => write!(f, "blame"),
             Embed(_) => write!(f, "embed"),
             Match { .. } => write!(f, "match"),
+            MatchFailure() => write!(f, "match_failure"),
             StaticAccess(_) => write!(f, "static_access"),
             ArrayMap() => write!(f, "map"),
             RecordMap() => write!(f, "record_map"),
@@ -1319,14 +1401,22 @@ impl fmt::Display for UnaryOp {
             StrChars() => write!(f, "str_chars"),
             StrUppercase() => write!(f, "str_uppercase"),
             StrLowercase() => write!(f, "str_lowercase"),
+            StrToSnakeCase() => write!(f, "str_to_snake_case"),
+            StrToKebabCase() => write!(f, "str_to_kebab_case"),
+            StrToCamelCase() => write!(f, "str_to_camel_case"),
             StrLength() => write!(f, "str_length"),
             ToStr() => write!(f, "to_str"),
             NumFromStr() => write!(f, "num_from_str"),
             EnumFromStr() => write!(f, "enum_from_str"),
+            EnumGetTag() => write!(f, "enum_get_tag"),
+            EnumGetArg() => write!(f, "enum_get_arg"),
+            EnumIsVariant() => write!(f, "enum_is_variant"),
             StrIsMatch() => write!(f, "str_is_match"),
             StrFind() => write!(f, "str_find"),
+            StrFindAll() => write!(f, "str_find_all"),
             StrIsMatchCompiled(_) => write!(f, "str_is_match_compiled"),
             StrFindCompiled(_) => write!(f, "str_find_compiled"),
+            StrFindAllCompiled(_) => write!(f, "str_find_all_compiled"),
             Force { .. } => write!(f, "force"),
             RecDefault() => write!(f, "rec_default"),
             RecForce() => write!(f, "rec_force"),
@@ -1428,6 +1518,37 @@ pub enum BinaryOp {
     /// Raise a number to a power.
     Pow(),
 
+    /// Apply a mathematical function, given as an enum tag such as `'Sqrt` or `'Sin`, to a
+    /// number. The result is computed on the nearest 64-bit float, unless it can be computed
+    /// exactly.
+    NumMath(),
+
+    /// The four-quadrant arctangent `atan2 y x` of two numbers.
+    NumAtan2(),
+
+    /// Round a number to an integer, following the rounding mode given as an enum tag.
+    NumRound(),
+
+    /// Greatest common divisor of two integers.
+    NumGcd(),
+
+    /// Bitwise and of two integers, using the two's complement representation.
+    NumBitAnd(),
+
+    /// Bitwise or of two integers, using the two's complement representation.
+    NumBitOr(),
+
+    /// Bitwise exclusive or of two integers, using the two's complement representation.
+    NumBitXor(),
+
+    /// Shift an integer to the left by a number of bits, or to the right if the number of bits is
+    /// negative.
+    NumBitShift(),
+
+    /// Convert a number to a decimal string with a given number of digits after the decimal
+    /// point.
+    NumToDecimalStr(),
+
     /// Concatenation of strings.
     StrConcat(),
 
@@ -1505,6 +1626,36 @@ pub enum BinaryOp {
     /// Hash a string.
     Hash(),
 
+    /// Encode a string, for example in base64.
+    Encode(),
+
+    /// Decode a string encoded by [`BinaryOp::Encode`].
+    Decode(),
+
+    /// Quote a string so that it can be used literally in another language, for example as a
+    /// JSON string or a shell word.
+    Quote(),
+
+    /// Check that a string is a valid IP address or CIDR block (see [crate::term::net]),
+    /// returning `'Ok` or `'Error` with a message explaining why it isn't.
+    NetValidate(),
+
+    /// Check whether a CIDR block contains an IP address or another CIDR block.
+    CidrContains(),
+
+    /// Split a CIDR block into subnets with a longer prefix length.
+    CidrSubnets(),
+
+    /// Check that a string is a valid semantic version or version requirement, returning `'Ok`
+    /// or `'Error` with a message explaining why it isn't.
+    SemverValidate(),
+
+    /// Compare two semantic versions according to their precedence.
+    SemverCompare(),
+
+    /// Check whether a semantic version satisfies a version requirement, such as `^1.2`.
+    SemverMatches(),
+
     /// Serialize a value to a string.
     Serialize(),
 
@@ -1517,6 +1668,20 @@ pub enum BinaryOp {
     /// Determine if a string is a substring of another one.
     StrContains(),
 
+    /// Determine if a string starts with another one.
+    StrStartsWith(),
+
+    /// Determine if a string ends with another one.
+    StrEndsWith(),
+
+    /// Indent each non-empty line of a string by a number of spaces. The arguments are in order
+    /// the string and the number of spaces.
+    StrIndent(),
+
+    /// Fill the placeholders of a template string with the values of the fields of a record. The
+    /// record must have been fully evaluated beforehand. See [string::NickelString::format].
+    StrFormat(),
+
     /// Seal a term with a sealing key (see [`Term::Sealed`]).
     Seal(),
 
@@ -1537,6 +1702,10 @@ pub enum BinaryOp {
     /// Append a note to the current diagnostic of a label.
     LabelAppendNote(),
 
+    /// Record in a label that the current contract is a branch of a union or an intersection
+    /// contract. The branch is given as an enum tag, see [crate::label::ContractBranch].
+    LabelGoBranch(),
+
     /// Look up the [`crate::label::TypeVarData`] associated with a [`SealingKey`] in the type
     /// environment of a [label](Term::Lbl)
     LookupTypeVar(),
@@ -1563,6 +1732,15 @@ impl fmt::Display for BinaryOp {
             Div() => write!(f, "div"),
             Modulo() => write!(f, "modulo"),
             Pow() => write!(f, "pow"),
+            NumMath() => write!(f, "num_math"),
+            NumAtan2() => write!(f, "num_atan2"),
+            NumRound() => write!(f, "num_round"),
+            NumGcd() => write!(f, "num_gcd"),
+            NumBitAnd() => write!(f, "num_bit_and"),
+            NumBitOr() => write!(f, "num_bit_or"),
+            NumBitXor() => write!(f, "num_bit_xor"),
+            NumBitShift() => write!(f, "num_bit_shift"),
+            NumToDecimalStr() => write!(f, "num_to_decimal_str"),
             StrConcat() => write!(f, "str_concat"),
             Eq() => write!(f, "eq"),
             LessThan() => write!(f, "less_than"),
@@ -1589,16 +1767,30 @@ impl fmt::Display for BinaryOp {
             ArrayElemAt() => write!(f, "elem_at"),
             Merge(_) => write!(f, "merge"),
             Hash() => write!(f, "hash"),
+            Encode() => write!(f, "encode"),
+            Decode() => write!(f, "decode"),
+            Quote() => write!(f, "quote"),
+            NetValidate() => write!(f, "net_validate"),
+            CidrContains() => write!(f, "cidr_contains"),
+            CidrSubnets() => write!(f, "cidr_subnets"),
+            SemverValidate() => write!(f, "semver_validate"),
+            SemverCompare() => write!(f, "semver_compare"),
+            SemverMatches() => write!(f, "semver_matches"),
             Serialize() => write!(f, "serialize"),
             Deserialize() => write!(f, "deserialize"),
             StrSplit() => write!(f, "str_split"),
             StrContains() => write!(f, "str_contains"),
+            StrStartsWith() => write!(f, "str_starts_with"),
+            StrEndsWith() => write!(f, "str_ends_with"),
+            StrIndent() => write!(f, "str_indent"),
+            StrFormat() => write!(f, "str_format"),
             Seal() => write!(f, "seal"),
             ArrayLazyAppCtr() => write!(f, "array_lazy_app_ctr"),
             RecordLazyAppCtr() => write!(f, "record_lazy_app_ctr"),
             LabelWithMessage() => write!(f, "label_with_message"),
             LabelWithNotes() => write!(f, "label_with_notes"),
             LabelAppendNote() => write!(f, "label_append_note"),
+            LabelGoBranch() => write!(f, "label_go_branch"),
             LookupTypeVar() => write!(f, "lookup_type_variable"),
         }
     }
@@ -1614,9 +1806,25 @@ pub enum NAryOp {
     /// Same as [`NAryOp::StrReplace`], but the pattern is interpreted as a regular expression.
     StrReplaceRegex(),
 
+    /// Same as [`NAryOp::StrReplaceRegex`], but references to capture groups in the replacement,
+    /// such as `$1` or `${name}`, are substituted with the corresponding matched text.
+    StrReplaceRegexCaptures(),
+
+    /// Same as [`NAryOp::StrReplaceRegex`], but the replacement is a function called on each
+    /// match, represented as a record as returned by [`UnaryOp::StrFindAll`].
+    StrReplaceRegexWith(),
+
     /// Return a substring of an original string.
     StrSubstr(),
 
+    /// Pad a string on the left up to a given length. Takes three arguments: the string, the
+    /// length, and the padding character.
+    StrPadLeft(),
+
+    /// Pad a string on the right up to a given length. Takes three arguments: the string, the
+    /// length, and the padding character.
+    StrPadRight(),
+
     /// The merge operator in contract mode (see [crate::eval::merge]). The arguments are in order
     /// the contract's label, the value to check, and the contract as a record.
     MergeContract(),
@@ -1654,6 +1862,15 @@ pub enum NAryOp {
     /// hood, as long as the array isn't modified later, this operation is constant in time and
     /// memory.
     ArraySlice(),
+
+    /// Apply a contract to a value and fully evaluate the result in isolation, catching blame
+    /// errors. Used to implement union contracts.
+    ///
+    /// Takes three arguments: the contract, the label and the value to check. Return `'Ok value`,
+    /// where `value` is the checked value, if the contract succeeds, or `'Error message`, where
+    /// `message` is the message of the label's current diagnostic (or an empty string), if the
+    /// contract blames.
+    ContractCheck(),
 }
 
 impl NAryOp {
@@ -1661,11 +1878,16 @@ impl NAryOp {
         match self {
             NAryOp::StrReplace()
             | NAryOp::StrReplaceRegex()
+            | NAryOp::StrReplaceRegexCaptures()
+            | NAryOp::StrReplaceRegexWith()
             | NAryOp::StrSubstr()
+            | NAryOp::StrPadLeft()
+            | NAryOp::StrPadRight()
             | NAryOp::MergeContract()
             | NAryOp::RecordUnsealTail()
             | NAryOp::InsertTypeVar()
-            | NAryOp::ArraySlice() => 3,
+            | NAryOp::ArraySlice()
+            | NAryOp::ContractCheck() => 3,
             NAryOp::RecordSealTail() => 4,
         }
     }
@@ -1677,12 +1899,17 @@ impl fmt::Display for NAryOp {
         match self {
             StrReplace() => write!(f, "str_replace"),
             StrReplaceRegex() => write!(f, "str_replace_regex"),
+            StrReplaceRegexCaptures() => write!(f, "str_replace_regex_captures"),
+            StrReplaceRegexWith() => write!(f, "str_replace_regex_with"),
             StrSubstr() => write!(f, "str_substr"),
+            StrPadLeft() => write!(f, "str_pad_left"),
+            StrPadRight() => write!(f, "str_pad_right"),
             MergeContract() => write!(f, "merge_contract"),
             RecordSealTail() => write!(f, "record_seal_tail"),
             RecordUnsealTail() => write!(f, "record_unseal_tail"),
             InsertTypeVar() => write!(f, "insert_type_variable"),
             ArraySlice() => write!(f, "array_slice"),
+            ContractCheck() => write!(f, "contract_check"),
         }
     }
 }
@@ -1899,6 +2126,20 @@ impl Traverse<RichTerm> for RichTerm {
                     pos,
                 )
             }
+            Term::MatchPattern(branches) => {
+                let branches: Result<Vec<MatchBranch>, E> = branches
+                    .into_iter()
+                    .map(|branch| {
+                        Ok(MatchBranch {
+                            pattern: branch.pattern,
+                            guard: branch.guard.map(|t| t.traverse(f, order)).transpose()?,
+                            body: branch.body.traverse(f, order)?,
+                        })
+                    })
+                    .collect();
+
+                RichTerm::new(Term::MatchPattern(branches?), pos)
+            }
             Term::Op1(op, t) => {
                 let t = t.traverse(f, order)?;
                 RichTerm::new(Term::Op1(op, t), pos)
@@ -1917,6 +2158,10 @@ impl Traverse<RichTerm> for RichTerm {
                 let t1 = t1.traverse(f, order)?;
                 RichTerm::new(Term::Sealed(i, t1, lbl), pos)
             }
+            Term::EnumVariant { tag, arg, attrs } => {
+                let arg = arg.traverse(f, order)?;
+                RichTerm::new(Term::EnumVariant { tag, arg, attrs }, pos)
+            }
             Term::Record(record) => {
                 // The annotation on `fields_res` uses Result's corresponding trait to convert from
                 // Iterator<Result> to a Result<Iterator>
@@ -2041,6 +2286,7 @@ impl Traverse<RichTerm> for RichTerm {
             }),
             Term::Fun(_, t)
             | Term::FunPattern(_, _, t)
+            | Term::EnumVariant { arg: t, .. }
             | Term::Op1(_, t)
             | Term::Sealed(_, t, _) => t.traverse_ref(f, state),
             Term::Let(_, t1, t2, _)
@@ -2067,6 +2313,13 @@ impl Traverse<RichTerm> for RichTerm {
                 .iter()
                 .find_map(|(_id, t)| t.traverse_ref(f, state))
                 .or_else(|| default.as_ref().and_then(|t| t.traverse_ref(f, state))),
+            Term::MatchPattern(branches) => branches.iter().find_map(|branch| {
+                branch
+                    .guard
+                    .as_ref()
+                    .and_then(|t| t.traverse_ref(f, state))
+                    .or_else(|| branch.body.traverse_ref(f, state))
+            }),
             Term::Array(ts, _) => ts.iter().find_map(|t| t.traverse_ref(f, state)),
             Term::OpN(_, ts) => ts.iter().find_map(|t| t.traverse_ref(f, state)),
             Term::Annotated(annot, t) => t
@@ -2386,6 +2639,7 @@ pub mod make {
         let attrs = LetAttrs {
             binding_type: BindingType::Normal,
             rec,
+            ..Default::default()
         };
         Term::Let(id.into(), t1.into(), t2.into(), attrs).into()
     }
@@ -2412,13 +2666,12 @@ pub mod make {
     where
         T1: Into<RichTerm>,
         T2: Into<RichTerm>,
-        D: Into<RecordPattern>,
+        D: Into<Destruct>,
         I: Into<LocIdent>,
     {
         Term::LetPattern(id.map(|i| i.into()), pat.into(), t1.into(), t2.into()).into()
     }
 
-    #[cfg(test)]
     pub fn if_then_else<T1, T2, T3>(cond: T1, t1: T2, t2: T3) -> RichTerm
     where
         T1: Into<RichTerm>,
@@ -0,0 +1,318 @@
+//! IP addresses and CIDR blocks, as handled by the `std.net` module of the standard library.
+//!
+//! Parsing is stricter than the one of [`std::net`] for IPv4 addresses (which is the most common
+//! source of mistakes in configurations), and reports why an input is invalid, so that the error
+//! can be shown as is in a contract violation.
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The maximum number of subnets that [`Cidr::subnets`] accepts to produce, as a power of two.
+pub const MAX_SUBNETS_LOG2: u8 = 16;
+
+/// Errors returned when parsing an IP address or a CIDR block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetError {
+    /// An IPv4 address which isn't made of exactly 4 dot-separated octets.
+    OctetCount { input: String, count: usize },
+    /// An octet of an IPv4 address which isn't a decimal number.
+    InvalidOctet { input: String, octet: String },
+    /// An octet of an IPv4 address with a leading zero, which some tools interpret as octal.
+    LeadingZero { input: String, octet: String },
+    /// An octet of an IPv4 address greater than 255.
+    OctetOutOfRange { input: String, octet: String },
+    /// An invalid IPv6 address.
+    InvalidIpv6 { input: String },
+    /// A CIDR block without a `/<prefix length>` part.
+    MissingPrefix { input: String },
+    /// A prefix length which isn't a decimal number.
+    InvalidPrefix { input: String, prefix: String },
+    /// A prefix length greater than the number of bits of the address.
+    PrefixOutOfRange {
+        input: String,
+        prefix: String,
+        max: u8,
+    },
+    /// A CIDR block whose address has bits set outside of the network prefix.
+    HostBitsSet { input: String, network: Cidr },
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NetError::*;
+
+        match self {
+            OctetCount { input, count } => write!(
+                f,
+                "invalid IPv4 address `{input}`: expected 4 dot-separated octets, found {count}"
+            ),
+            InvalidOctet { input, octet } => write!(
+                f,
+                "invalid IPv4 address `{input}`: octet `{octet}` isn't a decimal number"
+            ),
+            LeadingZero { input, octet } => write!(
+                f,
+                "invalid IPv4 address `{input}`: octet `{octet}` has a leading zero"
+            ),
+            OctetOutOfRange { input, octet } => write!(
+                f,
+                "invalid IPv4 address `{input}`: octet `{octet}` is greater than 255"
+            ),
+            InvalidIpv6 { input } => write!(f, "invalid IPv6 address `{input}`"),
+            MissingPrefix { input } => write!(
+                f,
+                "invalid CIDR block `{input}`: missing prefix length. \
+                Expected `<address>/<prefix length>`"
+            ),
+            InvalidPrefix { input, prefix } => write!(
+                f,
+                "invalid CIDR block `{input}`: prefix length `{prefix}` isn't a decimal number"
+            ),
+            PrefixOutOfRange { input, prefix, max } => write!(
+                f,
+                "invalid CIDR block `{input}`: prefix length {prefix} is greater than {max}"
+            ),
+            HostBitsSet { input, network } => write!(
+                f,
+                "invalid CIDR block `{input}`: host bits are set. \
+                The corresponding network is `{network}`"
+            ),
+        }
+    }
+}
+
+/// Errors returned by [`Cidr::subnets`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubnetError {
+    /// The new prefix length is shorter than the one of the block, or longer than the number of
+    /// bits of the address.
+    PrefixOutOfRange { cidr: Cidr, prefix_len: u8 },
+    /// The split would produce more than `2^MAX_SUBNETS_LOG2` subnets.
+    TooManySubnets { cidr: Cidr, prefix_len: u8 },
+}
+
+impl fmt::Display for SubnetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubnetError::PrefixOutOfRange { cidr, prefix_len } => write!(
+                f,
+                "can't split `{cidr}` into /{prefix_len} subnets: \
+                the new prefix length must be between {} and {}",
+                cidr.prefix_len,
+                cidr.max_prefix_len()
+            ),
+            SubnetError::TooManySubnets { cidr, prefix_len } => write!(
+                f,
+                "can't split `{cidr}` into /{prefix_len} subnets: \
+                this would produce 2^{} subnets, but at most 2^{MAX_SUBNETS_LOG2} are allowed",
+                prefix_len - cidr.prefix_len
+            ),
+        }
+    }
+}
+
+/// Parses an IPv4 address in dotted-decimal notation, such as `192.168.0.1`. Contrary to
+/// [`Ipv4Addr::from_str`], the error says which part of the address is invalid.
+pub fn parse_ipv4(input: &str) -> Result<Ipv4Addr, NetError> {
+    let parts: Vec<&str> = input.split('.').collect();
+
+    if parts.len() != 4 {
+        return Err(NetError::OctetCount {
+            input: input.to_owned(),
+            count: parts.len(),
+        });
+    }
+
+    let mut octets = [0u8; 4];
+
+    for (octet, part) in octets.iter_mut().zip(parts) {
+        let input = input.to_owned();
+
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(NetError::InvalidOctet {
+                input,
+                octet: part.to_owned(),
+            });
+        }
+
+        if part.len() > 1 && part.starts_with('0') {
+            return Err(NetError::LeadingZero {
+                input,
+                octet: part.to_owned(),
+            });
+        }
+
+        *octet = part.parse().map_err(|_| NetError::OctetOutOfRange {
+            input,
+            octet: part.to_owned(),
+        })?;
+    }
+
+    Ok(Ipv4Addr::from(octets))
+}
+
+/// Parses an IPv6 address, such as `2001:db8::1`.
+pub fn parse_ipv6(input: &str) -> Result<Ipv6Addr, NetError> {
+    input.parse().map_err(|_| NetError::InvalidIpv6 {
+        input: input.to_owned(),
+    })
+}
+
+/// Parses an IPv4 or an IPv6 address. The family is determined by the presence of a colon.
+pub fn parse_ip(input: &str) -> Result<IpAddr, NetError> {
+    if input.contains(':') {
+        parse_ipv6(input).map(IpAddr::V6)
+    } else {
+        parse_ipv4(input).map(IpAddr::V4)
+    }
+}
+
+/// A block of IP addresses in CIDR notation, such as `10.0.0.0/8` or `2001:db8::/32`. The
+/// address is always the network address, that is all the bits after the prefix are zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Parses a CIDR block. The address must be the network address: `10.0.0.1/8` is rejected,
+    /// as it's most likely a mistake for `10.0.0.0/8` or for a single address.
+    pub fn parse(input: &str) -> Result<Self, NetError> {
+        let Some((address, prefix)) = input.split_once('/') else {
+            return Err(NetError::MissingPrefix {
+                input: input.to_owned(),
+            });
+        };
+
+        let address = parse_ip(address)?;
+        let max = max_prefix_len(address);
+
+        if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(NetError::InvalidPrefix {
+                input: input.to_owned(),
+                prefix: prefix.to_owned(),
+            });
+        }
+
+        let prefix_len = prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix_len| *prefix_len <= max)
+            .ok_or_else(|| NetError::PrefixOutOfRange {
+                input: input.to_owned(),
+                prefix: prefix.to_owned(),
+                max,
+            })?;
+
+        let cidr = Cidr {
+            address,
+            prefix_len,
+        };
+
+        if to_bits(address) & cidr.host_mask() != 0 {
+            return Err(NetError::HostBitsSet {
+                input: input.to_owned(),
+                network: Cidr {
+                    address: from_bits(to_bits(address) & !cidr.host_mask(), address),
+                    prefix_len,
+                },
+            });
+        }
+
+        Ok(cidr)
+    }
+
+    /// The block made of the single address `address`.
+    pub fn from_address(address: IpAddr) -> Self {
+        Cidr {
+            address,
+            prefix_len: max_prefix_len(address),
+        }
+    }
+
+    /// The prefix length, that is the number of leading bits identifying the network.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// The maximum prefix length for the family of this block: 32 for IPv4 and 128 for IPv6.
+    pub fn max_prefix_len(&self) -> u8 {
+        max_prefix_len(self.address)
+    }
+
+    /// Returns `true` if all the addresses of `other` belong to this block. Blocks of different
+    /// families are always disjoint.
+    pub fn contains(&self, other: &Cidr) -> bool {
+        self.address.is_ipv4() == other.address.is_ipv4()
+            && other.prefix_len >= self.prefix_len
+            && to_bits(other.address) & !self.host_mask() == to_bits(self.address)
+    }
+
+    /// Splits this block into consecutive subnets with a longer prefix length `prefix_len`, in
+    /// increasing order of addresses.
+    pub fn subnets(&self, prefix_len: u8) -> Result<Vec<Cidr>, SubnetError> {
+        if prefix_len < self.prefix_len || prefix_len > self.max_prefix_len() {
+            return Err(SubnetError::PrefixOutOfRange {
+                cidr: *self,
+                prefix_len,
+            });
+        }
+
+        let count_log2 = prefix_len - self.prefix_len;
+
+        if count_log2 > MAX_SUBNETS_LOG2 {
+            return Err(SubnetError::TooManySubnets {
+                cidr: *self,
+                prefix_len,
+            });
+        }
+
+        let base = to_bits(self.address);
+        let shift = u32::from(self.max_prefix_len() - prefix_len);
+
+        Ok((0..1u128 << count_log2)
+            .map(|i| Cidr {
+                address: from_bits(base | i.checked_shl(shift).unwrap_or(0), self.address),
+                prefix_len,
+            })
+            .collect())
+    }
+
+    /// The mask of the bits of an address which aren't part of the network prefix.
+    fn host_mask(&self) -> u128 {
+        let host_bits = u32::from(self.max_prefix_len() - self.prefix_len);
+
+        1u128
+            .checked_shl(host_bits)
+            .map_or(u128::MAX, |bit| bit - 1)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+fn max_prefix_len(address: IpAddr) -> u8 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn to_bits(address: IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(address) => u128::from(u32::from(address)),
+        IpAddr::V6(address) => u128::from(address),
+    }
+}
+
+/// Converts bits back to an address of the same family as `family`.
+fn from_bits(bits: u128, family: IpAddr) -> IpAddr {
+    match family {
+        // The bits come from an IPv4 address and a mask, so they fit in 32 bits
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+    }
+}
@@ -1,10 +1,18 @@
 use std::ops::{Deref, DerefMut};
 
-use malachite::Rational;
+use malachite::{
+    num::{
+        arithmetic::traits::{Abs, Pow, UnsignedAbs},
+        basic::traits::Zero,
+        conversion::traits::{RoundingFrom, ToSci},
+    },
+    rounding_modes::RoundingMode,
+    Integer, Rational,
+};
 use serde::{Deserialize, Serialize};
 use unicode_segmentation::UnicodeSegmentation;
 
-use super::{array::Array, CompiledRegex, Number, Term};
+use super::{array::Array, record::RecordData, CompiledRegex, Number, Term};
 use crate::identifier::{Ident, LocIdent};
 
 /// A Nickel string is really just a Rust `String`, overlayed with some
@@ -330,6 +338,338 @@ impl NickelString {
         }
     }
 
+    /// Find all the successive non-overlapping matches in `self` for a given
+    /// `regex`, and return for each of them the match itself, the index in
+    /// `self` where it appears, and its capture groups, including named ones.
+    /// Capture groups which didn't participate in a match are represented
+    /// as empty strings.
+    ///
+    /// Note that matches will be ignored if either the match itself or any
+    /// of its capture groups begin or end in the middle of a Unicode extended
+    /// grapheme cluster.
+    ///
+    /// The time complexity of this method is `O(self.len())`.
+    pub fn find_all_regex(&self, regex: &CompiledRegex) -> Vec<RegexMatch> {
+        use grapheme_cluster_preservation::regex;
+
+        // Matches are found in order, so we can compute the grapheme cluster
+        // index of each match by walking the grapheme clusters of `self` only
+        // once.
+        let mut graphemes = self.grapheme_indices(true).enumerate();
+
+        regex::captures_iter(self, regex)
+            .map(|capt| {
+                let whole_match = capt.get(0).unwrap();
+                let index = graphemes
+                    .find(|(_, (byte_offset, _))| *byte_offset >= whole_match.start())
+                    .map(|(grapheme_idx, _)| grapheme_idx)
+                    // An empty match can happen at the very end of the string.
+                    .unwrap_or_else(|| self.length());
+
+                let group_str =
+                    |i: usize| -> NickelString { capt.get(i).map_or("", |m| m.as_str()).into() };
+
+                RegexMatch {
+                    mtch: whole_match.as_str().into(),
+                    index: index.into(),
+                    byte_range: whole_match.range(),
+                    groups: (1..capt.len()).map(group_str).collect(),
+                    named_groups: regex
+                        .capture_names()
+                        .enumerate()
+                        .filter_map(|(i, name)| Some((name?.to_owned(), group_str(i))))
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns a new string in which every occurence of `regex` in `self` is
+    /// replaced by `template`, where references to capture groups are
+    /// replaced by the corresponding matched text. `$1` or `${1}` refers to
+    /// the first capture group, `$name` or `${name}` to the capture group
+    /// named `name`, and `$$` is a literal `$`. See [`regex::Captures::expand`]
+    /// for more details.
+    ///
+    /// Note that this function will not replace matches that begin or end
+    /// in the middle of a Unicode extended grapheme cluster, or with capture
+    /// groups that do so.
+    ///
+    /// The time complexity of this method is `O(self.len())`, plus the
+    /// size of the replacements.
+    pub fn replace_regex_captures(&self, regex: &CompiledRegex, template: &str) -> NickelString {
+        use grapheme_cluster_preservation::regex;
+
+        let mut result = String::new();
+        let mut prev_match_end = 0;
+        for capt in regex::captures_iter(self, regex) {
+            let whole_match = capt.get(0).unwrap();
+            // Push everything between the last match and this one
+            result.push_str(&self[prev_match_end..whole_match.start()]);
+            // Push the replacement, with capture group references expanded
+            capt.expand(template, &mut result);
+            // Skip to the end of the match
+            prev_match_end = whole_match.end();
+        }
+        // Push whatever remains between the end of the match & the end of the
+        // string.
+        result.push_str(&self[prev_match_end..]);
+
+        result.into()
+    }
+
+    /// Returns `true` if `self` starts with `prefix`, and `false` otherwise.
+    ///
+    /// As for [Self::contains], `prefix` must end on a Unicode extended
+    /// grapheme cluster boundary of `self`.
+    ///
+    /// The time complexity of this method is `O(prefix.len())`.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        let mut graphemes = self.graphemes(true);
+        prefix
+            .graphemes(true)
+            .all(|grapheme| graphemes.next() == Some(grapheme))
+    }
+
+    /// Returns `true` if `self` ends with `suffix`, and `false` otherwise.
+    ///
+    /// As for [Self::contains], `suffix` must start on a Unicode extended
+    /// grapheme cluster boundary of `self`.
+    ///
+    /// The time complexity of this method is `O(suffix.len())`.
+    pub fn ends_with(&self, suffix: &str) -> bool {
+        let mut graphemes = self.graphemes(true).rev();
+        suffix
+            .graphemes(true)
+            .rev()
+            .all(|grapheme| graphemes.next() == Some(grapheme))
+    }
+
+    /// Returns a new string padded on the left with `fill` until it's `width`
+    /// Unicode extended grapheme clusters long. `fill` is expected to be a
+    /// single grapheme cluster. If `self` is already at least `width` long, it
+    /// is returned unchanged.
+    ///
+    /// The time complexity of this method is `O(self.len() + width)`.
+    pub fn pad_left(&self, width: usize, fill: &str) -> NickelString {
+        let mut result = String::with_capacity(self.len().max(width));
+        push_repeated(&mut result, fill, width.saturating_sub(self.length()));
+        result.push_str(self);
+        result.into()
+    }
+
+    /// Returns a new string padded on the right with `fill` until it's `width`
+    /// Unicode extended grapheme clusters long. `fill` is expected to be a
+    /// single grapheme cluster. If `self` is already at least `width` long, it
+    /// is returned unchanged.
+    ///
+    /// The time complexity of this method is `O(self.len() + width)`.
+    pub fn pad_right(&self, width: usize, fill: &str) -> NickelString {
+        let mut result = String::with_capacity(self.len().max(width));
+        result.push_str(self);
+        push_repeated(&mut result, fill, width.saturating_sub(self.length()));
+        result.into()
+    }
+
+    /// Returns a new string where `indent` spaces have been inserted at the
+    /// beginning of each non-empty line of `self`.
+    ///
+    /// The time complexity of this method is `O(self.len() + indent * lines)`.
+    pub fn indent(&self, indent: usize) -> NickelString {
+        let mut result = String::with_capacity(self.len());
+
+        for line in self.split_inclusive('\n') {
+            // We don't indent empty lines, to avoid introducing trailing whitespace.
+            if line != "\n" && line != "\r\n" {
+                push_repeated(&mut result, " ", indent);
+            }
+
+            result.push_str(line);
+        }
+
+        result.into()
+    }
+
+    /// Splits the string into words for case conversion. Words are delimited
+    /// by any non-alphanumeric character and by changes of case: a lowercase
+    /// letter or a digit followed by an uppercase letter starts a new word, as
+    /// well as the last uppercase letter of a sequence of uppercase letters
+    /// followed by a lowercase letter (as in `HTTPServer`, which is split into
+    /// `HTTP` and `Server`).
+    ///
+    /// The classification of each Unicode extended grapheme cluster is
+    /// determined by its first codepoint.
+    ///
+    /// The time complexity of this method is `O(self.len())`.
+    fn words(&self) -> Vec<&str> {
+        let first_char = |grapheme: &str| grapheme.chars().next().unwrap_or_default();
+        let graphemes: Vec<_> = self.grapheme_indices(true).collect();
+        let mut words = Vec::new();
+        let mut word_start = None;
+
+        for (i, &(offset, grapheme)) in graphemes.iter().enumerate() {
+            let c = first_char(grapheme);
+
+            if !c.is_alphanumeric() {
+                if let Some(start) = word_start.take() {
+                    words.push(&self[start..offset]);
+                }
+
+                continue;
+            }
+
+            match word_start {
+                // If a word has already been started, the previous grapheme cluster is
+                // alphanumeric.
+                Some(start) => {
+                    let prev = first_char(graphemes[i - 1].1);
+                    let next_is_lowercase = graphemes
+                        .get(i + 1)
+                        .is_some_and(|(_, next)| first_char(next).is_lowercase());
+
+                    if c.is_uppercase()
+                        && (prev.is_lowercase()
+                            || prev.is_numeric()
+                            || (prev.is_uppercase() && next_is_lowercase))
+                    {
+                        words.push(&self[start..offset]);
+                        word_start = Some(offset);
+                    }
+                }
+                None => word_start = Some(offset),
+            }
+        }
+
+        if let Some(start) = word_start {
+            words.push(&self[start..]);
+        }
+
+        words
+    }
+
+    /// Converts the string to `snake_case`: the words of the string (see
+    /// [Self::words]) are converted to lowercase and joined with underscores.
+    ///
+    /// The time complexity of this method is `O(self.len())`.
+    pub fn to_snake_case(&self) -> NickelString {
+        self.words()
+            .into_iter()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("_")
+            .into()
+    }
+
+    /// Converts the string to `kebab-case`: the words of the string (see
+    /// [Self::words]) are converted to lowercase and joined with dashes.
+    ///
+    /// The time complexity of this method is `O(self.len())`.
+    pub fn to_kebab_case(&self) -> NickelString {
+        self.words()
+            .into_iter()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join("-")
+            .into()
+    }
+
+    /// Converts the string to `camelCase`: the words of the string (see
+    /// [Self::words]) are concatenated, the first one in lowercase and the
+    /// following ones in lowercase with an uppercase initial.
+    ///
+    /// The time complexity of this method is `O(self.len())`.
+    pub fn to_camel_case(&self) -> NickelString {
+        let mut result = String::with_capacity(self.len());
+
+        for (i, word) in self.words().into_iter().enumerate() {
+            if i == 0 {
+                result.push_str(&word.to_lowercase());
+            } else {
+                let mut chars = word.chars();
+
+                if let Some(initial) = chars.next() {
+                    result.extend(initial.to_uppercase());
+                    result.push_str(&chars.as_str().to_lowercase());
+                }
+            }
+        }
+
+        result.into()
+    }
+
+    /// Replaces the placeholders of the template `self` with the values of the
+    /// corresponding fields of `values`, which must have been fully
+    /// substituted.
+    ///
+    /// A placeholder is of the form `{name}` or `{name:spec}`, where `spec` is
+    /// a format specification `[[fill]align][0][width][.precision]`:
+    ///
+    /// - `align` is one of `<` (left), `>` (right) or `^` (center), and `fill`
+    ///   is the grapheme cluster used for padding (a space by default). Numbers
+    ///   are aligned to the right by default, and other values to the left.
+    /// - `0` pads numbers with zeros after the sign, and is ignored for other
+    ///   values.
+    /// - `width` is the minimal length of the result, in Unicode extended
+    ///   grapheme clusters.
+    /// - `precision` is the exact number of digits after the decimal point for
+    ///   numbers, and the maximal length for other values, which are truncated
+    ///   if needed. It can't exceed [MAX_DECIMAL_DIGITS].
+    ///
+    /// Literal braces are written `{{` and `}}`.
+    ///
+    /// The time complexity of this method is `O(self.len())`, plus the size of
+    /// the formatted values.
+    pub fn format(&self, values: &RecordData) -> Result<NickelString, FormatError> {
+        let mut result = String::with_capacity(self.len());
+        let mut rest = self.as_str();
+
+        while let Some(brace_idx) = rest.find(['{', '}']) {
+            result.push_str(&rest[..brace_idx]);
+
+            let brace = if rest[brace_idx..].starts_with('{') {
+                '{'
+            } else {
+                '}'
+            };
+            rest = &rest[brace_idx + 1..];
+
+            // Escaped braces
+            if let Some(after) = rest.strip_prefix(brace) {
+                result.push(brace);
+                rest = after;
+                continue;
+            }
+
+            if brace == '}' {
+                return Err(FormatError::UnmatchedClosingBrace);
+            }
+
+            let Some(end_idx) = rest.find('}') else {
+                return Err(FormatError::UnclosedPlaceholder);
+            };
+            let placeholder = &rest[..end_idx];
+            rest = &rest[end_idx + 1..];
+
+            let (name, spec) = placeholder.split_once(':').unwrap_or((placeholder, ""));
+            let spec = FormatSpec::parse(spec).ok_or_else(|| FormatError::InvalidSpec {
+                placeholder: placeholder.to_owned(),
+            })?;
+
+            let value = values
+                .fields
+                .get(&LocIdent::from(name))
+                .and_then(|field| field.value.as_ref())
+                .ok_or_else(|| FormatError::MissingValue {
+                    name: name.to_owned(),
+                })?;
+
+            spec.write(&mut result, name, value.as_ref())?;
+        }
+
+        result.push_str(rest);
+        Ok(result.into())
+    }
+
     /// Consumes `self`, returning the Rust `String`.
     pub fn into_inner(self) -> String {
         self.0
@@ -351,6 +691,18 @@ pub enum RegexFindResult {
     },
 }
 
+/// A match of a regular expression, as returned by `NickelString`'s
+/// `find_all_regex` method.
+pub struct RegexMatch {
+    pub mtch: NickelString,
+    /// The index of the match, in Unicode extended grapheme clusters.
+    pub index: Number,
+    /// The byte offsets of the match in the original string.
+    pub byte_range: std::ops::Range<usize>,
+    pub groups: Vec<NickelString>,
+    pub named_groups: Vec<(String, NickelString)>,
+}
+
 /// Errors returned by `NickelString`'s `substring` method.
 pub enum SubstringError {
     /// The start index was not an int
@@ -402,6 +754,231 @@ impl std::fmt::Display for SubstringError {
     }
 }
 
+/// Errors returned by `NickelString`'s `format` method.
+pub enum FormatError {
+    /// A `{` wasn't closed by a matching `}`.
+    UnclosedPlaceholder,
+    /// A `}` wasn't closing a placeholder nor escaped as `}}`.
+    UnmatchedClosingBrace,
+    /// The format specification of a placeholder couldn't be parsed.
+    InvalidSpec { placeholder: String },
+    /// No value was provided for a placeholder.
+    MissingValue { name: String },
+    /// The value provided for a placeholder can't be converted to a string.
+    UnsupportedValue { name: String, type_of: String },
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use FormatError::*;
+
+        write!(f, "format: ")?;
+
+        match self {
+            UnclosedPlaceholder => write!(
+                f,
+                "unclosed placeholder. Literal braces must be escaped as `{{{{` and `}}}}`"
+            ),
+            UnmatchedClosingBrace => write!(
+                f,
+                "unmatched `}}`. Literal braces must be escaped as `{{{{` and `}}}}`"
+            ),
+            InvalidSpec { placeholder } => write!(
+                f,
+                "invalid placeholder `{{{placeholder}}}`. \
+                Expected `{{name}}` or `{{name:[[fill]align][0][width][.precision]}}`"
+            ),
+            MissingValue { name } => write!(f, "no value provided for placeholder `{name}`"),
+            UnsupportedValue { name, type_of } => write!(
+                f,
+                "can't convert the value of `{name}` to a string. \
+                Expected a String, a Number, a Bool, an enum tag or null, got a value of type {type_of}"
+            ),
+        }
+    }
+}
+
+/// The alignment of a formatted value in a placeholder. See [NickelString::format].
+#[derive(Clone, Copy)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// A parsed format specification `[[fill]align][0][width][.precision]`. See
+/// [NickelString::format].
+struct FormatSpec<'a> {
+    fill: &'a str,
+    align: Option<Align>,
+    zero: bool,
+    width: usize,
+    precision: Option<u32>,
+}
+
+impl<'a> FormatSpec<'a> {
+    /// Parses a format specification, or returns `None` if it's invalid.
+    fn parse(spec: &'a str) -> Option<Self> {
+        fn align(s: &str) -> Option<Align> {
+            match s {
+                "<" => Some(Align::Left),
+                ">" => Some(Align::Right),
+                "^" => Some(Align::Center),
+                _ => None,
+            }
+        }
+
+        fn split_digits(s: &str) -> (&str, &str) {
+            s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+        }
+
+        let mut result = FormatSpec {
+            fill: " ",
+            align: None,
+            zero: false,
+            width: 0,
+            precision: None,
+        };
+
+        let mut graphemes = spec.grapheme_indices(true);
+        let mut rest = match (graphemes.next(), graphemes.next()) {
+            (Some((_, fill)), Some((offset, next))) if align(next).is_some() => {
+                result.fill = fill;
+                result.align = align(next);
+                &spec[offset + next.len()..]
+            }
+            (Some((_, first)), _) if align(first).is_some() => {
+                result.align = align(first);
+                &spec[first.len()..]
+            }
+            _ => spec,
+        };
+
+        if let Some(after) = rest.strip_prefix('0') {
+            result.zero = true;
+            rest = after;
+        }
+
+        let (width, after) = split_digits(rest);
+        if !width.is_empty() {
+            result.width = width.parse().ok()?;
+        }
+        rest = after;
+
+        if let Some(after) = rest.strip_prefix('.') {
+            let (precision, after) = split_digits(after);
+            result.precision = Some(
+                precision
+                    .parse()
+                    .ok()
+                    .filter(|precision| *precision <= MAX_DECIMAL_DIGITS)?,
+            );
+            rest = after;
+        }
+
+        rest.is_empty().then_some(result)
+    }
+
+    /// Formats `value` according to this specification and appends it to `out`. `name` is the
+    /// name of the placeholder, used for error reporting.
+    fn write(&self, out: &mut String, name: &str, value: &Term) -> Result<(), FormatError> {
+        let truncate = |s: &str| -> String {
+            match self.precision {
+                Some(precision) => s.graphemes(true).take(precision as usize).collect(),
+                None => s.to_owned(),
+            }
+        };
+
+        let (text, is_number) = match value {
+            Term::Num(n) => match self.precision {
+                Some(precision) => (to_decimal_string(n, precision), true),
+                None => (format!("{}", n.to_sci()), true),
+            },
+            Term::Str(s) => (truncate(s), false),
+            Term::Bool(b) => (truncate(&b.to_string()), false),
+            Term::Enum(id) => (truncate(id.label()), false),
+            Term::Null => (truncate("null"), false),
+            _ => {
+                return Err(FormatError::UnsupportedValue {
+                    name: name.to_owned(),
+                    type_of: value.type_of().unwrap_or_else(|| String::from("Other")),
+                })
+            }
+        };
+
+        let padding = self.width.saturating_sub(text.graphemes(true).count());
+
+        if is_number && self.zero {
+            let (sign, digits) = match text.strip_prefix('-') {
+                Some(digits) => ("-", digits),
+                None => ("", text.as_str()),
+            };
+
+            out.push_str(sign);
+            push_repeated(out, "0", padding);
+            out.push_str(digits);
+
+            return Ok(());
+        }
+
+        let align = self
+            .align
+            .unwrap_or(if is_number { Align::Right } else { Align::Left });
+        let (left, right) = match align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+
+        push_repeated(out, self.fill, left);
+        out.push_str(&text);
+        push_repeated(out, self.fill, right);
+
+        Ok(())
+    }
+}
+
+/// Appends `count` copies of `s` to `out`.
+fn push_repeated(out: &mut String, s: &str, count: usize) {
+    out.reserve(s.len() * count);
+    (0..count).for_each(|_| out.push_str(s));
+}
+
+/// Round a number to the nearest integer, rounding half-way cases away from zero. Malachite only
+/// provides the ties-to-even rule (`RoundingMode::Nearest`).
+pub fn round_half_away_from_zero(n: &Number) -> Integer {
+    let half = Number::from_signeds(1, 2);
+    let magnitude = Integer::rounding_from(n.abs() + half, RoundingMode::Floor).0;
+
+    if *n < Number::ZERO {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// The largest number of digits after the decimal point accepted by [to_decimal_string] callers.
+/// The conversion scales the number by `10^digits`, which can't be computed for arbitrary counts.
+pub const MAX_DECIMAL_DIGITS: u32 = 10_000;
+
+/// Format a number as a decimal string with exactly `digits` digits after the decimal point. The
+/// last digit is rounded half away from zero.
+pub fn to_decimal_string(n: &Number, digits: u32) -> String {
+    let scaled = round_half_away_from_zero(&(n * Number::from(10).pow(u64::from(digits))));
+    let sign = if scaled < 0 { "-" } else { "" };
+    let digits = digits as usize;
+    let abs = scaled.unsigned_abs().to_string();
+    // Pad with zeros so that there's at least one digit before the decimal point.
+    let abs = format!("{abs:0>width$}", width = digits + 1);
+    let (int_part, frac_part) = abs.split_at(abs.len() - digits);
+
+    if digits == 0 {
+        format!("{sign}{int_part}")
+    } else {
+        format!("{sign}{int_part}.{frac_part}")
+    }
+}
+
 /// Types and functions designed to make it as easy as possible not to accidentally
 /// break up Unicode extended grapheme clusters in string operations.
 mod grapheme_cluster_preservation {
@@ -555,6 +1132,21 @@ mod grapheme_cluster_preservation {
                 .filter(|m| does_match_start_and_end_on_boundary(haystack, m))
         }
 
+        /// An iterator over the capture groups of the successive matches of
+        /// `needle` in `haystack`, filtering out any match where either the
+        /// match itself, or any of its participating capture groups, begin or
+        /// end in the middle of a Unicode extended grapheme cluster.
+        pub fn captures_iter<'a>(
+            haystack: &'a str,
+            needle: &'a Regex,
+        ) -> impl Iterator<Item = regex::Captures<'a>> {
+            needle.captures_iter(haystack).filter(|c| {
+                c.iter()
+                    .flatten()
+                    .all(|m| does_match_start_and_end_on_boundary(haystack, &m))
+            })
+        }
+
         /// Find the left-most match for `needle` in `haystack`, filtering out
         /// any match where either the match itself, or any of its capture
         /// groups, begin or end in the middle of a Unicode extended grapheme
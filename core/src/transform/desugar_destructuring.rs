@@ -18,6 +18,19 @@
 //! ...
 //! ```
 //!
+//! ## The array pattern
+//! ```text
+//! let [a, b ? 0, ..rest] = [1, 2, 3] in ...
+//! ```
+//! will be transformed to:
+//! ```text
+//! let %unnamed% = [1, 2, 3] in
+//! let a = %elem_at% %unnamed% 0 in
+//! let b = if 1 < %length% %unnamed% then %elem_at% %unnamed% 1 else 0 in
+//! let rest = %array_slice% (...) (%length% %unnamed%) %unnamed% in
+//! ...
+//! ```
+//!
 //! ## The function pattern
 //! ```text
 //! let f = fun x@{a, b=c} {d ? 2, ..w} => <do_something> in ...
@@ -30,14 +43,35 @@
 //!     <do_something>
 //! ) in ...
 //! ```
-use crate::destructuring::{FieldPattern, Match, RecordPattern};
+//!
+//! ## The match expression
+//! ```text
+//! match {
+//!   { foo = 'Bar, baz } if baz > 0 => baz,
+//!   _ => 0,
+//! }
+//! ```
+//! will be transformed to a function testing each branch in order:
+//! ```text
+//! fun %unnamed% =>
+//!   if %typeof% %unnamed% == 'Record && ... && (let baz = %unnamed%.baz in baz > 0) then
+//!     let baz = %unnamed%.baz in baz
+//!   else if true then
+//!     0
+//!   else
+//!     %match_failure% %unnamed%
+//! ```
+use crate::destructuring::{
+    ArrayPattern, Destruct, FieldPattern, Match, MatchBranch, Pattern, RecordPattern,
+};
 use crate::identifier::LocIdent;
 use crate::match_sharedterm;
+use crate::mk_app;
 use crate::term::{
-    make::{op1, op2},
-    BinaryOp::DynRemove,
-    BindingType, LetAttrs, RecordOpKind, RichTerm, Term, TypeAnnotation,
-    UnaryOp::StaticAccess,
+    make::{self as mk_term, op1, op2},
+    BinaryOp::{self, DynRemove},
+    BindingType, LetAttrs, NAryOp, RecordOpKind, RichTerm, Term, TypeAnnotation,
+    UnaryOp::{self, StaticAccess},
 };
 
 /// Entry point of the patterns desugaring.
@@ -49,6 +83,7 @@ pub fn transform_one(rt: RichTerm) -> RichTerm {
     match *rt.term {
         Term::LetPattern(..) => desugar_with_contract(rt),
         Term::FunPattern(..) => desugar_fun(rt),
+        Term::MatchPattern(..) => desugar_match(rt),
         _ => rt,
     }
 }
@@ -116,15 +151,12 @@ pub fn desugar(rt: RichTerm) -> RichTerm {
         Term::LetPattern(x, pat, t_, body) => {
             let pos = body.pos;
             let x = x.unwrap_or_else(LocIdent::fresh);
-            RichTerm::new(
-                Term::Let(
-                    x,
-                    t_,
-                    destruct_term(x, &pat, bind_open_field(x, &pat, body)),
-                    Default::default(),
-                ),
-                pos,
-            )
+            let body = match &pat {
+                Destruct::Record(pat) => destruct_term(x, pat, bind_open_field(x, pat, body)),
+                Destruct::Array(pat) => destruct_array(x, pat, bind_array_rest(x, pat, body)),
+            };
+
+            RichTerm::new(Term::Let(x, t_, body, Default::default()), pos)
         }
         _ => rt,
     })
@@ -194,6 +226,7 @@ fn destruct_term(x: LocIdent, pat: &RecordPattern, body: RichTerm) -> RichTerm {
                 LetAttrs {
                     binding_type: BindingType::Normal,
                     rec: false,
+                    ..Default::default()
                 },
             ),
             pos,
@@ -201,7 +234,7 @@ fn destruct_term(x: LocIdent, pat: &RecordPattern, body: RichTerm) -> RichTerm {
         Match::Assign(f, _, FieldPattern::RecordPattern(pattern)) => desugar(RichTerm::new(
             Term::LetPattern(
                 None,
-                pattern.clone(),
+                pattern.clone().into(),
                 op1(StaticAccess(*f), Term::Var(x)),
                 t,
             ),
@@ -211,7 +244,27 @@ fn destruct_term(x: LocIdent, pat: &RecordPattern, body: RichTerm) -> RichTerm {
             desugar(RichTerm::new(
                 Term::LetPattern(
                     Some(*alias),
-                    pattern.clone(),
+                    pattern.clone().into(),
+                    op1(StaticAccess(*f), Term::Var(x)),
+                    t,
+                ),
+                pos,
+            ))
+        }
+        Match::Assign(f, _, FieldPattern::ArrayPattern(pattern)) => desugar(RichTerm::new(
+            Term::LetPattern(
+                None,
+                pattern.clone().into(),
+                op1(StaticAccess(*f), Term::Var(x)),
+                t,
+            ),
+            pos,
+        )),
+        Match::Assign(f, _, FieldPattern::AliasedArrayPattern { alias, pattern }) => {
+            desugar(RichTerm::new(
+                Term::LetPattern(
+                    Some(*alias),
+                    pattern.clone().into(),
                     op1(StaticAccess(*f), Term::Var(x)),
                     t,
                 ),
@@ -220,3 +273,273 @@ fn destruct_term(x: LocIdent, pat: &RecordPattern, body: RichTerm) -> RichTerm {
         }
     })
 }
+
+/// Wrap `body` in a let construct binding the `..rest` capture of an array pattern, if any, to
+/// the elements of the array `x` which aren't matched by the pattern.
+fn bind_array_rest(x: LocIdent, pat: &ArrayPattern, body: RichTerm) -> RichTerm {
+    let Some(rest) = pat.rest else {
+        return body;
+    };
+
+    let length = || op1(UnaryOp::ArrayLength(), Term::Var(x));
+    let matched = mk_term::integer(pat.elements.len() as i64);
+    // If some elements have a default value, the array might be shorter than the pattern.
+    let start = if pat.min_length() < pat.elements.len() {
+        mk_term::if_then_else(
+            op2(BinaryOp::LessThan(), length(), matched.clone()),
+            length(),
+            matched,
+        )
+    } else {
+        matched
+    };
+
+    mk_term::let_in(
+        rest,
+        mk_term::opn(
+            NAryOp::ArraySlice(),
+            vec![start, length(), Term::Var(x).into()],
+        ),
+        body,
+    )
+}
+
+/// Bind the elements of an array pattern to the corresponding elements of the array `x`. An
+/// element which has a default value is replaced by it when the array is too short. The
+/// annotations of the elements are applied to the extracted values. Nested patterns are bound
+/// with `LetPattern`, which is then desugared together with its own contract by the rest of the
+/// traversal.
+fn destruct_array(x: LocIdent, pat: &ArrayPattern, body: RichTerm) -> RichTerm {
+    let pos = body.pos;
+
+    pat.elements
+        .iter()
+        .enumerate()
+        .rev()
+        .fold(body, |t, (i, elem)| {
+            let index = mk_term::integer(i as i64);
+            let mut value = op2(BinaryOp::ArrayElemAt(), Term::Var(x), index.clone());
+
+            if let Some(default) = &elem.field.value {
+                value = mk_term::if_then_else(
+                    op2(
+                        BinaryOp::LessThan(),
+                        index,
+                        op1(UnaryOp::ArrayLength(), Term::Var(x)),
+                    ),
+                    value,
+                    default.clone(),
+                );
+            }
+
+            let annotation = &elem.field.metadata.annotation;
+            if !annotation.is_empty() {
+                value = RichTerm::new(Term::Annotated(annotation.clone(), value), pos);
+            }
+
+            let term = match &elem.pattern {
+                FieldPattern::Ident(id) => Term::Let(*id, value, t, Default::default()),
+                FieldPattern::RecordPattern(pattern) => {
+                    Term::LetPattern(None, pattern.clone().into(), value, t)
+                }
+                FieldPattern::ArrayPattern(pattern) => {
+                    Term::LetPattern(None, pattern.clone().into(), value, t)
+                }
+                FieldPattern::AliasedRecordPattern { alias, pattern } => {
+                    Term::LetPattern(Some(*alias), pattern.clone().into(), value, t)
+                }
+                FieldPattern::AliasedArrayPattern { alias, pattern } => {
+                    Term::LetPattern(Some(*alias), pattern.clone().into(), value, t)
+                }
+            };
+
+            RichTerm::new(term, pos)
+        })
+}
+
+/// Desugar a match expression with general patterns to a function testing the branches in order,
+/// and raising an error if none of them matches. As for the other patterns, the transformation is
+/// not recursive: the guards and the bodies of the branches may still contain patterns.
+pub fn desugar_match(rt: RichTerm) -> RichTerm {
+    match_sharedterm!(match (rt.term) {
+        Term::MatchPattern(branches) => {
+            let x = LocIdent::fresh();
+            let failure = RichTerm::new(
+                Term::Op1(UnaryOp::MatchFailure(), Term::Var(x).into()),
+                rt.pos,
+            );
+
+            let body = branches
+                .into_iter()
+                .rev()
+                .fold(failure, |otherwise, branch| {
+                    desugar_branch(x, branch, otherwise)
+                });
+
+            RichTerm::new(Term::Fun(x, body), rt.pos)
+        }
+        _ => rt,
+    })
+}
+
+/// Desugar one branch of a match expression on the variable `x` to an if-then-else, falling back
+/// to `otherwise` if the branch doesn't match.
+fn desugar_branch(x: LocIdent, branch: MatchBranch, otherwise: RichTerm) -> RichTerm {
+    let MatchBranch {
+        pattern,
+        guard,
+        body,
+    } = branch;
+
+    let mut tests = Vec::new();
+    let mut bindings = Vec::new();
+    compile_pattern(&pattern, Term::Var(x).into(), &mut tests, &mut bindings);
+
+    let bind = |t: RichTerm| {
+        let pos = t.pos;
+        bindings
+            .iter()
+            .rev()
+            .fold(t, |t, (id, value)| mk_term::let_in(*id, value.clone(), t))
+            .with_pos(pos)
+    };
+
+    tests.extend(guard.map(bind));
+    let cond = tests
+        .into_iter()
+        .rev()
+        .reduce(|acc, test| mk_app!(op1(UnaryOp::BoolAnd(), test), acc))
+        .unwrap_or_else(|| Term::Bool(true).into());
+
+    mk_term::if_then_else(cond, bind(body), otherwise)
+}
+
+/// Generate the boolean tests deciding if `value` matches `pattern`, and the bindings introduced
+/// by the pattern. The tests are pushed in the order in which they must be evaluated: a test may
+/// only be well-defined if the previous ones succeeded.
+fn compile_pattern(
+    pattern: &Pattern,
+    value: RichTerm,
+    tests: &mut Vec<RichTerm>,
+    bindings: &mut Vec<(LocIdent, RichTerm)>,
+) {
+    let has_type = |value: &RichTerm, tag: &str| {
+        op2(
+            BinaryOp::Eq(),
+            op1(UnaryOp::Typeof(), value.clone()),
+            Term::Enum(tag.into()),
+        )
+    };
+
+    match pattern {
+        Pattern::Wildcard => (),
+        Pattern::Any(id) => bindings.push((*id, value)),
+        Pattern::Constant(constant) => {
+            let tag = match constant.as_ref() {
+                Term::Num(_) => "Number",
+                Term::Str(_) => "String",
+                Term::Bool(_) => "Bool",
+                _ => "Other",
+            };
+
+            tests.push(has_type(&value, tag));
+            tests.push(op2(BinaryOp::Eq(), value, constant.clone()));
+        }
+        Pattern::Enum(tag, None) => {
+            tests.push(has_type(&value, "Enum"));
+            tests.push(op2(BinaryOp::Eq(), value, Term::Enum(*tag)));
+        }
+        Pattern::Enum(tag, Some(arg)) => {
+            tests.push(has_type(&value, "Enum"));
+            tests.push(op1(UnaryOp::EnumIsVariant(), value.clone()));
+            tests.push(op2(
+                BinaryOp::Eq(),
+                op1(UnaryOp::EnumGetTag(), value.clone()),
+                Term::Enum(*tag),
+            ));
+
+            compile_pattern(arg, op1(UnaryOp::EnumGetArg(), value), tests, bindings);
+        }
+        Pattern::Record { fields, open, rest } => {
+            tests.push(has_type(&value, "Record"));
+
+            for (id, _) in fields {
+                tests.push(op2(
+                    BinaryOp::HasField(RecordOpKind::IgnoreEmptyOpt),
+                    Term::Str((*id).into()),
+                    value.clone(),
+                ));
+            }
+
+            if !open {
+                tests.push(op2(
+                    BinaryOp::Eq(),
+                    op1(
+                        UnaryOp::ArrayLength(),
+                        op1(UnaryOp::FieldsOf(), value.clone()),
+                    ),
+                    mk_term::integer(fields.len() as i64),
+                ));
+            }
+
+            for (id, pattern) in fields {
+                compile_pattern(
+                    pattern,
+                    op1(StaticAccess(*id), value.clone()),
+                    tests,
+                    bindings,
+                );
+            }
+
+            if let Some(rest) = rest {
+                let remaining = fields.iter().fold(value, |acc, (id, _)| {
+                    op2(
+                        DynRemove(RecordOpKind::default()),
+                        Term::Str((*id).into()),
+                        acc,
+                    )
+                });
+                bindings.push((*rest, remaining));
+            }
+        }
+        Pattern::Array {
+            elements,
+            open,
+            rest,
+        } => {
+            let length = || op1(UnaryOp::ArrayLength(), value.clone());
+            let expected = mk_term::integer(elements.len() as i64);
+
+            tests.push(has_type(&value, "Array"));
+            tests.push(if *open {
+                op2(BinaryOp::GreaterOrEq(), length(), expected.clone())
+            } else {
+                op2(BinaryOp::Eq(), length(), expected.clone())
+            });
+
+            for (i, pattern) in elements.iter().enumerate() {
+                compile_pattern(
+                    pattern,
+                    op2(
+                        BinaryOp::ArrayElemAt(),
+                        value.clone(),
+                        mk_term::integer(i as i64),
+                    ),
+                    tests,
+                    bindings,
+                );
+            }
+
+            if let Some(rest) = rest {
+                bindings.push((
+                    *rest,
+                    mk_term::opn(NAryOp::ArraySlice(), vec![expected, length(), value]),
+                ));
+            }
+        }
+        Pattern::Alias(id, pattern) => {
+            bindings.push((*id, value.clone()));
+            compile_pattern(pattern, value, tests, bindings);
+        }
+    }
+}
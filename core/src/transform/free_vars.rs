@@ -4,7 +4,7 @@
 //! the recursive fields that actually appear in the definition of each field when computing the
 //! fixpoint.
 use crate::{
-    destructuring::{FieldPattern, Match, RecordPattern},
+    destructuring::Destruct,
     identifier::Ident,
     term::{
         record::{Field, FieldDeps, RecordDeps},
@@ -97,6 +97,22 @@ impl CollectFreeVars for RichTerm {
                     t.collect_free_vars(free_vars);
                 }
             }
+            Term::MatchPattern(branches) => {
+                for branch in branches {
+                    let mut fresh = HashSet::new();
+
+                    if let Some(guard) = &mut branch.guard {
+                        guard.collect_free_vars(&mut fresh);
+                    }
+                    branch.body.collect_free_vars(&mut fresh);
+
+                    for id in branch.pattern.bound_idents() {
+                        fresh.remove(&id.ident());
+                    }
+
+                    free_vars.extend(fresh);
+                }
+            }
             Term::Op1(_, t) => t.collect_free_vars(free_vars),
             Term::Op2(_, t1, t2) => {
                 t1.collect_free_vars(free_vars);
@@ -107,7 +123,9 @@ impl CollectFreeVars for RichTerm {
                     t.collect_free_vars(free_vars);
                 }
             }
-            Term::Sealed(_, t, _) => t.collect_free_vars(free_vars),
+            Term::Sealed(_, t, _) | Term::EnumVariant { arg: t, .. } => {
+                t.collect_free_vars(free_vars)
+            }
             Term::Record(record) => {
                 for t in record.fields.values_mut() {
                     t.collect_free_vars(free_vars);
@@ -238,40 +256,8 @@ impl CollectFreeVars for Field {
 }
 
 /// Remove the variables bound by a destructuring pattern from a set of free variables.
-fn bind_pattern(dest_pat: &RecordPattern, free_vars: &mut HashSet<Ident>) {
-    let RecordPattern { matches, rest, .. } = dest_pat;
-    for m in matches {
-        bind_match(m, free_vars);
-    }
-
-    if let Some(rest) = rest {
-        free_vars.remove(&rest.ident());
-    }
-}
-
-/// Remove the variables bound by a match expression (constituents of a destructuring pattern) from
-/// a set of free variables.
-fn bind_match(m: &Match, free_vars: &mut HashSet<Ident>) {
-    match m {
-        Match::Assign(_, _, FieldPattern::Ident(ident)) => {
-            free_vars.remove(&ident.ident());
-        }
-        Match::Assign(_, _, FieldPattern::RecordPattern(sub_pattern)) => {
-            bind_pattern(sub_pattern, free_vars);
-        }
-        Match::Assign(
-            _,
-            _,
-            FieldPattern::AliasedRecordPattern {
-                alias,
-                pattern: sub_pattern,
-            },
-        ) => {
-            free_vars.remove(&alias.ident());
-            bind_pattern(sub_pattern, free_vars);
-        }
-        Match::Simple(id, _) => {
-            free_vars.remove(&id.ident());
-        }
+fn bind_pattern(dest_pat: &Destruct, free_vars: &mut HashSet<Ident>) {
+    for id in dest_pat.bound_idents() {
+        free_vars.remove(&id.ident());
     }
 }
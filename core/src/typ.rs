@@ -28,8 +28,10 @@
 //! # Enum types
 //!
 //! An enum type is also a row type where each element is a tag, such as `[| 'foo, 'bar, 'baz |]`.
-//! This type represent values that can be either `'foo`, `'bar` or `'baz`. Enums support row
-//! polymorphism as well.
+//! This type represent values that can be either `'foo`, `'bar` or `'baz`. A tag can carry a
+//! payload, in which case the row specifies the type of the payload: a value of type
+//! `[| 'Some Number, 'None |]` is either `'None` or a variant `'Some x` where `x` is a number.
+//! Enums support row polymorphism as well.
 //!
 //! # Contracts
 //!
@@ -47,8 +49,8 @@ use crate::{
     mk_app, mk_fun,
     position::TermPos,
     term::{
-        array::Array, make as mk_term, record::RecordData, string::NickelString, IndexMap,
-        RichTerm, Term, Traverse, TraverseControl, TraverseOrder,
+        array::Array, make as mk_term, record::RecordData, string::NickelString, BinaryOp,
+        IndexMap, RichTerm, Term, Traverse, TraverseControl, TraverseOrder, UnaryOp,
     },
 };
 
@@ -60,7 +62,8 @@ use std::{
 
 /// A record row, mapping an identifier to a type. A record type is a dictionary mapping
 /// identifiers to Nickel type. Record types are represented as sequences of `RecordRowF`, ending
-/// potentially with a type variable or `Dyn` in tail position.
+/// potentially with a type variable or `Dyn` in tail position. A row can be optional, written
+/// `id ? Type`, in which case the field may be absent, as for fields marked `optional`.
 ///
 /// # Type parameters
 ///
@@ -70,16 +73,24 @@ use std::{
 pub struct RecordRowF<Ty> {
     pub id: LocIdent,
     pub typ: Ty,
+    /// If the row is optional.
+    pub opt: bool,
 }
 
-/// An enum row, which is just an identifier. An enum type is a set of identifiers, represented as
-/// a sequence of `EnumRow`s, ending potentially with a type variable tail position.
+/// An enum row, mapping an identifier to an optional type. A row without type corresponds to a
+/// bare enum tag such as `'foo`, while a row with a type corresponds to an enum variant carrying a
+/// payload of this type, such as `'foo 5`. An enum type is a set of enum rows, represented as a
+/// sequence of `EnumRow`s, ending potentially with a type variable tail position.
 ///
-/// `EnumRowF` is the same as `EnumRow` and doesn't have any type parameter. We introduce the alias
-/// nonetheless for consistency with other parametrized type definitions. See [`TypeF`] for more
-/// details.
-pub type EnumRowF = LocIdent;
-pub type EnumRow = EnumRowF;
+/// # Type parameters
+///
+/// As other types with the `F` suffix, this type is parametrized by one or more recursive
+/// unfoldings (here, `Ty` for `TypeF`). See [`TypeF`] for more details.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnumRowF<Ty> {
+    pub id: LocIdent,
+    pub typ: Option<Ty>,
+}
 
 /// Generic sequence of record rows potentially with a type variable or `Dyn` in tail position.
 ///
@@ -107,12 +118,14 @@ pub enum RecordRowsF<Ty, RRows> {
 ///
 /// # Type parameters
 ///
+/// - `Ty` is the recursive unfolding of a Nickel type stored inside one row. In practice, a
+///   wrapper around an instantiation of `TypeF`.
 /// - `ERows` is the recursive unfolding of enum rows (the tail of this row sequence). In practice,
 ///   a wrapper around `EnumRowsF`.
 #[derive(Clone, PartialEq, Eq, Debug)]
-pub enum EnumRowsF<ERows> {
+pub enum EnumRowsF<Ty, ERows> {
     Empty,
-    Extend { row: EnumRowF, tail: ERows },
+    Extend { row: EnumRowF<Ty>, tail: ERows },
     TailVar(LocIdent),
 }
 
@@ -302,9 +315,11 @@ pub enum TypeF<Ty, RRows, ERows> {
 // `RecordRow` itself potentially contains occurrences of `Type` and `RecordRows`, which need to
 // be boxed. Hence, we don't need to additionally box `RecordRow`.
 
+/// Concrete, recursive definition for an enum row.
+pub type EnumRow = EnumRowF<Box<Type>>;
 /// Concrete, recursive definition for enum rows.
 #[derive(Clone, PartialEq, Debug)]
-pub struct EnumRows(pub EnumRowsF<Box<EnumRows>>);
+pub struct EnumRows(pub EnumRowsF<Box<Type>, Box<EnumRows>>);
 /// Concrete, recursive definition for a record row.
 pub type RecordRow = RecordRowF<Box<Type>>;
 #[derive(Clone, PartialEq, Debug)]
@@ -347,12 +362,13 @@ impl<Ty, RRows> RecordRowsF<Ty, RRows> {
         match self {
             RecordRowsF::Empty => Ok(RecordRowsF::Empty),
             RecordRowsF::Extend {
-                row: RecordRowF { id, typ },
+                row: RecordRowF { id, typ, opt },
                 tail,
             } => Ok(RecordRowsF::Extend {
                 row: RecordRowF {
                     id,
                     typ: f_ty(typ, state)?,
+                    opt,
                 },
                 tail: f_rrows(tail, state)?,
             }),
@@ -412,31 +428,41 @@ impl<Ty, RRows> RecordRowsF<Ty, RRows> {
     }
 }
 
-impl<ERows> EnumRowsF<ERows> {
-    /// Map functions over the tail of enum rows. The mutable state ( `S`) is threaded through the
-    /// calls to the mapped function. The function is fallible and may return an error `E`, which
-    /// causes `try_map_state` to return early with the same error.
+impl<Ty, ERows> EnumRowsF<Ty, ERows> {
+    /// Map functions over the children nodes of enum rows, when seen as a tree. The mutable state
+    /// ( `S`) is threaded through the calls to the mapped functions. Functions are fallible and may
+    /// return an error `E`, which causes `try_map_state` to return early with the same error.
     ///
     /// If we put aside the state and the error (see [EnumRowsF::map), this function makes
-    /// `EnumRowsF` a functor. As hinted by the type signature, this function just maps on
-    /// "one-level" of recursion, so to speak. Take the instantiated version `EnumRows`, and
-    /// enum rows of the form ``[| 'foo, 'bar, 'baz |]``. Then, calling `try_map_state(f_erows,
-    /// state)` on these rows will map `f_erows` onto ``[| 'bar, 'baz |]``.
+    /// `EnumRowsF` a functor (of arity 2). As hinted by the type signature, this function just
+    /// maps on "one-level" of recursion, so to speak. Take the instantiated version `EnumRows`,
+    /// and enum rows of the form ``[| 'foo T, 'bar, 'baz U |]``. Then, calling
+    /// `try_map_state(f_ty, f_erows, state)` on these rows will map `f_ty` onto `T` and `f_erows`
+    /// onto ``[| 'bar, 'baz U |]``.
     ///
-    /// Note that `f_erows` is just mapped once. Map isn't a recursive operation. It's however a
-    /// building block to express recursive operations: as an example, see [RecordRows::traverse].
-    pub fn try_map_state<ERowsO, FERows, S, E>(
+    /// Note that `f_ty` isn't mapped onto `U` recursively: map isn't a recursive operation. It's
+    /// however a building block to express recursive operations: as an example, see
+    /// [EnumRows::traverse].
+    pub fn try_map_state<TyO, ERowsO, FTy, FERows, S, E>(
         self,
-        f_erows: FERows,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
         state: &mut S,
-    ) -> Result<EnumRowsF<ERowsO>, E>
+    ) -> Result<EnumRowsF<TyO, ERowsO>, E>
     where
-        FERows: FnOnce(ERows, &mut S) -> Result<ERowsO, E>,
+        FTy: FnMut(Ty, &mut S) -> Result<TyO, E>,
+        FERows: FnMut(ERows, &mut S) -> Result<ERowsO, E>,
     {
         match self {
             EnumRowsF::Empty => Ok(EnumRowsF::Empty),
-            EnumRowsF::Extend { row, tail } => Ok(EnumRowsF::Extend {
-                row,
+            EnumRowsF::Extend {
+                row: EnumRowF { id, typ },
+                tail,
+            } => Ok(EnumRowsF::Extend {
+                row: EnumRowF {
+                    id,
+                    typ: typ.map(|ty| f_ty(ty, state)).transpose()?,
+                },
                 tail: f_erows(tail, state)?,
             }),
             EnumRowsF::TailVar(id) => Ok(EnumRowsF::TailVar(id)),
@@ -444,35 +470,51 @@ impl<ERows> EnumRowsF<ERows> {
     }
 
     /// Variant of `try_map_state` without threaded state.
-    pub fn try_map<ERowsO, FERows, E>(self, mut f_erows: FERows) -> Result<EnumRowsF<ERowsO>, E>
+    pub fn try_map<TyO, ERowsO, FTy, FERows, E>(
+        self,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
+    ) -> Result<EnumRowsF<TyO, ERowsO>, E>
     where
+        FTy: FnMut(Ty) -> Result<TyO, E>,
         FERows: FnMut(ERows) -> Result<ERowsO, E>,
     {
+        let f_ty_lifted = |ty: Ty, _: &mut ()| -> Result<TyO, E> { f_ty(ty) };
         let f_erows_lifted = |erows: ERows, _: &mut ()| -> Result<ERowsO, E> { f_erows(erows) };
-        self.try_map_state(f_erows_lifted, &mut ())
+        self.try_map_state(f_ty_lifted, f_erows_lifted, &mut ())
     }
 
     /// Variant of `try_map_state` with infallible functions.
-    pub fn map_state<ERowsO, FERows, S>(
+    pub fn map_state<TyO, ERowsO, FTy, FERows, S>(
         self,
+        mut f_ty: FTy,
         mut f_erows: FERows,
         state: &mut S,
-    ) -> EnumRowsF<ERowsO>
+    ) -> EnumRowsF<TyO, ERowsO>
     where
+        FTy: FnMut(Ty, &mut S) -> TyO,
         FERows: FnMut(ERows, &mut S) -> ERowsO,
     {
+        let f_ty_lifted = |ty: Ty, state: &mut S| -> Result<TyO, ()> { Ok(f_ty(ty, state)) };
         let f_erows_lifted =
             |erows: ERows, state: &mut S| -> Result<ERowsO, ()> { Ok(f_erows(erows, state)) };
-        self.try_map_state(f_erows_lifted, state).unwrap()
+        self.try_map_state(f_ty_lifted, f_erows_lifted, state)
+            .unwrap()
     }
 
     /// Variant of `try_map_state` without threaded state and with infallible functions.
-    pub fn map<ERowsO, FERows>(self, mut f_erows: FERows) -> EnumRowsF<ERowsO>
+    pub fn map<TyO, ERowsO, FTy, FERows>(
+        self,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
+    ) -> EnumRowsF<TyO, ERowsO>
     where
+        FTy: FnMut(Ty) -> TyO,
         FERows: FnMut(ERows) -> ERowsO,
     {
-        let f_erows_lifted = |erows: ERows, _: &mut ()| -> ERowsO { f_erows(erows) };
-        self.map_state(f_erows_lifted, &mut ())
+        let f_ty_lifted = |ty: Ty| -> Result<TyO, ()> { Ok(f_ty(ty)) };
+        let f_erows_lifted = |erows: ERows| -> Result<ERowsO, ()> { Ok(f_erows(erows)) };
+        self.try_map(f_ty_lifted, f_erows_lifted).unwrap()
     }
 }
 
@@ -639,6 +681,36 @@ impl Traverse<Type> for RecordRows {
     }
 }
 
+impl Traverse<Type> for EnumRows {
+    fn traverse<F, E>(self, f: &mut F, order: TraverseOrder) -> Result<EnumRows, E>
+    where
+        F: FnMut(Type) -> Result<Type, E>,
+    {
+        let rows = self.0.try_map_state(
+            |ty, f| Ok(Box::new(ty.traverse(f, order)?)),
+            |erows, f| Ok(Box::new(erows.traverse(f, order)?)),
+            f,
+        )?;
+
+        Ok(EnumRows(rows))
+    }
+
+    fn traverse_ref<S, U>(
+        &self,
+        f: &mut dyn FnMut(&Type, &S) -> TraverseControl<S, U>,
+        state: &S,
+    ) -> Option<U> {
+        match &self.0 {
+            EnumRowsF::Extend { row, tail } => row
+                .typ
+                .as_ref()
+                .and_then(|ty| ty.traverse_ref(f, state))
+                .or_else(|| tail.traverse_ref(f, state)),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UnboundTypeVariableError(pub LocIdent);
 
@@ -701,23 +773,25 @@ impl<'a> Iterator for RecordRowsIterator<'a, Type, RecordRows> {
                 Some(RecordRowsIteratorItem::Row(RecordRowF {
                     id: row.id,
                     typ: row.typ.as_ref(),
+                    opt: row.opt,
                 }))
             }
         })
     }
 }
 
-pub struct EnumRowsIterator<'a, ERows> {
+pub struct EnumRowsIterator<'a, Ty, ERows> {
     pub(crate) erows: Option<&'a ERows>,
+    pub(crate) ty: std::marker::PhantomData<Ty>,
 }
 
-pub enum EnumRowsIteratorItem<'a> {
+pub enum EnumRowsIteratorItem<'a, Ty> {
     TailVar(&'a LocIdent),
-    Row(&'a EnumRowF),
+    Row(EnumRowF<&'a Ty>),
 }
 
-impl<'a> Iterator for EnumRowsIterator<'a, EnumRows> {
-    type Item = EnumRowsIteratorItem<'a>;
+impl<'a> Iterator for EnumRowsIterator<'a, Type, EnumRows> {
+    type Item = EnumRowsIteratorItem<'a, Type>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.erows.and_then(|next| match next.0 {
@@ -731,7 +805,10 @@ impl<'a> Iterator for EnumRowsIterator<'a, EnumRows> {
             }
             EnumRowsF::Extend { ref row, ref tail } => {
                 self.erows = Some(tail);
-                Some(EnumRowsIteratorItem::Row(row))
+                Some(EnumRowsIteratorItem::Row(EnumRowF {
+                    id: row.id,
+                    typ: row.typ.as_ref().map(AsRef::as_ref),
+                }))
             }
         })
     }
@@ -750,19 +827,186 @@ fn get_var_contract(
         .clone())
 }
 
+/// Replace the type variables appearing in the type arguments of a contract application, such as
+/// `a` in `forall a. Pair a -> a`, with `Dyn`. The contract of a type variable depends on the
+/// polarity of its occurrences inside the applied contract, which is opaque at this point: we
+/// don't enforce parametricity there rather than risking a wrong blame.
+fn erase_type_var_args(t: &RichTerm) -> RichTerm {
+    let Term::App(head, arg) = t.as_ref() else {
+        return t.clone();
+    };
+
+    let arg = match arg.as_ref() {
+        Term::Type(ty) => {
+            let erased = ty
+                .clone()
+                .traverse(
+                    &mut |ty: Type| -> Result<_, Infallible> {
+                        Ok(match ty.typ {
+                            TypeF::Var(_) => Type::from(TypeF::Dyn).with_pos(ty.pos),
+                            _ => ty,
+                        })
+                    },
+                    TraverseOrder::BottomUp,
+                )
+                .unwrap();
+
+            RichTerm::new(Term::Type(erased), arg.pos)
+        }
+        Term::App(..) => erase_type_var_args(arg),
+        _ => arg.clone(),
+    };
+
+    RichTerm::new(Term::App(erase_type_var_args(head), arg), t.pos)
+}
+
+/// A transparent type alias, declared by `let type Name a b = <type> in <body>`. Contrary to
+/// contracts, which are opaque, the typechecker expands an application of the alias such as
+/// `Name Number String` to the definition of the alias where the parameters are substituted with
+/// the arguments. At runtime, the alias is a function from the contracts of the arguments to the
+/// contract of the substituted type.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TypeAlias {
+    /// The parameters of the alias, which are fresh identifiers.
+    pub params: Vec<LocIdent>,
+    /// The definition of the alias, where the parameters are type variables.
+    pub body: Type,
+}
+
+impl TypeAlias {
+    /// Build the runtime representation of the alias with parameters `params` and definition
+    /// `body`, that is `fun a b => <body>`. The parameters appear as contracts in `body`.
+    pub fn to_term(params: Vec<LocIdent>, body: Type) -> RichTerm {
+        let pos = body.pos;
+
+        params
+            .into_iter()
+            .rev()
+            .fold(RichTerm::new(Term::Type(body), pos), |t, param| {
+                RichTerm::new(Term::Fun(param, t), pos)
+            })
+    }
+
+    /// Recover a type alias from its runtime representation built by [Self::to_term]. The
+    /// parameters are renamed to fresh identifiers and turned into type variables, so that they
+    /// can be substituted with arbitrary types, including type variables of the same name.
+    pub fn from_term(rt: &RichTerm) -> Option<Self> {
+        let mut params = HashMap::new();
+        let mut fresh_params = Vec::new();
+        let mut rt = rt;
+
+        let body = loop {
+            match rt.as_ref() {
+                Term::Fun(param, body) => {
+                    let fresh = LocIdent::fresh().with_pos(param.pos);
+                    params.insert(param.ident(), fresh.ident());
+                    fresh_params.push(fresh);
+                    rt = body;
+                }
+                Term::Type(ty) => break ty.clone(),
+                _ => return None,
+            }
+        };
+
+        Some(TypeAlias {
+            params: fresh_params,
+            body: params_to_type_vars(body, &params),
+        })
+    }
+}
+
+/// Turn the contracts `a` standing for the parameters of a type alias into the type variables
+/// `params[a]`, including when they appear as arguments of an application such as `Pair a`.
+fn params_to_type_vars(ty: Type, params: &HashMap<Ident, Ident>) -> Type {
+    fn map_app_args(t: RichTerm, params: &HashMap<Ident, Ident>) -> RichTerm {
+        let pos = t.pos;
+
+        match t.as_ref() {
+            Term::App(head, arg) => {
+                let arg = match arg.as_ref() {
+                    Term::Var(id) if params.contains_key(&id.ident()) => RichTerm::new(
+                        Term::Type(Type::from(TypeF::Var(params[&id.ident()])).with_pos(arg.pos)),
+                        arg.pos,
+                    ),
+                    Term::Type(ty) => {
+                        RichTerm::new(Term::Type(params_to_type_vars(ty.clone(), params)), arg.pos)
+                    }
+                    Term::App(..) => map_app_args(arg.clone(), params),
+                    _ => arg.clone(),
+                };
+
+                RichTerm::new(Term::App(map_app_args(head.clone(), params), arg), pos)
+            }
+            _ => t,
+        }
+    }
+
+    ty.traverse(
+        &mut |ty: Type| -> Result<_, Infallible> {
+            let typ = match ty.typ {
+                TypeF::Flat(t) => match t.as_ref() {
+                    Term::Var(id) if params.contains_key(&id.ident()) => {
+                        TypeF::Var(params[&id.ident()])
+                    }
+                    _ => TypeF::Flat(map_app_args(t, params)),
+                },
+                typ => typ,
+            };
+
+            Ok(Type { typ, pos: ty.pos })
+        },
+        TraverseOrder::BottomUp,
+    )
+    .unwrap()
+}
+
 impl EnumRows {
-    fn subcontract(&self) -> Result<RichTerm, UnboundTypeVariableError> {
+    /// Construct the subcontract corresponding to an enum type.
+    fn subcontract(
+        &self,
+        vars: HashMap<Ident, RichTerm>,
+        pol: Polarity,
+        sy: &mut i32,
+    ) -> Result<RichTerm, UnboundTypeVariableError> {
         use crate::stdlib::internals;
 
         let mut cases = IndexMap::new();
         let mut has_tail = false;
         let value_arg = LocIdent::from("x");
         let label_arg = LocIdent::from("l");
+        let enum_fail = || mk_app!(internals::enum_fail(), mk_term::var(label_arg));
+        let is_variant = || mk_term::op1(UnaryOp::EnumIsVariant(), mk_term::var(value_arg));
 
         for row in self.iter() {
             match row {
-                EnumRowsIteratorItem::Row(id) => {
-                    cases.insert(*id, mk_term::var(value_arg));
+                EnumRowsIteratorItem::Row(EnumRowF { id, typ: None }) => {
+                    cases.insert(
+                        id,
+                        mk_term::if_then_else(is_variant(), enum_fail(), mk_term::var(value_arg)),
+                    );
+                }
+                EnumRowsIteratorItem::Row(EnumRowF { id, typ: Some(ty) }) => {
+                    let arg = mk_app!(
+                        mk_term::op2(
+                            BinaryOp::ApplyContract(),
+                            ty.subcontract(vars.clone(), pol, sy)?,
+                            mk_term::var(label_arg)
+                        ),
+                        mk_term::op1(UnaryOp::EnumGetArg(), mk_term::var(value_arg))
+                    );
+
+                    cases.insert(
+                        id,
+                        mk_term::if_then_else(
+                            is_variant(),
+                            Term::EnumVariant {
+                                tag: id,
+                                arg,
+                                attrs: Default::default(),
+                            },
+                            enum_fail(),
+                        ),
+                    );
                 }
                 EnumRowsIteratorItem::TailVar(_) => {
                     has_tail = true;
@@ -771,42 +1015,48 @@ impl EnumRows {
             }
         }
 
-        // If the enum type has a tail, the tail must be a universally quantified variable,
-        // and this means that the tag can be anything.
-        let case_body = if has_tail {
-            mk_term::var(value_arg)
-        }
-        // Otherwise, we build a match with all the tags as cases, which just returns the
-        // original argument, and a default case that blames.
+        // We build a match on the tag of the value, with one case per row. Bare tags are
+        // returned as they are, while the payload of variants is checked against the
+        // corresponding contract. If the enum type has a tail, the tail must be a universally
+        // quantified variable, and this means that any other tag is accepted. Otherwise, the
+        // default case blames.
         //
-        // For example, for an enum type [| 'foo, 'bar, 'baz |], the `case` function looks
+        // For example, for an enum type [| 'foo, 'bar Number |], the `case` function looks
         // like:
         //
         // ```
         // fun l x =>
         //   match {
-        //     'foo => x,
-        //     'bar => x,
-        //     'baz => x,
+        //     'foo => if %enum_is_variant% x then $enum_fail l else x,
+        //     'bar => if %enum_is_variant% x
+        //       then 'bar (%apply_contract% $num l (%enum_get_arg% x))
+        //       else $enum_fail l,
         //     _ => $enum_fail l
-        //   } x
+        //   } (%enum_get_tag% x)
         // ```
-        else {
-            mk_app!(
-                Term::Match {
-                    cases,
-                    default: Some(mk_app!(internals::enum_fail(), mk_term::var(label_arg))),
-                },
-                mk_term::var(value_arg)
-            )
+        let default = if has_tail {
+            mk_term::var(value_arg)
+        } else {
+            enum_fail()
         };
+
+        let case_body = mk_app!(
+            Term::Match {
+                cases,
+                default: Some(default),
+            },
+            mk_term::op1(UnaryOp::EnumGetTag(), mk_term::var(value_arg))
+        );
         let case = mk_fun!(label_arg, value_arg, case_body);
 
         Ok(mk_app!(internals::enums(), case))
     }
 
-    pub fn iter(&self) -> EnumRowsIterator<EnumRows> {
-        EnumRowsIterator { erows: Some(self) }
+    pub fn iter(&self) -> EnumRowsIterator<Type, EnumRows> {
+        EnumRowsIterator {
+            erows: Some(self),
+            ty: std::marker::PhantomData,
+        }
     }
 }
 
@@ -822,15 +1072,23 @@ impl RecordRows {
 
         // We begin by building a record whose arguments are contracts
         // derived from the types of the statically known fields.
+        // The optional fields are gathered in a separate record (the values are irrelevant), so
+        // that their absence isn't reported as a missing field.
         let mut rrows = self;
         let mut fcs = IndexMap::new();
+        let mut opts = IndexMap::new();
 
         while let RecordRowsF::Extend {
-            row: RecordRowF { id, typ: ty },
+            row: RecordRowF { id, typ: ty, opt },
             tail,
         } = &rrows.0
         {
             fcs.insert(*id, ty.subcontract(vars.clone(), pol, sy)?);
+
+            if *opt {
+                opts.insert(*id, Term::Null.into());
+            }
+
             rrows = tail
         }
 
@@ -845,8 +1103,9 @@ impl RecordRows {
         };
 
         let rec = RichTerm::from(Term::Record(RecordData::with_field_values(fcs)));
+        let opts = RichTerm::from(Term::Record(RecordData::with_field_values(opts)));
 
-        Ok(mk_app!(internals::record(), rec, tail))
+        Ok(mk_app!(internals::record(), rec, opts, tail))
     }
 
     /// Find a nested binding in a record row type. The nested field is given as a list of
@@ -900,6 +1159,54 @@ impl Type {
         Type { pos, ..self }
     }
 
+    /// Derive a static record type from a record contract literal, such as `{ port | Number }`,
+    /// which can then be understood structurally by the typechecker instead of as an opaque
+    /// contract. Each field must be annotated, and the type of the field is the type annotation
+    /// if there is one, or the unique contract annotation otherwise. An open record contract
+    /// gives a record type with a `Dyn` tail.
+    ///
+    /// Return `None` if the term isn't a record literal, or if one of its fields can't be
+    /// expressed as a record row: fields without annotation, with several contracts, or fields
+    /// whose name is defined by interpolation. Optional fields give optional rows.
+    pub fn from_record_contract(rt: &RichTerm) -> Option<Type> {
+        let record = match rt.as_ref() {
+            Term::Record(record) => record,
+            Term::RecRecord(record, dyn_fields, _) if dyn_fields.is_empty() => record,
+            _ => return None,
+        };
+
+        let tail = if record.attrs.open {
+            RecordRowsF::TailDyn
+        } else {
+            RecordRowsF::Empty
+        };
+
+        let rrows =
+            record
+                .fields
+                .iter()
+                .rev()
+                .try_fold(RecordRows(tail), |tail, (id, field)| {
+                    let annotation = &field.metadata.annotation;
+
+                    let typ = match (&annotation.typ, annotation.contracts.as_slice()) {
+                        (Some(labeled_ty), _) | (None, [labeled_ty]) => labeled_ty.typ.clone(),
+                        _ => return None,
+                    };
+
+                    Some(RecordRows(RecordRowsF::Extend {
+                        row: RecordRow {
+                            id: *id,
+                            typ: Box::new(typ),
+                            opt: field.metadata.opt,
+                        },
+                        tail: Box::new(tail),
+                    }))
+                })?;
+
+        Some(Type::from(TypeF::Record(rrows)).with_pos(rt.pos))
+    }
+
     /// Returns the same type with the position cleared (set to `None`).
     ///
     /// This is currently only used in test code, but because it's used from integration
@@ -956,6 +1263,17 @@ impl Type {
             ))
         }
 
+        fn optimize_erows(
+            erows: EnumRows,
+            vars_elide: VarsHashSet,
+            polarity: Polarity,
+        ) -> EnumRows {
+            EnumRows(erows.0.map(
+                |typ| Box::new(optimize(*typ, vars_elide.clone(), polarity)),
+                |erows| Box::new(optimize_erows(*erows, vars_elide.clone(), polarity)),
+            ))
+        }
+
         fn optimize(typ: Type, mut vars_elide: VarsHashSet, polarity: Polarity) -> Type {
             let mut pos = typ.pos;
 
@@ -991,6 +1309,7 @@ impl Type {
                 _ if matches!(polarity, Polarity::Positive) => TypeF::Dyn,
                 // Otherwise, we still recurse into non-primitive types
                 TypeF::Record(rrows) => TypeF::Record(optimize_rrows(rrows, vars_elide, polarity)),
+                TypeF::Enum(erows) => TypeF::Enum(optimize_erows(erows, vars_elide, polarity)),
                 TypeF::Dict {
                     type_fields,
                     flavour,
@@ -1084,7 +1403,7 @@ impl Type {
                 s.subcontract(vars.clone(), pol.flip(), sy)?,
                 t.subcontract(vars, pol, sy)?
             ),
-            TypeF::Flat(ref t) => t.clone(),
+            TypeF::Flat(ref t) => erase_type_var_args(t),
             TypeF::Var(id) => get_var_contract(&vars, id, self.pos)?,
             TypeF::Forall {
                 ref var,
@@ -1123,7 +1442,7 @@ impl Type {
                     body.subcontract(vars, pol, sy)?
                 )
             }
-            TypeF::Enum(ref erows) => erows.subcontract()?,
+            TypeF::Enum(ref erows) => erows.subcontract(vars, pol, sy)?,
             TypeF::Record(ref rrows) => rrows.subcontract(vars, pol, sy)?,
             // `{_: Dyn}` and `{_ | Dyn}` are equivalent, and both specialied to the constant-time
             // `dict_dyn`.
@@ -1197,7 +1516,7 @@ impl Traverse<Type> for Type {
         let typ = pre_map.typ.try_map_state(
             |ty, f| Ok(Box::new(ty.traverse(f, order)?)),
             |rrows, f| rrows.traverse(f, order),
-            |erows, _| Ok(erows),
+            |erows, f| erows.traverse(f, order),
             f,
         )?;
 
@@ -1233,7 +1552,6 @@ impl Traverse<Type> for Type {
             | TypeF::String
             | TypeF::Symbol
             | TypeF::Var(_)
-            | TypeF::Enum(_)
             | TypeF::Wildcard(_) => None,
             TypeF::Flat(rt) => rt.traverse_ref(f, state),
             TypeF::Arrow(t1, t2) => t1
@@ -1243,6 +1561,7 @@ impl Traverse<Type> for Type {
             | TypeF::Dict { type_fields: t, .. }
             | TypeF::Array(t) => t.traverse_ref(f, state),
             TypeF::Record(rrows) => rrows.traverse_ref(f, state),
+            TypeF::Enum(erows) => erows.traverse_ref(f, state),
         }
     }
 }
@@ -1,31 +1,32 @@
 use crate::{
-    destructuring::{FieldPattern, Match, RecordPattern},
+    destructuring::{ArrayPattern, Destruct, FieldPattern, Match, Pattern, RecordPattern},
     error::TypecheckError,
     identifier::LocIdent,
-    mk_uty_row,
-    term::{IndexMap, LabeledType},
-    typ::{RecordRowF, RecordRowsF, TypeF},
+    mk_uty_enum, mk_uty_row,
+    position::TermPos,
+    term::{record::Field, IndexMap, Term},
+    typ::{EnumRowF, EnumRowsF, RecordRowF, RecordRowsF, TypeF},
     typecheck::{UnifRecordRow, Unify},
 };
 
 use super::{
-    mk_uniftype, Context, Environment, GenericUnifRecordRowsIteratorItem, State, UnifRecordRows,
-    UnifType, VarLevelsData,
+    error::UnifError, mk_uniftype, Context, Environment, GenericUnifRecordRowsIteratorItem, State,
+    UnifEnumRows, UnifRecordRows, UnifType, VarLevelsData,
 };
 
 pub fn build_pattern_type_walk_mode(
     state: &mut State,
     ctxt: &Context,
-    pat: &RecordPattern,
-) -> Result<UnifRecordRows, TypecheckError> {
+    pat: &Destruct,
+) -> Result<UnifType, TypecheckError> {
     build_pattern_type(state, ctxt, pat, TypecheckMode::Walk)
 }
 
 pub fn build_pattern_type_check_mode(
     state: &mut State,
     ctxt: &Context,
-    pat: &RecordPattern,
-) -> Result<UnifRecordRows, TypecheckError> {
+    pat: &Destruct,
+) -> Result<UnifType, TypecheckError> {
     build_pattern_type(state, ctxt, pat, TypecheckMode::Check)
 }
 
@@ -42,27 +43,71 @@ enum TypecheckMode {
 fn build_pattern_type(
     state: &mut State,
     ctxt: &Context,
-    pat: &RecordPattern,
+    pat: &Destruct,
     mode: TypecheckMode,
-) -> Result<UnifRecordRows, TypecheckError> {
-    fn new_leaf_type(
-        state: &mut State,
-        ctxt: &Context,
-        mode: TypecheckMode,
-        ty_annot: Option<LabeledType>,
-    ) -> UnifType {
-        match mode {
-            TypecheckMode::Walk => mk_uniftype::dynamic(),
-            TypecheckMode::Check => {
-                if let Some(l_ty) = ty_annot {
-                    UnifType::from_type(l_ty.typ, &ctxt.term_env)
-                } else {
-                    state.table.fresh_type_uvar(ctxt.var_level)
+) -> Result<UnifType, TypecheckError> {
+    match pat {
+        Destruct::Record(pat) => Ok(UnifType::concrete(TypeF::Record(
+            build_record_pattern_type(state, ctxt, pat, mode)?,
+        ))),
+        Destruct::Array(pat) => build_array_pattern_type(state, ctxt, pat, mode),
+    }
+}
+
+/// Build the type of the values matched by the pattern `pat` of a record field or of an array
+/// element annotated with the metadata of `field`.
+fn build_field_pattern_type(
+    state: &mut State,
+    ctxt: &Context,
+    field: &Field,
+    pat: &FieldPattern,
+    mode: TypecheckMode,
+) -> Result<UnifType, TypecheckError> {
+    let ty = match pat {
+        FieldPattern::Ident(_) => {
+            let ty_annot = field.metadata.annotation.typ.clone();
+
+            return Ok(match mode {
+                TypecheckMode::Walk => mk_uniftype::dynamic(),
+                TypecheckMode::Check => {
+                    if let Some(l_ty) = ty_annot {
+                        UnifType::from_type(l_ty.typ, &ctxt.term_env)
+                    } else {
+                        state.table.fresh_type_uvar(ctxt.var_level)
+                    }
                 }
-            }
+            });
         }
+        FieldPattern::RecordPattern(r_pat)
+        | FieldPattern::AliasedRecordPattern { pattern: r_pat, .. } => UnifType::concrete(
+            TypeF::Record(build_record_pattern_type(state, ctxt, r_pat, mode)?),
+        ),
+        FieldPattern::ArrayPattern(a_pat)
+        | FieldPattern::AliasedArrayPattern { pattern: a_pat, .. } => {
+            build_array_pattern_type(state, ctxt, a_pat, mode)?
+        }
+    };
+
+    // If there are type annotations within nested patterns then we need to unify them with the
+    // pattern type we've built to ensure (1) that they're mutually compatible and (2) that we
+    // assign the annotated types to the right unification variables.
+    if let Some(annot_ty) = &field.metadata.annotation.typ {
+        let pos = annot_ty.typ.pos;
+        let annot_uty = UnifType::from_type(annot_ty.typ.clone(), &ctxt.term_env);
+        ty.clone()
+            .unify(annot_uty, state, ctxt)
+            .map_err(|e| e.into_typecheck_err(state, pos))?;
     }
 
+    Ok(ty)
+}
+
+fn build_record_pattern_type(
+    state: &mut State,
+    ctxt: &Context,
+    pat: &RecordPattern,
+    mode: TypecheckMode,
+) -> Result<UnifRecordRows, TypecheckError> {
     let tail = if pat.open {
         match mode {
             // We use a dynamic tail here since we're in walk mode,
@@ -78,51 +123,26 @@ fn build_pattern_type(
         }
     };
 
-    let mut rows = pat.matches.iter().map(|m| match m {
-        Match::Simple(id, field) => Ok(RecordRowF {
-            id: *id,
-            typ: Box::new(new_leaf_type(
-                state,
-                ctxt,
-                mode,
-                field.metadata.annotation.typ.clone(),
-            )),
-        }),
-        Match::Assign(id, field, FieldPattern::Ident(_)) => Ok(RecordRowF {
-            id: *id,
-            typ: Box::new(new_leaf_type(
-                state,
-                ctxt,
-                mode,
-                field.metadata.annotation.typ.clone(),
-            )),
-        }),
-        Match::Assign(
-            id,
-            field,
-            FieldPattern::RecordPattern(r_pat)
-            | FieldPattern::AliasedRecordPattern { pattern: r_pat, .. },
-        ) => {
-            let row_tys = build_pattern_type(state, ctxt, r_pat, mode)?;
-            let ty = UnifType::concrete(TypeF::Record(row_tys));
-
-            // If there are type annotations within nested record patterns
-            // then we need to unify them with the pattern type we've built
-            // to ensure (1) that they're mutually compatible and (2) that
-            // we assign the annotated types to the right unification variables.
-            if let Some(annot_ty) = &field.metadata.annotation.typ {
-                let pos = annot_ty.typ.pos;
-                let annot_uty = UnifType::from_type(annot_ty.typ.clone(), &ctxt.term_env);
-                ty.clone()
-                    .unify(annot_uty, state, ctxt)
-                    .map_err(|e| e.into_typecheck_err(state, pos))?;
-            }
+    let mut rows = pat.matches.iter().map(|m| {
+        let (id, field, typ) = match m {
+            Match::Simple(id, field) => (
+                *id,
+                field,
+                build_field_pattern_type(state, ctxt, field, &FieldPattern::Ident(*id), mode)?,
+            ),
+            Match::Assign(id, field, pat) => (
+                *id,
+                field,
+                build_field_pattern_type(state, ctxt, field, pat, mode)?,
+            ),
+        };
 
-            Ok(RecordRowF {
-                id: *id,
-                typ: Box::new(ty),
-            })
-        }
+        // A field with a default value may be absent from the destructured record.
+        Ok(RecordRowF {
+            id,
+            typ: Box::new(typ),
+            opt: field.value.is_some(),
+        })
     });
 
     rows.try_fold(tail, |tail, row: Result<UnifRecordRow, TypecheckError>| {
@@ -133,6 +153,147 @@ fn build_pattern_type(
     })
 }
 
+/// Build the type `Array a` of an array pattern, where `a` is the common type of the elements. In
+/// walk mode, the elements have type `Dyn`.
+fn build_array_pattern_type(
+    state: &mut State,
+    ctxt: &Context,
+    pat: &ArrayPattern,
+    mode: TypecheckMode,
+) -> Result<UnifType, TypecheckError> {
+    let elt_ty = match mode {
+        TypecheckMode::Walk => mk_uniftype::dynamic(),
+        TypecheckMode::Check => state.table.fresh_type_uvar(ctxt.var_level),
+    };
+
+    for elem in &pat.elements {
+        let ty = build_field_pattern_type(state, ctxt, &elem.field, &elem.pattern, mode)?;
+
+        if mode == TypecheckMode::Check {
+            let pos = elem
+                .field
+                .metadata
+                .annotation
+                .typ
+                .as_ref()
+                .map_or(TermPos::Original(pat.span), |annot_ty| annot_ty.typ.pos);
+            elt_ty
+                .clone()
+                .unify(ty, state, ctxt)
+                .map_err(|e| e.into_typecheck_err(state, pos))?;
+        }
+    }
+
+    Ok(mk_uniftype::array(elt_ty))
+}
+
+/// Unify the type `ty` of a matched value with the type of the values matched by the pattern `pat`
+/// of a match expression, and collect the variables bound by `pat` together with their type.
+///
+/// For example, checking the pattern `{ a = [x, ..rest], .. }` against `ty` unifies `ty` with
+/// `{ a : Array b; r }`, where `b` and `r` are fresh unification variables, and binds `x : b` and
+/// `rest : Array b`.
+pub fn check_match_pattern(
+    state: &mut State,
+    ctxt: &Context,
+    pat: &Pattern,
+    ty: UnifType,
+    bindings: &mut Vec<(LocIdent, UnifType)>,
+) -> Result<(), UnifError> {
+    match pat {
+        Pattern::Wildcard => Ok(()),
+        Pattern::Any(id) => {
+            bindings.push((*id, ty));
+            Ok(())
+        }
+        Pattern::Constant(constant) => {
+            let constant_ty = match constant.as_ref() {
+                Term::Num(_) => mk_uniftype::num(),
+                Term::Str(_) => mk_uniftype::str(),
+                Term::Bool(_) => mk_uniftype::bool(),
+                _ => mk_uniftype::dynamic(),
+            };
+
+            ty.unify(constant_ty, state, ctxt)
+        }
+        Pattern::Enum(tag, None) => {
+            let tail = state.table.fresh_erows_uvar(ctxt.var_level);
+            ty.unify(mk_uty_enum!(*tag; tail), state, ctxt)
+        }
+        Pattern::Enum(tag, Some(arg)) => {
+            let tail = state.table.fresh_erows_uvar(ctxt.var_level);
+            let arg_ty = state.table.fresh_type_uvar(ctxt.var_level);
+
+            let erows = UnifEnumRows::concrete(EnumRowsF::Extend {
+                row: EnumRowF {
+                    id: *tag,
+                    typ: Some(Box::new(arg_ty.clone())),
+                },
+                tail: Box::new(tail),
+            });
+
+            ty.unify(mk_uty_enum!(; erows), state, ctxt)?;
+            check_match_pattern(state, ctxt, arg, arg_ty, bindings)
+        }
+        Pattern::Record { fields, open, rest } => {
+            let tail = if *open {
+                state.table.fresh_rrows_uvar(ctxt.var_level)
+            } else {
+                mk_uty_row!()
+            };
+
+            let field_types: Vec<_> = fields
+                .iter()
+                .map(|_| state.table.fresh_type_uvar(ctxt.var_level))
+                .collect();
+
+            let rows = fields.iter().zip(field_types.iter()).rev().fold(
+                tail.clone(),
+                |tail, ((id, _), field_ty)| {
+                    UnifRecordRows::concrete(RecordRowsF::Extend {
+                        row: RecordRowF {
+                            id: *id,
+                            typ: Box::new(field_ty.clone()),
+                            opt: false,
+                        },
+                        tail: Box::new(tail),
+                    })
+                },
+            );
+
+            ty.unify(UnifType::concrete(TypeF::Record(rows)), state, ctxt)?;
+
+            for ((_, pat), field_ty) in fields.iter().zip(field_types) {
+                check_match_pattern(state, ctxt, pat, field_ty, bindings)?;
+            }
+
+            if let Some(rest) = rest {
+                bindings.push((*rest, UnifType::concrete(TypeF::Record(tail))));
+            }
+
+            Ok(())
+        }
+        Pattern::Array { elements, rest, .. } => {
+            let elt_ty = state.table.fresh_type_uvar(ctxt.var_level);
+            ty.unify(mk_uniftype::array(elt_ty.clone()), state, ctxt)?;
+
+            for pat in elements {
+                check_match_pattern(state, ctxt, pat, elt_ty.clone(), bindings)?;
+            }
+
+            if let Some(rest) = rest {
+                bindings.push((*rest, mk_uniftype::array(elt_ty)));
+            }
+
+            Ok(())
+        }
+        Pattern::Alias(id, pat) => {
+            bindings.push((*id, ty.clone()));
+            check_match_pattern(state, ctxt, pat, ty, bindings)
+        }
+    }
+}
+
 /// Extend `env` with any new bindings brought into scope in `pat`. The
 /// types of these bindings will be inferred from `pat_ty`.
 ///
@@ -140,66 +301,87 @@ fn build_pattern_type(
 /// `pat_ty` is `{ a : Num, b : Str }` then the `env` will be extended
 /// with `a : Num` and `rest : { b : Str }`.
 pub fn inject_pattern_variables(
+    state: &State,
+    env: &mut Environment,
+    pat: &Destruct,
+    pat_ty: UnifType,
+) {
+    match pat {
+        Destruct::Record(pat) => inject_record_pattern_variables(state, env, pat, pat_ty),
+        Destruct::Array(pat) => inject_array_pattern_variables(state, env, pat, pat_ty),
+    }
+}
+
+/// Extend `env` with the bindings brought into scope by the pattern of a record field or of an
+/// array element, where `ty` is the type of the matched value.
+fn inject_field_pattern_variables(
+    state: &State,
+    env: &mut Environment,
+    pat: &FieldPattern,
+    ty: UnifType,
+) {
+    match pat {
+        FieldPattern::Ident(bind_id) => {
+            env.insert(bind_id.ident(), ty);
+        }
+        // Since we don't have an alias in this branch, we can infer that the matched value is
+        // an intermediate value that isn't accessible from the code. e.g. the `foo` in a binding
+        // like:
+        //
+        // ```
+        // let { foo = { bar = baz } } = { foo.bar = 1 } in ...
+        // ```
+        //
+        // As such, we don't need to add it to the environment.
+        FieldPattern::RecordPattern(pattern) => {
+            inject_record_pattern_variables(state, env, pattern, ty)
+        }
+        FieldPattern::ArrayPattern(pattern) => {
+            inject_array_pattern_variables(state, env, pattern, ty)
+        }
+        FieldPattern::AliasedRecordPattern { alias, pattern } => {
+            env.insert(alias.ident(), ty.clone());
+            inject_record_pattern_variables(state, env, pattern, ty)
+        }
+        FieldPattern::AliasedArrayPattern { alias, pattern } => {
+            env.insert(alias.ident(), ty.clone());
+            inject_array_pattern_variables(state, env, pattern, ty)
+        }
+    }
+}
+
+fn inject_record_pattern_variables(
     state: &State,
     env: &mut Environment,
     pat: &RecordPattern,
-    pat_ty: UnifRecordRows,
+    pat_ty: UnifType,
 ) {
-    let pat_ty = pat_ty.into_root(state.table);
-    let mut type_map = RecordTypes::from(&pat_ty);
+    // The type of the pattern is built by `build_pattern_type`, which always returns a record
+    // type for record patterns, except in walk mode for patterns nested inside an array pattern,
+    // where the elements have type `Dyn`.
+    let UnifType::Concrete {
+        typ: TypeF::Record(rows),
+        ..
+    } = pat_ty.into_root(state.table)
+    else {
+        for id in pat.bound_idents() {
+            env.insert(id.ident(), mk_uniftype::dynamic());
+        }
+
+        return;
+    };
+
+    let rows = rows.into_root(state.table);
+    let mut type_map = RecordTypes::from(&rows);
 
     pat.matches.iter().for_each(|m| match m {
         Match::Simple(id, ..) => {
             let ty = type_map.get_type(id);
             env.insert(id.ident(), ty);
         }
-        Match::Assign(id, _, FieldPattern::Ident(bind_id)) => {
-            let ty = type_map.get_type(id);
-            env.insert(bind_id.ident(), ty);
-        }
-        Match::Assign(id, _, FieldPattern::RecordPattern(pat)) => {
-            let ty = type_map.get_type(id);
-
-            // Since we don't have a `bind_id` in this branch,
-            // we can infer that `id` is an intermediate value that
-            // isn't accessible from the code. e.g. the `foo` in a
-            // binding like:
-            //
-            // ```
-            // let { foo = { bar = baz } } = { foo.bar = 1 } in ...
-            // ```
-            //
-            // As such, we don't need to add it to the environment.
-            let UnifType::Concrete {
-                typ: TypeF::Record(rs),
-                ..
-            } = ty
-            else {
-                unreachable!(
-                    "since this is a destructured record, \
-                              its type was constructed by build_pattern_ty, \
-                              which means it must be a concrete record type"
-                )
-            };
-            inject_pattern_variables(state, env, pat, rs)
-        }
-        Match::Assign(id, _, FieldPattern::AliasedRecordPattern { alias, pattern }) => {
+        Match::Assign(id, _, pat) => {
             let ty = type_map.get_type(id);
-
-            env.insert(alias.ident(), ty.clone());
-
-            let UnifType::Concrete {
-                typ: TypeF::Record(rs),
-                ..
-            } = ty
-            else {
-                unreachable!(
-                    "since this is a destructured record, \
-                              its type was constructed by build_pattern_ty, \
-                              which means it must be a concrete record type"
-                )
-            };
-            inject_pattern_variables(state, env, pattern, rs)
+            inject_field_pattern_variables(state, env, pat, ty);
         }
     });
 
@@ -209,6 +391,29 @@ pub fn inject_pattern_variables(
     }
 }
 
+fn inject_array_pattern_variables(
+    state: &State,
+    env: &mut Environment,
+    pat: &ArrayPattern,
+    pat_ty: UnifType,
+) {
+    let elt_ty = match pat_ty.into_root(state.table) {
+        UnifType::Concrete {
+            typ: TypeF::Array(elt_ty),
+            ..
+        } => *elt_ty,
+        _ => mk_uniftype::dynamic(),
+    };
+
+    for elem in &pat.elements {
+        inject_field_pattern_variables(state, env, &elem.pattern, elt_ty.clone());
+    }
+
+    if let Some(id) = pat.rest {
+        env.insert(id.ident(), mk_uniftype::array(elt_ty));
+    }
+}
+
 /// A map of identifiers in a destructured record to their types.
 ///
 /// This allows us to be resilient to ordering differences between the
@@ -268,6 +473,7 @@ impl RecordTypes {
         let rows = known_types.iter().map(|(id, ty)| RecordRowF {
             id: *id,
             typ: Box::new(ty.clone()),
+            opt: false,
         });
         let rrows = rows.fold(tail, |tail, row| {
             UnifRecordRows::concrete(RecordRowsF::Extend {
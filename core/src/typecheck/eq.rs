@@ -52,6 +52,7 @@ use crate::{
     },
     identifier::LocIdent,
     term::{self, record::Field, IndexMap, UnaryOp},
+    typ::TypeAlias,
 };
 
 use std::fmt::Debug;
@@ -82,16 +83,46 @@ pub trait TermEnvironment: Clone {
     fn get_idx_then<F, T>(env: &Self, idx: &CacheIndex, f: F) -> T
     where
         F: FnOnce(Option<(&RichTerm, &Self)>) -> T;
+
+    /// Retrieve the type alias bound to `id`, together with the environment of its definition.
+    /// Type aliases are only expanded at typechecking time: by default, there are none.
+    fn get_type_alias(_env: &Self, _id: Ident) -> Option<(TypeAlias, Self)> {
+        None
+    }
 }
 
 /// A simple term environment, as a mapping from identifiers to a tuple of a term and an
 /// environment (i.e. a closure), sufficient for the needs of typechecking.
+///
+/// The second component maps the names of the type aliases in scope to their definition. A
+/// binding which shadows a type alias is mapped to `None`.
 #[derive(PartialEq, Clone, Debug)]
-pub struct SimpleTermEnvironment(pub GenericEnvironment<Ident, (RichTerm, SimpleTermEnvironment)>);
+pub struct SimpleTermEnvironment(
+    pub GenericEnvironment<Ident, (RichTerm, SimpleTermEnvironment)>,
+    pub GenericEnvironment<Ident, Option<(TypeAlias, SimpleTermEnvironment)>>,
+);
 
 impl SimpleTermEnvironment {
     pub fn new() -> Self {
-        SimpleTermEnvironment(GenericEnvironment::new())
+        SimpleTermEnvironment(GenericEnvironment::new(), GenericEnvironment::new())
+    }
+
+    /// Bind `id` to the term `rt`, closurized with the current environment. This shadows any type
+    /// alias of the same name.
+    pub fn insert(&mut self, id: Ident, rt: RichTerm) {
+        let env = self.clone();
+        self.0.insert(id, (rt, env));
+
+        if let Some(Some(_)) = self.1.get(&id) {
+            self.1.insert(id, None);
+        }
+    }
+
+    /// Bind `id` to the type alias `alias`, whose runtime representation is `rt`.
+    pub fn insert_type_alias(&mut self, id: Ident, rt: RichTerm, alias: TypeAlias) {
+        let env = self.clone();
+        self.0.insert(id, (rt, env.clone()));
+        self.1.insert(id, Some((alias, env)));
     }
 }
 
@@ -123,6 +154,10 @@ impl TermEnvironment for SimpleTermEnvironment {
     fn fast_eq(this: &Self, that: &Self) -> bool {
         GenericEnvironment::ptr_eq(&this.0, &that.0)
     }
+
+    fn get_type_alias(env: &Self, id: Ident) -> Option<(TypeAlias, Self)> {
+        env.1.get(&id).cloned().flatten()
+    }
 }
 
 impl std::iter::FromIterator<(Ident, (RichTerm, SimpleTermEnvironment))> for SimpleTermEnvironment {
@@ -132,6 +167,7 @@ impl std::iter::FromIterator<(Ident, (RichTerm, SimpleTermEnvironment))> for Sim
     {
         SimpleTermEnvironment(
             GenericEnvironment::<Ident, (RichTerm, SimpleTermEnvironment)>::from_iter(iter),
+            GenericEnvironment::new(),
         )
     }
 }
@@ -176,7 +212,7 @@ impl<C: Cache> FromEnv<C> for SimpleTermEnvironment {
                 (*id, borrowed)
             })
             .collect();
-        SimpleTermEnvironment(generic_env)
+        SimpleTermEnvironment(generic_env, GenericEnvironment::new())
     }
 }
 
@@ -282,6 +318,18 @@ fn contract_eq_bounded<E: TermEnvironment>(
         (Num(n1), Num(n2)) => n1 == n2,
         (Str(s1), Str(s2)) => s1 == s2,
         (Enum(id1), Enum(id2)) => id1 == id2,
+        (
+            EnumVariant {
+                tag: tag1,
+                arg: arg1,
+                ..
+            },
+            EnumVariant {
+                tag: tag2,
+                arg: arg2,
+                ..
+            },
+        ) => tag1 == tag2 && contract_eq_bounded(state, arg1, env1, arg2, env2),
         (SealingKey(s1), SealingKey(s2)) => s1 == s2,
         (Sealed(key1, inner1, _), Sealed(key2, inner2, _)) => {
             key1 == key2 && contract_eq_bounded(state, inner1, env1, inner2, env2)
@@ -479,19 +527,21 @@ where
         })
 }
 
-/// Convert record rows to a hashmap.
+/// Convert record rows to a hashmap from fields to their optionality and their type.
 ///
 /// Require the rows to be closed (i.e. the last element must be `RowEmpty`), otherwise `None` is
 /// returned. `None` is returned as well if a type encountered is not row, or if it is a enum row.
 fn rows_as_map<E: TermEnvironment>(
     erows: &GenericUnifRecordRows<E>,
-) -> Option<IndexMap<LocIdent, &GenericUnifType<E>>> {
+) -> Option<IndexMap<LocIdent, (bool, &GenericUnifType<E>)>> {
     let map: Option<IndexMap<LocIdent, _>> = erows
         .iter()
         .map(|item| match item {
-            GenericUnifRecordRowsIteratorItem::Row(RecordRowF { id, typ: types }) => {
-                Some((id, types))
-            }
+            GenericUnifRecordRowsIteratorItem::Row(RecordRowF {
+                id,
+                typ: types,
+                opt,
+            }) => Some((id, (opt, types))),
             _ => None,
         })
         .collect();
@@ -499,21 +549,23 @@ fn rows_as_map<E: TermEnvironment>(
     map
 }
 
-/// Convert enum rows to a hashset.
+/// Convert enum rows to a hashmap from tags to the type of their payload, if any.
 ///
 /// Require the rows to be closed (i.e. the last element must be `RowEmpty`), otherwise `None` is
 /// returned. `None` is returned as well if a type encountered is not row type, or if it is a
 /// record row.
-fn rows_as_set(erows: &UnifEnumRows) -> Option<HashSet<LocIdent>> {
-    let set: Option<HashSet<_>> = erows
+fn erows_as_map<E: TermEnvironment>(
+    erows: &GenericUnifEnumRows<E>,
+) -> Option<IndexMap<LocIdent, Option<&GenericUnifType<E>>>> {
+    let map: Option<IndexMap<LocIdent, _>> = erows
         .iter()
         .map(|item| match item {
-            UnifEnumRowsIteratorItem::Row(id) => Some(*id),
+            GenericUnifEnumRowsIteratorItem::Row(EnumRowF { id, typ }) => Some((id, typ)),
             _ => None,
         })
         .collect();
 
-    set
+    map
 }
 
 /// Check for contract equality between record fields. Fields are equal if they are both without a
@@ -578,19 +630,38 @@ fn type_eq_bounded<E: TermEnvironment>(
                         && type_eq_bounded(state, t1, env1, t2, env2)
                 }
                 (TypeF::Enum(uty1), TypeF::Enum(uty2)) => {
-                    let rows1 = rows_as_set(uty1);
-                    let rows2 = rows_as_set(uty2);
-                    rows1.is_some() && rows2.is_some() && rows1 == rows2
+                    fn payload_eq<E: TermEnvironment>(
+                        state: &mut State,
+                        uty1: &Option<&GenericUnifType<E>>,
+                        env1: &E,
+                        uty2: &Option<&GenericUnifType<E>>,
+                        env2: &E,
+                    ) -> bool {
+                        match (uty1, uty2) {
+                            (Some(uty1), Some(uty2)) => {
+                                type_eq_bounded(state, *uty1, env1, *uty2, env2)
+                            }
+                            (None, None) => true,
+                            _ => false,
+                        }
+                    }
+
+                    let map1 = erows_as_map(uty1);
+                    let map2 = erows_as_map(uty2);
+
+                    map1.zip(map2)
+                        .map(|(m1, m2)| map_eq(payload_eq, state, &m1, env1, &m2, env2))
+                        .unwrap_or(false)
                 }
                 (TypeF::Record(uty1), TypeF::Record(uty2)) => {
                     fn type_eq_bounded_wrapper<E: TermEnvironment>(
                         state: &mut State,
-                        uty1: &&GenericUnifType<E>,
+                        (opt1, uty1): &(bool, &GenericUnifType<E>),
                         env1: &E,
-                        uty2: &&GenericUnifType<E>,
+                        (opt2, uty2): &(bool, &GenericUnifType<E>),
                         env2: &E,
                     ) -> bool {
-                        type_eq_bounded(state, *uty1, env1, *uty2, env2)
+                        opt1 == opt2 && type_eq_bounded(state, *uty1, env1, *uty2, env2)
                     }
 
                     let map1 = rows_as_map(uty1);
@@ -636,8 +707,8 @@ fn type_eq_bounded<E: TermEnvironment>(
                             body2.subst(var2, &GenericUnifRecordRows::Constant(cst_id)),
                         ),
                         VarKind::EnumRows => (
-                            body1.subst(var1, &UnifEnumRows::Constant(cst_id)),
-                            body2.subst(var2, &UnifEnumRows::Constant(cst_id)),
+                            body1.subst(var1, &GenericUnifEnumRows::Constant(cst_id)),
+                            body2.subst(var2, &GenericUnifEnumRows::Constant(cst_id)),
                         ),
                     };
 
@@ -22,6 +22,10 @@ pub enum RowUnifError {
     ExtraDynTail(),
     /// There were two incompatible definitions for the same row.
     RowMismatch(LocIdent, Box<UnifError>),
+    /// An enum row was a bare tag on one side but carried a payload on the other side.
+    PayloadMismatch(LocIdent),
+    /// A record row was optional on one side but not on the other side.
+    OptionalityMismatch(LocIdent),
     /// A [row constraint][super::RowConstr] was violated.
     UnsatConstr(LocIdent, UnifType),
     /// Tried to unify a type constant with another different type.
@@ -55,6 +59,9 @@ impl RowUnifError {
             RowUnifError::ExtraRow(id) => UnifError::ExtraRow(id, left, right),
             RowUnifError::ExtraDynTail() => UnifError::ExtraDynTail(left, right),
             RowUnifError::RowMismatch(id, err) => UnifError::RowMismatch(id, left, right, err),
+            RowUnifError::PayloadMismatch(_) | RowUnifError::OptionalityMismatch(_) => {
+                UnifError::TypeMismatch(left, right)
+            }
             RowUnifError::UnsatConstr(id, uty) => UnifError::RowConflict(id, uty, left, right),
             RowUnifError::WithConst(c, k, uty) => UnifError::WithConst(c, k, uty),
             RowUnifError::ConstMismatch(k, c1, c2) => UnifError::ConstMismatch(k, c1, c2),
@@ -19,7 +19,8 @@ macro_rules! mk_uty_arrow {
 }
 
 /// Multi-ary enum row constructor for types implementing `Into<TypeWrapper>`.
-/// `mk_uty_enum_row!(id1, .., idn; tail)` correspond to `<id1, .., idn | tail>.
+/// `mk_uty_enum_row!(id1, .., idn; tail)` correspond to `<id1, .., idn | tail>. The rows built
+/// by this macro are bare tags, without payload.
 #[macro_export]
 macro_rules! mk_uty_enum_row {
     () => {
@@ -34,7 +35,10 @@ macro_rules! mk_uty_enum_row {
     ( $id:expr $(, $ids:expr )* $(; $tail:expr)?) => {
         $crate::typecheck::UnifEnumRows::concrete(
             $crate::typ::EnumRowsF::Extend {
-                row: LocIdent::from($id),
+                row: $crate::typ::EnumRowF {
+                    id: LocIdent::from($id),
+                    typ: None,
+                },
                 tail: Box::new($crate::mk_uty_enum_row!($( $ids ),* $(; $tail)?))
             }
         )
@@ -61,6 +65,7 @@ macro_rules! mk_uty_row {
                 row: $crate::typ::RecordRowF {
                     id: LocIdent::from($id),
                     typ: Box::new($ty.into()),
+                    opt: false,
                 },
                 tail: Box::new($crate::mk_uty_row!($(($ids, $tys)),* $(; $tail)?)),
             }
@@ -64,8 +64,8 @@ use crate::{
         TypeAnnotation,
     },
     typ::{
-        EnumRow, EnumRows, EnumRowsF, EnumRowsIterator, RecordRowF, RecordRows, RecordRowsF,
-        RecordRowsIterator, Type, TypeF, VarKind, VarKindDiscriminant,
+        EnumRowF, EnumRows, EnumRowsF, EnumRowsIterator, RecordRowF, RecordRows, RecordRowsF,
+        RecordRowsIterator, Type, TypeAlias, TypeF, VarKind, VarKindDiscriminant,
     },
     {mk_uty_arrow, mk_uty_enum, mk_uty_enum_row, mk_uty_record, mk_uty_row},
 };
@@ -129,17 +129,17 @@ pub enum GenericUnifRecordRows<E: TermEnvironment + Clone> {
     },
 }
 
-pub type UnifEnumRowsUnrolling = EnumRowsF<Box<UnifEnumRows>>;
+/// A unifiable enum row.
+pub type GenericUnifEnumRow<E> = EnumRowF<Box<GenericUnifType<E>>>;
+pub type GenericUnifEnumRowsUnrolling<E> =
+    EnumRowsF<Box<GenericUnifType<E>>, Box<GenericUnifEnumRows<E>>>;
 
-/// Unifiable enum rows. Same shape as [`crate::typ::EnumRows`] but where each tail may be a
-/// unification variable (or a constant).
-///
-/// Enum rows don't store any type (they are just a sequence of identifiers), so there is no
-/// `GenericUnifEnumRows` taking an additional `E` parameter.
+/// Unifiable enum rows. Same shape as [`crate::typ::EnumRows`] but where each type is unifiable,
+/// and each tail may be a unification variable (or a constant).
 #[derive(Clone, PartialEq, Debug)]
-pub enum UnifEnumRows {
+pub enum GenericUnifEnumRows<E: TermEnvironment + Clone> {
     Concrete {
-        erows: UnifEnumRowsUnrolling,
+        erows: GenericUnifEnumRowsUnrolling<E>,
         /// Additional metadata related to unification variable levels update. See [VarLevelsData].
         var_levels_data: VarLevelsData,
     },
@@ -244,24 +244,32 @@ impl<E: TermEnvironment> VarLevelUpperBound for GenericUnifTypeUnrolling<E> {
     }
 }
 
-impl VarLevelUpperBound for UnifEnumRows {
+impl<E: TermEnvironment> VarLevelUpperBound for GenericUnifEnumRows<E> {
     fn var_level_upper_bound(&self) -> VarLevel {
         match self {
-            UnifEnumRows::Concrete {
+            GenericUnifEnumRows::Concrete {
                 var_levels_data, ..
             } => var_levels_data.upper_bound,
-            UnifEnumRows::UnifVar { init_level, .. } => *init_level,
-            UnifEnumRows::Constant(_) => VarLevel::NO_VAR,
+            GenericUnifEnumRows::UnifVar { init_level, .. } => *init_level,
+            GenericUnifEnumRows::Constant(_) => VarLevel::NO_VAR,
         }
     }
 }
 
-impl VarLevelUpperBound for UnifEnumRowsUnrolling {
+impl<E: TermEnvironment> VarLevelUpperBound for GenericUnifEnumRowsUnrolling<E> {
     fn var_level_upper_bound(&self) -> VarLevel {
         match self {
             // A var that hasn't be instantiated yet isn't a unification variable
             EnumRowsF::Empty | EnumRowsF::TailVar(_) => VarLevel::NO_VAR,
-            EnumRowsF::Extend { row: _, tail } => tail.var_level_upper_bound(),
+            EnumRowsF::Extend {
+                row: EnumRowF { id: _, typ },
+                tail,
+            } => max(
+                tail.var_level_upper_bound(),
+                typ.as_ref()
+                    .map(|ty| ty.var_level_upper_bound())
+                    .unwrap_or(VarLevel::NO_VAR),
+            ),
         }
     }
 }
@@ -284,7 +292,7 @@ impl<E: TermEnvironment> VarLevelUpperBound for GenericUnifRecordRowsUnrolling<E
             // A var that hasn't be instantiated yet isn't a unification variable
             RecordRowsF::Empty | RecordRowsF::TailVar(_) | RecordRowsF::TailDyn => VarLevel::NO_VAR,
             RecordRowsF::Extend {
-                row: RecordRowF { id: _, typ },
+                row: RecordRowF { typ, .. },
                 tail,
             } => max(tail.var_level_upper_bound(), typ.var_level_upper_bound()),
         }
@@ -351,7 +359,7 @@ pub enum GenericUnifType<E: TermEnvironment> {
 }
 
 type GenericUnifTypeUnrolling<E> =
-    TypeF<Box<GenericUnifType<E>>, GenericUnifRecordRows<E>, UnifEnumRows>;
+    TypeF<Box<GenericUnifType<E>>, GenericUnifRecordRows<E>, GenericUnifEnumRows<E>>;
 
 impl<E: TermEnvironment> GenericUnifType<E> {
     /// Create a concrete generic unification type. Compute the variable levels data from the
@@ -399,14 +407,32 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<RecordRows> for GenericUn
     }
 }
 
-impl std::convert::TryInto<EnumRows> for UnifEnumRows {
+impl<E: TermEnvironment> GenericUnifEnumRows<E> {
+    /// Create concrete generic enum rows. Compute the variable levels data from the
+    /// subcomponents.
+    pub fn concrete(typ: GenericUnifEnumRowsUnrolling<E>) -> Self {
+        let upper_bound = typ.var_level_upper_bound();
+
+        GenericUnifEnumRows::Concrete {
+            erows: typ,
+            var_levels_data: VarLevelsData::new_from_bound(upper_bound),
+        }
+    }
+}
+
+impl<E: TermEnvironment + Clone> std::convert::TryInto<EnumRows> for GenericUnifEnumRows<E> {
     type Error = ();
 
     fn try_into(self) -> Result<EnumRows, ()> {
         match self {
-            UnifEnumRows::Concrete { erows, .. } => {
-                let converted: EnumRowsF<Box<EnumRows>> =
-                    erows.try_map(|erows| Ok(Box::new(UnifEnumRows::try_into(*erows)?)))?;
+            GenericUnifEnumRows::Concrete { erows, .. } => {
+                let converted: EnumRowsF<Box<Type>, Box<EnumRows>> = erows.try_map(
+                    |uty| Ok(Box::new(GenericUnifType::try_into(*uty)?)),
+                    |uerows| {
+                        let erows: EnumRows = (*uerows).try_into()?;
+                        Ok(Box::new(erows))
+                    },
+                )?;
                 Ok(EnumRows(converted))
             }
             _ => Err(()),
@@ -426,7 +452,7 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<Type> for GenericUnifType
                         Ok(Box::new(ty))
                     },
                     GenericUnifRecordRows::try_into,
-                    UnifEnumRows::try_into,
+                    GenericUnifEnumRows::try_into,
                 )?;
                 Ok(Type::from(converted))
             }
@@ -442,31 +468,25 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<Type> for GenericUnifType
     }
 }
 
-// As opposed to `UnifType` and `UnifRecordRows` which can contain types and thus contracts, with
-// all the subtleties associated with contract equality checking (see `typecheck::eq` module), we
-// can convert enum rows directly to unifiable enum rows without additional data: instead of
-// implementing a function `from_enum_rows`, we rather implement the more natural trait
-// `From<EnumRows>`.
-impl From<EnumRows> for UnifEnumRows {
-    fn from(erows: EnumRows) -> Self {
-        UnifEnumRows::concrete(erows.0.map(|erows| Box::new(UnifEnumRows::from(*erows))))
+impl<E: TermEnvironment + Clone> GenericUnifEnumRows<E> {
+    /// Create `GenericUnifEnumRows` from `EnumRows`. Contracts are represented as the separate
+    /// variant [`GenericUnifType::Contract`] which also stores a term environment, required for
+    /// checking type equality involving contracts.
+    pub fn from_enum_rows(erows: EnumRows, env: &E) -> Self {
+        let f_erow = |ty: Box<Type>| Box::new(GenericUnifType::from_type(*ty, env));
+        let f_erows =
+            |erows: Box<EnumRows>| Box::new(GenericUnifEnumRows::from_enum_rows(*erows, env));
+
+        GenericUnifEnumRows::concrete(erows.0.map(f_erow, f_erows))
     }
 }
 
-impl UnifEnumRows {
+impl<E: TermEnvironment> GenericUnifEnumRows<E> {
     /// Return an iterator producing immutable references to individual rows.
-    pub fn iter(&self) -> EnumRowsIterator<UnifEnumRows> {
-        EnumRowsIterator { erows: Some(self) }
-    }
-
-    /// Create concrete generic unification enum rows. Compute the variable levels data from the
-    /// subcomponents.
-    pub fn concrete(erows: UnifEnumRowsUnrolling) -> Self {
-        let upper_bound = erows.var_level_upper_bound();
-
-        UnifEnumRows::Concrete {
-            erows,
-            var_levels_data: VarLevelsData::new_from_bound(upper_bound),
+    pub(super) fn iter(&self) -> EnumRowsIterator<GenericUnifType<E>, GenericUnifEnumRows<E>> {
+        EnumRowsIterator {
+            erows: Some(self),
+            ty: std::marker::PhantomData,
         }
     }
 }
@@ -534,7 +554,11 @@ impl<E: TermEnvironment> Subst<GenericUnifType<E>> for GenericUnifType<E> {
                             *upper_bound = max(*upper_bound, new_ub);
                             new_rrows
                         },
-                        |erows, _| erows,
+                        |erows, upper_bound| {
+                            let (new_erows, new_ub) = erows.subst_levels(id, to);
+                            *upper_bound = max(*upper_bound, new_ub);
+                            new_erows
+                        },
                         &mut upper_bound,
                     ),
                     var_levels_data: VarLevelsData {
@@ -614,7 +638,11 @@ impl<E: TermEnvironment> Subst<GenericUnifRecordRows<E>> for GenericUnifType<E>
                         *upper_bound = max(*upper_bound, new_ub);
                         new_rrows
                     },
-                    |erows, _| erows,
+                    |erows, upper_bound| {
+                        let (new_erows, new_ub) = erows.subst_levels(id, to);
+                        *upper_bound = max(*upper_bound, new_ub);
+                        new_erows
+                    },
                     &mut upper_bound,
                 );
 
@@ -681,8 +709,8 @@ impl<E: TermEnvironment> Subst<GenericUnifRecordRows<E>> for GenericUnifRecordRo
     }
 }
 
-impl<E: TermEnvironment> Subst<UnifEnumRows> for GenericUnifType<E> {
-    fn subst_levels(self, id: &LocIdent, to: &UnifEnumRows) -> (Self, VarLevel) {
+impl<E: TermEnvironment> Subst<GenericUnifEnumRows<E>> for GenericUnifType<E> {
+    fn subst_levels(self, id: &LocIdent, to: &GenericUnifEnumRows<E>) -> (Self, VarLevel) {
         match self {
             GenericUnifType::Concrete {
                 typ,
@@ -727,8 +755,8 @@ impl<E: TermEnvironment> Subst<UnifEnumRows> for GenericUnifType<E> {
     }
 }
 
-impl<E: TermEnvironment> Subst<UnifEnumRows> for GenericUnifRecordRows<E> {
-    fn subst_levels(self, id: &LocIdent, to: &UnifEnumRows) -> (Self, VarLevel) {
+impl<E: TermEnvironment> Subst<GenericUnifEnumRows<E>> for GenericUnifRecordRows<E> {
+    fn subst_levels(self, id: &LocIdent, to: &GenericUnifEnumRows<E>) -> (Self, VarLevel) {
         match self {
             GenericUnifRecordRows::Concrete {
                 rrows,
@@ -769,23 +797,78 @@ impl<E: TermEnvironment> Subst<UnifEnumRows> for GenericUnifRecordRows<E> {
     }
 }
 
-impl Subst<UnifEnumRows> for UnifEnumRows {
-    fn subst_levels(self, id: &LocIdent, to: &UnifEnumRows) -> (Self, VarLevel) {
+/// Implement substitution of type variables and record rows variables for enum rows, which only
+/// substitutes inside the types of the rows.
+macro_rules! impl_subst_for_erows {
+    ($to:ty) => {
+        impl<E: TermEnvironment> Subst<$to> for GenericUnifEnumRows<E> {
+            fn subst_levels(self, id: &LocIdent, to: &$to) -> (Self, VarLevel) {
+                match self {
+                    GenericUnifEnumRows::Concrete {
+                        erows,
+                        var_levels_data,
+                    } => {
+                        let mut upper_bound = VarLevel::NO_VAR;
+
+                        let new_erows = erows.map_state(
+                            |ty, upper_bound| {
+                                let (new_ty, new_ub) = ty.subst_levels(id, to);
+                                *upper_bound = max(*upper_bound, new_ub);
+                                Box::new(new_ty)
+                            },
+                            |erows, upper_bound| {
+                                let (new_erows, new_ub) = erows.subst_levels(id, to);
+                                *upper_bound = max(*upper_bound, new_ub);
+                                Box::new(new_erows)
+                            },
+                            &mut upper_bound,
+                        );
+
+                        let new_uerows = GenericUnifEnumRows::Concrete {
+                            erows: new_erows,
+                            var_levels_data: VarLevelsData {
+                                upper_bound,
+                                ..var_levels_data
+                            },
+                        };
+
+                        (new_uerows, upper_bound)
+                    }
+                    _ => {
+                        let upper_bound = self.var_level_upper_bound();
+                        (self, upper_bound)
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_subst_for_erows!(GenericUnifType<E>);
+impl_subst_for_erows!(GenericUnifRecordRows<E>);
+
+impl<E: TermEnvironment> Subst<GenericUnifEnumRows<E>> for GenericUnifEnumRows<E> {
+    fn subst_levels(self, id: &LocIdent, to: &GenericUnifEnumRows<E>) -> (Self, VarLevel) {
         match self {
-            UnifEnumRows::Concrete {
+            GenericUnifEnumRows::Concrete {
                 erows: EnumRowsF::TailVar(var_id),
                 var_levels_data,
             } if var_id == *id => {
                 debug_assert!(var_levels_data.upper_bound == VarLevel::NO_VAR);
                 (to.clone(), to.var_level_upper_bound())
             }
-            UnifEnumRows::Concrete {
+            GenericUnifEnumRows::Concrete {
                 erows,
                 var_levels_data,
             } => {
                 let mut upper_bound = VarLevel::NO_VAR;
 
                 let new_erows = erows.map_state(
+                    |ty, upper_bound| {
+                        let (new_ty, new_ub) = ty.subst_levels(id, to);
+                        *upper_bound = max(*upper_bound, new_ub);
+                        Box::new(new_ty)
+                    },
                     |erows, upper_bound| {
                         let (new_erows, new_ub) = erows.subst_levels(id, to);
                         *upper_bound = max(*upper_bound, new_ub);
@@ -794,7 +877,7 @@ impl Subst<UnifEnumRows> for UnifEnumRows {
                     &mut upper_bound,
                 );
 
-                let new_uerows = UnifEnumRows::Concrete {
+                let new_uerows = GenericUnifEnumRows::Concrete {
                     erows: new_erows,
                     var_levels_data: VarLevelsData {
                         upper_bound,
@@ -816,24 +899,75 @@ impl Subst<UnifEnumRows> for UnifEnumRows {
 impl<E: TermEnvironment + Clone> GenericUnifType<E> {
     /// Create a [`GenericUnifType`] from a [`Type`]. Contracts are represented as the separate
     /// variant [`GenericUnifType::Contract`] which also stores a term environment, required for
-    /// checking type equality involving contracts.
+    /// checking type equality involving contracts. Applications of type aliases and record
+    /// contract literals (see [`Type::from_record_contract`]) are expanded instead.
     pub fn from_type(ty: Type, env: &E) -> Self {
         match ty.typ {
-            TypeF::Flat(t) => GenericUnifType::Contract(t, env.clone()),
+            TypeF::Flat(t) => GenericUnifType::from_type_alias(&t, env)
+                .or_else(|| {
+                    Type::from_record_contract(&t).map(|ty| GenericUnifType::from_type(ty, env))
+                })
+                .unwrap_or_else(|| GenericUnifType::Contract(t, env.clone())),
             ty => GenericUnifType::concrete(ty.map(
                 |ty_| Box::new(GenericUnifType::from_type(*ty_, env)),
                 |rrows| GenericUnifRecordRows::from_record_rows(rrows, env),
-                UnifEnumRows::from,
+                |erows| GenericUnifEnumRows::from_enum_rows(erows, env),
             )),
         }
     }
 }
 
+impl<E: TermEnvironment> GenericUnifType<E> {
+    /// Expand the contract `t` if it's a type alias applied to as many types as it has parameters,
+    /// such as `Pair Number` when `Pair` has been declared by `let type Pair a = ...`. The
+    /// definition of the alias is converted in the environment where it's declared, while the
+    /// arguments are converted in the current environment `env`.
+    fn from_type_alias(t: &RichTerm, env: &E) -> Option<Self> {
+        let mut args = Vec::new();
+        let mut head = t;
+
+        while let Term::App(fun, arg) = head.as_ref() {
+            args.push(arg);
+            head = fun;
+        }
+
+        let Term::Var(id) = head.as_ref() else {
+            return None;
+        };
+
+        let (alias, def_env) = E::get_type_alias(env, id.ident())?;
+
+        if alias.params.len() != args.len() {
+            return None;
+        }
+
+        let body = GenericUnifType::from_type(alias.body, &def_env);
+
+        Some(
+            alias
+                .params
+                .iter()
+                .zip(args.into_iter().rev())
+                .fold(body, |body, (param, arg)| {
+                    let arg_ty = match arg.as_ref() {
+                        Term::Type(ty) => ty.clone(),
+                        _ => Type::from(TypeF::Flat(arg.clone())).with_pos(arg.pos),
+                    };
+
+                    body.subst(param, &GenericUnifType::from_type(arg_ty, env))
+                }),
+        )
+    }
+}
+
 type UnifTypeUnrolling = GenericUnifTypeUnrolling<SimpleTermEnvironment>;
 type UnifRecordRowsUnrolling = GenericUnifRecordRowsUnrolling<SimpleTermEnvironment>;
+type UnifEnumRowsUnrolling = GenericUnifEnumRowsUnrolling<SimpleTermEnvironment>;
 
 pub type UnifRecordRow = GenericUnifRecordRow<SimpleTermEnvironment>;
 pub type UnifRecordRows = GenericUnifRecordRows<SimpleTermEnvironment>;
+pub type UnifEnumRow = GenericUnifEnumRow<SimpleTermEnvironment>;
+pub type UnifEnumRows = GenericUnifEnumRows<SimpleTermEnvironment>;
 pub type UnifType = GenericUnifType<SimpleTermEnvironment>;
 
 impl UnifRecordRows {
@@ -877,7 +1011,10 @@ impl UnifEnumRows {
             },
             UnifEnumRows::Constant(_) => EnumRows(EnumRowsF::Empty),
             UnifEnumRows::Concrete { erows, .. } => {
-                let mapped = erows.map(|erows| Box::new(erows.into_erows(table)));
+                let mapped = erows.map(
+                    |ty| Box::new(ty.into_type(table)),
+                    |erows| Box::new(erows.into_erows(table)),
+                );
                 EnumRows(mapped)
             }
         }
@@ -979,8 +1116,8 @@ impl From<RecordRowsF<Box<UnifType>, Box<UnifRecordRows>>> for UnifRecordRows {
     }
 }
 
-impl From<EnumRowsF<Box<UnifEnumRows>>> for UnifEnumRows {
-    fn from(erows: EnumRowsF<Box<UnifEnumRows>>) -> Self {
+impl From<EnumRowsF<Box<UnifType>, Box<UnifEnumRows>>> for UnifEnumRows {
+    fn from(erows: EnumRowsF<Box<UnifType>, Box<UnifEnumRows>>) -> Self {
         UnifEnumRows::concrete(erows)
     }
 }
@@ -1022,6 +1159,7 @@ impl<'a, E: TermEnvironment> Iterator
                     Some(GenericUnifRecordRowsIteratorItem::Row(RecordRowF {
                         id: row.id,
                         typ: row.typ.as_ref(),
+                        opt: row.opt,
                     }))
                 }
             },
@@ -1040,43 +1178,50 @@ impl<'a, E: TermEnvironment> Iterator
     }
 }
 
-/// Iterator items produced by [`EnumRowsIterator`].
-pub enum UnifEnumRowsIteratorItem<'a> {
+/// Iterator items produced by [EnumRowsIterator] on [GenericUnifEnumRows].
+pub enum GenericUnifEnumRowsIteratorItem<'a, E: TermEnvironment> {
     TailVar(&'a LocIdent),
     TailUnifVar { id: VarId, init_level: VarLevel },
     TailConstant(VarId),
-    Row(&'a EnumRow),
+    Row(EnumRowF<&'a GenericUnifType<E>>),
 }
 
-impl<'a> Iterator for EnumRowsIterator<'a, UnifEnumRows> {
-    type Item = UnifEnumRowsIteratorItem<'a>;
+pub type UnifEnumRowsIteratorItem<'a> = GenericUnifEnumRowsIteratorItem<'a, SimpleTermEnvironment>;
+
+impl<'a, E: TermEnvironment> Iterator
+    for EnumRowsIterator<'a, GenericUnifType<E>, GenericUnifEnumRows<E>>
+{
+    type Item = GenericUnifEnumRowsIteratorItem<'a, E>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.erows.and_then(|next| match next {
-            UnifEnumRows::Concrete { erows, .. } => match erows {
+            GenericUnifEnumRows::Concrete { erows, .. } => match erows {
                 EnumRowsF::Empty => {
                     self.erows = None;
                     None
                 }
                 EnumRowsF::TailVar(id) => {
                     self.erows = None;
-                    Some(UnifEnumRowsIteratorItem::TailVar(id))
+                    Some(GenericUnifEnumRowsIteratorItem::TailVar(id))
                 }
                 EnumRowsF::Extend { row, tail } => {
                     self.erows = Some(tail);
-                    Some(UnifEnumRowsIteratorItem::Row(row))
+                    Some(GenericUnifEnumRowsIteratorItem::Row(EnumRowF {
+                        id: row.id,
+                        typ: row.typ.as_ref().map(AsRef::as_ref),
+                    }))
                 }
             },
-            UnifEnumRows::UnifVar { id, init_level } => {
+            GenericUnifEnumRows::UnifVar { id, init_level } => {
                 self.erows = None;
-                Some(UnifEnumRowsIteratorItem::TailUnifVar {
+                Some(GenericUnifEnumRowsIteratorItem::TailUnifVar {
                     id: *id,
                     init_level: *init_level,
                 })
             }
-            UnifEnumRows::Constant(var_id) => {
+            GenericUnifEnumRows::Constant(var_id) => {
                 self.erows = None;
-                Some(UnifEnumRowsIteratorItem::TailConstant(*var_id))
+                Some(GenericUnifEnumRowsIteratorItem::TailConstant(*var_id))
             }
         })
     }
@@ -1367,6 +1512,10 @@ fn walk<V: TypecheckVisitor>(
             .try_for_each(|t| -> Result<(), TypecheckError> {
                 walk(state, ctxt.clone(), visitor, t)
             }),
+        Term::Let(x, re, rt, attrs) if attrs.type_alias => {
+            bind_type_alias(state, &mut ctxt, visitor, x, re)?;
+            walk(state, ctxt, visitor, rt)
+        }
         Term::Let(x, re, rt, attrs) => {
             let ty_let = binding_type(state, re.as_ref(), &ctxt, false);
 
@@ -1377,7 +1526,7 @@ fn walk<V: TypecheckVisitor>(
             // allocate all the term environments inside an arena, local to each statically typed
             // block, and use bare references to represent cycles. Then everything would be cleaned
             // at the end of the block.
-            ctxt.term_env.0.insert(x.ident(), (re.clone(), ctxt.term_env.clone()));
+            ctxt.term_env.insert(x.ident(), re.clone());
 
             if attrs.rec {
                 ctxt.type_env.insert(x.ident(), ty_let.clone());
@@ -1402,9 +1551,11 @@ fn walk<V: TypecheckVisitor>(
             }
 
             let pattern_ty = destructuring::build_pattern_type_walk_mode(state, &ctxt, pat)?;
-            for item in pattern_ty.iter() {
-                if let GenericUnifRecordRowsIteratorItem::Row(row) = item {
-                    visitor.visit_ident(&row.id, row.typ.clone());
+            if let UnifType::Concrete { typ: TypeF::Record(rows), .. } = &pattern_ty {
+                for item in rows.iter() {
+                    if let GenericUnifRecordRowsIteratorItem::Row(row) = item {
+                        visitor.visit_ident(&row.id, row.typ.clone());
+                    }
                 }
             }
             destructuring::inject_pattern_variables(state, &mut ctxt.type_env, pat, pattern_ty);
@@ -1420,6 +1571,22 @@ fn walk<V: TypecheckVisitor>(
                 walk(state, ctxt.clone(), visitor, case)
             })
         }
+        Term::MatchPattern(branches) => {
+            branches.iter().try_for_each(|branch| {
+                let mut ctxt = ctxt.clone();
+
+                for id in branch.pattern.bound_idents() {
+                    visitor.visit_ident(&id, mk_uniftype::dynamic());
+                    ctxt.type_env.insert(id.ident(), mk_uniftype::dynamic());
+                }
+
+                if let Some(guard) = &branch.guard {
+                    walk(state, ctxt.clone(), visitor, guard)?;
+                }
+
+                walk(state, ctxt, visitor, &branch.body)
+            })
+        }
         Term::RecRecord(record, dynamic, ..) => {
             for (id, field) in record.fields.iter() {
                 let field_type = field_type(
@@ -1475,12 +1642,38 @@ fn walk<V: TypecheckVisitor>(
         Term::Annotated(annot, rt) => {
             walk_annotated(state, ctxt, visitor, annot, rt)
         }
-        Term::Sealed(_, t, _) => walk(state, ctxt, visitor, t),
+        Term::Sealed(_, t, _) | Term::EnumVariant { arg: t, .. } => {
+            walk(state, ctxt, visitor, t)
+        }
         Term::Type(ty) => walk_type(state, ctxt, visitor, ty),
         Term::Closure(_) => unreachable!("should never see a closure at typechecking time"),
    }
 }
 
+/// Bring the type alias `id` declared by `let type` in scope, where `value` is the runtime
+/// representation of the alias (see [TypeAlias]). The alias is expanded by the typechecker when
+/// converting types. As a term, the alias is a contract, and has type `Dyn`.
+fn bind_type_alias<V: TypecheckVisitor>(
+    state: &mut State,
+    ctxt: &mut Context,
+    visitor: &mut V,
+    id: &LocIdent,
+    value: &RichTerm,
+) -> Result<(), TypecheckError> {
+    visitor.visit_ident(id, mk_uniftype::dynamic());
+    walk(state, ctxt.clone(), visitor, value)?;
+
+    match TypeAlias::from_term(value) {
+        Some(alias) => ctxt
+            .term_env
+            .insert_type_alias(id.ident(), value.clone(), alias),
+        None => ctxt.term_env.insert(id.ident(), value.clone()),
+    }
+
+    ctxt.type_env.insert(id.ident(), mk_uniftype::dynamic());
+    Ok(())
+}
+
 /// Same as [`walk`] but operate on a type, which can contain terms as contracts (`TypeF::Flat`),
 /// instead of a term.
 fn walk_type<V: TypecheckVisitor>(
@@ -1506,13 +1699,43 @@ fn walk_type<V: TypecheckVisitor>(
            walk_type(state, ctxt, visitor, ty2.as_ref())
        }
        TypeF::Record(rrows) => walk_rrows(state, ctxt, visitor, rrows),
-       TypeF::Flat(t) => walk(state, ctxt, visitor, t),
+       TypeF::Flat(t) => {
+           check_type_alias_arity(&ctxt, t)?;
+           walk(state, ctxt, visitor, t)
+       }
        TypeF::Dict { type_fields: ty2, .. }
        | TypeF::Array(ty2)
        | TypeF::Forall {body: ty2, ..} => walk_type(state, ctxt, visitor, ty2),
     }
 }
 
+/// Check that the contract `t`, if it's an application of a type alias (or the alias alone), has
+/// as many arguments as the alias has parameters. Otherwise, the alias would be silently
+/// partially applied, which fails at runtime far from the cause (see [TypeAlias]).
+fn check_type_alias_arity(ctxt: &Context, t: &RichTerm) -> Result<(), TypecheckError> {
+    let mut found = 0;
+    let mut head = t;
+
+    while let Term::App(fun, _) = head.as_ref() {
+        found += 1;
+        head = fun;
+    }
+
+    let Term::Var(id) = head.as_ref() else {
+        return Ok(());
+    };
+
+    match SimpleTermEnvironment::get_type_alias(&ctxt.term_env, id.ident()) {
+        Some((alias, _)) if alias.params.len() != found => Err(TypecheckError::TypeAliasArity {
+            alias: *id,
+            expected: alias.params.len(),
+            found,
+            pos: t.pos,
+        }),
+        _ => Ok(()),
+    }
+}
+
 /// Same as [`walk_type`] but operate on record rows.
 fn walk_rrows<V: TypecheckVisitor>(
     state: &mut State,
@@ -1705,17 +1928,16 @@ fn check<V: TypecheckVisitor>(
             check(state, ctxt, visitor, t, trg)
         }
         Term::FunPattern(x, pat, t) => {
-            let src_rows_ty = destructuring::build_pattern_type_check_mode(state, &ctxt, pat)?;
-            let src = UnifType::concrete(TypeF::Record(src_rows_ty.clone()));
+            let src = destructuring::build_pattern_type_check_mode(state, &ctxt, pat)?;
             let trg = state.table.fresh_type_uvar(ctxt.var_level);
             let arr = mk_uty_arrow!(src.clone(), trg.clone());
 
             if let Some(x) = x {
                 visitor.visit_ident(x, src.clone());
-                ctxt.type_env.insert(x.ident(), src);
+                ctxt.type_env.insert(x.ident(), src.clone());
             }
 
-            destructuring::inject_pattern_variables(state, &mut ctxt.type_env, pat, src_rows_ty);
+            destructuring::inject_pattern_variables(state, &mut ctxt.type_env, pat, src);
             ty.unify(arr, state, &ctxt)
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
             check(state, ctxt, visitor, t, trg)
@@ -1737,14 +1959,16 @@ fn check<V: TypecheckVisitor>(
             ty.unify(mk_uniftype::dynamic(), state, &ctxt)
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::Let(x, re, rt, attrs) if attrs.type_alias => {
+            bind_type_alias(state, &mut ctxt, visitor, x, re)?;
+            check(state, ctxt, visitor, rt, ty)
+        }
         Term::Let(x, re, rt, attrs) => {
             let ty_let = binding_type(state, re.as_ref(), &ctxt, true);
 
             // We don't support recursive binding when checking for contract equality. See the
             // `Let` case in `walk`.
-            ctxt.term_env
-                .0
-                .insert(x.ident(), (re.clone(), ctxt.term_env.clone()));
+            ctxt.term_env.insert(x.ident(), re.clone());
 
             if attrs.rec {
                 ctxt.type_env.insert(x.ident(), ty_let.clone());
@@ -1760,15 +1984,13 @@ fn check<V: TypecheckVisitor>(
         }
         Term::LetPattern(x, pat, re, rt) => {
             // The inferred type of the pattern w/ unification vars
-            let pattern_rows_type =
-                destructuring::build_pattern_type_check_mode(state, &ctxt, pat)?;
-            let pattern_type = UnifType::concrete(TypeF::Record(pattern_rows_type.clone()));
+            let pattern_type = destructuring::build_pattern_type_check_mode(state, &ctxt, pat)?;
             // The inferred type of the expr being bound
             let ty_let = binding_type(state, re.as_ref(), &ctxt, true);
 
             ty_let
                 .clone()
-                .unify(pattern_type, state, &ctxt)
+                .unify(pattern_type.clone(), state, &ctxt)
                 .map_err(|e| e.into_typecheck_err(state, re.pos))?;
 
             check(state, ctxt.clone(), visitor, re, ty_let.clone())?;
@@ -1778,18 +2000,19 @@ fn check<V: TypecheckVisitor>(
                 ctxt.type_env.insert(x.ident(), ty_let);
             }
 
-            for item in pattern_rows_type.iter() {
-                if let GenericUnifRecordRowsIteratorItem::Row(row) = item {
-                    visitor.visit_ident(&row.id, row.typ.clone());
+            if let UnifType::Concrete {
+                typ: TypeF::Record(rows),
+                ..
+            } = &pattern_type
+            {
+                for item in rows.iter() {
+                    if let GenericUnifRecordRowsIteratorItem::Row(row) = item {
+                        visitor.visit_ident(&row.id, row.typ.clone());
+                    }
                 }
             }
 
-            destructuring::inject_pattern_variables(
-                state,
-                &mut ctxt.type_env,
-                pat,
-                pattern_rows_type,
-            );
+            destructuring::inject_pattern_variables(state, &mut ctxt.type_env, pat, pattern_type);
 
             check(state, ctxt, visitor, rt, ty)
         }
@@ -1832,6 +2055,74 @@ fn check<V: TypecheckVisitor>(
                 .unify(mk_uty_enum!(; erows), state, &ctxt)
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::MatchPattern(branches) => {
+            let arg_type = state.table.fresh_type_uvar(ctxt.var_level);
+            let return_type = state.table.fresh_type_uvar(ctxt.var_level);
+
+            ty.unify(
+                mk_uty_arrow!(arg_type.clone(), return_type.clone()),
+                state,
+                &ctxt,
+            )
+            .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+
+            for branch in branches {
+                let mut bindings = Vec::new();
+                destructuring::check_match_pattern(
+                    state,
+                    &ctxt,
+                    &branch.pattern,
+                    arg_type.clone(),
+                    &mut bindings,
+                )
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+
+                let mut ctxt = ctxt.clone();
+                for (id, ty) in bindings {
+                    visitor.visit_ident(&id, ty.clone());
+                    ctxt.type_env.insert(id.ident(), ty);
+                }
+
+                if let Some(guard) = &branch.guard {
+                    check(state, ctxt.clone(), visitor, guard, mk_uniftype::bool())?;
+                }
+
+                check(state, ctxt, visitor, &branch.body, return_type.clone())?;
+            }
+
+            // If the branches only match enum tags or enum variants, without a catch-all branch,
+            // the match expression is only defined on these tags: the enum type of the argument
+            // is closed. A tag is only covered by a branch without a guard, since a guarded branch
+            // may not be taken even if the tag matches.
+            if branches
+                .iter()
+                .all(|branch| branch.pattern.as_enum_tag().is_some())
+            {
+                let tags: IndexMap<_, _> = branches
+                    .iter()
+                    .filter(|branch| branch.guard.is_none())
+                    .filter_map(|branch| branch.pattern.as_enum_tag())
+                    .collect();
+
+                let mut erows = mk_uty_enum_row!();
+
+                for (id, is_variant) in tags.into_iter().rev() {
+                    let typ =
+                        is_variant.then(|| Box::new(state.table.fresh_type_uvar(ctxt.var_level)));
+
+                    erows = UnifEnumRows::concrete(EnumRowsF::Extend {
+                        row: EnumRowF { id, typ },
+                        tail: Box::new(erows),
+                    });
+                }
+
+                arg_type
+                    .unify(mk_uty_enum!(; erows), state, &ctxt)
+                    .map_err(|err| err.into_typecheck_err(state, rt.pos))
+            } else {
+                Ok(())
+            }
+        }
         // Elimination forms (variable, function application and primitive operator application)
         // follow the inference discipline, following the Pfennig recipe and the current type
         // system specification (as far as typechecking is concerned, primitive operator
@@ -1853,6 +2144,23 @@ fn check<V: TypecheckVisitor>(
             ty.unify(mk_uty_enum!(*id; row), state, &ctxt)
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::EnumVariant { tag, arg, .. } => {
+            let tail = state.table.fresh_erows_uvar(ctxt.var_level);
+            let ty_arg = state.table.fresh_type_uvar(ctxt.var_level);
+
+            let erows = UnifEnumRows::concrete(EnumRowsF::Extend {
+                row: EnumRowF {
+                    id: *tag,
+                    typ: Some(Box::new(ty_arg.clone())),
+                },
+                tail: Box::new(tail),
+            });
+
+            ty.unify(mk_uty_enum!(; erows), state, &ctxt)
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+
+            check(state, ctxt, visitor, arg, ty_arg)
+        }
         // If some fields are defined dynamically, the only potential type that works is `{_ : a}`
         // for some `a`. In other words, the checking rule is not the same depending on the target
         // type: if the target type is a dictionary type, we simply check each field against the
@@ -1910,17 +2218,42 @@ fn check<V: TypecheckVisitor>(
                         check_field(state, ctxt.clone(), visitor, *id, field, (*rec_ty).clone())
                     })
             } else {
-                // Building the type {id1 : ?a1, id2: ?a2, .., idn: ?an}
+                // A field defined by the record can stand for an optional row of the expected type
+                let expected_optional: HashSet<Ident> = match &root_ty {
+                    UnifType::Concrete {
+                        typ: TypeF::Record(rows),
+                        ..
+                    } => rows
+                        .iter()
+                        .filter_map(|item| match item {
+                            GenericUnifRecordRowsIteratorItem::Row(row) if row.opt => {
+                                Some(row.id.ident())
+                            }
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => HashSet::new(),
+                };
+
+                // Building the type {id1 : ?a1, id2: ?a2, .., idn: ?an}, where the rows of
+                // optional fields are optional
                 let mut field_types: IndexMap<LocIdent, UnifType> = record
                     .fields
                     .keys()
                     .map(|id| (*id, state.table.fresh_type_uvar(ctxt.var_level)))
                     .collect();
 
-                let rows = field_types.iter().fold(
-                    mk_uty_row!(),
-                    |acc, (id, row_ty)| mk_uty_row!((*id, row_ty.clone()); acc),
-                );
+                let rows = field_types.iter().fold(mk_uty_row!(), |acc, (id, row_ty)| {
+                    UnifRecordRows::concrete(RecordRowsF::Extend {
+                        row: UnifRecordRow {
+                            id: *id,
+                            typ: Box::new(row_ty.clone()),
+                            opt: record.fields[id].metadata.opt
+                                || expected_optional.contains(&id.ident()),
+                        },
+                        tail: Box::new(acc),
+                    })
+                });
 
                 ty.unify(mk_uty_record!(; rows), state, &ctxt)
                     .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
@@ -2325,16 +2658,32 @@ fn replace_wildcards_with_var(
         ))
     }
 
+    fn replace_erows(
+        table: &mut UnifTable,
+        ctxt: &Context,
+        wildcard_vars: &mut Vec<UnifType>,
+        erows: EnumRows,
+    ) -> UnifEnumRows {
+        UnifEnumRows::concrete(erows.0.map_state(
+            |ty, (table, wildcard_vars)| {
+                Box::new(replace_wildcards_with_var(table, ctxt, wildcard_vars, *ty))
+            },
+            |erows, (table, wildcard_vars)| {
+                Box::new(replace_erows(table, ctxt, wildcard_vars, *erows))
+            },
+            &mut (table, wildcard_vars),
+        ))
+    }
+
     match ty.typ {
         TypeF::Wildcard(i) => get_wildcard_var(table, ctxt.var_level, wildcard_vars, i),
-        TypeF::Flat(t) => UnifType::Contract(t, ctxt.term_env.clone()),
+        TypeF::Flat(_) => UnifType::from_type(ty, &ctxt.term_env),
         _ => UnifType::concrete(ty.typ.map_state(
             |ty, (table, wildcard_vars)| {
                 Box::new(replace_wildcards_with_var(table, ctxt, wildcard_vars, *ty))
             },
             |rrows, (table, wildcard_vars)| replace_rrows(table, ctxt, wildcard_vars, rrows),
-            // Enum rows contain neither wildcards nor contracts
-            |erows, _| UnifEnumRows::from(erows),
+            |erows, (table, wildcard_vars)| replace_erows(table, ctxt, wildcard_vars, erows),
             &mut (table, wildcard_vars),
         )),
     }
@@ -2511,6 +2860,7 @@ pub fn infer_record_type(
                         row: UnifRecordRow {
                             id: *id,
                             typ: Box::new(uty),
+                            opt: field.metadata.opt,
                         },
                         tail: Box::new(r.into()),
                     }
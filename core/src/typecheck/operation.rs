@@ -6,7 +6,7 @@ use crate::{
     term::{BinaryOp, NAryOp, RecordExtKind, UnaryOp},
     typ::TypeF,
 };
-use crate::{mk_uty_arrow, mk_uty_enum, mk_uty_record};
+use crate::{mk_uty_arrow, mk_uty_enum, mk_uty_enum_row, mk_uty_record};
 
 /// Type of unary operations.
 pub fn get_uop_type(
@@ -60,6 +60,11 @@ pub fn get_uop_type(
         }
         // This should not happen, as a match primop is only produced during evaluation.
         UnaryOp::Match { .. } => panic!("cannot typecheck match primop"),
+        // forall a b. a -> b
+        UnaryOp::MatchFailure() => (
+            state.table.fresh_type_uvar(var_level),
+            state.table.fresh_type_uvar(var_level),
+        ),
         // Morally, Label -> Label
         // Dyn -> Dyn
         UnaryOp::ChangePolarity()
@@ -146,6 +151,10 @@ pub fn get_uop_type(
         UnaryOp::StrUppercase() => (mk_uniftype::str(), mk_uniftype::str()),
         // Str -> Str
         UnaryOp::StrLowercase() => (mk_uniftype::str(), mk_uniftype::str()),
+        // Str -> Str
+        UnaryOp::StrToSnakeCase() | UnaryOp::StrToKebabCase() | UnaryOp::StrToCamelCase() => {
+            (mk_uniftype::str(), mk_uniftype::str())
+        }
         // Str -> Num
         UnaryOp::StrLength() => (mk_uniftype::str(), mk_uniftype::num()),
         // Dyn -> Str
@@ -157,6 +166,12 @@ pub fn get_uop_type(
             mk_uniftype::str(),
             mk_uty_enum!(; state.table.fresh_erows_const(var_level)),
         ),
+        // Dyn -> Dyn
+        UnaryOp::EnumGetTag() | UnaryOp::EnumGetArg() => {
+            (mk_uniftype::dynamic(), mk_uniftype::dynamic())
+        }
+        // Dyn -> Bool
+        UnaryOp::EnumIsVariant() => (mk_uniftype::dynamic(), mk_uniftype::bool()),
         // Str -> Str -> Bool
         UnaryOp::StrIsMatch() => (
             mk_uniftype::str(),
@@ -174,6 +189,11 @@ pub fn get_uop_type(
                 )
             ),
         ),
+        // Str -> Str -> Array {matched: Str, index: Num, groups: Array Str, named_groups: {_: Str}}
+        UnaryOp::StrFindAll() => (
+            mk_uniftype::str(),
+            mk_uty_arrow!(mk_uniftype::str(), mk_uniftype::array(mk_uty_regex_match())),
+        ),
         // Str -> Bool
         UnaryOp::StrIsMatchCompiled(_) => (mk_uniftype::str(), mk_uniftype::bool()),
         // Str -> {matched: Str, index: Num, groups: Array Str}
@@ -185,6 +205,10 @@ pub fn get_uop_type(
                 ("groups", mk_uniftype::array(TypeF::String))
             ),
         ),
+        // Str -> Array {matched: Str, index: Num, groups: Array Str, named_groups: {_: Str}}
+        UnaryOp::StrFindAllCompiled(_) => {
+            (mk_uniftype::str(), mk_uniftype::array(mk_uty_regex_match()))
+        }
         // Dyn -> Dyn
         UnaryOp::Force { .. } => (mk_uniftype::dynamic(), mk_uniftype::dynamic()),
         // forall a. a -> a
@@ -339,6 +363,46 @@ pub fn get_bop_type(
             mk_uniftype::str(),
             mk_uniftype::str(),
         ),
+        // <Base64, Base64Url, Hex, Percent> -> Str -> Str
+        BinaryOp::Encode() | BinaryOp::Decode() => (
+            mk_uty_enum!("Base64", "Base64Url", "Hex", "Percent"),
+            mk_uniftype::str(),
+            mk_uniftype::str(),
+        ),
+        // <Json, Shell> -> Str -> Str
+        BinaryOp::Quote() => (
+            mk_uty_enum!("Json", "Shell"),
+            mk_uniftype::str(),
+            mk_uniftype::str(),
+        ),
+        // <Ipv4, Ipv6, Cidr> -> Str -> [| 'Ok, 'Error Str |]
+        BinaryOp::NetValidate() => (
+            mk_uty_enum!("Ipv4", "Ipv6", "Cidr"),
+            mk_uniftype::str(),
+            mk_uty_validation(),
+        ),
+        // Str -> Str -> Bool
+        BinaryOp::CidrContains() => (mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::bool()),
+        // Num -> Str -> Array Str
+        BinaryOp::CidrSubnets() => (
+            mk_uniftype::num(),
+            mk_uniftype::str(),
+            mk_uniftype::array(TypeF::String),
+        ),
+        // <Version, Requirement> -> Str -> [| 'Ok, 'Error Str |]
+        BinaryOp::SemverValidate() => (
+            mk_uty_enum!("Version", "Requirement"),
+            mk_uniftype::str(),
+            mk_uty_validation(),
+        ),
+        // Str -> Str -> <Lesser, Equal, Greater>
+        BinaryOp::SemverCompare() => (
+            mk_uniftype::str(),
+            mk_uniftype::str(),
+            mk_uty_enum!("Lesser", "Equal", "Greater"),
+        ),
+        // Str -> Str -> Bool
+        BinaryOp::SemverMatches() => (mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::bool()),
         // forall a. <Json, Yaml, Toml> -> a -> Str
         BinaryOp::Serialize() => {
             let ty_input = state.table.fresh_type_uvar(var_level);
@@ -356,8 +420,50 @@ pub fn get_bop_type(
         ),
         // Num -> Num -> Num
         BinaryOp::Pow() => (mk_uniftype::num(), mk_uniftype::num(), mk_uniftype::num()),
+        // <Sqrt, Exp, Ln, Log2, Log10, Sin, Cos, Tan, Asin, Acos, Atan> -> Num -> Num
+        BinaryOp::NumMath() => (
+            mk_uty_enum!(
+                "Sqrt", "Exp", "Ln", "Log2", "Log10", "Sin", "Cos", "Tan", "Asin", "Acos", "Atan"
+            ),
+            mk_uniftype::num(),
+            mk_uniftype::num(),
+        ),
+        // <Floor, Ceil, TowardZero, AwayFromZero, HalfToEven, HalfAwayFromZero> -> Num -> Num
+        BinaryOp::NumRound() => (
+            mk_uty_enum!(
+                "Floor",
+                "Ceil",
+                "TowardZero",
+                "AwayFromZero",
+                "HalfToEven",
+                "HalfAwayFromZero"
+            ),
+            mk_uniftype::num(),
+            mk_uniftype::num(),
+        ),
+        // Num -> Num -> Num
+        BinaryOp::NumAtan2()
+        | BinaryOp::NumGcd()
+        | BinaryOp::NumBitAnd()
+        | BinaryOp::NumBitOr()
+        | BinaryOp::NumBitXor()
+        | BinaryOp::NumBitShift() => (mk_uniftype::num(), mk_uniftype::num(), mk_uniftype::num()),
+        // Num -> Num -> Str
+        BinaryOp::NumToDecimalStr() => (mk_uniftype::num(), mk_uniftype::num(), mk_uniftype::str()),
         // Str -> Str -> Bool
         BinaryOp::StrContains() => (mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::bool()),
+        // Str -> Str -> Bool
+        BinaryOp::StrStartsWith() | BinaryOp::StrEndsWith() => {
+            (mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::bool())
+        }
+        // Str -> Num -> Str
+        BinaryOp::StrIndent() => (mk_uniftype::str(), mk_uniftype::num(), mk_uniftype::str()),
+        // Str -> { _ : Dyn } -> Str
+        BinaryOp::StrFormat() => (
+            mk_uniftype::str(),
+            mk_uniftype::dict(mk_uniftype::dynamic()),
+            mk_uniftype::str(),
+        ),
         // Str -> Str -> Array Str
         BinaryOp::StrSplit() => (
             mk_uniftype::str(),
@@ -407,6 +513,13 @@ pub fn get_bop_type(
             mk_uniftype::dynamic(),
             mk_uniftype::dynamic(),
         ),
+        // Morally: <| 'UnionLeft, 'UnionRight, 'IntersectionLeft, 'IntersectionRight |> -> Lbl -> Lbl
+        // Actual: Dyn -> Dyn -> Dyn
+        BinaryOp::LabelGoBranch() => (
+            mk_uniftype::dynamic(),
+            mk_uniftype::dynamic(),
+            mk_uniftype::dynamic(),
+        ),
         // Morally: Sym -> Lbl -> TypeVarData
         // Actual: Sym -> Dyn -> TypeVarData
         BinaryOp::LookupTypeVar() => (
@@ -424,15 +537,31 @@ pub fn get_nop_type(
 ) -> Result<(Vec<UnifType>, UnifType), TypecheckError> {
     Ok(match op {
         // Str -> Str -> Str -> Str
-        NAryOp::StrReplace() | NAryOp::StrReplaceRegex() => (
+        NAryOp::StrReplace() | NAryOp::StrReplaceRegex() | NAryOp::StrReplaceRegexCaptures() => (
             vec![mk_uniftype::str(), mk_uniftype::str(), mk_uniftype::str()],
             mk_uniftype::str(),
         ),
+        // Str -> Str
+        //   -> ({matched: Str, index: Num, groups: Array Str, named_groups: {_: Str}} -> Str)
+        //   -> Str
+        NAryOp::StrReplaceRegexWith() => (
+            vec![
+                mk_uniftype::str(),
+                mk_uniftype::str(),
+                mk_uty_arrow!(mk_uty_regex_match(), mk_uniftype::str()),
+            ],
+            mk_uniftype::str(),
+        ),
         // Str -> Num -> Num -> Str
         NAryOp::StrSubstr() => (
             vec![mk_uniftype::str(), mk_uniftype::num(), mk_uniftype::num()],
             mk_uniftype::str(),
         ),
+        // Str -> Num -> Str -> Str
+        NAryOp::StrPadLeft() | NAryOp::StrPadRight() => (
+            vec![mk_uniftype::str(), mk_uniftype::num(), mk_uniftype::str()],
+            mk_uniftype::str(),
+        ),
         // Dyn -> Dyn -> Dyn -> Dyn -> Dyn
         NAryOp::RecordSealTail() => (
             vec![
@@ -465,6 +594,16 @@ pub fn get_nop_type(
                 mk_uniftype::array(element_type),
             )
         }
+        // Morally: Contract -> Lbl -> Dyn -> <| 'Ok Dyn, 'Error String |>
+        // Actual: Dyn -> Dyn -> Dyn -> Dyn
+        NAryOp::ContractCheck() => (
+            vec![
+                mk_uniftype::dynamic(),
+                mk_uniftype::dynamic(),
+                mk_uniftype::dynamic(),
+            ],
+            mk_uniftype::dynamic(),
+        ),
         // This should not happen, as MergeContract() is only produced during evaluation.
         NAryOp::MergeContract() => panic!("cannot typecheck MergeContract()"),
         // Morally: Sym -> Polarity -> Lbl -> Lbl
@@ -479,3 +618,34 @@ pub fn get_nop_type(
         ),
     })
 }
+
+/// The type of a regex match, as returned by [`UnaryOp::StrFindAll`]:
+/// `{matched: Str, index: Num, groups: Array Str, named_groups: {_: Str}}`.
+fn mk_uty_regex_match() -> UnifType {
+    mk_uty_record!(
+        ("matched", TypeF::String),
+        ("index", TypeF::Number),
+        ("groups", mk_uniftype::array(TypeF::String)),
+        ("named_groups", mk_uniftype::dict(TypeF::String))
+    )
+}
+
+/// The type of the result of a validation primop, such as [`BinaryOp::NetValidate`]:
+/// `[| 'Ok, 'Error Str |]`.
+fn mk_uty_validation() -> UnifType {
+    let error_row = UnifEnumRows::concrete(EnumRowsF::Extend {
+        row: EnumRowF {
+            id: LocIdent::from("Error"),
+            typ: Some(Box::new(mk_uniftype::str())),
+        },
+        tail: Box::new(mk_uty_enum_row!()),
+    });
+
+    UnifType::concrete(TypeF::Enum(UnifEnumRows::concrete(EnumRowsF::Extend {
+        row: EnumRowF {
+            id: LocIdent::from("Ok"),
+            typ: None,
+        },
+        tail: Box::new(error_row),
+    })))
+}
@@ -426,7 +426,7 @@ impl UnifTable {
             uty.map_state(
                 |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
                 |rrows, table| update_rrows_with_lvl(table, rrows, level),
-                |erows, _table| erows,
+                |erows, table| update_erows_with_lvl(table, erows, level),
                 table,
             )
         }
@@ -462,6 +462,34 @@ impl UnifTable {
             }
         }
 
+        fn update_erows_with_lvl(
+            table: &mut UnifTable,
+            erows: UnifEnumRows,
+            level: VarLevel,
+        ) -> UnifEnumRows {
+            let erows = erows.into_root(table);
+
+            match erows {
+                UnifEnumRows::Concrete {
+                    erows,
+                    var_levels_data,
+                } => {
+                    let erows = erows.map_state(
+                        |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
+                        |erows, table| Box::new(update_erows_with_lvl(table, *erows, level)),
+                        table,
+                    );
+
+                    // See the note on `UnifRecordRows` above.
+                    UnifEnumRows::Concrete {
+                        erows,
+                        var_levels_data,
+                    }
+                }
+                UnifEnumRows::UnifVar { .. } | UnifEnumRows::Constant(_) => erows,
+            }
+        }
+
         fn update_utype_with_lvl(
             table: &mut UnifTable,
             uty: UnifType,
@@ -614,7 +642,7 @@ impl UnifTable {
                     let typ = typ.map_state(
                         |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
                         |rrows, table| update_rrows_with_lvl(table, rrows, level),
-                        |erows, _| erows,
+                        |erows, table| update_erows_with_lvl(table, erows, level),
                         table,
                     );
 
@@ -631,6 +659,34 @@ impl UnifTable {
             }
         }
 
+        fn update_erows_with_lvl(
+            table: &mut UnifTable,
+            erows: UnifEnumRows,
+            level: VarLevel,
+        ) -> UnifEnumRows {
+            let erows = erows.into_root(table);
+
+            match erows {
+                UnifEnumRows::Concrete {
+                    erows,
+                    var_levels_data,
+                } => {
+                    let erows = erows.map_state(
+                        |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
+                        |erows, table| Box::new(update_erows_with_lvl(table, *erows, level)),
+                        table,
+                    );
+
+                    // See the note on `UnifType` above.
+                    UnifEnumRows::Concrete {
+                        erows,
+                        var_levels_data,
+                    }
+                }
+                UnifEnumRows::UnifVar { .. } | UnifEnumRows::Constant(_) => erows,
+            }
+        }
+
         fn update_rrows_with_lvl(
             table: &mut UnifTable,
             rrows: UnifRecordRows,
@@ -760,11 +816,71 @@ impl UnifTable {
             level: VarLevel,
         ) -> UnifEnumRowsUnrolling {
             erows.map_state(
+                |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
                 |erows, table| Box::new(update_erows_with_lvl(table, *erows, level)),
                 table,
             )
         }
 
+        fn update_utype_with_lvl(
+            table: &mut UnifTable,
+            utype: UnifType,
+            level: VarLevel,
+        ) -> UnifType {
+            let utype = utype.into_root(table);
+
+            match utype {
+                UnifType::Concrete {
+                    typ,
+                    var_levels_data,
+                } => {
+                    let typ = typ.map_state(
+                        |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
+                        |rrows, table| update_rrows_with_lvl(table, rrows, level),
+                        |erows, table| update_erows_with_lvl(table, erows, level),
+                        table,
+                    );
+
+                    // Note that for `UnifType`, the variable levels data are concerned with type
+                    // unification variables, not enum rows unification variable. We thus let them
+                    // untouched, as updating type variable levels is an orthogonal concern.
+                    UnifType::Concrete {
+                        typ,
+                        var_levels_data,
+                    }
+                }
+                UnifType::UnifVar { .. } | UnifType::Constant(_) | UnifType::Contract(..) => utype,
+            }
+        }
+
+        fn update_rrows_with_lvl(
+            table: &mut UnifTable,
+            rrows: UnifRecordRows,
+            level: VarLevel,
+        ) -> UnifRecordRows {
+            let rrows = rrows.into_root(table);
+
+            match rrows {
+                UnifRecordRows::Concrete {
+                    rrows,
+                    var_levels_data,
+                } => {
+                    let rrows = rrows.map_state(
+                        |uty, table| Box::new(update_utype_with_lvl(table, *uty, level)),
+                        |rrows, table| Box::new(update_rrows_with_lvl(table, *rrows, level)),
+                        table,
+                    );
+
+                    // See the note on `UnifType` above.
+                    UnifRecordRows::Concrete {
+                        rrows,
+                        var_levels_data,
+                    }
+                }
+                UnifRecordRows::UnifVar { .. } | UnifRecordRows::Constant(_) => rrows,
+            }
+        }
+
         fn update_erows_with_lvl(
             table: &mut UnifTable,
             erows: UnifEnumRows,
@@ -1162,18 +1278,27 @@ impl Unify for UnifEnumRows {
                     Err(RowUnifError::UnboundTypeVariable(id))
                 }
                 (EnumRowsF::Empty, EnumRowsF::Empty) => Ok(()),
-                (EnumRowsF::Empty, EnumRowsF::Extend { row: ident, .. }) => {
-                    Err(RowUnifError::ExtraRow(ident))
+                (EnumRowsF::Empty, EnumRowsF::Extend { row, .. }) => {
+                    Err(RowUnifError::ExtraRow(row.id))
                 }
-                (EnumRowsF::Extend { row: ident, .. }, EnumRowsF::Empty) => {
-                    Err(RowUnifError::MissingRow(ident))
+                (EnumRowsF::Extend { row, .. }, EnumRowsF::Empty) => {
+                    Err(RowUnifError::MissingRow(row.id))
                 }
-                (EnumRowsF::Extend { row: id, tail }, erows2 @ EnumRowsF::Extend { .. }) => {
+                (EnumRowsF::Extend { row, tail }, erows2 @ EnumRowsF::Extend { .. }) => {
                     let uerows2 = UnifEnumRows::Concrete {
                         erows: erows2,
                         var_levels_data: var_levels2,
                     };
-                    let (_, t2_without_row) = uerows2.remove_row(&id, state, ctxt.var_level)?;
+                    let (row2, t2_without_row) = uerows2.remove_row(&row, state, ctxt.var_level)?;
+
+                    match (row.typ, row2.typ) {
+                        (Some(typ1), Some(typ2)) => typ1
+                            .unify(*typ2, state, ctxt)
+                            .map_err(|err| RowUnifError::RowMismatch(row.id, Box::new(err)))?,
+                        (None, None) => (),
+                        _ => return Err(RowUnifError::PayloadMismatch(row.id)),
+                    }
+
                     tail.unify(t2_without_row, state, ctxt)
                 }
             },
@@ -1241,6 +1366,21 @@ impl Unify for UnifRecordRows {
                 | (RecordRowsF::TailDyn, RecordRowsF::TailDyn) => Ok(()),
                 (RecordRowsF::Empty, RecordRowsF::TailDyn) => Err(RowUnifError::ExtraDynTail()),
                 (RecordRowsF::TailDyn, RecordRowsF::Empty) => Err(RowUnifError::MissingDynTail()),
+                // An optional row may be absent from the other side, in which case we just skip it.
+                (
+                    rrows1 @ (RecordRowsF::Empty | RecordRowsF::TailDyn),
+                    RecordRowsF::Extend {
+                        row: UnifRecordRow { opt: true, .. },
+                        tail,
+                    },
+                ) => UnifRecordRows::concrete(rrows1).unify(*tail, state, ctxt),
+                (
+                    RecordRowsF::Extend {
+                        row: UnifRecordRow { opt: true, .. },
+                        tail,
+                    },
+                    rrows2 @ (RecordRowsF::Empty | RecordRowsF::TailDyn),
+                ) => tail.unify(UnifRecordRows::concrete(rrows2), state, ctxt),
                 (
                     RecordRowsF::Empty,
                     RecordRowsF::Extend {
@@ -1269,28 +1409,33 @@ impl Unify for UnifRecordRows {
                     },
                     RecordRowsF::Empty,
                 ) => Err(RowUnifError::MissingRow(id)),
-                (
-                    RecordRowsF::Extend {
-                        row: UnifRecordRow { id, typ },
-                        tail,
-                    },
-                    rrows2 @ RecordRowsF::Extend { .. },
-                ) => {
+                (RecordRowsF::Extend { row, tail }, rrows2 @ RecordRowsF::Extend { .. }) => {
                     let urrows2 = UnifRecordRows::Concrete {
                         rrows: rrows2,
                         var_levels_data: var_levels2,
                     };
-                    let (ty2, urrows2_without_ty2) = urrows2
-                        .remove_row(&id, state, ctxt.var_level)
-                        .map_err(|err| match err {
-                            RemoveRRowError::Missing => RowUnifError::MissingRow(id),
-                            RemoveRRowError::Conflict => {
-                                RowUnifError::UnsatConstr(id, *typ.clone())
-                            }
-                        })?;
-                    typ.unify(ty2, state, ctxt)
-                        .map_err(|err| RowUnifError::RowMismatch(id, Box::new(err)))?;
-                    tail.unify(urrows2_without_ty2, state, ctxt)
+
+                    match urrows2.clone().remove_row(&row, state, ctxt.var_level) {
+                        // A field which may be absent can't be used where it's required, and the
+                        // other way around
+                        Ok((row2, _)) if row.opt != row2.opt => {
+                            Err(RowUnifError::OptionalityMismatch(row.id))
+                        }
+                        Ok((row2, urrows2_without_row2)) => {
+                            row.typ
+                                .unify(*row2.typ, state, ctxt)
+                                .map_err(|err| RowUnifError::RowMismatch(row.id, Box::new(err)))?;
+                            tail.unify(urrows2_without_row2, state, ctxt)
+                        }
+                        // An optional row may be absent from the other side
+                        Err(RemoveRRowError::Missing) if row.opt => {
+                            tail.unify(urrows2, state, ctxt)
+                        }
+                        Err(RemoveRRowError::Missing) => Err(RowUnifError::MissingRow(row.id)),
+                        Err(RemoveRRowError::Conflict) => {
+                            Err(RowUnifError::UnsatConstr(row.id, *row.typ))
+                        }
+                    }
                 }
             },
             (UnifRecordRows::UnifVar { id, init_level: _ }, urrows)
@@ -1329,26 +1474,25 @@ impl Unify for UnifRecordRows {
 }
 
 trait RemoveRow: Sized {
-    // The row data minus the identifier.
-    type RowContent;
+    type Row;
     type Error;
 
-    // Fetch a specific `row_id` from a row type, and return the content of the row together with
-    // the original row type without the found row.
+    // Fetch the row with the same identifier as `target` from a row type, and return the found row
+    // together with the original row type without the found row.
     //
     // If the searched row isn't found directly:
     // - If the row type is extensible, i.e. it ends with a free unification variable in tail
-    //   position, this function adds the missing row (with `row.types` as a type for record rows,
-    //   if allowed by row constraints) and then acts as if `remove_row` was called again on
-    //   this extended row type. That is, `remove_row` returns the new row and the extended type
-    //   without the added row).
+    //   position, this function adds the missing row (with a fresh unification variable as a type
+    //   for record rows, if allowed by row constraints, and for enum rows if `target` has a
+    //   payload) and then acts as if `remove_row` was called again on this extended row type.
+    //   That is, `remove_row` returns the new row and the extended type without the added row).
     // - Otherwise, raise a missing row error.
     fn remove_row(
         self,
-        row_id: &LocIdent,
+        target: &Self::Row,
         state: &mut State,
         var_level: VarLevel,
-    ) -> Result<(Self::RowContent, Self), Self::Error>;
+    ) -> Result<(Self::Row, Self), Self::Error>;
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -1361,15 +1505,15 @@ enum RemoveRRowError {
 }
 
 impl RemoveRow for UnifRecordRows {
-    type RowContent = UnifType;
+    type Row = UnifRecordRow;
     type Error = RemoveRRowError;
 
     fn remove_row(
         self,
-        target: &LocIdent,
+        target: &UnifRecordRow,
         state: &mut State,
         var_level: VarLevel,
-    ) -> Result<(UnifType, UnifRecordRows), RemoveRRowError> {
+    ) -> Result<(UnifRecordRow, UnifRecordRows), RemoveRRowError> {
         let rrows = self.into_root(state.table);
 
         match rrows {
@@ -1381,8 +1525,8 @@ impl RemoveRow for UnifRecordRows {
                     row: next_row,
                     tail,
                 } => {
-                    if target.ident() == next_row.id.ident() {
-                        Ok((*next_row.typ, *tail))
+                    if target.id.ident() == next_row.id.ident() {
+                        Ok((next_row, *tail))
                     } else {
                         let (extracted_row, rest) = tail.remove_row(target, state, var_level)?;
                         Ok((
@@ -1402,11 +1546,12 @@ impl RemoveRow for UnifRecordRows {
                 // `target`, to avoid producing ill-formed record rows later
                 state
                     .constr
-                    .insert(tail_var_id, HashSet::from([target.ident()]));
+                    .insert(tail_var_id, HashSet::from([target.id.ident()]));
 
                 let row_to_insert = UnifRecordRow {
-                    id: *target,
-                    typ: Box::new(fresh_uvar.clone()),
+                    id: target.id,
+                    typ: Box::new(fresh_uvar),
+                    opt: target.opt,
                 };
                 let tail_var = UnifRecordRows::UnifVar {
                     id: tail_var_id,
@@ -1414,7 +1559,7 @@ impl RemoveRow for UnifRecordRows {
                 };
 
                 let tail_extended = UnifRecordRows::concrete(RecordRowsF::Extend {
-                    row: row_to_insert,
+                    row: row_to_insert.clone(),
                     tail: Box::new(tail_var.clone()),
                 });
 
@@ -1422,7 +1567,7 @@ impl RemoveRow for UnifRecordRows {
                     .map_err(|_| RemoveRRowError::Conflict)?;
                 state.table.assign_rrows(var_id, tail_extended);
 
-                Ok((fresh_uvar, tail_var))
+                Ok((row_to_insert, tail_var))
             }
             UnifRecordRows::Constant(_) => Err(RemoveRRowError::Missing),
         }
@@ -1430,29 +1575,34 @@ impl RemoveRow for UnifRecordRows {
 }
 
 impl RemoveRow for UnifEnumRows {
-    type RowContent = ();
+    type Row = UnifEnumRow;
     type Error = RowUnifError;
 
     fn remove_row(
         self,
-        target: &LocIdent,
+        target: &UnifEnumRow,
         state: &mut State,
         var_level: VarLevel,
-    ) -> Result<((), UnifEnumRows), RowUnifError> {
+    ) -> Result<(UnifEnumRow, UnifEnumRows), RowUnifError> {
         let uerows = self.into_root(state.table);
 
         match uerows {
             UnifEnumRows::Concrete { erows, .. } => match erows {
-                EnumRowsF::Empty | EnumRowsF::TailVar(_) => Err(RowUnifError::MissingRow(*target)),
-                EnumRowsF::Extend { row, tail } => {
-                    if *target == row {
-                        Ok(((), *tail))
+                EnumRowsF::Empty | EnumRowsF::TailVar(_) => {
+                    Err(RowUnifError::MissingRow(target.id))
+                }
+                EnumRowsF::Extend {
+                    row: next_row,
+                    tail,
+                } => {
+                    if target.id == next_row.id {
+                        Ok((next_row, *tail))
                     } else {
-                        let (_, rest) = tail.remove_row(target, state, var_level)?;
+                        let (extracted_row, rest) = tail.remove_row(target, state, var_level)?;
                         Ok((
-                            (),
+                            extracted_row,
                             UnifEnumRows::concrete(EnumRowsF::Extend {
-                                row,
+                                row: next_row,
                                 tail: Box::new(rest),
                             }),
                         ))
@@ -1465,16 +1615,24 @@ impl RemoveRow for UnifEnumRows {
                     id: tail_var_id,
                     init_level: var_level,
                 };
+                let row_to_insert = UnifEnumRow {
+                    id: target.id,
+                    typ: target
+                        .typ
+                        .as_ref()
+                        .map(|_| Box::new(state.table.fresh_type_uvar(var_level))),
+                };
+
                 let new_tail = UnifEnumRows::concrete(EnumRowsF::Extend {
-                    row: *target,
+                    row: row_to_insert.clone(),
                     tail: Box::new(tail_var.clone()),
                 });
 
                 state.table.assign_erows(var_id, new_tail);
 
-                Ok(((), tail_var))
+                Ok((row_to_insert, tail_var))
             }
-            UnifEnumRows::Constant(_) => Err(RowUnifError::MissingRow(*target)),
+            UnifEnumRows::Constant(_) => Err(RowUnifError::MissingRow(target.id)),
         }
     }
 }
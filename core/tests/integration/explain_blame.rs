@@ -0,0 +1,66 @@
+use codespan::Files;
+use nickel_lang_core::{
+    error::{Error, EvalError, IntoDiagnostics},
+    label::{ContractStepKind, Label},
+};
+use nickel_lang_utils::test_program::program_from_expr;
+
+fn blame_label(s: &str, explain_blame: bool) -> Label {
+    let mut program = program_from_expr(s);
+    program.set_explain_blame(explain_blame);
+
+    match program.eval_full() {
+        Err(Error::EvalError(EvalError::BlameError { label, .. })) => label,
+        res => panic!("expected blame error, got {res:?}"),
+    }
+}
+
+#[test]
+fn no_trace_by_default() {
+    assert!(blame_label("[1, \"a\"] | Array Number", false)
+        .trace
+        .is_empty());
+}
+
+#[test]
+fn array_contract_trace() {
+    let kinds: Vec<_> = blame_label("[1, \"a\"] | Array Number", true)
+        .trace
+        .into_iter()
+        .map(|step| step.kind)
+        .collect();
+
+    assert_eq!(kinds.first(), Some(&ContractStepKind::Apply));
+    assert!(kinds.contains(&ContractStepKind::ArrayElements));
+    assert_eq!(kinds.last(), Some(&ContractStepKind::Apply));
+}
+
+#[test]
+fn record_contract_trace() {
+    let label = blame_label(
+        "let Server = { port | Number } in { server = { port = \"80\" } } | { server | Server }",
+        true,
+    );
+
+    let merged_fields: Vec<String> = label
+        .trace
+        .iter()
+        .filter_map(|step| match &step.kind {
+            ContractStepKind::Merge { field } => Some(field.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(merged_fields, ["server", "port"]);
+
+    // The trace is rendered as one note per step.
+    let mut files = Files::new();
+    let diagnostics = Error::EvalError(EvalError::BlameError {
+        evaluated_arg: None,
+        label,
+        call_stack: Default::default(),
+    })
+    .into_diagnostics(&mut files, None);
+    assert!(diagnostics
+        .iter()
+        .any(|diag| diag.message.starts_with("contract trace (1/")));
+}
@@ -0,0 +1,204 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
+
+use nickel_lang_core::{
+    cache::{normalize_path, Cache, EntryState, ErrorTolerance, SourcePath},
+    eval::cache::CacheImpl,
+    program::Program,
+    term::Term,
+    typecheck::Context,
+};
+
+/// A temporary directory holding the sources of a test, removed on drop.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new(name: &str) -> Self {
+        let dir =
+            std::env::temp_dir().join(format!("nickel-incremental-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Write a file with an explicit modification time, such that successive versions are
+/// distinguished even on filesystems with a coarse timestamp resolution.
+fn write(path: &Path, contents: &str, version: u64) {
+    fs::write(path, contents).unwrap();
+    fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(version))
+        .unwrap();
+}
+
+#[test]
+fn reevaluates_modified_imports() {
+    let dir = TestDir::new("reevaluates");
+    let main = dir.path().join("main.ncl");
+    let dep = dir.path().join("dep.ncl");
+
+    write(&main, r#"(import "dep.ncl").x + 1"#, 1);
+    write(&dep, "{ x = 1 }", 1);
+
+    let mut program: Program<CacheImpl> = Program::new_from_file(&main, std::io::sink()).unwrap();
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(2.into()));
+    assert!(!program.refresh());
+
+    write(&dep, "{ x = 10 }", 2);
+    assert!(program.refresh());
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(11.into()));
+}
+
+/// A trace output which can be inspected while the program is still alive.
+#[derive(Clone, Default)]
+struct Trace(Rc<RefCell<Vec<u8>>>);
+
+impl Trace {
+    /// The number of times `msg` has been traced.
+    fn count(&self, msg: &str) -> usize {
+        String::from_utf8_lossy(&self.0.borrow())
+            .lines()
+            .filter(|line| *line == format!("std.trace: {msg}"))
+            .count()
+    }
+}
+
+impl Write for Trace {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn reuses_unchanged_imports() {
+    let dir = TestDir::new("reuses");
+    let paths = ["main.ncl", "a.ncl", "b.ncl"].map(|name| dir.path().join(name));
+    let [main, a, b] = &paths;
+
+    write(
+        main,
+        r#"(import "a.ncl").x + (import "b.ncl").y + (import "b.ncl").y"#,
+        1,
+    );
+    write(a, r#"std.trace "a" { x = 1 }"#, 1);
+    write(b, r#"std.trace "b" { y = 2 }"#, 1);
+
+    let trace = Trace::default();
+    let mut program: Program<CacheImpl> = Program::new_from_file(main, trace.clone()).unwrap();
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(5.into()));
+    assert_eq!((trace.count("a"), trace.count("b")), (1, 1));
+
+    // Only the modified import is evaluated again.
+    write(a, r#"std.trace "a" { x = 10 }"#, 2);
+    assert!(program.refresh());
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(14.into()));
+    assert_eq!((trace.count("a"), trace.count("b")), (2, 1));
+
+    write(b, r#"std.trace "b" { y = 20 }"#, 2);
+    assert!(program.refresh());
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(50.into()));
+    assert_eq!((trace.count("a"), trace.count("b")), (2, 2));
+}
+
+#[test]
+fn invalidates_dependent_files_only() {
+    let dir = TestDir::new("dependents");
+    let paths = ["main.ncl", "a.ncl", "b.ncl"].map(|name| dir.path().join(name));
+    let [main, a, b] = &paths;
+
+    write(main, r#"(import "a.ncl").x + (import "b.ncl").y"#, 1);
+    write(a, "{ x = 1 }", 1);
+    write(b, "{ y = 2 }", 1);
+
+    let mut cache = Cache::new(ErrorTolerance::Strict);
+    let main_id = cache.add_file(main).unwrap();
+    cache.prepare(main_id, &Context::new()).unwrap();
+
+    let ids = paths.clone().map(|path| {
+        cache
+            .id_of(&SourcePath::Path(normalize_path(path).unwrap()))
+            .unwrap()
+    });
+    let [_, a_id, b_id] = ids;
+
+    write(a, "{ x = 3 }", 2);
+    assert_eq!(cache.refresh(), vec![a_id]);
+
+    assert_eq!(cache.entry_state(main_id), None);
+    assert_eq!(cache.entry_state(a_id), None);
+    assert_eq!(cache.entry_state(b_id), Some(EntryState::Transformed));
+
+    // The modified file is reloaded in place and keeps its id.
+    cache.prepare(main_id, &Context::new()).unwrap();
+
+    for (path, id) in paths.iter().zip(ids) {
+        let path = SourcePath::Path(normalize_path(path).unwrap());
+        assert_eq!(cache.id_of(&path), Some(id));
+        assert_eq!(cache.entry_state(id), Some(EntryState::Transformed));
+    }
+
+    assert_eq!(cache.source(a_id), "{ x = 3 }");
+    assert_eq!(cache.get_imports(main_id).count(), 2);
+}
+
+#[test]
+fn reports_deleted_imports() {
+    let dir = TestDir::new("deleted");
+    let main = dir.path().join("main.ncl");
+    let dep = dir.path().join("dep.ncl");
+
+    write(&main, r#"import "dep.ncl""#, 1);
+    write(&dep, "1", 1);
+
+    let mut program: Program<CacheImpl> = Program::new_from_file(&main, std::io::sink()).unwrap();
+    program.eval_full().unwrap();
+
+    fs::remove_file(&dep).unwrap();
+    assert!(program.refresh());
+    assert!(program.eval_full().is_err());
+}
+
+#[test]
+fn reloads_recreated_imports() {
+    let dir = TestDir::new("recreated");
+    let main = dir.path().join("main.ncl");
+    let dep = dir.path().join("dep.ncl");
+
+    write(&main, r#"let x = 1 in (import "dep.ncl") + x"#, 1);
+    write(&dep, "1", 1);
+
+    let mut program: Program<CacheImpl> = Program::new_from_file(&main, std::io::sink()).unwrap();
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(2.into()));
+
+    fs::remove_file(&dep).unwrap();
+    assert!(program.refresh());
+    assert!(program.eval_full().is_err());
+    assert!(!program.refresh());
+
+    write(&dep, "2", 2);
+    assert!(program.refresh());
+    assert_eq!(program.eval_full().unwrap().as_ref(), &Term::Num(3.into()));
+}
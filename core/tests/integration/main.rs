@@ -13,9 +13,15 @@ use serde::Deserialize;
 use test_generator::test_resources;
 
 mod contract_label_path;
+mod explain_blame;
 mod free_vars;
+mod incremental;
+mod parallel;
+mod persistent_cache;
 mod pretty;
+mod profile;
 mod query;
+mod resource_limits;
 mod stdlib_typecheck;
 
 #[test_resources("core/tests/integration/**/*.ncl")]
@@ -159,6 +165,8 @@ enum ErrorExpectation {
     EvalMissingFieldDef { field: String },
     #[serde(rename = "EvalError::MergeIncompatibleArgs")]
     EvalMergeIncompatibleArgs,
+    #[serde(rename = "EvalError::NonExhaustiveMatch")]
+    EvalNonExhaustiveMatch,
     #[serde(rename = "TypecheckError::UnboundIdentifier")]
     TypecheckUnboundIdentifier { identifier: String },
     #[serde(rename = "TypecheckError::UnboundTypeVariable")]
@@ -188,6 +196,12 @@ enum ErrorExpectation {
     TypecheckFlatTypeInTermPosition,
     #[serde(rename = "TypecheckError::VarLevelMismatch")]
     TypecheckVarLevelMismatch { type_var: String },
+    #[serde(rename = "TypecheckError::TypeAliasArity")]
+    TypecheckTypeAliasArity {
+        alias: String,
+        expected: usize,
+        found: usize,
+    },
     #[serde(rename = "ParseError")]
     AnyParseError,
     #[serde(rename = "ParseError::DuplicateIdentInRecordPattern")]
@@ -218,6 +232,7 @@ impl PartialEq<Error> for ErrorExpectation {
                 Error::EvalError(EvalError::MergeIncompatibleArgs { .. }),
             )
             | (EvalOther, Error::EvalError(EvalError::Other(..)))
+            | (EvalNonExhaustiveMatch, Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
             | (TypecheckRowMismatch, Error::TypecheckError(TypecheckError::RowMismatch(..)))
             | (
                 TypecheckMissingDynTail,
@@ -314,6 +329,19 @@ impl PartialEq<Error> for ErrorExpectation {
                     type_var: constant, ..
                 }),
             ) => ident == constant.label(),
+            (
+                TypecheckTypeAliasArity {
+                    alias,
+                    expected,
+                    found,
+                },
+                Error::TypecheckError(TypecheckError::TypeAliasArity {
+                    alias: alias1,
+                    expected: expected1,
+                    found: found1,
+                    ..
+                }),
+            ) => alias == alias1.label() && expected == expected1 && found == found1,
             // The clone is not ideal, but currently we can't compare `TypecheckError` directly
             // with an ErrorExpectation. Ideally, we would implement `eq` for all error subtypes,
             // and have the eq with `Error` just dispatch to those sub-eq functions.
@@ -345,6 +373,7 @@ impl std::fmt::Display for ErrorExpectation {
             EvalEqError => "EvalError::EqError".to_owned(),
             EvalOther => "EvalError::Other".to_owned(),
             EvalMergeIncompatibleArgs => "EvalError::MergeIncompatibleArgs".to_owned(),
+            EvalNonExhaustiveMatch => "EvalError::NonExhaustiveMatch".to_owned(),
             EvalNAryPrimopTypeError => "EvalError::NAryPrimopTypeError".to_owned(),
             EvalInfiniteRecursion => "EvalError::InfiniteRecursion".to_owned(),
             EvalIllegalPolymorphicTailAccess => {
@@ -390,6 +419,13 @@ impl std::fmt::Display for ErrorExpectation {
             TypecheckVarLevelMismatch { type_var: ident } => {
                 format!("TypecheckError::VarLevelMismatch({ident})")
             }
+            TypecheckTypeAliasArity {
+                alias,
+                expected,
+                found,
+            } => {
+                format!("TypecheckError::TypeAliasArity({alias}, {expected}, {found})")
+            }
             SerializeNumberOutOfRange => "ExportError::NumberOutOfRange".to_owned(),
         };
         write!(f, "{}", name)
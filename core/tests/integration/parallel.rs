@@ -0,0 +1,95 @@
+use std::num::NonZeroUsize;
+
+use assert_matches::assert_matches;
+use nickel_lang_core::{
+    error::{Error, EvalError},
+    serialize::{self, ExportFormat},
+};
+use nickel_lang_utils::test_program::program_from_expr;
+
+/// Export `s` to JSON, sequentially if `jobs` is `None`.
+fn export(s: &str, jobs: Option<usize>) -> Result<String, Error> {
+    let mut program = program_from_expr(s);
+    let result = match jobs.and_then(NonZeroUsize::new) {
+        Some(jobs) => program.eval_full_for_export_parallel(jobs)?,
+        None => program.eval_full_for_export()?,
+    };
+
+    serialize::validate(ExportFormat::Json, &result)?;
+    Ok(serialize::to_string(ExportFormat::Json, &result)?)
+}
+
+#[test]
+fn same_as_sequential() {
+    let source = r#"
+        let rec fib = fun n => if n < 2 then n else fib (n - 1) + fib (n - 2) in
+        {
+          small = fib 15,
+          large = 9007199254740993,
+          ratio = 1 / 3,
+          nested = { name = "nested", tags = ['foo, 'bar], fields = [{ x = null }] },
+          variant = 'Some small,
+          hidden | not_exported = fib 20,
+          missing | optional,
+          shared = small + 1,
+          contract | { value | Number } = { value = 1 },
+        }
+    "#;
+
+    let sequential = export(source, None).unwrap();
+    assert_eq!(export(source, Some(4)).unwrap(), sequential);
+    assert!(sequential.contains("9007199254740993"));
+    assert!(!sequential.contains("hidden"));
+}
+
+#[test]
+fn non_record() {
+    assert_eq!(
+        export("[1, 2, 3]", Some(4)).unwrap(),
+        export("[1, 2, 3]", None).unwrap()
+    );
+}
+
+#[test]
+fn deterministic_errors() {
+    // `c` is the first failing field forced by a sequential evaluation, although `b` comes
+    // first alphabetically.
+    let source = r#"
+        {
+          a = 1,
+          c | Number = "not a number",
+          b = 1 + "a",
+          d = std.fail_with "d",
+        }
+    "#;
+
+    assert_matches!(
+        export(source, None),
+        Err(Error::EvalError(EvalError::BlameError { .. }))
+    );
+
+    for _ in 0..5 {
+        assert_matches!(
+            export(source, Some(4)),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+    }
+}
+
+#[test]
+fn missing_definition() {
+    let source = "{ a = 1 + 1, b | Number, c = 2 }";
+
+    assert_matches!(
+        export(source, Some(4)),
+        Err(Error::EvalError(EvalError::MissingFieldDef { .. }))
+    );
+}
+
+#[test]
+fn non_exportable() {
+    let source = "{ a = 1, f = fun x => x, g = 2 }";
+
+    assert_matches!(export(source, None), Err(Error::ExportError(_)));
+    assert_matches!(export(source, Some(4)), Err(Error::ExportError(_)));
+}
@@ -0,0 +1,226 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use assert_matches::assert_matches;
+use nickel_lang_core::{
+    error::{Error, EvalError},
+    persistent_cache::PersistentCache,
+    term::{RichTerm, Term},
+};
+use nickel_lang_utils::test_program::TestProgram;
+
+/// A temporary directory holding both the cache and the sources of a test, removed on drop.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "nickel-persistent-cache-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+
+    fn cache_dir(&self) -> PathBuf {
+        self.0.join("cache")
+    }
+
+    fn write(&self, name: &str, content: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn entries(&self) -> HashSet<PathBuf> {
+        fs::read_dir(self.cache_dir())
+            .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+            .unwrap_or_default()
+    }
+
+    fn size(&self) -> u64 {
+        self.entries()
+            .iter()
+            .map(|path| fs::metadata(path).unwrap().len())
+            .sum()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn eval_file(path: &Path, cache: PersistentCache) -> Result<RichTerm, Error> {
+    let mut program = TestProgram::new_from_file(path, std::io::stderr()).unwrap();
+    program.set_persistent_cache(Some(cache));
+    program.eval_full()
+}
+
+fn eval_file_num(path: &Path, cache: PersistentCache) -> String {
+    match eval_file(path, cache).unwrap().as_ref() {
+        Term::Num(n) => n.to_string(),
+        t => panic!("expected a number, got {t:?}"),
+    }
+}
+
+#[test]
+fn restores_prepared_sources() {
+    let dir = TestDir::new("restore");
+    let cache = PersistentCache::new(dir.cache_dir());
+
+    let main = dir.write("main.ncl", "1");
+    assert_eq!(eval_file_num(&main, cache.clone()), "1");
+    let before = dir.entries();
+    // The two modules of the standard library and `main.ncl`.
+    assert_eq!(before.len(), 3);
+
+    // Once restored, sources don't need to be stored again.
+    assert_eq!(eval_file_num(&main, cache.clone()), "1");
+    assert_eq!(dir.entries(), before);
+
+    dir.write("main.ncl", "2");
+    assert_eq!(eval_file_num(&main, cache.clone()), "2");
+    let new_entries: Vec<_> = dir.entries().difference(&before).cloned().collect();
+    let [new_entry] = new_entries.as_slice() else {
+        panic!("expected a single new entry, got {new_entries:?}")
+    };
+
+    // Replace the entry of the new version of `main.ncl` by the one of the old version, which is
+    // the only previous entry of the same size (the ones of the standard library are much larger):
+    // the restored term is used instead of the source.
+    let size = |path: &PathBuf| fs::metadata(path).unwrap().len();
+    let old_entry = before
+        .iter()
+        .find(|entry| size(entry) == size(new_entry))
+        .unwrap();
+    fs::copy(old_entry, new_entry).unwrap();
+    assert_eq!(eval_file_num(&main, cache), "1");
+}
+
+#[test]
+fn restores_imports() {
+    let dir = TestDir::new("imports");
+    let cache = PersistentCache::new(dir.cache_dir());
+
+    dir.write(
+        "lib.ncl",
+        "{ double : Number -> Number = fun x => 2 * x, Pos = std.contract.from_predicate (fun x => x > 0) }",
+    );
+    let main = dir.write(
+        "main.ncl",
+        "let lib = import \"lib.ncl\" in let x = lib.double 2 in let y : _ = 1 in (x + y | lib.Pos)",
+    );
+
+    for _ in 0..2 {
+        assert_eq!(eval_file_num(&main, cache.clone()), "5");
+    }
+
+    // Modifying the main file only invalidates its own entry.
+    let before = dir.entries();
+    dir.write(
+        "main.ncl",
+        "let lib = import \"lib.ncl\" in let x = lib.double 3 in let y : _ = 1 in (x + y | lib.Pos)",
+    );
+    assert_eq!(eval_file_num(&main, cache.clone()), "7");
+    assert_eq!(dir.entries().difference(&before).count(), 1);
+
+    // Positions in restored terms still refer to the right files.
+    dir.write(
+        "main.ncl",
+        "let lib = import \"lib.ncl\" in let x = lib.double 3 in let y : _ = 10 in (x - y | lib.Pos)",
+    );
+    for _ in 0..2 {
+        assert_matches!(
+            eval_file(&main, cache.clone()),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+    }
+}
+
+#[test]
+fn invalidated_by_modified_imports() {
+    let dir = TestDir::new("invalidation");
+    let cache = PersistentCache::new(dir.cache_dir());
+
+    dir.write("lib.ncl", "1 : Number");
+    let main = dir.write(
+        "main.ncl",
+        "let x : Number = import \"lib.ncl\" in x + 1 : Number",
+    );
+    assert_eq!(eval_file_num(&main, cache.clone()), "2");
+
+    // The main file didn't change, but it must be typechecked again.
+    dir.write("lib.ncl", "\"a\" : String");
+    assert_matches!(eval_file(&main, cache), Err(Error::TypecheckError(_)));
+}
+
+#[test]
+fn ignores_corrupted_entries() {
+    let dir = TestDir::new("corrupted");
+    let cache = PersistentCache::new(dir.cache_dir());
+
+    dir.write("lib.ncl", "{ x = 1 }");
+    let main = dir.write("main.ncl", "(import \"lib.ncl\").x + 1");
+    assert_eq!(eval_file_num(&main, cache.clone()), "2");
+
+    for entry in dir.entries() {
+        let data = fs::read(&entry).unwrap();
+        fs::write(&entry, &data[..data.len() / 2]).unwrap();
+    }
+
+    for _ in 0..2 {
+        assert_eq!(eval_file_num(&main, cache.clone()), "2");
+    }
+}
+
+#[test]
+fn ignores_entries_with_trailing_data() {
+    let dir = TestDir::new("trailing");
+    let cache = PersistentCache::new(dir.cache_dir());
+
+    let main = dir.write("main.ncl", "1");
+    assert_eq!(eval_file_num(&main, cache.clone()), "1");
+    let before = dir.entries();
+
+    dir.write("main.ncl", "2");
+    assert_eq!(eval_file_num(&main, cache.clone()), "2");
+    let new_entries: Vec<_> = dir.entries().difference(&before).cloned().collect();
+    let [new_entry] = new_entries.as_slice() else {
+        panic!("expected a single new entry, got {new_entries:?}")
+    };
+
+    // Replace the entry of the new version of `main.ncl` by the one of the old version followed by
+    // garbage (see `restores_prepared_sources`): the whole entry must be decoded exactly to be
+    // used, so the source is prepared again.
+    let size = |path: &PathBuf| fs::metadata(path).unwrap().len();
+    let old_entry = before
+        .iter()
+        .find(|entry| size(entry) == size(new_entry))
+        .unwrap();
+    let mut data = fs::read(old_entry).unwrap();
+    data.push(0);
+    fs::write(new_entry, data).unwrap();
+    assert_eq!(eval_file_num(&main, cache), "2");
+}
+
+#[test]
+fn bounded_size() {
+    let dir = TestDir::new("bounded");
+    let max_size = 100_000;
+    let cache = PersistentCache::new(dir.cache_dir()).with_max_size(max_size);
+
+    dir.write("lib.ncl", "{ x = 1 }");
+    let main = dir.write("main.ncl", "(import \"lib.ncl\").x + 1");
+
+    for _ in 0..2 {
+        assert_eq!(eval_file_num(&main, cache.clone()), "2");
+        assert!(dir.size() <= max_size);
+        assert!(!dir.entries().is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+use nickel_lang_core::eval::profile::Profile;
+use nickel_lang_utils::test_program::program_from_expr;
+
+fn profile(s: &str) -> Profile {
+    let mut program = program_from_expr(s);
+    program.enable_profiling();
+    program.eval_full().unwrap();
+    program.take_profile().unwrap()
+}
+
+/// Return the number of steps executed with a stack whose innermost frame is named `name`.
+fn self_steps(profile: &Profile, name: &str) -> u64 {
+    profile
+        .stacks()
+        .into_iter()
+        .filter(|(stack, _)| stack.last().is_some_and(|label| label.name == name))
+        .map(|(_, steps)| steps)
+        .sum()
+}
+
+#[test]
+fn functions() {
+    let profile = profile(
+        "let rec fib = fun n => if n < 2 then n else fib (n - 1) + fib (n - 2) in
+        let double = fun x => x * 2 in
+        double (fib 10)",
+    );
+
+    assert!(self_steps(&profile, "fib") > 100);
+    assert!(self_steps(&profile, "double") > 0);
+
+    let entries = profile.entries();
+    let fib = entries
+        .iter()
+        .find(|entry| entry.label.name == "fib")
+        .unwrap();
+
+    // Recursive calls are only counted once in the total.
+    assert!(fib.total_steps <= profile.total_steps());
+    assert!(fib.total_steps >= fib.self_steps);
+}
+
+#[test]
+fn fields_and_contracts() {
+    let profile = profile(
+        "let Positive = std.contract.from_predicate (fun x => x > 0) in
+        {
+          expensive = std.array.fold_left (+) 0 (std.array.range 0 100),
+          checked | Positive = 1,
+        }",
+    );
+
+    assert!(profile
+        .stacks()
+        .iter()
+        .any(|(stack, _)| stack.iter().any(|label| label.name == "expensive")));
+    assert!(profile
+        .stacks()
+        .iter()
+        .any(|(stack, _)| stack.iter().any(|label| label.name.starts_with("contract"))));
+}
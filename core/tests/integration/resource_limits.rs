@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use assert_matches::assert_matches;
+use nickel_lang_core::{
+    error::{Error, EvalError},
+    eval::{
+        cache::CacheImpl,
+        limits::{EvalLimits, ResourceLimit},
+    },
+    repl::{Repl, ReplImpl},
+};
+use nickel_lang_utils::test_program::program_from_expr;
+
+const LOOP: &str = "let rec f = fun x => f (x + 1) in f 0";
+
+/// A long computation which, contrary to [LOOP], doesn't build a chain of thunks. Such a chain
+/// grows with the number of steps, and dropping it can overflow the stack of the test thread.
+const LONG_COMPUTATION: &str = "std.array.fold_left (+) 0 (std.array.range 0 100000)";
+
+/// A computation which never terminates. The recursive call is in tail position and the wildcard
+/// case doesn't bind the argument, so the environment doesn't grow and, contrary to [LOOP], no
+/// chain of thunks is built.
+const INFINITE_LOOP: &str = "let rec f = match { _ => f null } in f null";
+
+fn eval_with_limits(s: &str, limits: EvalLimits) -> Result<(), Error> {
+    let mut program = program_from_expr(s);
+    program.set_limits(limits);
+    program.eval_full().map(|_| ())
+}
+
+#[test]
+fn step_limit() {
+    let limits = EvalLimits {
+        max_steps: Some(10_000),
+        ..Default::default()
+    };
+
+    assert_matches!(
+        eval_with_limits(LONG_COMPUTATION, limits),
+        Err(Error::EvalError(EvalError::ResourceLimitExceeded {
+            limit: ResourceLimit::Steps(10_000),
+            ..
+        }))
+    );
+    assert_matches!(eval_with_limits("std.array.range 0 100", limits), Ok(()));
+}
+
+#[test]
+fn step_limit_in_union_contracts() {
+    let limits = EvalLimits {
+        max_steps: Some(20_000),
+        ..Default::default()
+    };
+    let contract = "std.contract.from_predicate (fun x => std.array.fold_left (+) 0 (std.array.range 0 100) < 0)";
+
+    // Each branch of a union contract is tried in an isolated evaluation. Checking one element
+    // stays below the limit, but the steps of the isolated evaluations add up.
+    assert_matches!(
+        eval_with_limits(&format!("1 | ({contract}) \\/ Number"), limits),
+        Ok(())
+    );
+    assert_matches!(
+        eval_with_limits(
+            &format!("[1, 2, 3] | Array (({contract}) \\/ Number)"),
+            limits
+        ),
+        Err(Error::EvalError(EvalError::ResourceLimitExceeded {
+            limit: ResourceLimit::Steps(20_000),
+            ..
+        }))
+    );
+}
+
+#[test]
+fn stack_depth_limit() {
+    let limits = EvalLimits {
+        max_stack_depth: Some(1_000),
+        ..Default::default()
+    };
+
+    assert_matches!(
+        eval_with_limits(
+            "let rec sum = fun n => if n == 0 then 0 else n + sum (n - 1) in sum 100000",
+            limits
+        ),
+        Err(Error::EvalError(EvalError::ResourceLimitExceeded {
+            limit: ResourceLimit::StackDepth(1_000),
+            ..
+        }))
+    );
+}
+
+#[test]
+fn cache_size_limit() {
+    let limits = EvalLimits {
+        max_cache_size: Some(1_000),
+        ..Default::default()
+    };
+
+    assert_matches!(
+        eval_with_limits(LOOP, limits),
+        Err(Error::EvalError(EvalError::ResourceLimitExceeded {
+            limit: ResourceLimit::CacheSize(1_000),
+            ..
+        }))
+    );
+}
+
+#[test]
+fn timeout() {
+    let limits = EvalLimits {
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    };
+
+    assert_matches!(
+        eval_with_limits(INFINITE_LOOP, limits),
+        Err(Error::EvalError(EvalError::ResourceLimitExceeded {
+            limit: ResourceLimit::Timeout(_),
+            ..
+        }))
+    );
+}
+
+#[test]
+fn repl_limits() {
+    let mut repl = ReplImpl::<CacheImpl>::new(std::io::sink());
+    repl.load_stdlib().unwrap();
+    repl.set_limits(EvalLimits {
+        max_steps: Some(10_000),
+        ..Default::default()
+    });
+
+    assert_matches!(
+        repl.eval_full(LONG_COMPUTATION),
+        Err(Error::EvalError(EvalError::ResourceLimitExceeded {
+            limit: ResourceLimit::Steps(10_000),
+            ..
+        }))
+    );
+    // Each input is subject to the limits separately.
+    assert_matches!(repl.eval_full("std.array.range 0 100"), Ok(_));
+}
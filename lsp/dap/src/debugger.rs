@@ -0,0 +1,582 @@
+//! The debugging session: breakpoints, stepping, and inspection of the state of the virtual
+//! machine while the evaluation is suspended.
+//!
+//! The evaluation runs on the main thread and calls [Session::step] before each step through a
+//! [SessionHook]. When the evaluation must stop (on a breakpoint, after a step request, or when
+//! the client asks for a pause), the session blocks in [Session::step] and serves the requests of
+//! the client until it asks to resume the evaluation.
+use std::{
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    io::{self, Stdout},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::{Receiver, TryRecvError},
+};
+
+use codespan::FileId;
+use codespan_reporting::term::termcolor::NoColor;
+use log::debug;
+use nickel_lang_core::{
+    cache::{Cache, SourcePath},
+    error::{report_with, Error, IntoDiagnostics},
+    eval::{
+        cache::{Cache as _, CacheImpl},
+        callstack::CallDescr,
+        debug::DebugHook,
+        Closure, Environment, VirtualMachine,
+    },
+    position::RawSpan,
+    term::{RichTerm, Term},
+    transform,
+};
+use serde_json::{json, Value};
+
+use crate::protocol::{Output, Request};
+
+/// The virtual machine driven by the debugger.
+pub type Vm = VirtualMachine<Cache, CacheImpl>;
+
+/// The identifier of the only thread of the evaluation.
+pub const THREAD_ID: i64 = 1;
+
+/// The maximum length of the values displayed in the variables view.
+const MAX_VALUE_LENGTH: usize = 80;
+
+/// A source line: a file and a 0-based line index.
+type Location = (FileId, usize);
+
+/// How the evaluation should proceed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Run until a breakpoint is hit.
+    Continue,
+    /// Stop at the next step, for the given reason.
+    Stop(&'static str),
+    /// Stop at the next step on a different line.
+    StepIn { from: Location },
+    /// Stop at the next step on a different line, unless it is part of the forcing of a thunk or
+    /// of the operand of a primitive operation started from the current line.
+    StepOver { from: Location, depth: usize },
+    /// Stop at the next step which isn't part of the forcing of the current thunk or operand.
+    StepOut { depth: usize },
+}
+
+/// A value which can be expanded in the variables view of the client.
+#[derive(Clone)]
+enum Container {
+    /// The bindings of an environment.
+    Env(Environment),
+    /// The fields of a record or the elements of an array.
+    Value(Closure),
+}
+
+/// The state of a debugging session.
+pub struct Session {
+    requests: Receiver<Request>,
+    output: Output<Stdout>,
+    /// The breakpoints, as 1-based line numbers indexed by canonicalized file paths.
+    breakpoints: HashMap<PathBuf, BTreeSet<usize>>,
+    /// The canonicalized paths of the files of the cache, if they correspond to actual files.
+    paths: HashMap<FileId, Option<PathBuf>>,
+    stdlib_ids: Option<Vec<FileId>>,
+    mode: Mode,
+    /// The location of the last step in user code.
+    last_location: Option<Location>,
+    /// The containers of the variables view, where the container of reference `n` is at index
+    /// `n - 1`. Cleared when the evaluation resumes.
+    containers: Vec<Container>,
+}
+
+impl Session {
+    pub fn new(requests: Receiver<Request>, output: Output<Stdout>) -> Self {
+        Session {
+            requests,
+            output,
+            breakpoints: HashMap::new(),
+            paths: HashMap::new(),
+            stdlib_ids: None,
+            mode: Mode::Continue,
+            last_location: None,
+            containers: Vec::new(),
+        }
+    }
+
+    pub fn output(&mut self) -> &mut Output<Stdout> {
+        &mut self.output
+    }
+
+    pub fn requests(&self) -> &Receiver<Request> {
+        &self.requests
+    }
+
+    /// Stop at the first step of the evaluation.
+    pub fn stop_on_entry(&mut self) {
+        self.mode = Mode::Stop("entry");
+    }
+
+    /// Handle the requests which are valid at any time. Return `false` if the request isn't one
+    /// of them.
+    pub fn handle_common(&mut self, request: &Request) -> io::Result<bool> {
+        match request.command.as_str() {
+            "setBreakpoints" => self.set_breakpoints(request)?,
+            "threads" => self.output.respond(
+                request,
+                json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+            )?,
+            "disconnect" | "terminate" => {
+                self.output.respond(request, Value::Null)?;
+                self.output.event("terminated", Value::Null)?;
+                std::process::exit(0);
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn set_breakpoints(&mut self, request: &Request) -> io::Result<()> {
+        let args = &request.arguments;
+        let Some(path) = args["source"]["path"].as_str() else {
+            return self
+                .output
+                .respond_error(request, "breakpoints require a source path");
+        };
+
+        let lines: BTreeSet<usize> = args["breakpoints"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|breakpoint| breakpoint["line"].as_u64())
+            .map(|line| line as usize)
+            .collect();
+
+        let breakpoints: Vec<Value> = lines
+            .iter()
+            .map(|line| json!({ "verified": true, "line": line }))
+            .collect();
+
+        let path = Path::new(path);
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+        debug!("breakpoints in {}: {lines:?}", path.display());
+        self.breakpoints.insert(path, lines);
+
+        self.output
+            .respond(request, json!({ "breakpoints": breakpoints }))
+    }
+
+    /// Called before each step of the evaluation of `current`.
+    pub fn step(&mut self, vm: &mut Vm, current: &Closure) -> io::Result<()> {
+        self.poll()?;
+
+        let Some(location) = self.location(vm, current) else {
+            return Ok(());
+        };
+
+        let depth = vm.stack_depth();
+        let mode = self.mode;
+        let reason = match mode {
+            Mode::Stop(reason) => Some(reason),
+            Mode::StepIn { from } if from != location => Some("step"),
+            Mode::StepOver {
+                from,
+                depth: from_depth,
+            } if from != location && depth <= from_depth => Some("step"),
+            Mode::StepOut { depth: from_depth } if depth < from_depth => Some("step"),
+            _ if self.last_location != Some(location) && self.is_breakpoint(vm, location) => {
+                Some("breakpoint")
+            }
+            _ => None,
+        };
+
+        self.last_location = Some(location);
+
+        match reason {
+            Some(reason) => self.suspend(vm, current, location, reason),
+            None => Ok(()),
+        }
+    }
+
+    /// Handle the requests received while the evaluation is running.
+    fn poll(&mut self) -> io::Result<()> {
+        loop {
+            let request = match self.requests.try_recv() {
+                Ok(request) => request,
+                Err(TryRecvError::Empty) => return Ok(()),
+                Err(TryRecvError::Disconnected) => std::process::exit(0),
+            };
+
+            if self.handle_common(&request)? {
+                continue;
+            }
+
+            match request.command.as_str() {
+                "pause" => {
+                    self.mode = Mode::Stop("pause");
+                    self.output.respond(&request, Value::Null)?;
+                }
+                _ => self
+                    .output
+                    .respond_error(&request, "the evaluation is running")?,
+            }
+        }
+    }
+
+    /// Return the location of the closure about to be evaluated, if it is in user code.
+    fn location(&mut self, vm: &Vm, current: &Closure) -> Option<Location> {
+        let span = current.body.pos.into_opt()?;
+        let stdlib_ids = self.stdlib_ids.get_or_insert_with(|| {
+            vm.import_resolver()
+                .get_all_stdlib_modules_file_id()
+                .unwrap_or_default()
+        });
+
+        if stdlib_ids.contains(&span.src_id) {
+            return None;
+        }
+
+        let location = vm
+            .import_resolver()
+            .files()
+            .location(span.src_id, span.start)
+            .ok()?;
+
+        Some((span.src_id, location.line.to_usize()))
+    }
+
+    fn path(&mut self, vm: &Vm, file_id: FileId) -> Option<&PathBuf> {
+        self.paths
+            .entry(file_id)
+            .or_insert_with(|| {
+                Path::new(vm.import_resolver().name(file_id))
+                    .canonicalize()
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    fn is_breakpoint(&mut self, vm: &Vm, (file_id, line): Location) -> bool {
+        if self.breakpoints.is_empty() {
+            return false;
+        }
+
+        let Some(path) = self.path(vm, file_id).cloned() else {
+            return false;
+        };
+
+        self.breakpoints
+            .get(&path)
+            .is_some_and(|lines| lines.contains(&(line + 1)))
+    }
+
+    /// Suspend the evaluation and serve the requests of the client until it resumes the
+    /// evaluation.
+    fn suspend(
+        &mut self,
+        vm: &mut Vm,
+        current: &Closure,
+        location: Location,
+        reason: &str,
+    ) -> io::Result<()> {
+        self.output.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        )?;
+
+        let depth = vm.stack_depth();
+
+        loop {
+            let Ok(request) = self.requests.recv() else {
+                std::process::exit(0);
+            };
+
+            if self.handle_common(&request)? {
+                continue;
+            }
+
+            let mode = match request.command.as_str() {
+                "continue" => Mode::Continue,
+                "next" => Mode::StepOver {
+                    from: location,
+                    depth,
+                },
+                "stepIn" => Mode::StepIn { from: location },
+                "stepOut" => Mode::StepOut { depth },
+                _ => {
+                    self.serve(vm, current, &request)?;
+                    continue;
+                }
+            };
+
+            self.mode = mode;
+            self.containers.clear();
+
+            return self
+                .output
+                .respond(&request, json!({ "allThreadsContinued": true }));
+        }
+    }
+
+    /// Serve an inspection request while the evaluation is suspended.
+    fn serve(&mut self, vm: &mut Vm, current: &Closure, request: &Request) -> io::Result<()> {
+        match request.command.as_str() {
+            "pause" => self.output.respond(request, Value::Null),
+            "stackTrace" => {
+                let frames = self.stack_frames(vm, current);
+                let total = frames.len();
+                self.output.respond(
+                    request,
+                    json!({ "stackFrames": frames, "totalFrames": total }),
+                )
+            }
+            "scopes" => {
+                // Only the environment of the innermost frame is available: the machine doesn't
+                // keep the environments of the callers.
+                let scopes = if request.arguments["frameId"].as_i64().unwrap_or(0) == 0 {
+                    let reference = self.container(Container::Env(current.env.clone()));
+                    vec![json!({
+                        "name": "Locals",
+                        "variablesReference": reference,
+                        "expensive": false,
+                    })]
+                } else {
+                    Vec::new()
+                };
+
+                self.output.respond(request, json!({ "scopes": scopes }))
+            }
+            "variables" => {
+                let reference = request.arguments["variablesReference"]
+                    .as_u64()
+                    .unwrap_or(0) as usize;
+                let variables = self.variables(vm, reference);
+                self.output
+                    .respond(request, json!({ "variables": variables }))
+            }
+            "evaluate" => {
+                let expression = request.arguments["expression"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned();
+
+                match evaluate(vm, &current.env, expression) {
+                    Ok(result) => {
+                        let (value, container) = describe(vm, &result);
+                        let reference = container.map(|c| self.container(c)).unwrap_or(0);
+                        self.output.respond(
+                            request,
+                            json!({ "result": value, "variablesReference": reference }),
+                        )
+                    }
+                    Err(error) => {
+                        let message = report(vm, error);
+                        self.output.respond_error(request, &message)
+                    }
+                }
+            }
+            command => self
+                .output
+                .respond_error(request, &format!("unsupported request `{command}`")),
+        }
+    }
+
+    /// Register a container and return its variables reference.
+    fn container(&mut self, container: Container) -> usize {
+        self.containers.push(container);
+        self.containers.len()
+    }
+
+    /// Build the stack frames of the current evaluation, from the innermost to the outermost one.
+    ///
+    /// The innermost frame is the closure being evaluated, and the other frames are the calls of
+    /// the call stack. Each frame is named after the function it belongs to, which is the function
+    /// of the next call.
+    fn stack_frames(&mut self, vm: &Vm, current: &Closure) -> Vec<Value> {
+        let stdlib_ids = self.stdlib_ids.clone().unwrap_or_default();
+        let (calls, _) = vm.call_stack().group_by_calls(&stdlib_ids);
+
+        let spans = std::iter::once(current.body.pos.into_opt())
+            .chain(calls.iter().map(|CallDescr { span, .. }| Some(*span)));
+        let names = calls
+            .iter()
+            .map(|CallDescr { head, .. }| {
+                head.as_ref()
+                    .map(|id| id.to_string())
+                    .unwrap_or_else(|| String::from("<func>"))
+            })
+            .chain(std::iter::once(String::from("<main>")));
+
+        spans
+            .zip(names)
+            .enumerate()
+            .map(|(id, (span, name))| {
+                let mut frame = json!({ "id": id, "name": name, "line": 0, "column": 0 });
+
+                if let Some(span) = span {
+                    self.locate(vm, &mut frame, span);
+                }
+
+                frame
+            })
+            .collect()
+    }
+
+    /// Fill the source and the position of a stack frame.
+    fn locate(&mut self, vm: &Vm, frame: &mut Value, span: RawSpan) {
+        let files = vm.import_resolver().files();
+        let (Ok(start), Ok(end)) = (
+            files.location(span.src_id, span.start),
+            files.location(span.src_id, span.end),
+        ) else {
+            return;
+        };
+
+        let name = files.name(span.src_id).to_string_lossy().into_owned();
+        frame["line"] = json!(start.line.to_usize() + 1);
+        frame["column"] = json!(start.column.to_usize() + 1);
+        frame["endLine"] = json!(end.line.to_usize() + 1);
+        frame["endColumn"] = json!(end.column.to_usize() + 1);
+        frame["source"] = match self.path(vm, span.src_id) {
+            Some(path) => json!({ "name": name, "path": path }),
+            None => json!({ "name": name, "presentationHint": "deemphasize" }),
+        };
+    }
+
+    fn variables(&mut self, vm: &Vm, reference: usize) -> Vec<Value> {
+        let Some(container) = reference
+            .checked_sub(1)
+            .and_then(|index| self.containers.get(index))
+            .cloned()
+        else {
+            return Vec::new();
+        };
+
+        let is_env = matches!(container, Container::Env(_));
+        let mut children: Vec<(String, Option<Closure>)> = match container {
+            Container::Env(env) => env
+                .iter()
+                .filter(|(id, _)| !id.label().starts_with('%'))
+                .map(|(id, idx)| (id.to_string(), Some(vm.cache.get(idx.clone()))))
+                .collect(),
+            Container::Value(Closure { body, env }) => match body.as_ref() {
+                Term::Record(record) => record
+                    .fields
+                    .iter()
+                    .map(|(id, field)| {
+                        let value = field.value.as_ref().map(|value| resolve(vm, value, &env));
+                        (id.to_string(), value)
+                    })
+                    .collect(),
+                Term::Array(elements, _) => elements
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| (index.to_string(), Some(resolve(vm, value, &env))))
+                    .collect(),
+                _ => Vec::new(),
+            },
+        };
+
+        if is_env {
+            children.sort_by(|(name1, _), (name2, _)| name1.cmp(name2));
+        }
+
+        children
+            .into_iter()
+            .map(|(name, value)| {
+                let (value, container) = match value {
+                    Some(value) => describe(vm, &value),
+                    None => (String::from("<no value>"), None),
+                };
+                let reference = container.map(|c| self.container(c)).unwrap_or(0);
+
+                json!({ "name": name, "value": value, "variablesReference": reference })
+            })
+            .collect()
+    }
+}
+
+/// Return the closure of a value stored in a record or an array, fetching it from the cache if
+/// needed.
+fn resolve(vm: &Vm, value: &RichTerm, env: &Environment) -> Closure {
+    match value.as_ref() {
+        Term::Closure(idx) => vm.cache.get(idx.clone()),
+        _ => Closure {
+            body: value.clone(),
+            env: env.clone(),
+        },
+    }
+}
+
+/// Describe a value without forcing it, and return a container if it can be expanded.
+fn describe(vm: &Vm, clos: &Closure) -> (String, Option<Container>) {
+    match clos.body.as_ref() {
+        Term::Closure(idx) => describe(vm, &vm.cache.get(idx.clone())),
+        Term::Record(record) => (
+            format!("{{ {} fields }}", record.fields.len()),
+            Some(Container::Value(clos.clone())),
+        ),
+        Term::Array(elements, _) => (
+            format!("[ {} elements ]", elements.len()),
+            Some(Container::Value(clos.clone())),
+        ),
+        Term::Fun(..) | Term::FunPattern(..) | Term::Match { .. } | Term::MatchPattern(..) => {
+            (String::from("<function>"), None)
+        }
+        term if term.is_whnf() => (truncate(clos.body.to_string()), None),
+        _ => (String::from("<not evaluated>"), None),
+    }
+}
+
+fn truncate(mut value: String) -> String {
+    if let Some((index, _)) = value.char_indices().nth(MAX_VALUE_LENGTH) {
+        value.truncate(index);
+        value.push('…');
+    }
+
+    value
+}
+
+/// Parse and evaluate an expression in the environment `env`, without disturbing the suspended
+/// evaluation.
+fn evaluate(vm: &mut Vm, env: &Environment, expression: String) -> Result<Closure, Error> {
+    let cache = vm.import_resolver_mut();
+    let file_id = cache.add_string(
+        SourcePath::Generated(String::from("debugger expression")),
+        expression,
+    );
+    let (term, errors) = cache.parse_nocache(file_id)?;
+
+    if !errors.no_errors() {
+        return Err(Error::ParseErrors(errors));
+    }
+
+    let term = transform::transform(term, None).map_err(|err| Error::ParseErrors(err.into()))?;
+
+    Ok(vm.eval_isolated(Closure {
+        body: term,
+        env: env.clone(),
+    })?)
+}
+
+/// Render an error as plain text.
+fn report(vm: &mut Vm, error: impl IntoDiagnostics<FileId>) -> String {
+    let cache = vm.import_resolver_mut();
+    let stdlib_ids = cache.get_all_stdlib_modules_file_id();
+    let mut buffer = NoColor::new(Vec::new());
+    report_with(&mut buffer, cache.files_mut(), stdlib_ids.as_ref(), error);
+
+    String::from_utf8_lossy(&buffer.into_inner()).into_owned()
+}
+
+/// The debug hook forwarding the evaluation steps to a shared session.
+pub struct SessionHook(pub Rc<RefCell<Session>>);
+
+impl DebugHook<Cache, CacheImpl> for SessionHook {
+    fn step(&mut self, vm: &mut Vm, current: &Closure) {
+        if let Err(err) = self.0.borrow_mut().step(vm, current) {
+            // The connection with the client is lost: there's nobody to report to anymore.
+            log::error!("lost the connection with the client: {err}");
+            std::process::exit(1);
+        }
+    }
+}
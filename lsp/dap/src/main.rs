@@ -0,0 +1,145 @@
+use std::{
+    cell::RefCell,
+    io::{self, BufReader},
+    rc::Rc,
+    sync::mpsc,
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use log::debug;
+use nickel_lang_core::{eval::cache::CacheImpl, program::Program};
+use serde_json::{json, Value};
+
+mod debugger;
+mod protocol;
+
+use debugger::{Session, SessionHook};
+use protocol::{read_request, Output, Request};
+
+#[derive(clap::Parser, Debug)]
+/// A debug adapter for the Nickel language, speaking the Debug Adapter Protocol on the standard
+/// input and output.
+struct Opt {}
+
+/// The arguments of the launch request.
+struct Launch {
+    program: String,
+    stop_on_entry: bool,
+}
+
+fn main() -> Result<()> {
+    use clap::Parser;
+
+    env_logger::init();
+
+    let _options = Opt::parse();
+
+    // Requests are read on a separate thread, so that the evaluation can check for new requests
+    // (such as pauses or new breakpoints) without blocking.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut stdin = BufReader::new(io::stdin());
+
+        while let Ok(Some(request)) = read_request(&mut stdin) {
+            debug!("received request `{}`", request.command);
+
+            if sender.send(request).is_err() {
+                break;
+            }
+        }
+    });
+
+    let session = Rc::new(RefCell::new(Session::new(
+        receiver,
+        Output::new(io::stdout()),
+    )));
+    let launch = configure(&mut session.borrow_mut())?;
+
+    if launch.stop_on_entry {
+        session.borrow_mut().stop_on_entry();
+    }
+
+    let mut program = Program::<CacheImpl>::new_from_file(&launch.program, io::stderr())?;
+    program.set_debug_hook(Box::new(SessionHook(session.clone())));
+
+    let (category, output, exit_code) = match program.eval_full() {
+        Ok(result) => ("stdout", format!("{result}\n"), 0),
+        Err(error) => ("stderr", program.report_as_plain_str(error), 1),
+    };
+
+    let mut session = session.borrow_mut();
+    let client = session.output();
+    client.event("output", json!({ "category": category, "output": output }))?;
+    client.event("exited", json!({ "exitCode": exit_code }))?;
+    client.event("terminated", Value::Null)?;
+
+    // Serve the remaining requests, until the client disconnects.
+    while let Ok(request) = session.requests().recv() {
+        if !session.handle_common(&request)? {
+            session
+                .output()
+                .respond_error(&request, "the evaluation is over")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle the initialization sequence of the protocol, up to the `configurationDone` request.
+/// Return the arguments of the launch request.
+fn configure(session: &mut Session) -> Result<Launch> {
+    let mut launch = None;
+    let mut configured = false;
+
+    // Clients may send the launch request before or after the end of the configuration.
+    loop {
+        if configured {
+            if let Some(launch) = launch.take() {
+                return Ok(launch);
+            }
+        }
+
+        let request: Request = session
+            .requests()
+            .recv()
+            .map_err(|_| anyhow!("the client disconnected before launching a program"))?;
+
+        if session.handle_common(&request)? {
+            continue;
+        }
+
+        match request.command.as_str() {
+            "initialize" => {
+                session.output().respond(
+                    &request,
+                    json!({
+                        "supportsConfigurationDoneRequest": true,
+                        "supportsEvaluateForHovers": true,
+                        "supportsTerminateRequest": true,
+                    }),
+                )?;
+                session.output().event("initialized", Value::Null)?;
+            }
+            "launch" => match request.arguments["program"].as_str() {
+                Some(program) => {
+                    launch = Some(Launch {
+                        program: program.to_owned(),
+                        stop_on_entry: request.arguments["stopOnEntry"].as_bool().unwrap_or(false),
+                    });
+                    session.output().respond(&request, Value::Null)?;
+                }
+                None => session
+                    .output()
+                    .respond_error(&request, "missing `program` launch argument")?,
+            },
+            "configurationDone" => {
+                configured = true;
+                session.output().respond(&request, Value::Null)?;
+            }
+            command => session
+                .output()
+                .respond_error(&request, &format!("unexpected request `{command}`"))?,
+        }
+    }
+}
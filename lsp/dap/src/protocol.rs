@@ -0,0 +1,151 @@
+//! The wire format of the Debug Adapter Protocol.
+//!
+//! Messages are JSON objects preceded by a `Content-Length` header, as in the Language Server
+//! Protocol. The client sends requests, and the adapter sends back responses and events. See
+//! <https://microsoft.github.io/debug-adapter-protocol/specification>.
+use std::io::{self, BufRead, Write};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// A request sent by the client.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+    pub seq: i64,
+    pub command: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// Read the next request from `reader`. Return `None` at the end of the input. Messages which
+/// aren't requests are ignored.
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    loop {
+        let mut content_length = None;
+
+        loop {
+            let mut line = String::new();
+
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse::<usize>().ok();
+                }
+            }
+        }
+
+        let Some(content_length) = content_length else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "missing Content-Length header",
+            ));
+        };
+
+        let mut content = vec![0; content_length];
+        reader.read_exact(&mut content)?;
+
+        let message: Value = serde_json::from_slice(&content)?;
+
+        if message.get("type").and_then(Value::as_str) == Some("request") {
+            return Ok(Some(serde_json::from_value(message)?));
+        }
+    }
+}
+
+/// The output channel of the adapter, which numbers the messages sent to the client.
+pub struct Output<W: Write> {
+    writer: W,
+    seq: i64,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(writer: W) -> Self {
+        Output { writer, seq: 0 }
+    }
+
+    fn send(&mut self, mut message: Value) -> io::Result<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+
+        let content = serde_json::to_string(&message)?;
+        write!(
+            self.writer,
+            "Content-Length: {}\r\n\r\n{content}",
+            content.len()
+        )?;
+        self.writer.flush()
+    }
+
+    /// Send a successful response to `request`.
+    pub fn respond(&mut self, request: &Request, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+            "success": true,
+            "body": body,
+        }))
+    }
+
+    /// Send an error response to `request`.
+    pub fn respond_error(&mut self, request: &Request, message: &str) -> io::Result<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "command": request.command,
+            "success": false,
+            "message": message,
+        }))
+    }
+
+    /// Send an event.
+    pub fn event(&mut self, event: &str, body: Value) -> io::Result<()> {
+        self.send(json!({
+            "type": "event",
+            "event": event,
+            "body": body,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut output = Output::new(Vec::new());
+        let request = Request {
+            seq: 3,
+            command: String::from("threads"),
+            arguments: Value::Null,
+        };
+        output.respond(&request, json!({ "threads": [] })).unwrap();
+
+        let written = String::from_utf8(output.writer).unwrap();
+        let (header, content) = written.split_once("\r\n\r\n").unwrap();
+        assert_eq!(header, format!("Content-Length: {}", content.len()));
+
+        let response: Value = serde_json::from_str(content).unwrap();
+        assert_eq!(response["seq"], 1);
+        assert_eq!(response["request_seq"], 3);
+        assert_eq!(response["success"], true);
+
+        let frame = |content: &str| format!("Content-Length: {}\r\n\r\n{content}", content.len());
+        // Messages which aren't requests are skipped.
+        let input = frame(r#"{"seq":1,"type":"response"}"#)
+            + &frame(r#"{"seq":2,"type":"request","command":"next"}"#);
+        let request = read_request(&mut input.as_bytes()).unwrap().unwrap();
+        assert_eq!(request.seq, 2);
+        assert_eq!(request.command, "next");
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+/// How long to wait for a message of the adapter before failing.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A debug adapter driven through its standard input and output.
+struct Client {
+    adapter: Child,
+    stdin: ChildStdin,
+    messages: Receiver<Value>,
+    seq: i64,
+}
+
+impl Client {
+    fn spawn() -> Self {
+        let mut adapter = Command::new(env!("CARGO_BIN_EXE_nickel-dap"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("the debug adapter should be runnable");
+        let stdin = adapter.stdin.take().unwrap();
+        let mut stdout = BufReader::new(adapter.stdout.take().unwrap());
+
+        // Messages are read on a separate thread, so that a test waiting for a message which
+        // never comes fails instead of hanging.
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || {
+            while let Some(message) = read_message(&mut stdout) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Client {
+            adapter,
+            stdin,
+            messages,
+            seq: 0,
+        }
+    }
+
+    /// Send a request and return its sequence number.
+    fn send(&mut self, command: &str, arguments: Value) -> i64 {
+        self.seq += 1;
+
+        let content = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        write!(
+            self.stdin,
+            "Content-Length: {}\r\n\r\n{content}",
+            content.len()
+        )
+        .unwrap();
+        self.stdin.flush().unwrap();
+
+        self.seq
+    }
+
+    /// Wait for the next message satisfying `pred`, skipping the other ones.
+    fn wait_for(&mut self, what: &str, pred: impl Fn(&Value) -> bool) -> Value {
+        loop {
+            let message = self
+                .messages
+                .recv_timeout(TIMEOUT)
+                .unwrap_or_else(|_| panic!("the adapter didn't send {what}"));
+
+            if pred(&message) {
+                return message;
+            }
+        }
+    }
+
+    /// Send a request and wait for its successful response. Return the body of the response.
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        let seq = self.send(command, arguments);
+        let response = self.wait_for(&format!("a response to `{command}`"), |message| {
+            message["type"] == "response" && message["request_seq"] == seq
+        });
+
+        assert_eq!(response["success"], true, "`{command}` failed: {response}");
+        response["body"].clone()
+    }
+
+    /// Wait for an event and return its body.
+    fn event(&mut self, event: &str) -> Value {
+        self.wait_for(&format!("a `{event}` event"), |message| {
+            message["type"] == "event" && message["event"] == event
+        })["body"]
+            .clone()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.adapter.kill();
+        let _ = self.adapter.wait();
+    }
+}
+
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+
+        match line.trim_end() {
+            "" => break,
+            line => {
+                if let Some(length) = line.strip_prefix("Content-Length:") {
+                    content_length = length.trim().parse::<usize>().ok();
+                }
+            }
+        }
+    }
+
+    let mut content = vec![0; content_length?];
+    reader.read_exact(&mut content).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn input_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/inputs")
+        .join(name)
+}
+
+/// Return the variables of the innermost frame, as a list of names and values.
+fn locals(client: &mut Client) -> Vec<(String, String)> {
+    let scopes = client.request("scopes", json!({ "frameId": 0 }));
+    let reference = scopes["scopes"][0]["variablesReference"].clone();
+    let variables = client.request("variables", json!({ "variablesReference": reference }));
+
+    variables["variables"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variable| {
+            (
+                variable["name"].as_str().unwrap().to_owned(),
+                variable["value"].as_str().unwrap().to_owned(),
+            )
+        })
+        .collect()
+}
+
+/// The line of the innermost stack frame.
+fn current_line(client: &mut Client) -> u64 {
+    let trace = client.request("stackTrace", json!({ "threadId": 1 }));
+    trace["stackFrames"][0]["line"].as_u64().unwrap()
+}
+
+#[test]
+fn breakpoint_step_and_inspect() {
+    let program = input_path("functions.ncl");
+    let mut client = Client::spawn();
+
+    client.request("initialize", json!({ "adapterID": "nickel" }));
+    client.event("initialized");
+
+    let breakpoints = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": program }, "breakpoints": [{ "line": 5 }] }),
+    );
+    assert_eq!(breakpoints["breakpoints"][0]["verified"], true);
+
+    client.request("launch", json!({ "program": program }));
+    client.request("configurationDone", Value::Null);
+
+    // The body of `double`, before its argument is forced.
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    assert_eq!(current_line(&mut client), 5);
+    assert!(locals(&mut client).contains(&(String::from("z"), String::from("<not evaluated>"))));
+
+    // Stepping in goes to the argument `add 1 2` of `double`, then to the definition of `add`
+    // and finally to its body.
+    for line in [7, 1, 2] {
+        client.request("stepIn", json!({ "threadId": 1 }));
+        let stopped = client.event("stopped");
+        assert_eq!(stopped["reason"], "step");
+        assert_eq!(current_line(&mut client), line);
+    }
+
+    let vars = locals(&mut client);
+    assert!(vars.contains(&(String::from("x"), String::from("1"))));
+    assert!(vars.contains(&(String::from("y"), String::from("2"))));
+
+    let value = client.request("evaluate", json!({ "expression": "x + y", "frameId": 0 }));
+    assert_eq!(value["result"], "3");
+
+    // The evaluation goes back to the body of `double`, which hits the breakpoint again.
+    client.request("continue", json!({ "threadId": 1 }));
+    let stopped = client.event("stopped");
+    assert_eq!(stopped["reason"], "breakpoint");
+    assert!(locals(&mut client).contains(&(String::from("z"), String::from("3"))));
+
+    client.request("continue", json!({ "threadId": 1 }));
+    let output = client.event("output");
+    assert_eq!(output["category"], "stdout");
+    assert!(output["output"].as_str().unwrap().contains("result = 6"));
+
+    let exited = client.event("exited");
+    assert_eq!(exited["exitCode"], 0);
+
+    client.request("disconnect", Value::Null);
+}
@@ -29,6 +29,7 @@ fn eval(server: &mut Server, uri: &Url) -> Result<(), Error> {
     if let Some(file_id) = server.cache.file_id(uri)? {
         // TODO: avoid cloning the cache. Maybe we can have a VM with a &mut Cache?
         let mut vm = VirtualMachine::<_, CacheImpl>::new(server.cache.clone(), std::io::stderr());
+        vm.set_limits(server.eval_limits);
         let rt = vm.prepare_eval(file_id)?;
         if let Err(e) = vm.eval_full(rt) {
             let diags = e.into_diagnostics(server.cache.files_mut(), None);
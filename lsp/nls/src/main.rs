@@ -1,9 +1,10 @@
-use std::{fs, io, path::PathBuf};
+use std::{fs, io, path::PathBuf, time::Duration};
 
 use anyhow::Result;
 
 use log::debug;
 use lsp_server::Connection;
+use nickel_lang_core::eval::limits::EvalLimits;
 
 mod actions;
 mod analysis;
@@ -31,6 +32,29 @@ struct Opt {
     /// The trace output file, disables tracing if not given
     #[arg(short, long)]
     trace: Option<PathBuf>,
+
+    /// Abort the evaluations run by the `eval` command after the given number of evaluation steps
+    #[arg(long, value_name = "STEPS")]
+    max_steps: Option<u64>,
+
+    /// Abort the evaluations run by the `eval` command when the size of the stack of the
+    /// interpreter exceeds the given depth
+    #[arg(long, value_name = "DEPTH")]
+    max_stack_depth: Option<usize>,
+
+    /// Abort the evaluations run by the `eval` command when they allocate more than the given
+    /// number of elements in the evaluation cache
+    #[arg(long, value_name = "ELEMENTS")]
+    max_cache_size: Option<usize>,
+
+    /// Abort the evaluations run by the `eval` command after the given number of seconds
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    let secs: f64 = s.parse().map_err(|err| format!("{err}"))?;
+    Duration::try_from_secs_f64(secs).map_err(|err| format!("{err}"))
 }
 
 fn main() -> Result<()> {
@@ -56,7 +80,14 @@ fn main() -> Result<()> {
 
     connection.initialize(serde_json::to_value(capabilities)?)?;
 
-    let _server = Server::new(connection).run();
+    let eval_limits = EvalLimits {
+        max_steps: options.max_steps,
+        max_stack_depth: options.max_stack_depth,
+        max_cache_size: options.max_cache_size,
+        timeout: options.timeout,
+    };
+
+    let _server = Server::new(connection, eval_limits).run();
 
     Ok(())
 }
@@ -125,18 +125,18 @@ impl PositionLookup {
             match term.as_ref() {
                 Term::Fun(id, _) | Term::Let(id, _, _, _) => idents.push(*id),
                 Term::FunPattern(id, pat, _) | Term::LetPattern(id, pat, _, _) => {
-                    let ids = pat.matches.iter().flat_map(|m| {
-                        m.to_flattened_bindings()
-                            .into_iter()
-                            .map(|(_path, id, _)| id)
-                    });
-                    idents.extend(ids.chain(*id).chain(pat.rest))
+                    idents.extend(pat.bound_idents().into_iter().chain(*id))
                 }
                 Term::Var(id) => idents.push(*id),
                 Term::Record(data) | Term::RecRecord(data, _, _) => {
                     idents.extend(data.fields.keys().cloned());
                 }
                 Term::Match { cases, .. } => idents.extend(cases.keys().cloned()),
+                Term::MatchPattern(branches) => idents.extend(
+                    branches
+                        .iter()
+                        .flat_map(|branch| branch.pattern.bound_idents()),
+                ),
                 _ => {}
             }
             TraverseControl::<(), ()>::Continue
@@ -33,9 +33,8 @@ fn get_defs(term: &RichTerm, ident: Option<LocIdent>, server: &Server) -> Option
         }
         (Term::LetPattern(_, pat, value, _), Some(hovered_id)) => {
             let (mut path, _, _) = pat
-                .matches
-                .iter()
-                .flat_map(|m| m.to_flattened_bindings())
+                .to_flattened_bindings()
+                .into_iter()
                 .find(|(_path, bound_id, _)| bound_id.ident() == hovered_id.ident)?;
             path.reverse();
             let (last, path) = path.split_last()?;
@@ -21,6 +21,7 @@ use lsp_types::{
 
 use nickel_lang_core::{
     cache::{Cache, ErrorTolerance},
+    eval::limits::EvalLimits,
     position::{RawPos, TermPos},
     stdlib::StdlibModule,
     term::RichTerm,
@@ -48,6 +49,8 @@ pub struct Server {
     pub analysis: AnalysisRegistry,
     pub initial_ctxt: Context,
     pub initial_term_env: crate::usage::Environment,
+    /// The resource limits of the evaluations run by the `eval` command.
+    pub eval_limits: EvalLimits,
 }
 
 impl Server {
@@ -84,7 +87,7 @@ impl Server {
         }
     }
 
-    pub fn new(connection: Connection) -> Server {
+    pub fn new(connection: Connection, eval_limits: EvalLimits) -> Server {
         let mut cache = Cache::new(ErrorTolerance::Tolerant);
         // We don't recover from failing to load the stdlib for now.
         cache.load_stdlib().unwrap();
@@ -96,6 +99,7 @@ impl Server {
             analysis: AnalysisRegistry::default(),
             initial_ctxt,
             initial_term_env: crate::usage::Environment::new(),
+            eval_limits,
         }
     }
 
@@ -115,12 +115,25 @@ impl UsageLookup {
                             new_env.insert_def(Def::Fn { ident });
                         }
 
-                        for m in &pat.matches {
-                            for (_path, id, _field) in m.to_flattened_bindings() {
+                        for (_path, id, _field) in pat.to_flattened_bindings() {
+                            new_env.insert_def(Def::Fn { ident: id.into() });
+                        }
+                        TraverseControl::ContinueWithScope(new_env)
+                    }
+                    Term::MatchPattern(branches) => {
+                        for branch in branches {
+                            let mut new_env = env.clone();
+                            for id in branch.pattern.bound_idents() {
                                 new_env.insert_def(Def::Fn { ident: id.into() });
                             }
+
+                            if let Some(guard) = &branch.guard {
+                                self.fill(guard, &new_env);
+                            }
+                            self.fill(&branch.body, &new_env);
                         }
-                        TraverseControl::ContinueWithScope(new_env)
+
+                        TraverseControl::SkipBranch
                     }
                     Term::Let(id, val, body, attrs) => {
                         let mut new_env = env.clone();
@@ -150,17 +163,15 @@ impl UsageLookup {
                             self.add_sym(def);
                         }
 
-                        for m in &pat.matches {
-                            for (path, id, _field) in m.to_flattened_bindings() {
-                                let path = path.iter().map(|i| i.ident()).rev().collect();
-                                let def = Def::Let {
-                                    ident: LocIdent::from(id),
-                                    value: val.clone(),
-                                    path,
-                                };
-                                new_env.insert_def(def.clone());
-                                self.add_sym(def);
-                            }
+                        for (path, id, _field) in pat.to_flattened_bindings() {
+                            let path = path.iter().map(|i| i.ident()).rev().collect();
+                            let def = Def::Let {
+                                ident: LocIdent::from(id),
+                                value: val.clone(),
+                                path,
+                            };
+                            new_env.insert_def(def.clone());
+                            self.add_sym(def);
                         }
                         TraverseControl::ContinueWithScope(new_env)
                     }
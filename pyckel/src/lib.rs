@@ -1,13 +1,20 @@
-use std::io::Cursor;
+use std::{io::Cursor, time::Duration};
 
 use nickel_lang_core::{
     error::Error,
-    eval::cache::{Cache, CacheImpl},
+    eval::{
+        cache::{Cache, CacheImpl},
+        limits::EvalLimits,
+    },
     program::Program,
     serialize,
 };
 
-use pyo3::{create_exception, exceptions::PyException, prelude::*};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+};
 
 create_exception!(pyckel, NickelException, PyException);
 
@@ -17,10 +24,31 @@ fn error_to_exception<E: Into<Error>, EC: Cache>(error: E, program: &mut Program
 }
 
 /// Evaluate from a Python str of a Nickel expression to a Python str of the resulting JSON.
+///
+/// The evaluation can be bounded by a maximum number of evaluation steps, a maximum stack depth,
+/// a maximum number of elements allocated in the evaluation cache and a timeout in seconds. A
+/// `NickelException` is raised if a limit is exceeded.
 #[pyfunction]
-pub fn run(s: String) -> PyResult<String> {
+pub fn run(
+    s: String,
+    max_steps: Option<u64>,
+    max_stack_depth: Option<usize>,
+    max_cache_size: Option<usize>,
+    timeout: Option<f64>,
+) -> PyResult<String> {
+    let timeout = timeout
+        .map(Duration::try_from_secs_f64)
+        .transpose()
+        .map_err(|err| PyValueError::new_err(format!("invalid timeout: {err}")))?;
+
     let mut program: Program<CacheImpl> =
         Program::new_from_source(Cursor::new(s), "python", std::io::sink())?;
+    program.set_limits(EvalLimits {
+        max_steps,
+        max_stack_depth,
+        max_cache_size,
+        timeout,
+    });
 
     let term = program
         .eval_full()